@@ -0,0 +1,230 @@
+// Copyright Claudio Mattera 2021.
+// Distributed under the MIT License or Apache 2.0 License at your option.
+// See accompanying files License-MIT.txt and License-Apache-2.0, or online at
+// https://opensource.org/licenses/MIT
+// https://opensource.org/licenses/Apache-2.0
+
+//! Arrow record batch implementation
+
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::sync::Arc;
+
+use chrono::{DateTime, Utc};
+
+use rinfluxdb_types::{Columns, FromQueryResult, Value, ValueError};
+
+use arrow2::array::{Array, BooleanArray, PrimitiveArray, Utf8Array};
+use arrow2::datatypes::{DataType, Field, Schema, TimeUnit};
+use arrow2::error::ArrowError;
+use arrow2::record_batch::RecordBatch;
+
+/// Wrapper around [Arrow](https://lib.rs/crates/arrow2) record batch
+///
+/// It is not possible to implement
+/// [`FromQueryResult`](rinfluxdb_types::FromQueryResult) directly for Arrow
+/// record batches, so the newtype pattern is used.
+/// Tags are ignored.
+///
+/// The index is stored in the first column, named `index`, followed by the
+/// other columns in their original order.
+/// Instants are stored as nanosecond timestamps in UTC, and missing values
+/// as nulls.
+/// Columns containing no values are stored as floating point columns.
+///
+/// ```
+/// # use std::convert::TryFrom;
+/// # use chrono::{TimeZone, Utc};
+/// # use rinfluxdb_types::{Columns, Value};
+/// # use rinfluxdb_arrow::RecordBatchWrapper;
+/// let index = vec![
+///     Utc.with_ymd_and_hms(2021, 3, 4, 17, 0, 0).unwrap(),
+///     Utc.with_ymd_and_hms(2021, 3, 4, 18, 0, 0).unwrap(),
+/// ];
+/// let mut columns = Columns::new();
+/// columns.insert("temperature".to_string(), vec![Value::Float(28.4), Value::Null]);
+/// columns.insert("room".to_string(), vec![Value::String("kitchen".into()), Value::Null]);
+///
+/// let RecordBatchWrapper(batch) = RecordBatchWrapper::try_from(
+///     ("environment".to_string(), index, columns),
+/// )?;
+///
+/// assert_eq!(batch.num_rows(), 2);
+/// assert_eq!(batch.num_columns(), 3);
+/// assert_eq!(batch.schema().field(1).name(), "temperature");
+/// assert_eq!(batch.column(2).null_count(), 1);
+/// # Ok::<(), arrow2::error::ArrowError>(())
+/// ```
+pub struct RecordBatchWrapper(pub RecordBatch);
+
+impl TryFrom<(String, Vec<DateTime<Utc>>, Columns)> for RecordBatchWrapper {
+    type Error = ArrowError;
+
+    fn try_from(
+        (_name, index, columns): (String, Vec<DateTime<Utc>>, Columns),
+    ) -> Result<Self, Self::Error> {
+        let mut fields = vec![Field::new("index", timestamp_type(), false)];
+        let mut arrays: Vec<Arc<dyn Array>> = vec![Arc::new(datetimes_to_array(
+            index.into_iter().map(Some),
+        ))];
+
+        for (name, column) in columns {
+            let array: Arc<dyn Array> = match column.iter().find(|value| !value.is_null()) {
+                Some(Value::Float(_)) => Arc::new(PrimitiveArray::<f64>::from(
+                    optional_values(column, Value::try_into_f64)?,
+                )),
+                Some(Value::Integer(_)) => Arc::new(PrimitiveArray::<i64>::from(
+                    optional_values(column, Value::try_into_i64)?,
+                )),
+                Some(Value::UnsignedInteger(_)) => Arc::new(PrimitiveArray::<u64>::from(
+                    optional_values(column, Value::try_into_u64)?,
+                )),
+                Some(Value::String(_)) => Arc::new(Utf8Array::<i32>::from(
+                    optional_values(column, Value::try_into_string)?,
+                )),
+                Some(Value::Boolean(_)) => Arc::new(BooleanArray::from(
+                    optional_values(column, Value::try_into_boolean)?,
+                )),
+                Some(Value::Timestamp(_)) => Arc::new(datetimes_to_array(
+                    optional_values(column, Value::try_into_timestamp)?.into_iter(),
+                )),
+                Some(Value::Null) => unreachable!(),
+                None => Arc::new(PrimitiveArray::<f64>::new_null(
+                    DataType::Float64,
+                    column.len(),
+                )),
+            };
+            fields.push(Field::new(&name, array.data_type().clone(), true));
+            arrays.push(array);
+        }
+
+        let batch = RecordBatch::try_new(Arc::new(Schema::new(fields)), arrays)?;
+        Ok(RecordBatchWrapper(batch))
+    }
+}
+
+impl FromQueryResult for RecordBatchWrapper {
+    type Error = ArrowError;
+
+    fn from_query_result(
+        name: String,
+        index: Vec<DateTime<Utc>>,
+        columns: Columns,
+        _tags: Option<&HashMap<String, String>>,
+    ) -> Result<Self, Self::Error> {
+        Self::try_from((name, index, columns))
+    }
+}
+
+fn timestamp_type() -> DataType {
+    DataType::Timestamp(TimeUnit::Nanosecond, Some("UTC".to_string()))
+}
+
+fn datetimes_to_array<A>(column: A) -> PrimitiveArray<i64>
+where
+    A: Iterator<Item=Option<DateTime<Utc>>>
+{
+    column
+        .map(|value| value.map(|value| value.timestamp_nanos()))
+        .collect::<PrimitiveArray<i64>>()
+        .to(timestamp_type())
+}
+
+fn optional_values<T, F>(column: Vec<Value>, convert: F) -> Result<Vec<Option<T>>, ArrowError>
+where
+    F: Fn(Value) -> Result<T, ValueError>,
+{
+    column
+        .into_iter()
+        .map(|value| match value {
+            Value::Null => Ok(None),
+            value => convert(value).map(Some).map_err(value_error),
+        })
+        .collect()
+}
+
+fn value_error(error: ValueError) -> ArrowError {
+    ArrowError::InvalidArgumentError(error.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::convert::TryInto;
+
+    use chrono::TimeZone;
+
+    use super::*;
+
+    #[test]
+    fn arrow_record_batch_creation() -> Result<(), Box<dyn std::error::Error>> {
+        let name: String = "environment".into();
+        let index: Vec<DateTime<Utc>> = vec![
+            Utc.with_ymd_and_hms(2021, 10, 20, 5, 20, 21).unwrap(),
+            Utc.with_ymd_and_hms(2021, 10, 20, 5, 20, 22).unwrap(),
+        ];
+        let mut columns: Columns = Columns::new();
+        columns.insert(
+            "temperature".into(),
+            vec![Value::Float(23.2), Value::Null],
+        );
+        columns.insert(
+            "humidity".into(),
+            vec![Value::UnsignedInteger(40_u64), Value::UnsignedInteger(38_u64)],
+        );
+        columns.insert(
+            "rain".into(),
+            vec![Value::Boolean(false), Value::Boolean(true)],
+        );
+        columns.insert(
+            "wind".into(),
+            vec![Value::Null, Value::Null],
+        );
+
+        let RecordBatchWrapper(batch) = (name, index.clone(), columns).try_into()?;
+
+        let schema = batch.schema();
+        let fields: Vec<(&str, &DataType)> = schema
+            .fields()
+            .iter()
+            .map(|field| (field.name().as_str(), field.data_type()))
+            .collect();
+        assert_eq!(
+            fields,
+            vec![
+                ("index", &timestamp_type()),
+                ("temperature", &DataType::Float64),
+                ("humidity", &DataType::UInt64),
+                ("rain", &DataType::Boolean),
+                ("wind", &DataType::Float64),
+            ],
+        );
+
+        let timestamps = batch.column(0)
+            .as_any()
+            .downcast_ref::<PrimitiveArray<i64>>()
+            .expect("index is not a timestamp array");
+        assert_eq!(timestamps.value(1), index[1].timestamp_nanos());
+
+        assert_eq!(batch.column(1).null_count(), 1);
+        assert_eq!(batch.column(4).null_count(), 2);
+
+        Ok(())
+    }
+
+    #[test]
+    fn arrow_record_batch_type_mismatch() {
+        let mut columns: Columns = Columns::new();
+        columns.insert(
+            "temperature".into(),
+            vec![Value::Float(23.2), Value::String("warm".into())],
+        );
+        let index = vec![
+            Utc.with_ymd_and_hms(2021, 10, 20, 5, 20, 21).unwrap(),
+            Utc.with_ymd_and_hms(2021, 10, 20, 5, 20, 22).unwrap(),
+        ];
+
+        let batch: Result<RecordBatchWrapper, _> = ("environment".to_string(), index, columns).try_into();
+
+        assert!(batch.is_err());
+    }
+}
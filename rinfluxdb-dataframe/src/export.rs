@@ -0,0 +1,310 @@
+// Copyright Claudio Mattera 2021.
+// Distributed under the MIT License or Apache 2.0 License at your option.
+// See accompanying files License-MIT.txt and License-Apache-2.0, or online at
+// https://opensource.org/licenses/MIT
+// https://opensource.org/licenses/Apache-2.0
+
+use std::collections::HashMap;
+use std::io::{Read, Write};
+
+use chrono::{DateTime, SecondsFormat, Utc};
+
+use indexmap::IndexMap;
+
+use serde::{Deserialize, Serialize};
+
+use thiserror::Error;
+
+use rinfluxdb_types::DataFrameError;
+
+use super::{Column, DataFrame};
+
+/// An error occurred while exporting or importing a dataframe
+#[derive(Error, Debug)]
+pub enum ExportError {
+    /// Error while reading or writing CSV
+    #[error("CSV error")]
+    Csv(#[from] csv::Error),
+
+    /// Error while reading or writing JSON
+    #[error("JSON error")]
+    Json(#[from] serde_json::Error),
+
+    /// The input does not describe a valid dataframe
+    #[error("Invalid dataframe: {0}")]
+    Invalid(String),
+
+    /// Error while creating the dataframe
+    #[error("Dataframe error")]
+    DataFrame(#[from] DataFrameError),
+}
+
+impl DataFrame {
+    /// Write the dataframe as CSV
+    ///
+    /// The first column is the index, named `time`, followed by the other
+    /// columns in their original order.
+    /// Instants are formatted according to RFC3339, and missing values are
+    /// written as empty cells.
+    ///
+    /// ```
+    /// # use std::convert::TryFrom;
+    /// # use chrono::{TimeZone, Utc};
+    /// # use rinfluxdb_types::{Columns, Value};
+    /// # use rinfluxdb_dataframe::DataFrame;
+    /// let index = vec![
+    ///     Utc.with_ymd_and_hms(2021, 3, 4, 17, 0, 0).unwrap(),
+    ///     Utc.with_ymd_and_hms(2021, 3, 4, 18, 0, 0).unwrap(),
+    /// ];
+    /// let mut columns = Columns::new();
+    /// columns.insert("temperature".to_string(), vec![Value::Float(28.0), Value::Float(29.2)]);
+    /// columns.insert("room".to_string(), vec![Value::String("kitchen".into()), Value::Null]);
+    /// let dataframe = DataFrame::try_from(("environment".to_string(), index, columns))?;
+    ///
+    /// let mut output = Vec::new();
+    /// dataframe.to_csv(&mut output)?;
+    ///
+    /// assert_eq!(
+    ///     String::from_utf8(output)?,
+    ///     "time,temperature,room\n\
+    ///     2021-03-04T17:00:00Z,28.0,kitchen\n\
+    ///     2021-03-04T18:00:00Z,29.2,\n",
+    /// );
+    ///
+    /// let parsed = DataFrame::from_csv(dataframe.to_csv_string()?.as_bytes())?;
+    /// assert_eq!(parsed.column("temperature"), dataframe.column("temperature"));
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn to_csv<W>(&self, writer: W) -> Result<(), ExportError>
+    where
+        W: Write,
+    {
+        let mut writer = csv::Writer::from_writer(writer);
+
+        let names = self.column_names();
+        let mut header = vec!["time"];
+        header.extend(names.iter());
+        writer.write_record(&header)?;
+
+        for (position, instant) in self.index.iter().enumerate() {
+            let mut record = vec![format_instant(instant)];
+            record.extend(
+                names
+                    .iter()
+                    .map(|name| format_cell(&self.columns[*name], position)),
+            );
+            writer.write_record(&record)?;
+        }
+
+        writer.flush().map_err(csv::Error::from)?;
+        Ok(())
+    }
+
+    /// Return the dataframe as a CSV string
+    pub fn to_csv_string(&self) -> Result<String, ExportError> {
+        let mut output = Vec::new();
+        self.to_csv(&mut output)?;
+        String::from_utf8(output).map_err(|error| ExportError::Invalid(error.to_string()))
+    }
+
+    /// Read a dataframe from CSV
+    ///
+    /// The first column is the index, and it must contain instants formatted
+    /// according to RFC3339.
+    /// The type of the other columns is inferred from their values, trying
+    /// booleans, integers, unsigned integers, floating point numbers,
+    /// instants and finally strings.
+    /// Empty cells are missing values, and columns containing only missing
+    /// values are stored as float columns.
+    ///
+    /// CSV does not store the dataframe name, so the dataframe has an empty
+    /// name, which can be set with [`with_name`](DataFrame::with_name).
+    pub fn from_csv<R>(reader: R) -> Result<Self, ExportError>
+    where
+        R: Read,
+    {
+        let mut reader = csv::Reader::from_reader(reader);
+
+        let names: Vec<String> = reader
+            .headers()?
+            .iter()
+            .skip(1)
+            .map(String::from)
+            .collect();
+
+        let mut index = Vec::new();
+        let mut cells: Vec<Vec<String>> = vec![Vec::new(); names.len()];
+        for record in reader.records() {
+            let record = record?;
+            let mut record = record.iter();
+
+            let instant = record
+                .next()
+                .ok_or_else(|| ExportError::Invalid("missing index".into()))?;
+            index.push(parse_instant(instant)?);
+
+            for (column, cell) in cells.iter_mut().zip(record) {
+                column.push(cell.to_string());
+            }
+        }
+
+        let columns = names
+            .into_iter()
+            .zip(cells)
+            .map(|(name, cells)| (name, infer_column(cells)))
+            .collect();
+
+        let dataframe = DataFrame {
+            name: String::new(),
+            index,
+            columns,
+            tags: HashMap::new(),
+        };
+        dataframe.validate()?;
+        Ok(dataframe)
+    }
+
+    /// Return the dataframe as JSON
+    ///
+    /// The JSON document contains the dataframe name, its index as RFC3339
+    /// instants, its columns with their types and its tags, if any, so that
+    /// a dataframe can be read back exactly by
+    /// [`from_json`](DataFrame::from_json).
+    ///
+    /// ```
+    /// # use std::convert::TryFrom;
+    /// # use chrono::{TimeZone, Utc};
+    /// # use rinfluxdb_types::{Columns, Value};
+    /// # use rinfluxdb_dataframe::DataFrame;
+    /// let index = vec![Utc.with_ymd_and_hms(2021, 3, 4, 17, 0, 0).unwrap()];
+    /// let mut columns = Columns::new();
+    /// columns.insert("temperature".to_string(), vec![Value::Float(28.4)]);
+    /// let dataframe = DataFrame::try_from(("environment".to_string(), index, columns))?;
+    ///
+    /// let json = dataframe.to_json()?;
+    /// assert_eq!(
+    ///     json,
+    ///     r#"{"name":"environment","index":["2021-03-04T17:00:00Z"],"columns":{"temperature":{"type":"float","values":[28.4]}}}"#,
+    /// );
+    ///
+    /// let parsed = DataFrame::from_json(&json)?;
+    /// assert_eq!(parsed.name(), "environment");
+    /// assert_eq!(parsed.column("temperature"), dataframe.column("temperature"));
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn to_json(&self) -> Result<String, ExportError> {
+        let document = JsonDataFrame {
+            name: self.name.clone(),
+            index: self.index.clone(),
+            columns: self
+                .columns
+                .iter()
+                .map(|(name, column)| (name.clone(), column.clone()))
+                .collect(),
+            tags: self.tags.clone(),
+        };
+        Ok(serde_json::to_string(&document)?)
+    }
+
+    /// Read a dataframe from JSON created by [`to_json`](DataFrame::to_json)
+    pub fn from_json(input: &str) -> Result<Self, ExportError> {
+        let document: JsonDataFrame = serde_json::from_str(input)?;
+        let dataframe = DataFrame {
+            name: document.name,
+            index: document.index,
+            columns: document.columns.into_iter().collect(),
+            tags: document.tags,
+        };
+        dataframe.validate()?;
+        Ok(dataframe)
+    }
+
+    /// Check that all columns have the same length as the index
+    fn validate(&self) -> Result<(), ExportError> {
+        match self
+            .columns
+            .iter()
+            .find(|(_, column)| column.len() != self.index.len())
+        {
+            Some((name, _)) => Err(ExportError::Invalid(format!(
+                "column {} does not match the index length",
+                name,
+            ))),
+            None => Ok(()),
+        }
+    }
+}
+
+/// A dataframe as stored in JSON
+#[derive(Serialize, Deserialize)]
+struct JsonDataFrame {
+    name: String,
+    index: Vec<DateTime<Utc>>,
+    columns: IndexMap<String, Column>,
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    tags: HashMap<String, String>,
+}
+
+fn format_instant(instant: &DateTime<Utc>) -> String {
+    instant.to_rfc3339_opts(SecondsFormat::AutoSi, true)
+}
+
+fn parse_instant(cell: &str) -> Result<DateTime<Utc>, ExportError> {
+    DateTime::parse_from_rfc3339(cell)
+        .map(|instant| instant.with_timezone(&Utc))
+        .map_err(|_| ExportError::Invalid(format!("invalid instant {}", cell)))
+}
+
+/// Format a value for CSV
+///
+/// Floating point values are always written with a decimal part, so that
+/// their type is inferred correctly when reading them back.
+fn format_cell(column: &Column, position: usize) -> String {
+    match column {
+        Column::Float(values) => values[position].map(|value| format!("{:?}", value)),
+        Column::Integer(values) => values[position].map(|value| value.to_string()),
+        Column::UnsignedInteger(values) => values[position].map(|value| value.to_string()),
+        Column::String(values) => values[position].clone(),
+        Column::Boolean(values) => values[position].map(|value| value.to_string()),
+        Column::Timestamp(values) => values[position].as_ref().map(format_instant),
+    }
+    .unwrap_or_default()
+}
+
+/// Infer the type of a CSV column from its values
+fn infer_column(cells: Vec<String>) -> Column {
+    fn parse_all<T>(cells: &[String], parse: impl Fn(&str) -> Option<T>) -> Option<Vec<Option<T>>> {
+        cells
+            .iter()
+            .map(|cell| match cell.as_str() {
+                "" => Some(None),
+                cell => parse(cell).map(Some),
+            })
+            .collect()
+    }
+
+    if cells.iter().all(String::is_empty) {
+        return Column::Float(vec![None; cells.len()]);
+    }
+
+    let boolean = |cell: &str| match cell {
+        "true" => Some(true),
+        "false" => Some(false),
+        _ => None,
+    };
+
+    parse_all(&cells, boolean)
+        .map(Column::Boolean)
+        .or_else(|| parse_all(&cells, |cell| cell.parse().ok()).map(Column::Integer))
+        .or_else(|| parse_all(&cells, |cell| cell.parse().ok()).map(Column::UnsignedInteger))
+        .or_else(|| parse_all(&cells, |cell| cell.parse().ok()).map(Column::Float))
+        .or_else(|| parse_all(&cells, |cell| parse_instant(cell).ok()).map(Column::Timestamp))
+        .unwrap_or_else(|| {
+            Column::String(
+                cells
+                    .into_iter()
+                    .map(|cell| if cell.is_empty() { None } else { Some(cell) })
+                    .collect(),
+            )
+        })
+}
@@ -6,59 +6,662 @@
 
 //! Dummy dataframe implementation
 
-use std::collections::HashMap;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
 use std::convert::TryFrom;
 use std::fmt;
 
 use chrono::{DateTime, Utc};
 
-use rinfluxdb_types::{DataFrameError, Value};
+use indexmap::IndexMap;
+
+use serde::{Deserialize, Serialize};
+
+use rinfluxdb_types::{Columns, DataFrameError, FromQueryResult, Value};
+
+use rinfluxdb_lineprotocol::{FieldValue, Line, Measurement};
+
+mod export;
+pub use export::ExportError;
 
 /// Column type
-#[derive(Clone, Debug, PartialEq)]
+///
+/// Values are optional, since InfluxDB returns missing values for fields
+/// that were not written at some instants.
+/// Columns containing only missing values are stored as float columns.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", content = "values", rename_all = "snake_case")]
 pub enum Column {
     /// A column of floating point values
-    Float(Vec<f64>),
+    Float(Vec<Option<f64>>),
 
     /// A column of integer values
-    Integer(Vec<i64>),
+    Integer(Vec<Option<i64>>),
 
     /// A column of unsigned integer values
-    UnsignedInteger(Vec<u64>),
+    UnsignedInteger(Vec<Option<u64>>),
 
     /// A column of string values
-    String(Vec<String>),
+    String(Vec<Option<String>>),
 
     /// A column of boolean values
-    Boolean(Vec<bool>),
+    Boolean(Vec<Option<bool>>),
 
     /// A column of datetime values
-    Timestamp(Vec<DateTime<Utc>>),
+    Timestamp(Vec<Option<DateTime<Utc>>>),
 }
 
 impl Column {
-    fn display_index(&self, index: usize, f: &mut fmt::Formatter) -> fmt::Result {
+    /// Return the number of values
+    pub fn len(&self) -> usize {
+        match self {
+            Column::Float(values) => values.len(),
+            Column::Integer(values) => values.len(),
+            Column::UnsignedInteger(values) => values.len(),
+            Column::String(values) => values.len(),
+            Column::Boolean(values) => values.len(),
+            Column::Timestamp(values) => values.len(),
+        }
+    }
+
+    /// Check whether the column has no values
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Return the value at a position, or nothing if it is out of range
+    ///
+    /// Missing values are returned as [`Value::Null`].
+    pub fn get(&self, index: usize) -> Option<Value> {
+        if index < self.len() {
+            Some(self.value(index))
+        } else {
+            None
+        }
+    }
+
+    /// Return the values if this is a float column
+    pub fn as_float_slice(&self) -> Option<&[Option<f64>]> {
+        match self {
+            Column::Float(values) => Some(values),
+            _ => None,
+        }
+    }
+
+    /// Return the values if this is an integer column
+    pub fn as_integer_slice(&self) -> Option<&[Option<i64>]> {
+        match self {
+            Column::Integer(values) => Some(values),
+            _ => None,
+        }
+    }
+
+    /// Return the values if this is an unsigned integer column
+    pub fn as_unsigned_integer_slice(&self) -> Option<&[Option<u64>]> {
         match self {
-            Column::Float(values) => write!(f, "{:16}  ", values[index])?,
-            Column::Integer(values) => write!(f, "{:16}  ", values[index])?,
-            Column::UnsignedInteger(values) => write!(f, "{:16}  ", values[index])?,
-            Column::String(values) => write!(f, "{:16}  ", values[index])?,
-            Column::Boolean(values) => write!(f, "{:16}  ", values[index])?,
-            Column::Timestamp(values) => write!(f, "{:16}  ", values[index])?,
+            Column::UnsignedInteger(values) => Some(values),
+            _ => None,
         }
+    }
+
+    /// Return the values if this is a string column
+    pub fn as_string_slice(&self) -> Option<&[Option<String>]> {
+        match self {
+            Column::String(values) => Some(values),
+            _ => None,
+        }
+    }
 
+    /// Return the values if this is a boolean column
+    pub fn as_boolean_slice(&self) -> Option<&[Option<bool>]> {
+        match self {
+            Column::Boolean(values) => Some(values),
+            _ => None,
+        }
+    }
+
+    /// Return the values if this is a datetime column
+    pub fn as_timestamp_slice(&self) -> Option<&[Option<DateTime<Utc>>]> {
+        match self {
+            Column::Timestamp(values) => Some(values),
+            _ => None,
+        }
+    }
+
+    /// Create a column picking values at some positions
+    ///
+    /// Missing positions result in missing values.
+    fn take(&self, positions: &[Option<usize>]) -> Column {
+        fn pick<T: Clone>(values: &[Option<T>], positions: &[Option<usize>]) -> Vec<Option<T>> {
+            positions
+                .iter()
+                .map(|position| position.and_then(|position| values[position].clone()))
+                .collect()
+        }
+
+        match self {
+            Column::Float(values) => Column::Float(pick(values, positions)),
+            Column::Integer(values) => Column::Integer(pick(values, positions)),
+            Column::UnsignedInteger(values) => Column::UnsignedInteger(pick(values, positions)),
+            Column::String(values) => Column::String(pick(values, positions)),
+            Column::Boolean(values) => Column::Boolean(pick(values, positions)),
+            Column::Timestamp(values) => Column::Timestamp(pick(values, positions)),
+        }
+    }
+
+    /// Append the values of another column of the same type
+    fn append(&mut self, other: &Column) -> Result<(), DataFrameError> {
+        match (self, other) {
+            (Column::Float(values), Column::Float(other)) => values.extend_from_slice(other),
+            (Column::Integer(values), Column::Integer(other)) => values.extend_from_slice(other),
+            (Column::UnsignedInteger(values), Column::UnsignedInteger(other)) => {
+                values.extend_from_slice(other)
+            }
+            (Column::String(values), Column::String(other)) => values.extend_from_slice(other),
+            (Column::Boolean(values), Column::Boolean(other)) => values.extend_from_slice(other),
+            (Column::Timestamp(values), Column::Timestamp(other)) => {
+                values.extend_from_slice(other)
+            }
+            _ => return Err(DataFrameError::Creation),
+        }
         Ok(())
     }
+
+    fn display_index(&self, index: usize, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:16}  ", self.value(index))
+    }
+
+    fn value(&self, index: usize) -> Value {
+        let value = match self {
+            Column::Float(values) => values[index].map(Value::Float),
+            Column::Integer(values) => values[index].map(Value::Integer),
+            Column::UnsignedInteger(values) => values[index].map(Value::UnsignedInteger),
+            Column::String(values) => values[index].clone().map(Value::String),
+            Column::Boolean(values) => values[index].map(Value::Boolean),
+            Column::Timestamp(values) => values[index].map(Value::Timestamp),
+        };
+        value.unwrap_or(Value::Null)
+    }
+}
+
+/// Convert a column of values, keeping missing values
+fn optional_values<T, F>(column: Vec<Value>, convert: F) -> Result<Vec<Option<T>>, DataFrameError>
+where
+    F: Fn(Value) -> Result<T, rinfluxdb_types::ValueError>,
+{
+    column
+        .into_iter()
+        .map(|value| match value {
+            Value::Null => Ok(None),
+            value => convert(value).map(Some).map_err(DataFrameError::from),
+        })
+        .collect()
+}
+
+/// A single value of a dataframe in long format
+///
+/// A dataframe in long format has one record for each value, rather than one
+/// row for each instant and one column for each field.
+#[derive(Clone, Debug, PartialEq)]
+pub struct LongRecord {
+    /// The instant of the value
+    pub time: DateTime<Utc>,
+
+    /// The name of the field
+    pub field: String,
+
+    /// The value
+    pub value: Value,
+
+    /// The tags of the series the value belongs to
+    pub tags: BTreeMap<String, String>,
+}
+
+/// How rows are matched when joining dataframes
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum JoinKind {
+    /// Keep only instants present in both dataframes
+    Inner,
+
+    /// Keep instants present in either dataframe, filling the other
+    /// dataframe's columns with missing values
+    Outer,
+}
+
+/// A row of a dataframe
+///
+/// A row contains the instant and the values of all columns at that
+/// instant.
+#[derive(Clone, Copy, Debug)]
+pub struct Row<'a> {
+    dataframe: &'a DataFrame,
+    position: usize,
+}
+
+impl<'a> Row<'a> {
+    /// Return the instant of the row
+    pub fn time(&self) -> DateTime<Utc> {
+        self.dataframe.index[self.position]
+    }
+
+    /// Return the value of a column, or nothing if there is no such column
+    ///
+    /// Missing values are returned as [`Value::Null`].
+    pub fn get(&self, column: &str) -> Option<Value> {
+        self.dataframe
+            .columns
+            .get(column)
+            .map(|values| values.value(self.position))
+    }
+
+    /// Return the values of all columns, in the order of the columns
+    pub fn values(&self) -> Vec<(&'a str, Value)> {
+        self.dataframe
+            .columns
+            .iter()
+            .map(|(name, column)| (name.as_str(), column.value(self.position)))
+            .collect()
+    }
 }
 
 /// A time-indexed dataframe
 ///
 /// A dataframe contains multiple named columns indexed by the same index.
+///
+/// ```
+/// # use std::convert::TryFrom;
+/// # use chrono::{TimeZone, Utc};
+/// # use rinfluxdb_types::{Columns, Value};
+/// # use rinfluxdb_dataframe::DataFrame;
+/// let index = vec![
+///     Utc.with_ymd_and_hms(2021, 3, 4, 17, 0, 0).unwrap(),
+///     Utc.with_ymd_and_hms(2021, 3, 4, 18, 0, 0).unwrap(),
+/// ];
+/// let mut columns = Columns::new();
+/// columns.insert("temperature".to_string(), vec![Value::Float(28.4), Value::Float(29.2)]);
+/// columns.insert("room".to_string(), vec![Value::String("kitchen".into()), Value::Null]);
+/// let dataframe = DataFrame::try_from(("environment".to_string(), index, columns))?;
+///
+/// assert_eq!(dataframe.len(), 2);
+/// assert_eq!(dataframe.index()[1], Utc.with_ymd_and_hms(2021, 3, 4, 18, 0, 0).unwrap());
+/// assert_eq!(dataframe.column_names(), vec!["temperature", "room"]);
+///
+/// let temperature = dataframe.column("temperature").and_then(|column| column.as_float_slice());
+/// assert_eq!(temperature, Some(&[Some(28.4), Some(29.2)][..]));
+///
+/// let rooms: Vec<Value> = dataframe
+///     .rows()
+///     .filter_map(|row| row.get("room"))
+///     .collect();
+/// assert_eq!(rooms, vec![Value::String("kitchen".into()), Value::Null]);
+/// # Ok::<(), rinfluxdb_types::DataFrameError>(())
+/// ```
 #[derive(Clone, Debug)]
 pub struct DataFrame {
     name: String,
     index: Vec<DateTime<Utc>>,
-    columns: HashMap<String, Column>,
+    columns: IndexMap<String, Column>,
+    tags: HashMap<String, String>,
+}
+
+impl DataFrame {
+    /// Return the dataframe name
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Return the dataframe with a different name
+    pub fn with_name<T>(mut self, name: T) -> Self
+    where
+        T: Into<String>,
+    {
+        self.name = name.into();
+        self
+    }
+
+    /// Return the tags of the series the dataframe was constructed from
+    ///
+    /// Tags are only known for dataframes returned by queries grouped by
+    /// tags.
+    pub fn tags(&self) -> &HashMap<String, String> {
+        &self.tags
+    }
+
+    /// Return the index
+    pub fn index(&self) -> &[DateTime<Utc>] {
+        &self.index
+    }
+
+    /// Return the number of rows
+    pub fn len(&self) -> usize {
+        self.index.len()
+    }
+
+    /// Check whether the dataframe has no rows
+    pub fn is_empty(&self) -> bool {
+        self.index.is_empty()
+    }
+
+    /// Return the names of all columns, in their original order
+    pub fn column_names(&self) -> Vec<&str> {
+        self.columns.keys().map(String::as_str).collect()
+    }
+
+    /// Iterate over all columns and their names, in their original order
+    pub fn columns(&self) -> impl Iterator<Item = (&str, &Column)> {
+        self.columns.iter().map(|(name, column)| (name.as_str(), column))
+    }
+
+    /// Return a column, or nothing if there is no such column
+    pub fn column(&self, name: &str) -> Option<&Column> {
+        self.columns.get(name)
+    }
+
+    /// Iterate over all rows, sorted as the index
+    pub fn rows(&self) -> impl Iterator<Item = Row<'_>> {
+        (0..self.index.len()).map(move |position| Row {
+            dataframe: self,
+            position,
+        })
+    }
+
+    /// Join with another dataframe, aligning rows on the index
+    ///
+    /// The joined dataframe has the name of this dataframe, its index is
+    /// sorted, and it contains the columns of both dataframes.
+    /// If an instant appears multiple times in an index, only its first row
+    /// is used.
+    /// [`DataFrameError::DuplicateColumn`] is returned if both dataframes
+    /// have a column with the same name.
+    ///
+    /// ```
+    /// # use std::convert::TryFrom;
+    /// # use chrono::{TimeZone, Utc};
+    /// # use rinfluxdb_types::{Columns, Value};
+    /// # use rinfluxdb_dataframe::{DataFrame, JoinKind};
+    /// let first = Utc.with_ymd_and_hms(2021, 3, 4, 17, 0, 0).unwrap();
+    /// let second = Utc.with_ymd_and_hms(2021, 3, 4, 18, 0, 0).unwrap();
+    ///
+    /// let mut columns = Columns::new();
+    /// columns.insert("temperature".to_string(), vec![Value::Float(28.4), Value::Float(29.2)]);
+    /// let temperature = DataFrame::try_from(("temperature".to_string(), vec![first, second], columns))?;
+    ///
+    /// let mut columns = Columns::new();
+    /// columns.insert("humidity".to_string(), vec![Value::Float(41.0)]);
+    /// let humidity = DataFrame::try_from(("humidity".to_string(), vec![second], columns))?;
+    ///
+    /// let inner = temperature.join(&humidity, JoinKind::Inner)?;
+    /// assert_eq!(inner.index(), &[second]);
+    ///
+    /// let outer = temperature.join(&humidity, JoinKind::Outer)?;
+    /// assert_eq!(outer.index(), &[first, second]);
+    /// assert_eq!(
+    ///     outer.column("humidity").and_then(|column| column.as_float_slice()),
+    ///     Some(&[None, Some(41.0)][..]),
+    /// );
+    /// # Ok::<(), rinfluxdb_types::DataFrameError>(())
+    /// ```
+    pub fn join(&self, other: &DataFrame, kind: JoinKind) -> Result<DataFrame, DataFrameError> {
+        if let Some(name) = self.columns.keys().find(|name| other.columns.contains_key(*name)) {
+            return Err(DataFrameError::DuplicateColumn(name.clone()));
+        }
+
+        let positions = self.positions();
+        let other_positions = other.positions();
+
+        let index: Vec<DateTime<Utc>> = match kind {
+            JoinKind::Inner => positions
+                .keys()
+                .filter(|instant| other_positions.contains_key(instant))
+                .cloned()
+                .collect(),
+            JoinKind::Outer => positions
+                .keys()
+                .chain(other_positions.keys())
+                .cloned()
+                .collect::<BTreeSet<_>>()
+                .into_iter()
+                .collect(),
+        };
+
+        let rows: Vec<Option<usize>> = index
+            .iter()
+            .map(|instant| positions.get(instant).copied())
+            .collect();
+        let other_rows: Vec<Option<usize>> = index
+            .iter()
+            .map(|instant| other_positions.get(instant).copied())
+            .collect();
+
+        let columns = self
+            .columns
+            .iter()
+            .map(|(name, column)| (name.clone(), column.take(&rows)))
+            .chain(
+                other
+                    .columns
+                    .iter()
+                    .map(|(name, column)| (name.clone(), column.take(&other_rows))),
+            )
+            .collect();
+
+        Ok(DataFrame {
+            name: self.name.clone(),
+            index,
+            columns,
+            tags: self.tags.clone(),
+        })
+    }
+
+    /// Append the rows of another dataframe with the same columns
+    ///
+    /// Rows are appended as they are, without sorting the index.
+    /// [`DataFrameError::SchemaMismatch`] is returned if the dataframes have
+    /// different column names or types.
+    ///
+    /// ```
+    /// # use std::convert::TryFrom;
+    /// # use chrono::{TimeZone, Utc};
+    /// # use rinfluxdb_types::{Columns, Value};
+    /// # use rinfluxdb_dataframe::DataFrame;
+    /// let mut columns = Columns::new();
+    /// columns.insert("temperature".to_string(), vec![Value::Float(28.4)]);
+    /// let index = vec![Utc.with_ymd_and_hms(2021, 3, 4, 17, 0, 0).unwrap()];
+    /// let first = DataFrame::try_from(("environment".to_string(), index, columns))?;
+    ///
+    /// let mut columns = Columns::new();
+    /// columns.insert("temperature".to_string(), vec![Value::Float(29.2)]);
+    /// let index = vec![Utc.with_ymd_and_hms(2021, 3, 4, 18, 0, 0).unwrap()];
+    /// let second = DataFrame::try_from(("environment".to_string(), index, columns))?;
+    ///
+    /// let dataframe = first.concat(&second)?;
+    /// assert_eq!(dataframe.len(), 2);
+    /// # Ok::<(), rinfluxdb_types::DataFrameError>(())
+    /// ```
+    pub fn concat(&self, other: &DataFrame) -> Result<DataFrame, DataFrameError> {
+        if let Some(name) = other.columns.keys().find(|name| !self.columns.contains_key(*name)) {
+            return Err(DataFrameError::SchemaMismatch(name.clone()));
+        }
+
+        let mut columns = self.columns.clone();
+        for (name, column) in columns.iter_mut() {
+            let other_column = other
+                .columns
+                .get(name)
+                .ok_or_else(|| DataFrameError::SchemaMismatch(name.clone()))?;
+            column
+                .append(other_column)
+                .map_err(|_| DataFrameError::SchemaMismatch(name.clone()))?;
+        }
+
+        let mut index = self.index.clone();
+        index.extend_from_slice(&other.index);
+
+        Ok(DataFrame {
+            name: self.name.clone(),
+            index,
+            columns,
+            tags: self.tags.clone(),
+        })
+    }
+
+    /// Map each instant to its first position in the index
+    fn positions(&self) -> BTreeMap<DateTime<Utc>, usize> {
+        let mut positions = BTreeMap::new();
+        for (position, instant) in self.index.iter().enumerate() {
+            positions.entry(*instant).or_insert(position);
+        }
+        positions
+    }
+
+    /// Convert the dataframe to long format
+    ///
+    /// Records are sorted by time, and then by field name, and they carry
+    /// the tags of the dataframe.
+    /// Missing values are skipped.
+    ///
+    /// ```
+    /// # use std::convert::TryFrom;
+    /// # use chrono::{TimeZone, Utc};
+    /// # use rinfluxdb_types::{Columns, Value};
+    /// # use rinfluxdb_dataframe::DataFrame;
+    /// let index = vec![
+    ///     Utc.with_ymd_and_hms(2021, 3, 4, 18, 0, 0).unwrap(),
+    ///     Utc.with_ymd_and_hms(2021, 3, 4, 17, 0, 0).unwrap(),
+    /// ];
+    /// let mut columns = Columns::new();
+    /// columns.insert("temperature".to_string(), vec![Value::Float(29.2), Value::Float(28.4)]);
+    /// columns.insert("humidity".to_string(), vec![Value::Float(37.0), Value::Float(41.0)]);
+    /// columns.insert("wind".to_string(), vec![Value::Null, Value::Null]);
+    /// let dataframe = DataFrame::try_from(("environment".to_string(), index, columns))?;
+    ///
+    /// let records = dataframe.to_long_format();
+    ///
+    /// assert_eq!(records.len(), 4);
+    /// assert_eq!(records[0].time, Utc.with_ymd_and_hms(2021, 3, 4, 17, 0, 0).unwrap());
+    /// assert_eq!(records[0].field, "humidity");
+    /// assert_eq!(records[0].value, Value::Float(41.0));
+    /// assert_eq!(records[1].field, "temperature");
+    /// assert_eq!(records[3].value, Value::Float(29.2));
+    /// assert!(records[0].tags.is_empty());
+    /// # Ok::<(), rinfluxdb_types::DataFrameError>(())
+    /// ```
+    pub fn to_long_format(&self) -> Vec<LongRecord> {
+        self.to_long_format_with_tags(&self.tags)
+    }
+
+    /// Convert the dataframe to long format, attaching tags to all records
+    ///
+    /// This is useful for dataframes returned by grouped queries, whose tags
+    /// are returned separately.
+    pub fn to_long_format_with_tags(&self, tags: &HashMap<String, String>) -> Vec<LongRecord> {
+        let tags: BTreeMap<String, String> = tags
+            .iter()
+            .map(|(name, value)| (name.clone(), value.clone()))
+            .collect();
+
+        let mut columns: Vec<(&String, &Column)> = self.columns.iter().collect();
+        columns.sort_by_key(|(name, _)| *name);
+
+        let mut records: Vec<LongRecord> = self.index
+            .iter()
+            .enumerate()
+            .flat_map(|(i, time)| {
+                let tags = &tags;
+                columns.iter().filter_map(move |(field, column)| {
+                    let value = column.value(i);
+                    if value.is_null() {
+                        return None;
+                    }
+                    Some(LongRecord {
+                        time: *time,
+                        field: (*field).clone(),
+                        value,
+                        tags: tags.clone(),
+                    })
+                })
+            })
+            .collect();
+
+        // The sort is stable, so records at the same time stay sorted by
+        // field name
+        records.sort_by_key(|record| record.time);
+        records
+    }
+
+    /// Convert the dataframe to lines, one for each row
+    ///
+    /// The index is used as timestamp, the tag columns as tags, and all other
+    /// columns as fields.
+    /// Tag values are formatted as strings.
+    /// Missing values and empty tag values are skipped, as well as rows that
+    /// have no field values, since they cannot be written.
+    ///
+    /// This allows to write back the result of a query, for instance after
+    /// downsampling it.
+    ///
+    /// ```
+    /// # use std::convert::TryFrom;
+    /// # use chrono::{TimeZone, Utc};
+    /// # use rinfluxdb_types::{Columns, Value};
+    /// # use rinfluxdb_lineprotocol::{FieldValue, Measurement, TagValue};
+    /// # use rinfluxdb_dataframe::DataFrame;
+    /// let index = vec![
+    ///     Utc.with_ymd_and_hms(2021, 3, 4, 17, 0, 0).unwrap(),
+    ///     Utc.with_ymd_and_hms(2021, 3, 4, 18, 0, 0).unwrap(),
+    /// ];
+    /// let mut columns = Columns::new();
+    /// columns.insert("temperature".to_string(), vec![Value::Float(28.4), Value::Null]);
+    /// columns.insert("room".to_string(), vec![Value::String("kitchen".into()), Value::Null]);
+    /// let dataframe = DataFrame::try_from(("environment".to_string(), index, columns))?;
+    ///
+    /// let lines = dataframe.to_lines("environment_1h", &["room"]);
+    ///
+    /// assert_eq!(lines.len(), 1);
+    /// assert_eq!(lines[0].measurement(), &Measurement::from("environment_1h"));
+    /// assert_eq!(lines[0].tag("room"), Some(&TagValue::from("kitchen")));
+    /// assert_eq!(lines[0].field("temperature"), Some(&FieldValue::Float(28.4)));
+    /// assert_eq!(lines[0].timestamp(), Some(&Utc.with_ymd_and_hms(2021, 3, 4, 17, 0, 0).unwrap()));
+    /// # Ok::<(), rinfluxdb_types::DataFrameError>(())
+    /// ```
+    pub fn to_lines<M>(&self, measurement: M, tag_columns: &[&str]) -> Vec<Line>
+    where
+        M: Into<Measurement>,
+    {
+        let measurement = measurement.into();
+
+        let mut columns: Vec<(&String, &Column)> = self.columns.iter().collect();
+        columns.sort_by_key(|(name, _)| *name);
+
+        self.index
+            .iter()
+            .enumerate()
+            .filter_map(|(i, time)| {
+                let mut line = Line::new(measurement.clone());
+                let mut has_fields = false;
+                for (name, column) in columns.iter() {
+                    let value = column.value(i);
+                    if value.is_null() {
+                        continue;
+                    }
+                    if tag_columns.contains(&name.as_str()) {
+                        let value = value.to_string();
+                        if !value.is_empty() {
+                            line.insert_tag(name.as_str(), value);
+                        }
+                    } else if let Ok(value) = FieldValue::try_from(value) {
+                        line.insert_field(name.as_str(), value);
+                        has_fields = true;
+                    }
+                }
+                line.set_timestamp(*time);
+                if has_fields {
+                    Some(line)
+                } else {
+                    None
+                }
+            })
+            .collect()
+    }
 }
 
 impl fmt::Display for DataFrame {
@@ -85,53 +688,37 @@ impl fmt::Display for DataFrame {
     }
 }
 
-impl TryFrom<(String, Vec<DateTime<Utc>>, HashMap<String, Vec<Value>>)> for DataFrame {
+impl TryFrom<(String, Vec<DateTime<Utc>>, Columns)> for DataFrame {
     type Error = DataFrameError;
 
     fn try_from(
-        (name, index, columns): (String, Vec<DateTime<Utc>>, HashMap<String, Vec<Value>>),
+        (name, index, columns): (String, Vec<DateTime<Utc>>, Columns),
     ) -> Result<Self, Self::Error> {
-        let columns: HashMap<String, Result<Column, Self::Error>> = columns
+        let columns: IndexMap<String, Result<Column, Self::Error>> = columns
             .into_iter()
             .map(|(name, column)| {
-                let column = match column.first() {
-                    Some(Value::Float(_)) => Ok(Column::Float(
-                        column
-                            .into_iter()
-                            .map(|element| element.into_f64())
-                            .collect(),
-                    )),
-                    Some(Value::Integer(_)) => Ok(Column::Integer(
-                        column
-                            .into_iter()
-                            .map(|element| element.into_i64())
-                            .collect(),
-                    )),
-                    Some(Value::UnsignedInteger(_)) => Ok(Column::UnsignedInteger(
-                        column
-                            .into_iter()
-                            .map(|element| element.into_u64())
-                            .collect(),
-                    )),
-                    Some(Value::String(_)) => Ok(Column::String(
-                        column
-                            .into_iter()
-                            .map(|element| element.into_string())
-                            .collect(),
-                    )),
-                    Some(Value::Boolean(_)) => Ok(Column::Boolean(
-                        column
-                            .into_iter()
-                            .map(|element| element.into_boolean())
-                            .collect(),
-                    )),
-                    Some(Value::Timestamp(_)) => Ok(Column::Timestamp(
-                        column
-                            .into_iter()
-                            .map(|element| element.into_timestamp())
-                            .collect(),
-                    )),
-                    None => Err(DataFrameError::Creation),
+                let column = match column.iter().find(|value| !value.is_null()) {
+                    Some(Value::Float(_)) => {
+                        optional_values(column, Value::try_into_f64).map(Column::Float)
+                    }
+                    Some(Value::Integer(_)) => {
+                        optional_values(column, Value::try_into_i64).map(Column::Integer)
+                    }
+                    Some(Value::UnsignedInteger(_)) => {
+                        optional_values(column, Value::try_into_u64).map(Column::UnsignedInteger)
+                    }
+                    Some(Value::String(_)) => {
+                        optional_values(column, Value::try_into_string).map(Column::String)
+                    }
+                    Some(Value::Boolean(_)) => {
+                        optional_values(column, Value::try_into_boolean).map(Column::Boolean)
+                    }
+                    Some(Value::Timestamp(_)) => {
+                        optional_values(column, Value::try_into_timestamp).map(Column::Timestamp)
+                    }
+                    Some(Value::Null) => unreachable!(),
+                    None if column.is_empty() => Err(DataFrameError::Creation),
+                    None => optional_values(column, Value::try_into_f64).map(Column::Float),
                 };
                 (name, column)
             })
@@ -143,17 +730,52 @@ impl TryFrom<(String, Vec<DateTime<Utc>>, HashMap<String, Vec<Value>>)> for Data
             name,
             index,
             columns,
+            tags: HashMap::new(),
         })
     }
 }
 
-fn flatten_map<K, V, E>(map: HashMap<K, Result<V, E>>) -> Result<HashMap<K, V>, E>
+impl FromQueryResult for DataFrame {
+    type Error = DataFrameError;
+
+    /// Construct a dataframe from a query result, keeping its tags
+    ///
+    /// ```
+    /// # use std::collections::HashMap;
+    /// # use chrono::{TimeZone, Utc};
+    /// # use rinfluxdb_types::{Columns, FromQueryResult, Value};
+    /// # use rinfluxdb_dataframe::DataFrame;
+    /// let index = vec![Utc.with_ymd_and_hms(2021, 3, 4, 17, 0, 0).unwrap()];
+    /// let mut columns = Columns::new();
+    /// columns.insert("temperature".to_string(), vec![Value::Float(28.4)]);
+    /// let mut tags = HashMap::new();
+    /// tags.insert("room".to_string(), "kitchen".to_string());
+    ///
+    /// let dataframe = DataFrame::from_query_result("environment".to_string(), index, columns, Some(&tags))?;
+    ///
+    /// assert_eq!(dataframe.tags().get("room"), Some(&"kitchen".to_string()));
+    /// assert_eq!(dataframe.to_long_format()[0].tags.get("room"), Some(&"kitchen".to_string()));
+    /// # Ok::<(), rinfluxdb_types::DataFrameError>(())
+    /// ```
+    fn from_query_result(
+        name: String,
+        index: Vec<DateTime<Utc>>,
+        columns: Columns,
+        tags: Option<&HashMap<String, String>>,
+    ) -> Result<Self, Self::Error> {
+        let mut dataframe = Self::try_from((name, index, columns))?;
+        dataframe.tags = tags.cloned().unwrap_or_default();
+        Ok(dataframe)
+    }
+}
+
+fn flatten_map<K, V, E>(map: IndexMap<K, Result<V, E>>) -> Result<IndexMap<K, V>, E>
 where
     K: Eq + std::hash::Hash,
     E: std::error::Error,
 {
     map.into_iter()
-        .try_fold(HashMap::new(), |mut accumulator, (name, column)| {
+        .try_fold(IndexMap::new(), |mut accumulator, (name, column)| {
             let column = column?;
             accumulator.insert(name, column);
             Ok(accumulator)
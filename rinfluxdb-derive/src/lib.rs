@@ -0,0 +1,238 @@
+// Copyright Claudio Mattera 2021.
+// Distributed under the MIT License or Apache 2.0 License at your option.
+// See accompanying files License-MIT.txt and License-Apache-2.0, or online at
+// https://opensource.org/licenses/MIT
+// https://opensource.org/licenses/Apache-2.0
+
+//! Derive macros for InfluxDB line protocol
+//!
+//! This crate is re-exported by `rinfluxdb-lineprotocol` when its feature
+//! `derive` is enabled, and should not be used directly.
+
+use proc_macro::TokenStream;
+
+use proc_macro2::{Span, TokenStream as TokenStream2};
+
+use quote::quote;
+
+use syn::spanned::Spanned;
+use syn::{
+    parse_macro_input, Attribute, Data, DeriveInput, Error, Fields, Lit, Meta, NestedMeta, Type,
+};
+
+/// Derive `IntoLine` for a struct with named fields
+///
+/// The struct is converted to a line whose measurement is the name of the
+/// struct, and where each struct field is a line field with the same name.
+/// The following attributes customize the conversion.
+///
+/// * `#[influx(measurement = "name")]` on the struct sets the measurement.
+/// * `#[influx(tag)]` on a field stores it as a tag.
+/// * `#[influx(timestamp)]` on a field of type `DateTime<Utc>` uses it as the
+///   line timestamp.
+/// * `#[influx(rename = "name")]` on a field sets the field or tag name.
+/// * `#[influx(skip)]` on a field ignores it.
+///
+/// Fields of type `Option<T>` are omitted from the line when they are `None`.
+///
+/// The generated code refers to crate `rinfluxdb_lineprotocol`, which must be
+/// a dependency of the crate using the derive macro.
+#[proc_macro_derive(IntoLine, attributes(influx))]
+pub fn derive_into_line(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand_into_line(input)
+        .unwrap_or_else(Error::into_compile_error)
+        .into()
+}
+
+fn expand_into_line(input: DeriveInput) -> Result<TokenStream2, Error> {
+    let fields = match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) => &fields.named,
+            _ => {
+                return Err(Error::new_spanned(
+                    &input.ident,
+                    "IntoLine can only be derived for structs with named fields",
+                ))
+            }
+        },
+        _ => {
+            return Err(Error::new_spanned(
+                &input.ident,
+                "IntoLine can only be derived for structs",
+            ))
+        }
+    };
+
+    let mut measurement = input.ident.to_string();
+    for option in influx_options(&input.attrs)? {
+        match option.kind {
+            OptionKind::Measurement(name) => measurement = name,
+            _ => return Err(Error::new(option.span, "unexpected struct attribute")),
+        }
+    }
+
+    let mut statements = Vec::new();
+    let mut timestamp = None;
+    let mut has_fields = false;
+
+    for field in fields {
+        let ident = field.ident.as_ref().expect("Named field without a name");
+
+        let mut name = ident.to_string();
+        let mut kind = FieldKind::Field;
+        for option in influx_options(&field.attrs)? {
+            match option.kind {
+                OptionKind::Tag => kind = FieldKind::Tag,
+                OptionKind::Timestamp => kind = FieldKind::Timestamp,
+                OptionKind::Skip => kind = FieldKind::Skip,
+                OptionKind::Rename(rename) => name = rename,
+                _ => return Err(Error::new(option.span, "unexpected field attribute")),
+            }
+        }
+
+        let insert = match kind {
+            FieldKind::Skip => continue,
+            FieldKind::Timestamp => {
+                if timestamp.is_some() {
+                    return Err(Error::new_spanned(field, "multiple timestamp fields"));
+                }
+                timestamp = Some(ident);
+                continue;
+            }
+            FieldKind::Tag => quote! { line.insert_tag(#name, value); },
+            FieldKind::Field => {
+                has_fields = true;
+                quote! { line.insert_field(#name, value); }
+            }
+        };
+
+        let statement = if is_option(&field.ty) {
+            quote! {
+                if let Some(value) = self.#ident {
+                    #insert
+                }
+            }
+        } else {
+            quote! {
+                let value = self.#ident;
+                #insert
+            }
+        };
+        statements.push(statement);
+    }
+
+    if !has_fields {
+        return Err(Error::new_spanned(
+            &input.ident,
+            "IntoLine requires at least one field that is not a tag or a timestamp",
+        ));
+    }
+
+    let timestamp = timestamp.map(|ident| {
+        let field = fields
+            .iter()
+            .find(|field| field.ident.as_ref() == Some(ident))
+            .expect("Timestamp field not found");
+        if is_option(&field.ty) {
+            quote! {
+                if let Some(timestamp) = self.#ident {
+                    line.set_timestamp(timestamp);
+                }
+            }
+        } else {
+            quote! { line.set_timestamp(self.#ident); }
+        }
+    });
+
+    let name = &input.ident;
+    let (impl_generics, type_generics, where_clause) = input.generics.split_for_impl();
+
+    Ok(quote! {
+        impl #impl_generics ::rinfluxdb_lineprotocol::IntoLine for #name #type_generics #where_clause {
+            fn into_line(self) -> ::rinfluxdb_lineprotocol::Line {
+                let mut line = ::rinfluxdb_lineprotocol::Line::new(#measurement);
+                #(#statements)*
+                #timestamp
+                line
+            }
+        }
+    })
+}
+
+enum FieldKind {
+    Field,
+    Tag,
+    Timestamp,
+    Skip,
+}
+
+struct InfluxOption {
+    kind: OptionKind,
+    span: Span,
+}
+
+enum OptionKind {
+    Measurement(String),
+    Rename(String),
+    Tag,
+    Timestamp,
+    Skip,
+}
+
+/// Parse all options in `#[influx(...)]` attributes
+fn influx_options(attributes: &[Attribute]) -> Result<Vec<InfluxOption>, Error> {
+    let mut options = Vec::new();
+
+    for attribute in attributes {
+        if !attribute.path.is_ident("influx") {
+            continue;
+        }
+
+        let list = match attribute.parse_meta()? {
+            Meta::List(list) => list,
+            meta => return Err(Error::new_spanned(meta, "expected #[influx(...)]")),
+        };
+
+        for nested in list.nested {
+            let span = nested.span();
+            let kind = match nested {
+                NestedMeta::Meta(Meta::Path(path)) if path.is_ident("tag") => OptionKind::Tag,
+                NestedMeta::Meta(Meta::Path(path)) if path.is_ident("timestamp") => {
+                    OptionKind::Timestamp
+                }
+                NestedMeta::Meta(Meta::Path(path)) if path.is_ident("skip") => OptionKind::Skip,
+                NestedMeta::Meta(Meta::NameValue(pair)) if pair.path.is_ident("measurement") => {
+                    OptionKind::Measurement(string_literal(&pair.lit)?)
+                }
+                NestedMeta::Meta(Meta::NameValue(pair)) if pair.path.is_ident("rename") => {
+                    OptionKind::Rename(string_literal(&pair.lit)?)
+                }
+                nested => return Err(Error::new_spanned(nested, "unknown influx attribute")),
+            };
+            options.push(InfluxOption { kind, span });
+        }
+    }
+
+    Ok(options)
+}
+
+fn string_literal(literal: &Lit) -> Result<String, Error> {
+    match literal {
+        Lit::Str(string) => Ok(string.value()),
+        literal => Err(Error::new_spanned(literal, "expected a string literal")),
+    }
+}
+
+/// Check whether a type is syntactically an `Option`
+fn is_option(ty: &Type) -> bool {
+    match ty {
+        Type::Path(path) => path
+            .path
+            .segments
+            .last()
+            .map(|segment| segment.ident == "Option")
+            .unwrap_or(false),
+        _ => false,
+    }
+}
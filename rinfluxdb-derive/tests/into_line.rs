@@ -0,0 +1,77 @@
+// Copyright Claudio Mattera 2021.
+// Distributed under the MIT License or Apache 2.0 License at your option.
+// See accompanying files License-MIT.txt and License-Apache-2.0, or online at
+// https://opensource.org/licenses/MIT
+// https://opensource.org/licenses/Apache-2.0
+
+use chrono::{DateTime, TimeZone, Utc};
+
+use rinfluxdb_derive::IntoLine;
+use rinfluxdb_lineprotocol::IntoLine as _;
+
+#[derive(IntoLine)]
+#[influx(measurement = "indoor_environment")]
+struct Reading {
+    #[influx(tag)]
+    room: String,
+    temperature: f64,
+    #[influx(rename = "rh")]
+    humidity: Option<i64>,
+    #[influx(skip)]
+    #[allow(dead_code)]
+    note: Vec<String>,
+    #[influx(timestamp)]
+    time: DateTime<Utc>,
+}
+
+#[derive(IntoLine)]
+struct Status<'a> {
+    #[influx(tag)]
+    host: &'a str,
+    online: bool,
+    #[influx(timestamp)]
+    time: Option<DateTime<Utc>>,
+}
+
+#[test]
+fn derive_with_attributes() {
+    let reading = Reading {
+        room: "bedroom".into(),
+        temperature: 21.5,
+        humidity: Some(40),
+        note: vec![],
+        time: Utc.with_ymd_and_hms(2021, 3, 4, 17, 0, 0).unwrap(),
+    };
+
+    assert_eq!(
+        reading.into_line().to_string(),
+        "indoor_environment,room=bedroom rh=40i,temperature=21.5 1614877200000000000",
+    );
+}
+
+#[test]
+fn derive_skips_missing_values() {
+    let reading = Reading {
+        room: "kitchen".into(),
+        temperature: 23.0,
+        humidity: None,
+        note: vec!["ignored".into()],
+        time: Utc.with_ymd_and_hms(2021, 3, 4, 17, 0, 0).unwrap(),
+    };
+
+    assert_eq!(
+        reading.into_line().to_string(),
+        "indoor_environment,room=kitchen temperature=23 1614877200000000000",
+    );
+}
+
+#[test]
+fn derive_with_defaults() {
+    let status = Status {
+        host: "server01",
+        online: true,
+        time: None,
+    };
+
+    assert_eq!(status.into_line().to_string(), "Status,host=server01 online=true");
+}
@@ -4,11 +4,29 @@
 // https://opensource.org/licenses/MIT
 // https://opensource.org/licenses/Apache-2.0
 
+use std::time::Duration;
+
 use thiserror::Error;
 
+use chrono::{DateTime, SecondsFormat, Utc};
+
+use serde_json::{json, Value as JsonValue};
+
+use url::Url;
+
+use reqwest::header::{HeaderMap, RETRY_AFTER};
+use reqwest::StatusCode;
+
+use rinfluxdb_types::{parse_retry_after, MetricsObserver, Ping, TimeSeries, TransportError, Value};
+
+use super::dialect::Dialect;
+use super::query::Query;
+use super::querybuilder::QueryBuilder;
 use super::response::ResponseError;
 
+#[cfg(feature = "client-async")]
 pub mod r#async;
+#[cfg(feature = "client-blocking")]
 pub mod blocking;
 
 /// An error occurred during interfacing with an InfluxDB server
@@ -22,6 +40,10 @@ pub enum ClientError {
     #[error("URL parse error")]
     UrlError(#[from] url::ParseError),
 
+    /// Error occurred within a custom transport
+    #[error("Transport error: {0}")]
+    TransportError(TransportError),
+
     /// Error occurred while parsing a datetime
     #[error("Chrono parse error")]
     ParseDatetimeError(#[from] chrono::ParseError),
@@ -29,4 +51,291 @@ pub enum ClientError {
     /// Error occurred while parsing format
     #[error("Format parse error")]
     ResponseError(#[from] ResponseError),
+
+    /// A row could not be deserialized
+    #[error("Deserialization error")]
+    DeserializeError(#[from] serde_json::Error),
+
+    /// The request requires an organization, but the client has none
+    #[error("Missing organization")]
+    MissingOrganization,
+
+    /// The organization of the client does not exist
+    #[error("Organization {0} not found")]
+    OrganizationNotFound(String),
+
+    /// An expected tag was missing
+    #[error("Missing tag \"{0}\"")]
+    ExpectedTagError(String),
+
+    /// Signing in did not return a session cookie
+    #[error("Missing session cookie")]
+    MissingSessionCookie,
+
+    /// Server is rejecting requests because too many were sent
+    #[error("Rate limited")]
+    RateLimited {
+        /// How long to wait before sending the next request, from header
+        /// `Retry-After`
+        retry_after: Option<Duration>,
+    },
+}
+
+/// The default value of header `User-Agent`, including the crate version
+const DEFAULT_USER_AGENT: &str = concat!("rinfluxdb/", env!("CARGO_PKG_VERSION"));
+
+fn user_agent(user_agent: Option<String>, application: Option<String>) -> String {
+    let user_agent = user_agent.unwrap_or_else(|| DEFAULT_USER_AGENT.to_string());
+    match application {
+        Some(application) => format!("{} {}", user_agent, application),
+        None => user_agent,
+    }
+}
+
+/// The organization owning the queried buckets, required by InfluxDB 2.x
+#[derive(Clone, Debug)]
+enum OrganizationRef {
+    Name(String),
+    Id(String),
+}
+
+impl OrganizationRef {
+    /// Return the name of the request parameter and its value
+    fn parameter(&self) -> (&'static str, &str) {
+        match self {
+            OrganizationRef::Name(name) => ("org", name),
+            OrganizationRef::Id(id) => ("orgID", id),
+        }
+    }
+}
+
+/// Return the URL for a Flux query, including the organization if known
+fn query_url(base_url: &Url, organization: Option<&OrganizationRef>) -> Result<Url, url::ParseError> {
+    let mut url = base_url.join("/api/v2/query")?;
+    if let Some(organization) = organization {
+        let (key, value) = organization.parameter();
+        url.query_pairs_mut().append_pair(key, value);
+    }
+    Ok(url)
+}
+
+/// Return the URL of an API endpoint, escaping each path segment
+fn api_url(base_url: &Url, segments: &[&str]) -> Result<Url, url::ParseError> {
+    let mut url = base_url.join("/api/v2")?;
+    url.path_segments_mut()
+        .map_err(|_| url::ParseError::RelativeUrlWithCannotBeABaseBase)?
+        .extend(segments);
+    Ok(url)
+}
+
+/// Return the JSON body for a Flux query
+///
+/// Parameters bound to the query are sent as an AST of statement
+/// `option params = {...}`, which is prepended to the query by the server.
+fn query_body(query: &Query, dialect: &Dialect) -> String {
+    let annotations: Vec<&str> = dialect
+        .annotations
+        .iter()
+        .map(|annotation| annotation.as_str())
+        .collect();
+    let mut body = json!({
+        "query": query.as_ref(),
+        "type": "flux",
+        "dialect": {
+            "annotations": annotations,
+            "delimiter": char::from(dialect.delimiter).to_string(),
+            "header": dialect.header,
+        },
+    });
+
+    if !query.parameters().is_empty() {
+        let properties: Vec<JsonValue> = query
+            .parameters()
+            .iter()
+            .filter_map(|(name, value)| {
+                literal(value).map(|value| {
+                    json!({
+                        "type": "Property",
+                        "key": {"type": "Identifier", "name": name},
+                        "value": value,
+                    })
+                })
+            })
+            .collect();
+        body["extern"] = json!({
+            "type": "File",
+            "package": null,
+            "imports": null,
+            "body": [{
+                "type": "OptionStatement",
+                "assignment": {
+                    "type": "VariableAssignment",
+                    "id": {"type": "Identifier", "name": "params"},
+                    "init": {"type": "ObjectExpression", "properties": properties},
+                },
+            }],
+        });
+    }
+
+    body.to_string()
+}
+
+/// Return the AST of a Flux literal, or nothing for missing values
+fn literal(value: &Value) -> Option<JsonValue> {
+    let literal = match value {
+        Value::Float(value) => json!({"type": "FloatLiteral", "value": value}),
+        Value::Integer(value) => json!({"type": "IntegerLiteral", "value": value.to_string()}),
+        Value::UnsignedInteger(value) => {
+            json!({"type": "UnsignedIntegerLiteral", "value": value.to_string()})
+        }
+        Value::String(value) => json!({"type": "StringLiteral", "value": value}),
+        Value::Boolean(value) => json!({"type": "BooleanLiteral", "value": value}),
+        Value::Timestamp(value) => json!({
+            "type": "DateTimeLiteral",
+            "value": value.to_rfc3339_opts(SecondsFormat::AutoSi, true),
+        }),
+        Value::Null => return None,
+    };
+    Some(literal)
+}
+
+/// Check whether a client error was caused by missing or expired credentials
+fn is_unauthorized(error: &ClientError) -> bool {
+    match error {
+        ClientError::ReqwestError(error) => error.status() == Some(StatusCode::UNAUTHORIZED),
+        _ => false,
+    }
+}
+
+/// Parse header `Retry-After`, either as a number of seconds or as a date
+fn retry_after(headers: &HeaderMap) -> Option<Duration> {
+    parse_retry_after(headers.get(RETRY_AFTER)?.to_str().ok()?)
+}
+
+/// Return an error if a response asks the client to slow down
+///
+/// Status 429 always does, while status 503 does only with header
+/// `Retry-After`.
+fn rate_limited(status: StatusCode, headers: &HeaderMap) -> Option<ClientError> {
+    let is_rate_limited = status == StatusCode::TOO_MANY_REQUESTS
+        || (status == StatusCode::SERVICE_UNAVAILABLE && headers.contains_key(RETRY_AFTER));
+    if is_rate_limited {
+        Some(ClientError::RateLimited {
+            retry_after: retry_after(headers),
+        })
+    } else {
+        None
+    }
+}
+
+/// Check whether a client error is likely to be temporary
+///
+/// Errors returned by custom transports are always considered temporary.
+#[cfg(feature = "client-blocking")]
+fn is_transient_error(error: &ClientError) -> bool {
+    match error {
+        ClientError::ReqwestError(error) => is_transient(error),
+        ClientError::TransportError(_) => true,
+        _ => false,
+    }
+}
+
+/// Check whether a request error is likely to be temporary
+#[cfg(not(target_arch = "wasm32"))]
+fn is_transient(error: &reqwest::Error) -> bool {
+    error.is_connect() || error.is_timeout() || error.is_request()
+}
+
+/// Check whether a request error is likely to be temporary
+///
+/// Browsers do not report connection errors separately.
+#[cfg(target_arch = "wasm32")]
+fn is_transient(error: &reqwest::Error) -> bool {
+    error.is_timeout() || error.is_request()
+}
+
+/// Notify an observer of the outcome of a query
+///
+/// Parameter `result` contains the size of the response body.
+fn observe_query(
+    observer: Option<&dyn MetricsObserver>,
+    query_bytes: usize,
+    result: Result<usize, &ClientError>,
+    elapsed: Duration,
+) {
+    if let Some(observer) = observer {
+        match result {
+            Ok(response_bytes) => observer.on_query_success(query_bytes, response_bytes, elapsed),
+            Err(error) => observer.on_query_failure(query_bytes, elapsed, error),
+        }
+    }
+}
+
+/// Create a query for the most recent values of a field in every series
+///
+/// Records are read since the Unix epoch, since Flux requires a range.
+fn last_reading_query(bucket: &str, measurement: &str, field: &str, tags: &[(&str, &str)]) -> Query {
+    // Flux requires a range, so start from before any storable instant
+    let mut builder = QueryBuilder::from(bucket)
+        .range_start(rinfluxdb_types::Duration::Days(-100_000))
+        .measurement(measurement)
+        .field(field);
+    for (name, value) in tags {
+        builder = builder.tag(name, value);
+    }
+    builder.aggregate("last").build()
+}
+
+/// Return the most recent value of a field among all series
+fn parse_last_reading(mut series: TimeSeries, field: &str) -> Option<(DateTime<Utc>, Value)> {
+    series
+        .0
+        .remove(field)?
+        .into_iter()
+        .max_by_key(|(instant, _value)| *instant)
+}
+
+/// Extract the server version and build type from response headers
+fn ping_from_headers(headers: &reqwest::header::HeaderMap) -> Ping {
+    let header = |name: &str| {
+        headers
+            .get(name)
+            .and_then(|value| value.to_str().ok())
+            .map(|value| value.to_string())
+    };
+    Ping {
+        version: header("X-Influxdb-Version"),
+        build: header("X-Influxdb-Build"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn last_reading_query_with_tags() {
+        let expected = r#"from(bucket: "house")
+  |> range(start: -100000d)
+  |> filter(fn: (r) =>
+    r._measurement == "indoor_environment"
+  )
+  |> filter(fn: (r) =>
+    r._field == "temperature"
+  )
+  |> filter(fn: (r) =>
+    r["room"] == "bedroom \"north\""
+  )
+  |> last()
+  |> yield()"#;
+
+        let actual = last_reading_query(
+            "house",
+            "indoor_environment",
+            "temperature",
+            &[("room", "bedroom \"north\"")],
+        );
+
+        assert_eq!(actual.as_ref(), expected);
+    }
 }
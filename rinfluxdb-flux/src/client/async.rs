@@ -5,80 +5,1192 @@
 // https://opensource.org/licenses/Apache-2.0
 
 use std::collections::HashMap;
-use std::convert::TryFrom;
+use std::sync::Arc;
+#[cfg(not(target_arch = "wasm32"))]
+use std::time::Duration;
 
 use tracing::*;
 
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, SecondsFormat, Utc};
 
-use reqwest::header::{HeaderMap, HeaderValue, ACCEPT, CONTENT_TYPE};
+use serde::de::DeserializeOwned;
+
+use serde_json::json;
+
+use reqwest::header::{HeaderMap, HeaderValue, ACCEPT, CONTENT_TYPE, SET_COOKIE};
 use reqwest::Client as ReqwestClient;
 use reqwest::ClientBuilder as ReqwestClientBuilder;
+use reqwest::RequestBuilder as ReqwestRequestBuilder;
+use reqwest::Response as ReqwestResponse;
+#[cfg(not(target_arch = "wasm32"))]
+use reqwest::Proxy;
+use reqwest::StatusCode;
+#[cfg(any(
+    feature = "default-tls",
+    feature = "native-tls",
+    feature = "native-tls-vendored",
+    feature = "rustls-tls",
+    feature = "rustls-tls-native-roots",
+))]
+use reqwest::Certificate;
+#[cfg(any(
+    feature = "native-tls",
+    feature = "native-tls-vendored",
+    feature = "rustls-tls",
+    feature = "rustls-tls-native-roots",
+))]
+use reqwest::Identity;
+
+#[cfg(not(target_arch = "wasm32"))]
+use tokio::time::sleep;
+
+#[cfg(target_arch = "wasm32")]
+use gloo_timers::future::sleep;
 
 use url::Url;
 
-use rinfluxdb_types::Value;
+use async_trait::async_trait;
 
-use super::ClientError;
+use futures_util::stream::{self, Stream, StreamExt};
+
+use rinfluxdb_types::{
+    apply_authentication, cookie_from_set_cookie_headers, record_response, AsyncQueryClient, Authentication, Failover, FailoverStrategy, FromQueryResult, MetricsObserver, Ping,
+    QueryClientError, RetryPolicy, Rows, Session, Stopwatch, TimeSeries, TimeWindow, TimeWindows, Value, SIGNIN_PATH,
+};
+
+use super::{
+    api_url, is_unauthorized, is_transient, observe_query, ping_from_headers, last_reading_query, parse_last_reading, query_body, query_url, rate_limited, retry_after, user_agent, ClientError,
+    OrganizationRef,
+};
 
 use super::super::query::Query;
-use super::super::response::{from_str, ResponseError};
+use super::super::delete::DeletePredicate;
+use super::super::dialect::Dialect;
+use super::super::response::{from_str_merged_with_dialect, from_str_with_dialect, ResponseError};
+use super::super::types::{TaggedDataframe, TagsMap};
+use super::super::management::{
+    Bucket, BucketUpdate, Buckets, NewBucket, Organization, OrganizationUpdate, Organizations,
+};
+use super::super::tasks::{LogEvent, Logs, NewTask, Run, Runs, Task, TaskStatus, Tasks};
 
 /// A client for performing frequent Flux queries in a convenient way
 #[derive(Debug)]
 pub struct Client {
     client: ReqwestClient,
     base_url: Url,
-    credentials: Option<(String, String)>,
+    fallback_urls: Vec<Url>,
+    failover: Failover,
+    authentication: Authentication,
+    session: Session,
+    organization: Option<OrganizationRef>,
+    dialect: Dialect,
+    retry_policy: RetryPolicy,
+    metrics_observer: Option<Arc<dyn MetricsObserver>>,
 }
 
-impl Client {
-    pub fn new(base_url: Url, credentials: Option<(String, String)>) -> Result<Self, ClientError> {
+/// A builder for clients
+///
+/// Clients send a `User-Agent` header containing the crate version, such as
+/// `rinfluxdb/0.2.0`.
+/// An application identifier can be appended to it, or it can be replaced
+/// altogether.
+///
+/// ```
+/// use url::Url;
+/// use rinfluxdb_flux::r#async::ClientBuilder;
+///
+/// let client = ClientBuilder::new(Url::parse("https://example.com/")?)
+///     .credentials("username", "password")
+///     .application("collector/1.4.2")
+///     .build()?;
+/// # Ok::<(), rinfluxdb_flux::ClientError>(())
+/// ```
+#[derive(Debug)]
+pub struct ClientBuilder {
+    base_url: Url,
+    fallback_urls: Vec<Url>,
+    failover_strategy: FailoverStrategy,
+    authentication: Authentication,
+    organization: Option<OrganizationRef>,
+    dialect: Dialect,
+    retry_policy: RetryPolicy,
+    metrics_observer: Option<Arc<dyn MetricsObserver>>,
+    user_agent: Option<String>,
+    http: ReqwestClientBuilder,
+    application: Option<String>,
+}
+
+impl ClientBuilder {
+    /// Create a new builder for a client to an InfluxDB server
+    pub fn new(base_url: Url) -> Self {
+        Self {
+            base_url,
+            fallback_urls: Vec::new(),
+            failover_strategy: FailoverStrategy::default(),
+            authentication: Authentication::None,
+            organization: None,
+            dialect: Dialect::default(),
+            retry_policy: RetryPolicy::never(),
+            metrics_observer: None,
+            user_agent: None,
+            application: None,
+            http: ReqwestClientBuilder::new(),
+        }
+    }
+
+    /// Set username and password for servers that require authentication
+    pub fn credentials<T, S>(mut self, username: T, password: S) -> Self
+    where
+        T: Into<String>,
+        S: Into<String>,
+    {
+        self.authentication = Authentication::Basic {
+            username: username.into(),
+            password: password.into(),
+        };
+        self
+    }
+
+    /// Set the API token for servers that require token authentication
+    ///
+    /// The token is sent in header `Authorization: Token <token>`, as
+    /// required by InfluxDB 2.x.
+    pub fn token<T>(mut self, token: T) -> Self
+    where
+        T: Into<String>,
+    {
+        self.authentication = Authentication::Token(token.into());
+        self
+    }
+
+    /// Set the authentication method
+    pub fn authentication(mut self, authentication: Authentication) -> Self {
+        self.authentication = authentication;
+        self
+    }
+
+    /// Set the name of the organization owning the queried buckets
+    ///
+    /// InfluxDB 2.x requires either the organization name or its identifier
+    /// to be sent with every query.
+    pub fn organization<T>(mut self, organization: T) -> Self
+    where
+        T: Into<String>,
+    {
+        self.organization = Some(OrganizationRef::Name(organization.into()));
+        self
+    }
+
+    /// Set the identifier of the organization owning the queried buckets
+    ///
+    /// This replaces the organization name, if it was set.
+    pub fn organization_id<T>(mut self, organization_id: T) -> Self
+    where
+        T: Into<String>,
+    {
+        self.organization = Some(OrganizationRef::Id(organization_id.into()));
+        self
+    }
+
+    /// Set the format of CSV responses
+    ///
+    /// By default, all annotations are requested.
+    /// Responses are parsed according to the dialect.
+    pub fn dialect(mut self, dialect: Dialect) -> Self {
+        self.dialect = dialect;
+        self
+    }
+
+    /// Add URLs of other servers to fail over to
+    ///
+    /// Queries are sent to the next URL when the connection fails or when the
+    /// server responds with a 5xx status code, after retrying them according
+    /// to the retry policy.
+    /// Other requests are always sent to the base URL.
+    pub fn fallback_urls<I>(mut self, urls: I) -> Self
+    where
+        I: IntoIterator<Item = Url>,
+    {
+        self.fallback_urls.extend(urls);
+        self
+    }
+
+    /// Set the strategy for choosing among the base URL and the fallback URLs
+    ///
+    /// By default, requests are sent to the base URL first.
+    pub fn failover_strategy(mut self, strategy: FailoverStrategy) -> Self {
+        self.failover_strategy = strategy;
+        self
+    }
+
+    /// Set the policy for retrying failed requests
+    ///
+    /// By default, requests are never retried.
+    pub fn retry_policy(mut self, retry_policy: RetryPolicy) -> Self {
+        self.retry_policy = retry_policy;
+        self
+    }
+
+    /// Notify an observer of writes and queries sent by the client
+    ///
+    /// By default, no observer is notified.
+    pub fn metrics_observer<T>(mut self, observer: T) -> Self
+    where
+        T: MetricsObserver + 'static,
+    {
+        self.metrics_observer = Some(Arc::new(observer));
+        self
+    }
+
+    /// Set the timeout for whole requests, from connecting to reading the
+    /// response body
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.http = self.http.timeout(timeout);
+        self
+    }
+
+    /// Set the timeout for connecting to the server
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn connect_timeout(mut self, timeout: Duration) -> Self {
+        self.http = self.http.connect_timeout(timeout);
+        self
+    }
+
+    /// Set the maximum number of idle connections kept open to each host
+    ///
+    /// By default, there is no limit.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn pool_max_idle_per_host(mut self, max: usize) -> Self {
+        self.http = self.http.pool_max_idle_per_host(max);
+        self
+    }
+
+    /// Set how long idle connections are kept open
+    ///
+    /// By default, idle connections are closed after 90 seconds.
+    /// Passing `None` keeps them open indefinitely.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn pool_idle_timeout<D>(mut self, timeout: D) -> Self
+    where
+        D: Into<Option<Duration>>,
+    {
+        self.http = self.http.pool_idle_timeout(timeout);
+        self
+    }
+
+    /// Set the interval of TCP keepalive probes
+    ///
+    /// By default, TCP keepalive is disabled.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn tcp_keepalive<D>(mut self, interval: D) -> Self
+    where
+        D: Into<Option<Duration>>,
+    {
+        self.http = self.http.tcp_keepalive(interval);
+        self
+    }
+
+    /// Use HTTP/2 without negotiating it first
+    ///
+    /// This only works with servers known to support HTTP/2, and it allows
+    /// sending concurrent requests over a single connection.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn http2_prior_knowledge(mut self) -> Self {
+        self.http = self.http.http2_prior_knowledge();
+        self
+    }
+
+    /// Send requests through a proxy
+    ///
+    /// By default, proxies are read from the environment variables
+    /// `HTTP_PROXY`, `HTTPS_PROXY` and `ALL_PROXY`.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn proxy(mut self, proxy: Proxy) -> Self {
+        self.http = self.http.proxy(proxy);
+        self
+    }
+
+    /// Ignore proxies set in the environment
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn no_proxy(mut self) -> Self {
+        self.http = self.http.no_proxy();
+        self
+    }
+
+    /// Trust an additional root certificate, such as a private CA
+    #[cfg(any(
+        feature = "default-tls",
+        feature = "native-tls",
+        feature = "native-tls-vendored",
+        feature = "rustls-tls",
+        feature = "rustls-tls-native-roots",
+    ))]
+    pub fn add_root_certificate(mut self, certificate: Certificate) -> Self {
+        self.http = self.http.add_root_certificate(certificate);
+        self
+    }
+
+    /// Authenticate to the server with a client certificate
+    ///
+    /// This requires either feature `native-tls` or a Rustls backend.
+    #[cfg(any(
+        feature = "native-tls",
+        feature = "native-tls-vendored",
+        feature = "rustls-tls",
+        feature = "rustls-tls-native-roots",
+    ))]
+    pub fn identity(mut self, identity: Identity) -> Self {
+        self.http = self.http.identity(identity);
+        self
+    }
+
+    /// Accept invalid server certificates
+    ///
+    /// This is dangerous, and should only be used for testing against servers
+    /// with self-signed certificates.
+    #[cfg(any(
+        feature = "default-tls",
+        feature = "native-tls",
+        feature = "native-tls-vendored",
+        feature = "rustls-tls",
+        feature = "rustls-tls-native-roots",
+    ))]
+    pub fn danger_accept_invalid_certs(mut self, accept: bool) -> Self {
+        self.http = self.http.danger_accept_invalid_certs(accept);
+        self
+    }
+
+    /// Replace the default `User-Agent` header
+    pub fn user_agent<T>(mut self, user_agent: T) -> Self
+    where
+        T: Into<String>,
+    {
+        self.user_agent = Some(user_agent.into());
+        self
+    }
+
+    /// Append an application identifier to the `User-Agent` header
+    pub fn application<T>(mut self, application: T) -> Self
+    where
+        T: Into<String>,
+    {
+        self.application = Some(application.into());
+        self
+    }
+
+    /// Build the client
+    pub fn build(self) -> Result<Client, ClientError> {
         let mut headers = HeaderMap::new();
         headers.insert(ACCEPT, HeaderValue::from_static("application/csv"));
-        headers.insert(CONTENT_TYPE, HeaderValue::from_static("application/vnd.flux"));
+        headers.insert(CONTENT_TYPE, HeaderValue::from_static("application/json"));
 
-        let client = ReqwestClientBuilder::new()
+        let client = self
+            .http
             .default_headers(headers)
+            .user_agent(user_agent(self.user_agent, self.application))
             .build()?;
 
-        Ok(Self {
+        Ok(Client {
             client,
-            base_url,
-            credentials,
+            base_url: self.base_url,
+            fallback_urls: self.fallback_urls,
+            failover: Failover::new(self.failover_strategy),
+            authentication: self.authentication,
+            session: Session::default(),
+            organization: self.organization,
+            dialect: self.dialect,
+            retry_policy: self.retry_policy,
+            metrics_observer: self.metrics_observer,
         })
     }
+}
+
+impl Client {
+    /// Create a new client to an InfluxDB server
+    ///
+    /// Parameter `credentials` can be used to provide username and password if
+    /// the server requires authentication.
+    pub fn new(base_url: Url, credentials: Option<(String, String)>) -> Result<Self, ClientError> {
+        let mut builder = ClientBuilder::new(base_url);
+        if let Some((username, password)) = credentials {
+            builder = builder.credentials(username, password);
+        }
+        builder.build()
+    }
+
+    /// Create a new client to an InfluxDB 2.x server
+    ///
+    /// Queries are sent on behalf of organization `organization`, and
+    /// authenticated with API token `token`.
+    pub fn new_v2<T, S>(base_url: Url, organization: T, token: S) -> Result<Self, ClientError>
+    where
+        T: Into<String>,
+        S: Into<String>,
+    {
+        ClientBuilder::new(base_url)
+            .organization(organization)
+            .token(token)
+            .build()
+    }
 
+    /// Query the server and return a single dataframe
+    ///
+    /// All tables in the response are merged, as in
+    /// [`from_str_merged`](crate::from_str_merged).
+    /// Use [`fetch_tagged_readings`](Client::fetch_tagged_readings) for
+    /// queries returning tables for different series.
     #[instrument(
         name = "Fetching readings",
-        skip(self),
+        skip(self, query),
+        fields(
+            query_length = query.as_ref().len(),
+            status = field::Empty,
+            elapsed_ms = field::Empty,
+        ),
     )]
     pub async fn fetch_readings<DF, E>(&self, query: Query) -> Result<DF, ClientError>
     where
-        DF: TryFrom<(String, Vec<DateTime<Utc>>, HashMap<String, Vec<Value>>), Error = E>,
+        DF: FromQueryResult<Error = E>,
+        E: Into<ResponseError>,
+    {
+        let text = self.fetch_text(&query).await?;
+
+        let dataframe = from_str_merged_with_dialect(&text, &self.dialect)?;
+
+        Ok(dataframe)
+    }
+
+    /// Query the server and return a dataframe for each group of tables
+    ///
+    /// Tables are grouped by measurement and group key, as in
+    /// [`from_str`](crate::from_str), and each dataframe is returned with
+    /// the values of its group key columns.
+    #[instrument(
+        name = "Fetching tagged readings",
+        skip(self, query),
+        fields(
+            query_length = query.as_ref().len(),
+            status = field::Empty,
+            elapsed_ms = field::Empty,
+        ),
+    )]
+    pub async fn fetch_tagged_readings<DF, E>(&self, query: Query) -> Result<Vec<TaggedDataframe<DF>>, ClientError>
+    where
+        DF: FromQueryResult<Error = E>,
+        E: Into<ResponseError>,
+    {
+        let text = self.fetch_text(&query).await?;
+
+        let dataframes = from_str_with_dialect(&text, &self.dialect)?;
+
+        Ok(dataframes)
+    }
+
+    /// Query the server over consecutive windows of time
+    ///
+    /// A query is created for each window by calling `query`, and the
+    /// returned stream yields windows in order, querying at most
+    /// `concurrency` windows at any time.
+    /// Each item contains the window together with its dataframe, so an
+    /// interrupted export can be resumed by passing
+    /// [`TimeWindows::resume_from`](rinfluxdb_types::TimeWindows::resume_from)
+    /// the index of the first failed window.
+    ///
+    /// Note that windows include their start and exclude their stop, as
+    /// Flux `range()`, so the query should select
+    /// `range(start: window.start, stop: window.stop)`.
+    pub fn fetch_windowed<'a, DF, E, F>(
+        &'a self,
+        windows: TimeWindows,
+        query: F,
+        concurrency: usize,
+    ) -> impl Stream<Item = (TimeWindow, Result<DF, ClientError>)> + 'a
+    where
+        DF: FromQueryResult<Error = E> + 'a,
+        E: Into<ResponseError> + 'a,
+        F: Fn(&TimeWindow) -> Query + 'a,
+    {
+        stream::iter(windows)
+            .map(move |window| {
+                let query = query(&window);
+                async move {
+                    let result = self.fetch_readings(query).await;
+                    (window, result)
+                }
+            })
+            .buffered(concurrency.max(1))
+    }
+
+    /// Query the server one window at a time, and merge the results in a
+    /// single dataframe
+    ///
+    /// A query is created for each window by calling `query`, as in
+    /// [`fetch_windowed()`](Client::fetch_windowed), and the tables of all
+    /// windows are merged as in [`from_str_merged`](crate::from_str_merged).
+    /// Windows are queried concurrently, with at most `concurrency` queries
+    /// in flight at any time.
+    pub async fn fetch_windowed_merged<DF, E, F>(
+        &self,
+        windows: TimeWindows,
+        query: F,
+        concurrency: usize,
+    ) -> Result<DF, ClientError>
+    where
+        DF: FromQueryResult<Error = E>,
         E: Into<ResponseError>,
+        F: Fn(&TimeWindow) -> Query,
     {
-        let url = self.base_url.join("/api/v2/query")?;
-        let mut request = self.client
-            .post(url);
+        let texts: Vec<_> = stream::iter(windows)
+            .map(|window| {
+                let query = query(&window);
+                async move { self.fetch_text(&query).await }
+            })
+            .buffered(concurrency.max(1))
+            .collect()
+            .await;
 
-        if let Some((username, password)) = &self.credentials {
-            request = request.basic_auth(username, Some(password));
+        let mut text = String::new();
+        for result in texts {
+            text.push_str(&result?);
+            text.push('\n');
         }
 
-        request = request.body(query.as_ref().to_owned());
+        let dataframe = from_str_merged_with_dialect(&text, &self.dialect)?;
+
+        Ok(dataframe)
+    }
+
+    /// Send a query and return the response body
+    async fn fetch_text(&self, query: &Query) -> Result<String, ClientError> {
+        let body = query_body(query, &self.dialect);
+
+        let observer = self.metrics_observer.as_deref();
+        let query_bytes = query.as_ref().len();
+        if let Some(observer) = observer {
+            observer.on_query_start(query_bytes);
+        }
+        let stopwatch = Stopwatch::start();
+        let result = self.post_query(&body).await;
+        observe_query(observer, query_bytes, result.as_ref().map(String::len), stopwatch.elapsed());
+        result
+    }
+
+    /// Query the server for each query, running queries concurrently
+    ///
+    /// At most `concurrency` queries are in flight at any time, and a limit
+    /// of zero is treated as one.
+    /// Results are returned in the same order as the queries, and each query
+    /// fails independently of the others.
+    ///
+    /// ```no_run
+    /// # use url::Url;
+    /// # use rinfluxdb_types::Duration;
+    /// # use rinfluxdb_flux::{ClientError, QueryBuilder};
+    /// # use rinfluxdb_flux::r#async::Client;
+    /// # use rinfluxdb_dataframe::DataFrame;
+    /// # async fn example() -> Result<(), ClientError> {
+    /// let client = Client::new_v2(Url::parse("https://example.com/")?, "my-org", "my-token")?;
+    ///
+    /// let queries = ["cpu", "mem", "disk"]
+    ///     .iter()
+    ///     .map(|measurement| {
+    ///         QueryBuilder::from("telegraf/autogen")
+    ///             .range_start(Duration::Minutes(-15))
+    ///             .measurement(*measurement)
+    ///             .build()
+    ///     })
+    ///     .collect();
+    ///
+    /// for result in client.fetch_many::<DataFrame, _>(queries, 2).await {
+    ///     println!("{}", result?);
+    /// }
+    /// # Ok::<(), ClientError>(())
+    /// # }
+    /// ```
+    #[instrument(
+        name = "Fetching many readings",
+        skip(self, queries),
+        fields(queries = queries.len()),
+    )]
+    pub async fn fetch_many<DF, E>(
+        &self,
+        queries: Vec<Query>,
+        concurrency: usize,
+    ) -> Vec<Result<DF, ClientError>>
+    where
+        DF: FromQueryResult<Error = E>,
+        E: Into<ResponseError>,
+    {
+        stream::iter(queries)
+            .map(|query| self.fetch_readings(query))
+            .buffered(concurrency.max(1))
+            .collect()
+            .await
+    }
+
+    /// Query the server and deserialize each row to a struct
+    ///
+    /// This is an alternative to dataframes for users that prefer their own
+    /// types.
+    /// Rows are built as in [`fetch_readings`](Client::fetch_readings), one
+    /// for each instant, and each row maps column names to struct fields.
+    /// The instant is stored in column `time` as a RFC3339 string, which can
+    /// be deserialized to a [`DateTime`](chrono::DateTime) field when
+    /// Chrono's feature `serde` is enabled.
+    ///
+    /// [`ClientError::DeserializeError`](ClientError::DeserializeError) is
+    /// returned if a row cannot be deserialized.
+    #[instrument(
+        name = "Fetching rows",
+        skip(self, query),
+        fields(
+            query_length = query.as_ref().len(),
+            status = field::Empty,
+            elapsed_ms = field::Empty,
+        ),
+    )]
+    pub async fn fetch_into<T>(&self, query: Query) -> Result<Vec<T>, ClientError>
+    where
+        T: DeserializeOwned,
+    {
+        let rows: Rows = self.fetch_readings(query).await?;
+        Ok(rows.deserialize()?)
+    }
+
+    /// Query the server for the most recent value of a field
+    ///
+    /// Records of the field in the measurement are restricted to the ones
+    /// matching all specified tags, and the most recent one is returned.
+    /// If no record matches, `None` is returned.
+    ///
+    /// Bucket, measurement, field and tags are written as string literals.
+    #[instrument(
+        name = "Fetching last reading",
+        skip(self),
+        fields(
+            status = field::Empty,
+            elapsed_ms = field::Empty,
+        ),
+    )]
+    pub async fn last_reading(
+        &self,
+        bucket: &str,
+        measurement: &str,
+        field: &str,
+        tags: &[(&str, &str)],
+    ) -> Result<Option<(DateTime<Utc>, Value)>, ClientError> {
+        let query = last_reading_query(bucket, measurement, field, tags);
+        let series: TimeSeries = self.fetch_readings(query).await?;
+        Ok(parse_last_reading(series, field))
+    }
+
+    /// Create a task in the organization of the client
+    ///
+    /// [`ClientError::MissingOrganization`](ClientError::MissingOrganization)
+    /// is returned if the client has no organization.
+    /// Requests to manage tasks are not retried.
+    #[instrument(
+        name = "Creating task",
+        skip(self),
+        fields(
+            status = field::Empty,
+            elapsed_ms = field::Empty,
+        ),
+    )]
+    pub async fn create_task(&self, task: NewTask) -> Result<Task, ClientError> {
+        let organization = self
+            .organization
+            .as_ref()
+            .ok_or(ClientError::MissingOrganization)?;
+        let request = self
+            .client
+            .post(api_url(&self.base_url, &["tasks"])?)
+            .body(task.body(organization.parameter()).to_string());
+        self.send_json(request).await
+    }
+
+    /// List the tasks of the organization of the client
+    ///
+    /// All tasks visible to the client are listed if it has no organization.
+    /// At most 500 tasks are returned.
+    pub async fn tasks(&self) -> Result<Vec<Task>, ClientError> {
+        let mut url = api_url(&self.base_url, &["tasks"])?;
+        url.query_pairs_mut().append_pair("limit", "500");
+        if let Some(organization) = &self.organization {
+            let (key, value) = organization.parameter();
+            url.query_pairs_mut().append_pair(key, value);
+        }
+        let tasks: Tasks = self.send_json(self.client.get(url)).await?;
+        Ok(tasks.tasks)
+    }
+
+    /// Enable a task, so that it is run according to its schedule
+    pub async fn enable_task(&self, id: &str) -> Result<Task, ClientError> {
+        self.set_task_status(id, TaskStatus::Active).await
+    }
+
+    /// Disable a task, so that it is not run anymore
+    pub async fn disable_task(&self, id: &str) -> Result<Task, ClientError> {
+        self.set_task_status(id, TaskStatus::Inactive).await
+    }
+
+    async fn set_task_status(&self, id: &str, status: TaskStatus) -> Result<Task, ClientError> {
+        let request = self
+            .client
+            .patch(api_url(&self.base_url, &["tasks", id])?)
+            .body(json!({ "status": status.as_str() }).to_string());
+        self.send_json(request).await
+    }
+
+    /// Delete a task
+    pub async fn delete_task(&self, id: &str) -> Result<(), ClientError> {
+        let request = self.client.delete(api_url(&self.base_url, &["tasks", id])?);
+        self.send(request).await?;
+        Ok(())
+    }
+
+    /// List the runs of a task
+    pub async fn task_runs(&self, id: &str) -> Result<Vec<Run>, ClientError> {
+        let url = api_url(&self.base_url, &["tasks", id, "runs"])?;
+        let runs: Runs = self.send_json(self.client.get(url)).await?;
+        Ok(runs.runs)
+    }
+
+    /// List the log messages of a task
+    pub async fn task_logs(&self, id: &str) -> Result<Vec<LogEvent>, ClientError> {
+        let url = api_url(&self.base_url, &["tasks", id, "logs"])?;
+        let logs: Logs = self.send_json(self.client.get(url)).await?;
+        Ok(logs.events)
+    }
+
+    /// List all organizations visible to the client
+    ///
+    /// At most 100 organizations are returned.
+    /// Requests to manage organizations and buckets are not retried.
+    pub async fn organizations(&self) -> Result<Vec<Organization>, ClientError> {
+        let mut url = api_url(&self.base_url, &["orgs"])?;
+        url.query_pairs_mut().append_pair("limit", "100");
+        let organizations: Organizations = self.send_json(self.client.get(url)).await?;
+        Ok(organizations.orgs)
+    }
+
+    /// Create an organization
+    #[instrument(
+        name = "Creating organization",
+        skip(self, name),
+        fields(
+            status = field::Empty,
+            elapsed_ms = field::Empty,
+        ),
+    )]
+    pub async fn create_organization<T>(&self, name: T) -> Result<Organization, ClientError>
+    where
+        T: Into<String>,
+    {
+        let request = self
+            .client
+            .post(api_url(&self.base_url, &["orgs"])?)
+            .body(json!({ "name": name.into() }).to_string());
+        self.send_json(request).await
+    }
+
+    /// Change the name or description of an organization
+    pub async fn update_organization(
+        &self,
+        id: &str,
+        update: OrganizationUpdate,
+    ) -> Result<Organization, ClientError> {
+        let request = self
+            .client
+            .patch(api_url(&self.base_url, &["orgs", id])?)
+            .body(json!(update).to_string());
+        self.send_json(request).await
+    }
+
+    /// Delete an organization
+    pub async fn delete_organization(&self, id: &str) -> Result<(), ClientError> {
+        let request = self.client.delete(api_url(&self.base_url, &["orgs", id])?);
+        self.send(request).await?;
+        Ok(())
+    }
+
+    /// List the buckets of the organization of the client
+    ///
+    /// All buckets visible to the client are listed if it has no
+    /// organization.
+    /// At most 100 buckets are returned.
+    pub async fn buckets(&self) -> Result<Vec<Bucket>, ClientError> {
+        let mut url = api_url(&self.base_url, &["buckets"])?;
+        url.query_pairs_mut().append_pair("limit", "100");
+        if let Some(organization) = &self.organization {
+            let (key, value) = organization.parameter();
+            url.query_pairs_mut().append_pair(key, value);
+        }
+        let buckets: Buckets = self.send_json(self.client.get(url)).await?;
+        Ok(buckets.buckets)
+    }
+
+    /// Create a bucket in the organization of the client
+    ///
+    /// If the client was configured with an organization name, its identifier
+    /// is looked up first.
+    /// [`ClientError::MissingOrganization`](ClientError::MissingOrganization)
+    /// is returned if the client has no organization.
+    #[instrument(
+        name = "Creating bucket",
+        skip(self),
+        fields(
+            status = field::Empty,
+            elapsed_ms = field::Empty,
+        ),
+    )]
+    pub async fn create_bucket(&self, bucket: NewBucket) -> Result<Bucket, ClientError> {
+        let organization_id = self.organization_id().await?;
+        let request = self
+            .client
+            .post(api_url(&self.base_url, &["buckets"])?)
+            .body(bucket.body(&organization_id).to_string());
+        self.send_json(request).await
+    }
+
+    /// Change the name, description or retention rules of a bucket
+    pub async fn update_bucket(&self, id: &str, update: BucketUpdate) -> Result<Bucket, ClientError> {
+        let request = self
+            .client
+            .patch(api_url(&self.base_url, &["buckets", id])?)
+            .body(update.body().to_string());
+        self.send_json(request).await
+    }
+
+    /// Delete a bucket and all its data
+    pub async fn delete_bucket(&self, id: &str) -> Result<(), ClientError> {
+        let request = self.client.delete(api_url(&self.base_url, &["buckets", id])?);
+        self.send(request).await?;
+        Ok(())
+    }
+
+    /// Return the identifier of the organization of the client
+    async fn organization_id(&self) -> Result<String, ClientError> {
+        let name = match &self.organization {
+            None => return Err(ClientError::MissingOrganization),
+            Some(OrganizationRef::Id(id)) => return Ok(id.clone()),
+            Some(OrganizationRef::Name(name)) => name,
+        };
+
+        let mut url = api_url(&self.base_url, &["orgs"])?;
+        url.query_pairs_mut().append_pair("org", name);
+        let organizations: Organizations = self.send_json(self.client.get(url)).await?;
+        organizations
+            .orgs
+            .into_iter()
+            .find(|organization| &organization.name == name)
+            .map(|organization| organization.id)
+            .ok_or_else(|| ClientError::OrganizationNotFound(name.clone()))
+    }
+
+    /// Delete points in a time range from a bucket
+    ///
+    /// Only points matching the predicate are deleted, or all points in the
+    /// time range if the predicate is empty.
+    /// Both start and stop times are inclusive.
+    /// [`ClientError::MissingOrganization`](ClientError::MissingOrganization)
+    /// is returned if the client has no organization.
+    /// Requests are not retried.
+    #[instrument(
+        name = "Deleting data",
+        skip(self, bucket),
+        fields(
+            database = bucket.as_ref(),
+            status = field::Empty,
+            elapsed_ms = field::Empty,
+        ),
+    )]
+    pub async fn delete<T>(
+        &self,
+        bucket: T,
+        start: DateTime<Utc>,
+        stop: DateTime<Utc>,
+        predicate: DeletePredicate,
+    ) -> Result<(), ClientError>
+    where
+        T: AsRef<str>,
+    {
+        let organization = self
+            .organization
+            .as_ref()
+            .ok_or(ClientError::MissingOrganization)?;
+
+        let mut url = api_url(&self.base_url, &["delete"])?;
+        let (key, value) = organization.parameter();
+        url.query_pairs_mut()
+            .append_pair(key, value)
+            .append_pair("bucket", bucket.as_ref());
+
+        let mut body = json!({
+            "start": start.to_rfc3339_opts(SecondsFormat::AutoSi, true),
+            "stop": stop.to_rfc3339_opts(SecondsFormat::AutoSi, true),
+        });
+        if !predicate.is_empty() {
+            body["predicate"] = json!(predicate.to_string());
+        }
+
+        self.send(self.client.post(url).body(body.to_string())).await?;
+        Ok(())
+    }
+
+    /// Send a query body and return the response body
+    ///
+    /// If the session expired, the client signs in again and sends the
+    /// request once more.
+    async fn post_query(&self, body: &str) -> Result<String, ClientError> {
+        let cookie = self.session_cookie().await?;
+        let response = match self.post_query_with_cookie(body, cookie.as_deref()).await {
+            Err(error) if cookie.is_some() && is_unauthorized(&error) => {
+                debug!("Session expired, signing in again");
+                self.session.clear();
+                let cookie = self.session_cookie().await?;
+                self.post_query_with_cookie(body, cookie.as_deref()).await
+            }
+            result => result,
+        }?;
+
+        Ok(response.text().await?)
+    }
+
+    /// Send a query body with a session cookie and check the response status
+    async fn post_query_with_cookie(&self, body: &str, cookie: Option<&str>) -> Result<ReqwestResponse, ClientError> {
+        let response = send_with_failover(&self.base_urls(), &self.retry_policy, |base_url| {
+            let url = query_url(base_url, self.organization.as_ref())?;
+            Ok(apply_authentication(self.client.post(url), &self.authentication, cookie).body(body.to_string()))
+        }).await?;
+
+        if let Some(error) = rate_limited(response.status(), response.headers()) {
+            return Err(error);
+        }
+        Ok(response.error_for_status()?)
+    }
+
+    /// Send a request to an API endpoint and parse its JSON response
+    async fn send_json<T>(&self, request: ReqwestRequestBuilder) -> Result<T, ClientError>
+    where
+        T: DeserializeOwned,
+    {
+        let response = self.send(request.header(ACCEPT, "application/json")).await?;
+        let text = response.text().await?;
+
+        Ok(serde_json::from_str(&text)?)
+    }
+
+    /// Send a request to an API endpoint and check the response status
+    ///
+    /// If the session expired, the client signs in again and sends the
+    /// request once more.
+    async fn send(&self, request: ReqwestRequestBuilder) -> Result<ReqwestResponse, ClientError> {
+        let cookie = self.session_cookie().await?;
+        match self.send_with_cookie(&request, cookie.as_deref()).await {
+            Err(error) if cookie.is_some() && is_unauthorized(&error) => {
+                debug!("Session expired, signing in again");
+                self.session.clear();
+                let cookie = self.session_cookie().await?;
+                self.send_with_cookie(&request, cookie.as_deref()).await
+            }
+            result => result,
+        }
+    }
+
+    /// Send a request with a session cookie and check the response status
+    async fn send_with_cookie(
+        &self,
+        request: &ReqwestRequestBuilder,
+        cookie: Option<&str>,
+    ) -> Result<ReqwestResponse, ClientError> {
+        let request = request
+            .try_clone()
+            .expect("Requests to API endpoints do not have streaming bodies");
+        let request = apply_authentication(request, &self.authentication, cookie);
 
-        debug!("Sending request to {}", self.base_url);
         trace!("Request: {:?}", request);
 
-        let response = request.send().await?;
+        let response = send_request(request).await?;
+        if let Some(error) = rate_limited(response.status(), response.headers()) {
+            return Err(error);
+        }
+        Ok(response.error_for_status()?)
+    }
+
+    /// Return the session cookie, signing in if there is no session yet
+    ///
+    /// `None` is returned if the client does not use session authentication.
+    async fn session_cookie(&self) -> Result<Option<String>, ClientError> {
+        let (username, password) = match &self.authentication {
+            Authentication::Session { username, password } => (username, password),
+            _ => return Ok(None),
+        };
+        if let Some(cookie) = self.session.cookie() {
+            return Ok(Some(cookie));
+        }
+
+        debug!("Signing in as {}", username);
+        let response = send_with_failover(&self.base_urls(), &self.retry_policy, |base_url| {
+            Ok(self.client
+                .post(base_url.join(SIGNIN_PATH)?)
+                .basic_auth(username, Some(password)))
+        }).await?.error_for_status()?;
+        let cookie = cookie_from_set_cookie_headers(
+            response.headers().get_all(SET_COOKIE).iter().filter_map(|value| value.to_str().ok()),
+        )
+        .ok_or(ClientError::MissingSessionCookie)?;
+
+        self.session.set_cookie(cookie.clone());
+        Ok(Some(cookie))
+    }
+
+    /// Return the base URL and the fallback URLs in the order they are tried
+    fn base_urls(&self) -> Vec<&Url> {
+        let urls: Vec<&Url> = std::iter::once(&self.base_url)
+            .chain(self.fallback_urls.iter())
+            .collect();
+        self.failover
+            .order(urls.len())
+            .into_iter()
+            .map(|index| urls[index])
+            .collect()
+    }
 
+    /// Check that the server is reachable, and return its version and build type
+    ///
+    /// A request is sent to `/ping`, or to `/health` if the server does not
+    /// provide it, as is the case for some InfluxDB 2.x deployments.
+    /// Requests are not retried.
+    pub async fn ping(&self) -> Result<Ping, ClientError> {
+        let mut response = send_request(self.client.get(self.base_url.join("/ping")?)).await?;
+        if response.status() == StatusCode::NOT_FOUND {
+            debug!("Endpoint /ping not found, falling back to /health");
+            response = send_request(self.client.get(self.base_url.join("/health")?)).await?;
+        }
         let response = response.error_for_status()?;
+        Ok(ping_from_headers(response.headers()))
+    }
 
-        let text = response.text().await?;
+    /// Check whether the server is reachable and healthy
+    pub async fn is_healthy(&self) -> bool {
+        self.ping().await.is_ok()
+    }
+}
 
-        let dataframe = from_str(&text)?;
+/// Run queries through the client as text
+///
+/// Queries are run as [`fetch_readings`](Client::fetch_readings) and
+/// [`fetch_tagged_readings`](Client::fetch_tagged_readings) do, and
+/// dataframes are grouped by tag from the group keys of their tables.
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+impl<DF, E> AsyncQueryClient<DF> for Client
+where
+    DF: FromQueryResult<Error = E> + Send,
+    E: Into<ResponseError>,
+{
+    async fn fetch_dataframe(&self, query: &str) -> Result<DF, QueryClientError> {
+        Ok(self.fetch_readings(Query::new(query)).await?)
+    }
 
-        Ok(dataframe)
+    async fn fetch_dataframes_by_tag(&self, query: &str, tag: &str) -> Result<HashMap<String, DF>, QueryClientError> {
+        let dataframes: Vec<TaggedDataframe<DF>> = self.fetch_tagged_readings(Query::new(query)).await?;
+        let dataframes = dataframes
+            .into_iter()
+            .map(|(dataframe, mut tags)| {
+                let value = tags
+                    .remove(tag)
+                    .ok_or_else(|| ClientError::ExpectedTagError(tag.to_string()))?;
+                Ok((value, dataframe))
+            })
+            .collect::<Result<_, ClientError>>()?;
+        Ok(dataframes)
     }
+
+    async fn fetch_tagged_dataframes(&self, query: &str) -> Result<Vec<(DF, TagsMap)>, QueryClientError> {
+        Ok(self.fetch_tagged_readings(Query::new(query)).await?)
+    }
+}
+
+/// Send a request to each URL in turn, until a server does not fail
+///
+/// The request for each URL is created by `request`, and it is retried
+/// according to a policy.
+async fn send_with_failover<F>(
+    urls: &[&Url],
+    retry_policy: &RetryPolicy,
+    request: F,
+) -> Result<ReqwestResponse, ClientError>
+where
+    F: Fn(&Url) -> Result<ReqwestRequestBuilder, ClientError>,
+{
+    let (last, others) = urls.split_last().expect("Clients have at least one URL");
+    for url in others {
+        let request = request(url)?;
+        debug!("Sending request to {}", url);
+        trace!("Request: {:?}", request);
+        match send_with_retry(request, retry_policy).await {
+            Ok(response) if Failover::is_failover_status(response.status().as_u16()) => {
+                warn!(url = %url, status = response.status().as_u16(), "Server {} failed with status {}, failing over", url, response.status());
+            }
+            Err(error) if is_transient(&error) => {
+                warn!(url = %url, error = %error, "Server {} failed: {}, failing over", url, error);
+            }
+            result => return Ok(result?),
+        }
+    }
+
+    let request = request(last)?;
+    debug!("Sending request to {}", last);
+    trace!("Request: {:?}", request);
+    Ok(send_with_retry(request, retry_policy).await?)
+}
+
+/// Send a request, retrying it according to a policy
+///
+/// Requests whose body cannot be cloned are sent only once.
+async fn send_with_retry(
+    request: ReqwestRequestBuilder,
+    retry_policy: &RetryPolicy,
+) -> Result<ReqwestResponse, reqwest::Error> {
+    let mut attempt = 1;
+    loop {
+        let attempt_request = match request.try_clone() {
+            Some(attempt_request) if attempt < retry_policy.max_attempts() => attempt_request,
+            _ => return send_request(request).await,
+        };
+
+        let delay = match send_request(attempt_request).await {
+            Ok(response) if retry_policy.is_retryable_status(response.status().as_u16()) => {
+                let delay = match retry_policy.retry_delay(attempt, retry_after(response.headers())) {
+                    Some(delay) => delay,
+                    // The server asked to wait longer than the policy allows
+                    None => return Ok(response),
+                };
+                warn!(attempt, status = response.status().as_u16(), "Attempt {} failed with status {}, retrying", attempt, response.status());
+                delay
+            }
+            Err(error) if is_transient(&error) => {
+                warn!(attempt, error = %error, "Attempt {} failed: {}, retrying", attempt, error);
+                retry_policy.delay(attempt)
+            }
+            result => return result,
+        };
+
+        sleep(delay).await;
+        attempt += 1;
+    }
+}
+
+/// Send a request through Reqwest
+///
+/// The status code and the duration of the request are recorded on the
+/// current span.
+async fn send_request(request: ReqwestRequestBuilder) -> Result<ReqwestResponse, reqwest::Error> {
+    let stopwatch = Stopwatch::start();
+    let response = request.send().await;
+    record_response(response.as_ref().map(|response| response.status().as_u16()), stopwatch.elapsed());
+    response
 }
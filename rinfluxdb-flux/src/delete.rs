@@ -0,0 +1,73 @@
+// Copyright Claudio Mattera 2021.
+// Distributed under the MIT License or Apache 2.0 License at your option.
+// See accompanying files License-MIT.txt and License-Apache-2.0, or online at
+// https://opensource.org/licenses/MIT
+// https://opensource.org/licenses/Apache-2.0
+
+use std::fmt;
+
+/// A predicate selecting the points to delete from an InfluxDB 2.x bucket
+///
+/// The predicate is a conjunction of equalities on the measurement and on
+/// tags.
+/// InfluxDB does not support other operators in delete predicates.
+/// An empty predicate selects all points in the time range.
+///
+/// ```
+/// # use rinfluxdb_flux::DeletePredicate;
+/// let predicate = DeletePredicate::new()
+///     .measurement("indoor_environment")
+///     .tag("room", "kid's \"bedroom\"");
+///
+/// assert_eq!(
+///     predicate.to_string(),
+///     r#"_measurement="indoor_environment" AND room="kid's \"bedroom\"""#,
+/// );
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct DeletePredicate {
+    conditions: Vec<(String, String)>,
+}
+
+impl DeletePredicate {
+    /// Create an empty predicate
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Delete only points of a measurement
+    pub fn measurement<T>(self, measurement: T) -> Self
+    where
+        T: Into<String>,
+    {
+        self.tag("_measurement", measurement)
+    }
+
+    /// Delete only points with a tag value
+    pub fn tag<T, S>(mut self, tag: T, value: S) -> Self
+    where
+        T: Into<String>,
+        S: Into<String>,
+    {
+        self.conditions.push((tag.into(), value.into()));
+        self
+    }
+
+    /// Check whether the predicate has no conditions
+    pub fn is_empty(&self) -> bool {
+        self.conditions.is_empty()
+    }
+}
+
+impl fmt::Display for DeletePredicate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let conditions: Vec<String> = self
+            .conditions
+            .iter()
+            .map(|(tag, value)| {
+                format!("{}=\"{}\"", tag, value.replace('\\', "\\\\").replace('"', "\\\""))
+            })
+            .collect();
+        write!(f, "{}", conditions.join(" AND "))
+    }
+}
@@ -0,0 +1,91 @@
+// Copyright Claudio Mattera 2021.
+// Distributed under the MIT License or Apache 2.0 License at your option.
+// See accompanying files License-MIT.txt and License-Apache-2.0, or online at
+// https://opensource.org/licenses/MIT
+// https://opensource.org/licenses/Apache-2.0
+
+/// An annotation row in a CSV response
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Annotation {
+    /// Data type of each column, `#datatype`
+    DataType,
+
+    /// Whether each column is part of the group key, `#group`
+    Group,
+
+    /// Default value of each column, `#default`
+    Default,
+}
+
+impl Annotation {
+    /// Return the name used by InfluxDB for this annotation
+    ///
+    /// ```
+    /// # use rinfluxdb_flux::Annotation;
+    /// assert_eq!(Annotation::DataType.as_str(), "datatype");
+    /// assert_eq!(Annotation::Group.as_str(), "group");
+    /// ```
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Annotation::DataType => "datatype",
+            Annotation::Group => "group",
+            Annotation::Default => "default",
+        }
+    }
+}
+
+/// The format of CSV responses to Flux queries
+///
+/// By default all annotations are requested, cells are separated by commas,
+/// and each table starts with a header row.
+///
+/// The parser adapts to the dialect, but some information is lost when
+/// annotations are not requested.
+/// Without `#datatype`, booleans and numbers are detected from their text,
+/// and numbers are always parsed as floats.
+/// Without `#group`, all columns except the reserved ones are stored as
+/// values, including tags.
+/// Without `#default`, empty cells are parsed as empty values.
+/// Without a header row column names are unknown, so responses cannot be
+/// parsed to dataframes.
+///
+/// ```
+/// # use rinfluxdb_flux::{Annotation, Dialect};
+/// let dialect = Dialect {
+///     annotations: vec![Annotation::DataType],
+///     delimiter: b';',
+///     ..Dialect::default()
+/// };
+///
+/// assert!(dialect.has_annotation(Annotation::DataType));
+/// assert!(!dialect.has_annotation(Annotation::Group));
+/// assert!(dialect.header);
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Dialect {
+    /// Annotations included in responses
+    pub annotations: Vec<Annotation>,
+
+    /// Cell delimiter, which must be an ASCII character
+    pub delimiter: u8,
+
+    /// Whether each table starts with a header row
+    pub header: bool,
+}
+
+impl Dialect {
+    /// Check whether an annotation is included in responses
+    pub fn has_annotation(&self, annotation: Annotation) -> bool {
+        self.annotations.contains(&annotation)
+    }
+}
+
+impl Default for Dialect {
+    fn default() -> Self {
+        Self {
+            annotations: vec![Annotation::DataType, Annotation::Group, Annotation::Default],
+            delimiter: b',',
+            header: true,
+        }
+    }
+}
@@ -6,17 +6,27 @@
 
 //! Functions and data types to construct Flux queries
 
-#[cfg(feature = "client")]
+#[cfg(any(feature = "client-blocking", feature = "client-async"))]
 mod client;
 
+mod delete;
+mod dialect;
 mod query;
 mod querybuilder;
 mod response;
 mod types;
 
-#[cfg(feature = "client")]
+#[cfg(any(feature = "client-blocking", feature = "client-async"))]
+pub mod management;
+
+#[cfg(any(feature = "client-blocking", feature = "client-async"))]
+pub mod tasks;
+
+#[cfg(any(feature = "client-blocking", feature = "client-async"))]
 pub use self::client::*;
 
+pub use self::delete::*;
+pub use self::dialect::*;
 pub use self::query::*;
 pub use self::querybuilder::*;
 pub use self::response::*;
@@ -0,0 +1,215 @@
+// Copyright Claudio Mattera 2021.
+// Distributed under the MIT License or Apache 2.0 License at your option.
+// See accompanying files License-MIT.txt and License-Apache-2.0, or online at
+// https://opensource.org/licenses/MIT
+// https://opensource.org/licenses/Apache-2.0
+
+//! Data types to manage buckets and organizations on InfluxDB 2.x servers
+//!
+//! Buckets and organizations are managed through the clients' methods, such
+//! as [`create_bucket`](super::blocking::Client::create_bucket) and
+//! [`organizations`](super::blocking::Client::organizations).
+
+use std::time::Duration as StdDuration;
+
+use serde::{Deserialize, Serialize};
+
+use serde_json::{json, Value as JsonValue};
+
+/// An organization existing on a server
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+pub struct Organization {
+    /// Organization identifier
+    pub id: String,
+
+    /// Organization name
+    pub name: String,
+
+    /// Organization description
+    pub description: Option<String>,
+}
+
+/// Changes to an organization
+///
+/// Only the properties that are set are changed.
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+pub struct OrganizationUpdate {
+    /// New organization name
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+
+    /// New organization description
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+}
+
+/// A rule for deleting old data from a bucket
+///
+/// ```
+/// # use std::time::Duration;
+/// # use rinfluxdb_flux::management::RetentionRule;
+/// let rule = RetentionRule::expire(Duration::from_secs(30 * 24 * 60 * 60));
+///
+/// assert_eq!(rule.every_seconds, 2_592_000);
+/// assert_eq!(rule.shard_group_duration_seconds, None);
+/// ```
+#[derive(Clone, Debug, Deserialize, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RetentionRule {
+    /// Number of seconds data is kept, or zero to keep it forever
+    pub every_seconds: u64,
+
+    /// Number of seconds covered by each shard group
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub shard_group_duration_seconds: Option<u64>,
+}
+
+impl RetentionRule {
+    /// Create a rule deleting data older than a duration
+    pub fn expire(every: StdDuration) -> Self {
+        Self {
+            every_seconds: every.as_secs(),
+            shard_group_duration_seconds: None,
+        }
+    }
+
+    /// Return the rule as expected by InfluxDB
+    fn to_json(&self) -> JsonValue {
+        let mut rule = json!(self);
+        rule["type"] = JsonValue::from("expire");
+        rule
+    }
+}
+
+/// A bucket existing on a server
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct Bucket {
+    /// Bucket identifier
+    pub id: String,
+
+    /// Identifier of the organization owning the bucket
+    #[serde(rename = "orgID")]
+    pub org_id: String,
+
+    /// Bucket name
+    pub name: String,
+
+    /// Bucket description
+    pub description: Option<String>,
+
+    /// Rules for deleting old data
+    #[serde(default)]
+    pub retention_rules: Vec<RetentionRule>,
+}
+
+/// A bucket to be created
+///
+/// ```
+/// # use std::time::Duration;
+/// # use rinfluxdb_flux::management::NewBucket;
+/// let bucket = NewBucket::new("house")
+///     .description("Indoor environment")
+///     .retention(Duration::from_secs(7 * 24 * 60 * 60));
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct NewBucket {
+    name: String,
+    description: Option<String>,
+    retention_rules: Vec<RetentionRule>,
+}
+
+impl NewBucket {
+    /// Create a bucket keeping data forever
+    pub fn new<T>(name: T) -> Self
+    where
+        T: Into<String>,
+    {
+        Self {
+            name: name.into(),
+            description: None,
+            retention_rules: vec![],
+        }
+    }
+
+    /// Set the bucket description
+    pub fn description<T>(mut self, description: T) -> Self
+    where
+        T: Into<String>,
+    {
+        self.description = Some(description.into());
+        self
+    }
+
+    /// Delete data older than a duration
+    pub fn retention(self, every: StdDuration) -> Self {
+        self.retention_rule(RetentionRule::expire(every))
+    }
+
+    /// Set the rule for deleting old data
+    pub fn retention_rule(mut self, rule: RetentionRule) -> Self {
+        self.retention_rules = vec![rule];
+        self
+    }
+
+    /// Return the body of the creation request
+    pub(crate) fn body(&self, organization_id: &str) -> JsonValue {
+        let mut body = json!({
+            "orgID": organization_id,
+            "name": self.name,
+            "retentionRules": retention_rules(&self.retention_rules),
+        });
+        if let Some(description) = &self.description {
+            body["description"] = JsonValue::from(description.as_str());
+        }
+        body
+    }
+}
+
+/// Changes to a bucket
+///
+/// Only the properties that are set are changed.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct BucketUpdate {
+    /// New bucket name
+    pub name: Option<String>,
+
+    /// New bucket description
+    pub description: Option<String>,
+
+    /// New rules for deleting old data
+    pub retention_rules: Option<Vec<RetentionRule>>,
+}
+
+impl BucketUpdate {
+    /// Return the body of the update request
+    pub(crate) fn body(&self) -> JsonValue {
+        let mut body = json!({});
+        if let Some(name) = &self.name {
+            body["name"] = JsonValue::from(name.as_str());
+        }
+        if let Some(description) = &self.description {
+            body["description"] = JsonValue::from(description.as_str());
+        }
+        if let Some(rules) = &self.retention_rules {
+            body["retentionRules"] = retention_rules(rules);
+        }
+        body
+    }
+}
+
+fn retention_rules(rules: &[RetentionRule]) -> JsonValue {
+    JsonValue::Array(rules.iter().map(RetentionRule::to_json).collect())
+}
+
+/// Response to a request for listing organizations
+#[derive(Deserialize)]
+pub(crate) struct Organizations {
+    pub(crate) orgs: Vec<Organization>,
+}
+
+/// Response to a request for listing buckets
+#[derive(Deserialize)]
+pub(crate) struct Buckets {
+    pub(crate) buckets: Vec<Bucket>,
+}
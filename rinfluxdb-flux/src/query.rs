@@ -4,9 +4,20 @@
 // https://opensource.org/licenses/MIT
 // https://opensource.org/licenses/Apache-2.0
 
+use std::fmt;
+
+use rinfluxdb_types::Value;
+
 /// A Flux query
-#[derive(Debug, PartialEq)]
-pub struct Query(String);
+///
+/// With feature `serde`, queries can be serialized and deserialized along
+/// with their parameters, e.g. to store them in configuration files.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Query {
+    query: String,
+    parameters: Vec<(String, Value)>,
+}
 
 impl Query {
     /// Create a query from a string-like object
@@ -14,12 +25,58 @@ impl Query {
     where
         T: Into<String>,
     {
-        Self(query.into())
+        Self {
+            query: query.into(),
+            parameters: vec![],
+        }
+    }
+
+    /// Bind a value to a parameter
+    ///
+    /// Parameters are not formatted in the query text, but sent to the server
+    /// alongside it as an `option params = {...}` statement, and they are
+    /// accessed in the query as `params.<name>`.
+    /// This way values supplied by users cannot alter the query.
+    /// Names must be valid Flux identifiers, and missing values cannot be
+    /// bound, since Flux has no literal for them.
+    ///
+    /// ```
+    /// # use rinfluxdb_types::Value;
+    /// # use rinfluxdb_flux::Query;
+    /// let query = Query::new(r#"
+    ///     from(bucket: params.bucket)
+    ///         |> range(start: -1h)
+    ///         |> filter(fn: (r) => r.room == params.room)
+    /// "#)
+    ///     .bind("bucket", "house")
+    ///     .bind("room", r#"kitchen" or true or r.room == ""#);
+    ///
+    /// assert_eq!(query.parameters()[0], ("bucket".to_string(), Value::from("house")));
+    /// ```
+    pub fn bind<T, V>(mut self, name: T, value: V) -> Self
+    where
+        T: Into<String>,
+        V: Into<Value>,
+    {
+        self.parameters.push((name.into(), value.into()));
+        self
+    }
+
+    /// Return the parameters bound to values
+    pub fn parameters(&self) -> &[(String, Value)] {
+        &self.parameters
     }
 }
 
 impl AsRef<str> for Query {
     fn as_ref(&self) -> &str {
-        self.0.as_ref()
+        self.query.as_ref()
+    }
+}
+
+impl fmt::Display for Query {
+    /// Format the query text, without its parameters
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.query)
     }
 }
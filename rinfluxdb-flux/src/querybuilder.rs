@@ -4,12 +4,20 @@
 // https://opensource.org/licenses/MIT
 // https://opensource.org/licenses/Apache-2.0
 
-use std::fmt::Write;
+use std::collections::HashSet;
+use std::fmt::{self, Write};
 
-use rinfluxdb_types::{Duration, InstantOrDuration};
+use rinfluxdb_types::{BuildError, Duration, InstantOrDuration};
 
 use super::query::Query;
 
+#[derive(Debug)]
+enum Source {
+    Bucket(String),
+    Join(Vec<(String, QueryBuilder)>, Vec<String>),
+    Union(Vec<(String, QueryBuilder)>),
+}
+
 #[derive(Debug)]
 enum Statement {
     Range(InstantOrDuration, InstantOrDuration),
@@ -20,6 +28,12 @@ enum Statement {
     Aggregate(String),
     Duplicate(String, String),
     AggregateWindow(String, Duration),
+    Group(Vec<String>),
+    Keep(Vec<String>),
+    Drop(Vec<String>),
+    Sort(Vec<String>, bool),
+    Limit(u64, u64),
+    Pivot(Vec<String>, Vec<String>, String),
 }
 
 /// A builder for Flux queries
@@ -48,8 +62,47 @@ enum Statement {
 ///   |> yield()"#,
 /// );
 /// ```
+///
+/// Pipelines can be combined with [`join`](QueryBuilder::join) and
+/// [`union`](QueryBuilder::union).
+/// Each pipeline is assigned to a named stream, and further stages can be
+/// added after the combining statement.
+///
+/// ```
+/// # use rinfluxdb_types::Duration;
+/// # use rinfluxdb_flux::QueryBuilder;
+/// let cpu = QueryBuilder::from("telegraf/autogen")
+///     .range_start(Duration::Minutes(-15))
+///     .measurement("cpu");
+/// let mem = QueryBuilder::from("telegraf/autogen")
+///     .range_start(Duration::Minutes(-15))
+///     .measurement("mem");
+///
+/// let query = QueryBuilder::join(("cpu", cpu), ("mem", mem), vec!["_time", "host"])
+///     .keep(vec!["_time", "host", "_value_cpu", "_value_mem"])
+///     .try_build()?;
+///
+/// assert_eq!(
+///     query.as_ref(),
+///     r#"cpu = from(bucket: "telegraf/autogen")
+///   |> range(start: -15m)
+///   |> filter(fn: (r) =>
+///     r._measurement == "cpu"
+///   )
+/// mem = from(bucket: "telegraf/autogen")
+///   |> range(start: -15m)
+///   |> filter(fn: (r) =>
+///     r._measurement == "mem"
+///   )
+/// join(tables: {cpu: cpu, mem: mem}, on: ["_time", "host"])
+///   |> keep(columns: ["_time", "host", "_value_cpu", "_value_mem"])
+///   |> yield()"#,
+/// );
+/// # Ok::<(), rinfluxdb_types::BuildError>(())
+/// ```
+#[derive(Debug)]
 pub struct QueryBuilder {
-    bucket: String,
+    source: Source,
     statements: Vec<Statement>,
 }
 
@@ -60,7 +113,44 @@ impl QueryBuilder {
         T: Into<String>,
     {
         Self {
-            bucket: bucket.into(),
+            source: Source::Bucket(bucket.into()),
+            statements: vec![],
+        }
+    }
+
+    /// Create a query joining two pipelines on some columns
+    ///
+    /// Each pipeline is assigned to a stream with the given name.
+    /// Columns that are not joined on are suffixed by the stream name, such
+    /// as `_value_cpu`.
+    pub fn join<T, S, I, C>(left: (T, QueryBuilder), right: (S, QueryBuilder), on: I) -> Self
+    where
+        T: Into<String>,
+        S: Into<String>,
+        I: IntoIterator<Item = C>,
+        C: Into<String>,
+    {
+        let tables = vec![(left.0.into(), left.1), (right.0.into(), right.1)];
+        Self {
+            source: Source::Join(tables, on.into_iter().map(Into::into).collect()),
+            statements: vec![],
+        }
+    }
+
+    /// Create a query merging the tables of several pipelines
+    ///
+    /// Each pipeline is assigned to a stream with the given name.
+    pub fn union<I, T>(tables: I) -> Self
+    where
+        I: IntoIterator<Item = (T, QueryBuilder)>,
+        T: Into<String>,
+    {
+        let tables = tables
+            .into_iter()
+            .map(|(name, table)| (name.into(), table))
+            .collect();
+        Self {
+            source: Source::Union(tables),
             statements: vec![],
         }
     }
@@ -106,6 +196,42 @@ impl QueryBuilder {
         self
     }
 
+    /// Keep only records of a measurement
+    ///
+    /// This is a shorthand for a filter on column `_measurement`.
+    pub fn measurement<T>(self, measurement: T) -> Self
+    where
+        T: AsRef<str>,
+    {
+        let filter = format!("r._measurement == {}", string_literal(measurement.as_ref()));
+        self.filter(filter)
+    }
+
+    /// Keep only records of a field
+    ///
+    /// This is a shorthand for a filter on column `_field`.
+    pub fn field<T>(self, field: T) -> Self
+    where
+        T: AsRef<str>,
+    {
+        let filter = format!("r._field == {}", string_literal(field.as_ref()));
+        self.filter(filter)
+    }
+
+    /// Keep only records where a tag has a value
+    pub fn tag<T, S>(self, tag: T, value: S) -> Self
+    where
+        T: AsRef<str>,
+        S: AsRef<str>,
+    {
+        let filter = format!(
+            "r[{}] == {}",
+            string_literal(tag.as_ref()),
+            string_literal(value.as_ref()),
+        );
+        self.filter(filter)
+    }
+
     /// Add a window to the query
     pub fn window<T>(mut self, every: T) -> Self
     where
@@ -152,77 +278,380 @@ impl QueryBuilder {
         self
     }
 
+    /// Group records by the values of some columns
+    ///
+    /// Without columns, all records are merged in a single table.
+    pub fn group<I, T>(mut self, columns: I) -> Self
+    where
+        I: IntoIterator<Item = T>,
+        T: Into<String>,
+    {
+        self.statement(Statement::Group(columns.into_iter().map(Into::into).collect()));
+        self
+    }
+
+    /// Keep only some columns, dropping all others
+    pub fn keep<I, T>(mut self, columns: I) -> Self
+    where
+        I: IntoIterator<Item = T>,
+        T: Into<String>,
+    {
+        self.statement(Statement::Keep(columns.into_iter().map(Into::into).collect()));
+        self
+    }
+
+    /// Drop some columns, keeping all others
+    pub fn drop<I, T>(mut self, columns: I) -> Self
+    where
+        I: IntoIterator<Item = T>,
+        T: Into<String>,
+    {
+        self.statement(Statement::Drop(columns.into_iter().map(Into::into).collect()));
+        self
+    }
+
+    /// Sort records by some columns, in descending order if `desc` is true
+    pub fn sort<I, T>(mut self, columns: I, desc: bool) -> Self
+    where
+        I: IntoIterator<Item = T>,
+        T: Into<String>,
+    {
+        self.statement(Statement::Sort(columns.into_iter().map(Into::into).collect(), desc));
+        self
+    }
+
+    /// Return at most `n` records for each table, skipping the first `offset`
+    pub fn limit(mut self, n: u64, offset: u64) -> Self {
+        self.statement(Statement::Limit(n, offset));
+        self
+    }
+
+    /// Pivot records into tables with one column for each value of some
+    /// columns
+    ///
+    /// The most common pivot creates a column for each field, as in
+    /// `pivot(vec!["_time"], vec!["_field"], "_value")`.
+    pub fn pivot<I, T, J, S, V>(mut self, row_key: I, column_key: J, value_column: V) -> Self
+    where
+        I: IntoIterator<Item = T>,
+        T: Into<String>,
+        J: IntoIterator<Item = S>,
+        S: Into<String>,
+        V: Into<String>,
+    {
+        self.statement(Statement::Pivot(
+            row_key.into_iter().map(Into::into).collect(),
+            column_key.into_iter().map(Into::into).collect(),
+            value_column.into(),
+        ));
+        self
+    }
+
+    /// Create the Flux query, validating it first
+    ///
+    /// An error is returned if the bucket is empty, if no range is set
+    /// before windows and aggregations, if more than one range is set, if
+    /// a window duration is not positive, or if a column name is empty.
+    /// Combined pipelines are validated as well, and their stream names must
+    /// be unique valid identifiers.
+    /// InfluxDB rejects queries without a range, so checking it here avoids
+    /// an obscure server error at runtime.
+    ///
+    /// ```
+    /// # use rinfluxdb_types::{BuildError, Duration};
+    /// # use rinfluxdb_flux::QueryBuilder;
+    /// let result = QueryBuilder::from("telegraf/autogen")
+    ///     .range_start(Duration::Minutes(-15))
+    ///     .window(Duration::Minutes(0))
+    ///     .try_build();
+    ///
+    /// assert_eq!(result, Err(BuildError::InvalidDuration("0m".into())));
+    ///
+    /// let result = QueryBuilder::from("telegraf/autogen")
+    ///     .mean()
+    ///     .range_start(Duration::Minutes(-15))
+    ///     .try_build();
+    ///
+    /// assert_eq!(result, Err(BuildError::MissingRange));
+    /// ```
+    pub fn try_build(self) -> Result<Query, BuildError> {
+        self.validate(&mut HashSet::new())?;
+        Ok(self.build())
+    }
+
+    /// Validate the pipeline and all combined pipelines
+    ///
+    /// Names of streams are collected in `names`, since they must be unique
+    /// in the whole script.
+    fn validate(&self, names: &mut HashSet<String>) -> Result<(), BuildError> {
+        // Combined pipelines set their own ranges
+        let mut ranges = match &self.source {
+            Source::Bucket(bucket) if bucket.is_empty() => {
+                return Err(BuildError::EmptySelection);
+            }
+            Source::Bucket(_) => 0,
+            Source::Join(tables, _) | Source::Union(tables) if tables.is_empty() => {
+                return Err(BuildError::EmptySelection);
+            }
+            Source::Join(_, on) if on.iter().any(String::is_empty) => {
+                return Err(BuildError::InvalidIdentifier(String::new()));
+            }
+            Source::Join(tables, _) | Source::Union(tables) => {
+                for (name, table) in tables {
+                    if !is_identifier(name) {
+                        return Err(BuildError::InvalidIdentifier(name.clone()));
+                    }
+                    if !names.insert(name.clone()) {
+                        return Err(BuildError::ConflictingClauses(format!(
+                            "stream {} is defined more than once",
+                            name,
+                        )));
+                    }
+                    table.validate(names)?;
+                }
+                1
+            }
+        };
+
+        for statement in &self.statements {
+            match statement {
+                Statement::Range(..) | Statement::RangeStart(..) | Statement::RangeStop(..) => {
+                    ranges += 1;
+                }
+                Statement::Window(_)
+                | Statement::Aggregate(_)
+                | Statement::AggregateWindow(..)
+                    if ranges == 0 =>
+                {
+                    return Err(BuildError::MissingRange);
+                }
+                Statement::Window(every) | Statement::AggregateWindow(_, every)
+                    if !every.is_positive() =>
+                {
+                    return Err(BuildError::InvalidDuration(every.to_string()));
+                }
+                Statement::Group(columns)
+                | Statement::Keep(columns)
+                | Statement::Drop(columns)
+                | Statement::Sort(columns, _)
+                    if columns.iter().any(String::is_empty) =>
+                {
+                    return Err(BuildError::InvalidIdentifier(String::new()));
+                }
+                Statement::Pivot(row_key, column_key, value_column)
+                    if row_key.iter().chain(column_key).any(String::is_empty)
+                        || value_column.is_empty() =>
+                {
+                    return Err(BuildError::InvalidIdentifier(String::new()));
+                }
+                _ => {}
+            }
+        }
+        match ranges {
+            0 => return Err(BuildError::MissingRange),
+            1 => {}
+            _ => {
+                return Err(BuildError::ConflictingClauses(
+                    "range is set more than once".into(),
+                ))
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Create the Flux query and return its text
+    pub fn build_string(self) -> String {
+        self.to_string()
+    }
+
     /// Create the Flux query
     pub fn build(self) -> Query {
-        let mut result = String::new();
+        Query::new(self.to_string())
+    }
 
-        writeln!(&mut result, "from(bucket: \"{}\")", self.bucket).unwrap();
+    /// Write the definitions of the streams of all combined pipelines
+    fn write_streams(&self, result: &mut String) {
+        if let Source::Join(tables, _) | Source::Union(tables) = &self.source {
+            for (name, table) in tables {
+                table.write_streams(result);
+                write!(result, "{} = ", name).unwrap();
+                table.write_pipeline(result);
+            }
+        }
+    }
+
+    /// Write the source of the pipeline and all its stages
+    fn write_pipeline(&self, result: &mut String) {
+        match &self.source {
+            Source::Bucket(bucket) => {
+                writeln!(result, "from(bucket: {})", string_literal(bucket)).unwrap();
+            }
+            Source::Join(tables, on) => {
+                let tables: Vec<String> = tables
+                    .iter()
+                    .map(|(name, _)| format!("{}: {}", name, name))
+                    .collect();
+                writeln!(
+                    result,
+                    "join(tables: {{{}}}, on: {})",
+                    tables.join(", "),
+                    string_array(on),
+                )
+                .unwrap();
+            }
+            Source::Union(tables) => {
+                let tables: Vec<&str> = tables.iter().map(|(name, _)| name.as_str()).collect();
+                writeln!(result, "union(tables: [{}])", tables.join(", ")).unwrap();
+            }
+        }
 
-        for statement in self.statements {
+        for statement in &self.statements {
 
             // TODO: Return error if vecs have not expected number of arguments
             match statement {
                 Statement::Range(start, stop) => writeln!(
-                    &mut result,
+                    result,
                     "  |> range(start: {}, stop: {})",
-                    start.to_string(),
-                    stop.to_string(),
+                    start,
+                    stop,
                 )
                 .unwrap(),
                 Statement::RangeStart(start) => writeln!(
-                    &mut result,
+                    result,
                     "  |> range(start: {})",
-                    start.to_string(),
+                    start,
                 )
                 .unwrap(),
                 Statement::RangeStop(stop) => writeln!(
-                    &mut result,
+                    result,
                     "  |> range(stop: {})",
-                    stop.to_string(),
+                    stop,
                 )
                 .unwrap(),
                 Statement::Filter(filter) => {
-                    writeln!(&mut result, "  |> filter(fn: (r) =>").unwrap();
+                    writeln!(result, "  |> filter(fn: (r) =>").unwrap();
                     for line in filter.lines() {
-                        writeln!(&mut result, "    {}", line.trim_start()).unwrap();
+                        writeln!(result, "    {}", line.trim_start()).unwrap();
                     }
-                    writeln!(&mut result, "  )").unwrap();
+                    writeln!(result, "  )").unwrap();
                 }
                 Statement::Window(every) => writeln!(
-                    &mut result,
+                    result,
                     "  |> window(every: {})",
-                    every.to_string(),
+                    every,
                 )
                 .unwrap(),
                 Statement::Aggregate(fn_) => writeln!(
-                    &mut result,
+                    result,
                     "  |> {}()",
                     fn_,
                 )
                 .unwrap(),
                 Statement::Duplicate(column, as_) => writeln!(
-                    &mut result,
+                    result,
                     "  |> duplicate(column: \"{}\", as: \"{}\")",
                     column,
                     as_,
                 )
                 .unwrap(),
                 Statement::AggregateWindow(fn_, every) => writeln!(
-                    &mut result,
+                    result,
                     "  |> aggregate_window(fn: {}, every: {})",
                     fn_,
-                    every.to_string(),
+                    every,
+                )
+                .unwrap(),
+                Statement::Group(columns) => writeln!(
+                    result,
+                    "  |> group(columns: {})",
+                    string_array(columns),
+                )
+                .unwrap(),
+                Statement::Keep(columns) => writeln!(
+                    result,
+                    "  |> keep(columns: {})",
+                    string_array(columns),
+                )
+                .unwrap(),
+                Statement::Drop(columns) => writeln!(
+                    result,
+                    "  |> drop(columns: {})",
+                    string_array(columns),
+                )
+                .unwrap(),
+                Statement::Sort(columns, desc) => writeln!(
+                    result,
+                    "  |> sort(columns: {}, desc: {})",
+                    string_array(columns),
+                    desc,
+                )
+                .unwrap(),
+                Statement::Limit(n, 0) => writeln!(
+                    result,
+                    "  |> limit(n: {})",
+                    n,
+                )
+                .unwrap(),
+                Statement::Limit(n, offset) => writeln!(
+                    result,
+                    "  |> limit(n: {}, offset: {})",
+                    n,
+                    offset,
+                )
+                .unwrap(),
+                Statement::Pivot(row_key, column_key, value_column) => writeln!(
+                    result,
+                    "  |> pivot(rowKey: {}, columnKey: {}, valueColumn: {})",
+                    string_array(row_key),
+                    string_array(column_key),
+                    string_literal(value_column),
                 )
                 .unwrap(),
             }
         }
+    }
+}
+
+impl fmt::Display for QueryBuilder {
+    /// Format the text of the query that would be built
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut result = String::new();
 
-        write!(&mut result, "  |> yield()").unwrap();
+        self.write_streams(&mut result);
+        self.write_pipeline(&mut result);
 
-        Query::new(result)
+        write!(f, "{}  |> yield()", result)
     }
 }
 
+/// Check whether a name is a valid Flux identifier
+fn is_identifier(name: &str) -> bool {
+    let mut characters = name.chars();
+    match characters.next() {
+        Some(first) if first.is_ascii_alphabetic() || first == '_' => {
+            characters.all(|character| character.is_ascii_alphanumeric() || character == '_')
+        }
+        _ => false,
+    }
+}
+
+/// Format a list of strings as a Flux array literal
+fn string_array(values: &[String]) -> String {
+    let values: Vec<String> = values.iter().map(|value| string_literal(value)).collect();
+    format!("[{}]", values.join(", "))
+}
+
+/// Format a string as a Flux string literal, escaping quotes, backslashes
+/// and dollar signs, so that `${...}` is not interpolated
+pub(crate) fn string_literal(value: &str) -> String {
+    let escaped = value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('$', "\\$");
+    format!("\"{}\"", escaped)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -299,4 +728,166 @@ mod tests {
 
         assert_eq!(actual, expected);
     }
+
+    #[test]
+    fn query_with_table_statements() {
+        let expected = Query::new(
+            r#"from(bucket: "telegraf/autogen")
+  |> range(start: -1h)
+  |> group(columns: ["host", "cpu"])
+  |> keep(columns: ["_time", "_value", "host", "cpu"])
+  |> drop(columns: ["cpu"])
+  |> sort(columns: ["_value"], desc: true)
+  |> limit(n: 10)
+  |> limit(n: 5, offset: 2)
+  |> yield()"#,
+        );
+
+        let actual = QueryBuilder::from("telegraf/autogen")
+            .range_start(Duration::Hours(-1))
+            .group(vec!["host", "cpu"])
+            .keep(vec!["_time", "_value", "host", "cpu"])
+            .drop(vec!["cpu"])
+            .sort(vec!["_value"], true)
+            .limit(10, 0)
+            .limit(5, 2)
+            .try_build();
+
+        assert_eq!(actual, Ok(expected));
+    }
+
+    #[test]
+    fn query_with_empty_group() {
+        let actual = QueryBuilder::from("telegraf/autogen")
+            .range_start(Duration::Hours(-1))
+            .group(Vec::<String>::new())
+            .keep(vec![r#"quoted "name""#])
+            .build();
+
+        assert!(actual.as_ref().contains("  |> group(columns: [])\n"));
+        assert!(actual.as_ref().contains(r#"  |> keep(columns: ["quoted \"name\""])"#));
+    }
+
+    #[test]
+    fn query_with_typed_filters() {
+        let expected = Query::new(
+            r#"from(bucket: "telegraf/autogen")
+  |> range(start: -15m)
+  |> filter(fn: (r) =>
+    r._measurement == "cpu"
+  )
+  |> filter(fn: (r) =>
+    r._field == "usage_system"
+  )
+  |> filter(fn: (r) =>
+    r["cpu"] == "cpu-total"
+  )
+  |> pivot(rowKey: ["_time"], columnKey: ["_field"], valueColumn: "_value")
+  |> yield()"#,
+        );
+
+        let actual = QueryBuilder::from("telegraf/autogen")
+            .range_start(Duration::Minutes(-15))
+            .measurement("cpu")
+            .field("usage_system")
+            .tag("cpu", "cpu-total")
+            .pivot(vec!["_time"], vec!["_field"], "_value")
+            .try_build();
+
+        assert_eq!(actual, Ok(expected));
+    }
+
+    #[test]
+    fn union_query() {
+        let expected = Query::new(
+            r#"first = from(bucket: "first")
+  |> range(start: -1h)
+second = from(bucket: "second")
+  |> range(start: -1h)
+union(tables: [first, second])
+  |> sort(columns: ["_time"], desc: false)
+  |> yield()"#,
+        );
+
+        let actual = QueryBuilder::union(vec![
+            ("first", QueryBuilder::from("first").range_start(Duration::Hours(-1))),
+            ("second", QueryBuilder::from("second").range_start(Duration::Hours(-1))),
+        ])
+        .sort(vec!["_time"], false)
+        .try_build();
+
+        assert_eq!(actual, Ok(expected));
+    }
+
+    #[test]
+    fn try_build_invalid_streams() {
+        let actual = QueryBuilder::union(vec![
+            ("first", QueryBuilder::from("first").range_start(Duration::Hours(-1))),
+            ("first", QueryBuilder::from("second").range_start(Duration::Hours(-1))),
+        ])
+        .try_build();
+        assert!(matches!(actual, Err(BuildError::ConflictingClauses(_))));
+
+        let actual = QueryBuilder::join(
+            ("first stream", QueryBuilder::from("first").range_start(Duration::Hours(-1))),
+            ("second", QueryBuilder::from("second").range_start(Duration::Hours(-1))),
+            vec!["_time"],
+        )
+        .try_build();
+        assert_eq!(actual, Err(BuildError::InvalidIdentifier("first stream".into())));
+
+        let actual = QueryBuilder::join(
+            ("first", QueryBuilder::from("first")),
+            ("second", QueryBuilder::from("second").range_start(Duration::Hours(-1))),
+            vec!["_time"],
+        )
+        .try_build();
+        assert_eq!(actual, Err(BuildError::MissingRange));
+    }
+
+    #[test]
+    fn try_build_empty_column() {
+        let actual = QueryBuilder::from("telegraf/autogen")
+            .range_start(Duration::Hours(-1))
+            .drop(vec![""])
+            .try_build();
+
+        assert_eq!(actual, Err(BuildError::InvalidIdentifier(String::new())));
+    }
+
+    #[test]
+    fn try_build_conflicting_ranges() {
+        let actual = QueryBuilder::from("telegraf/autogen")
+            .range_start(Duration::Minutes(-15))
+            .range_stop(Duration::Minutes(-5))
+            .try_build();
+
+        assert!(matches!(actual, Err(BuildError::ConflictingClauses(_))));
+    }
+
+    #[test]
+    fn try_build_missing_range() {
+        let actual = QueryBuilder::from("telegraf/autogen")
+            .filter(r#"r._measurement == "cpu""#)
+            .try_build();
+
+        assert_eq!(actual, Err(BuildError::MissingRange));
+    }
+
+    #[test]
+    fn display_builder() {
+        let builder = QueryBuilder::from("telegraf/autogen")
+            .range_start(Duration::Minutes(-15))
+            .measurement("cpu");
+
+        assert_eq!(builder.to_string(), builder.build_string());
+    }
+
+    #[test]
+    fn string_literal_escapes_interpolation() {
+        assert_eq!(
+            string_literal(r#"a "${secrets.get(key: "token")}" \ b"#),
+            r#""a \"\${secrets.get(key: \"token\")}\" \\ b""#,
+        );
+    }
 }
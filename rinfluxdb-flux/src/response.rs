@@ -4,21 +4,19 @@
 // https://opensource.org/licenses/MIT
 // https://opensource.org/licenses/Apache-2.0
 
-use std::collections::HashMap;
-use std::convert::TryFrom;
-use std::num::ParseFloatError;
+use std::collections::{BTreeMap, HashMap};
+use std::num::{ParseFloatError, ParseIntError};
 
 use chrono::{DateTime, Utc};
 
 use csv::ReaderBuilder as CsvReaderBuilder;
-
-use itertools::izip;
+use csv::StringRecord;
 
 use thiserror::Error;
 
-use rinfluxdb_types::Value;
+use rinfluxdb_types::{Columns, FromQueryResult, IndexMap, Value};
 
-use super::ResponseResult;
+use super::{Dialect, ResponseResult, TaggedDataframe};
 
 /// An error occurred while parsing format
 #[derive(Error, Debug)]
@@ -43,10 +41,22 @@ pub enum ResponseError {
     #[error("CSV parse error")]
     CsvError(#[from] csv::Error),
 
-    /// Error occurred while parsing a datetime
-    #[error("Chrono parse error")]
+    /// Error occurred while parsing a floating point number
+    #[error("Float parse error")]
     ParseFloatError(#[from] ParseFloatError),
 
+    /// Error occurred while parsing an integer number
+    #[error("Integer parse error")]
+    ParseIntError(#[from] ParseIntError),
+
+    /// A required column is missing
+    #[error("Missing column {0}")]
+    MissingColumn(String),
+
+    /// A generic error occurred
+    #[error("value error {0}")]
+    ValueError(String),
+
     /// Input is not a valid ISO8601 datetime
     #[error("could not parse datetime")]
     DatetimeError(#[from] chrono::ParseError),
@@ -54,68 +64,650 @@ pub enum ResponseError {
     /// Error while creating dataframe
     #[error("could not create dataframe")]
     DataFrameError(#[from] rinfluxdb_types::DataFrameError),
+
+    /// The server reported an error in an error table
+    ///
+    /// Errors can occur after some tables were already streamed, in which
+    /// case the previous tables are discarded.
+    #[error("server error: {message}")]
+    Server {
+        /// The error message
+        message: String,
+
+        /// The error code, if any
+        reference: Option<i64>,
+    },
 }
 
-/// Parse an annotated CSV response returned from InfluxDB to a list of tagged dataframes.
-pub fn from_str<DF, E>(input: &str) -> ResponseResult<DF>
+/// Parse an annotated CSV response returned from InfluxDB to tagged
+/// dataframes
+///
+/// The response can contain multiple tables, possibly with different
+/// schemas.
+/// Each table is expected to have a `_time` column, which is used as index.
+/// If a table has columns `_field` and `_value`, the value is stored in a
+/// column named after the field.
+/// Otherwise, as for pivoted tables, every column that is not part of the
+/// group key is stored in a column with the same name.
+///
+/// Values are decoded according to the `#datatype` annotation, and empty
+/// values are replaced by the `#default` annotation.
+///
+/// Tables are grouped by measurement and by the values of their group key
+/// columns, as in the `#group` annotation, excluding `_start`, `_stop`,
+/// `_measurement` and `_field`.
+/// Tables in the same group are merged in a single dataframe, named after
+/// the `_measurement` column, so tables of different fields of the same
+/// series end up in the same dataframe.
+/// Every dataframe is returned together with its group key values, in the
+/// order they first appear in the response.
+///
+/// If the response contains an error table, with columns `error` and
+/// `reference`, [`ResponseError::Server`] is returned.
+///
+/// ```
+/// # use chrono::{TimeZone, Utc};
+/// # use rinfluxdb_types::{TimeSeries, Value};
+/// # use rinfluxdb_flux::{from_str, ResponseError, TaggedDataframe};
+/// let input = "\
+/// #datatype,string,long,dateTime:RFC3339,double,string,string,string\r\n\
+/// #group,false,false,false,false,true,true,true\r\n\
+/// #default,_result,,,,,,\r\n\
+/// ,result,table,_time,_value,_field,_measurement,room\r\n\
+/// ,,0,2021-03-04T17:00:00Z,28.4,temperature,environment,bedroom\r\n\
+/// ,,1,2021-03-04T17:00:00Z,41,humidity,environment,bedroom\r\n\
+/// ,,2,2021-03-04T18:00:00Z,22.1,temperature,environment,kitchen\r\n\
+/// \r\n";
+///
+/// let dataframes: Vec<TaggedDataframe<TimeSeries>> = from_str(input)?;
+///
+/// assert_eq!(dataframes.len(), 2);
+/// let (bedroom, tags) = &dataframes[0];
+/// assert_eq!(tags["room"], "bedroom");
+/// assert_eq!(
+///     bedroom.field("humidity"),
+///     Some(&[(Utc.with_ymd_and_hms(2021, 3, 4, 17, 0, 0).unwrap(), Value::Float(41.0))][..]),
+/// );
+/// # Ok::<(), ResponseError>(())
+/// ```
+pub fn from_str<DF, E>(input: &str) -> ResponseResult<Vec<TaggedDataframe<DF>>>
 where
-    DF: TryFrom<(String, Vec<DateTime<Utc>>, HashMap<String, Vec<Value>>), Error = E>,
+    DF: FromQueryResult<Error = E>,
     E: Into<ResponseError>,
 {
-    let payloads: Vec<_> = input.split("\r\n\r\n").collect();
+    from_str_with_dialect(input, &Dialect::default())
+}
 
-    for payload in payloads {
-        if payload.is_empty() {
-            break;
-        }
-        println!("{}", payload);
-        println!("-------------");
-
-
-        let mut csv = CsvReaderBuilder::new()
-            .comment(None)
-            .has_headers(false)
-            .from_reader(payload.as_bytes());
-        let mut rows = csv.records();
-        let data_types = rows.next().ok_or(ResponseError::DataTypes)??;
-        let grouping = rows.next().ok_or(ResponseError::Grouping)??;
-        let default = rows.next().ok_or(ResponseError::Default)??;
-        let columns = rows.next().ok_or(ResponseError::Columns)??;
-
-        let columns: Vec<_> = izip!(
-                columns.into_iter(),
-                data_types.into_iter(),
-                grouping.into_iter(),
-                default.into_iter()
-            )
-            .skip(1)
-            .collect();
+/// Parse a CSV response in a specific dialect returned from InfluxDB to
+/// tagged dataframes
+///
+/// This is the same as [`from_str`], but for responses to queries that
+/// requested a dialect other than the default one.
+/// See [`Dialect`] for how missing annotations are handled.
+/// Without a `#group` annotation, all tables of a measurement are merged in
+/// a single dataframe without tags.
+///
+/// ```
+/// # use chrono::{TimeZone, Utc};
+/// # use rinfluxdb_types::{TimeSeries, Value};
+/// # use rinfluxdb_flux::{from_str_with_dialect, Dialect, ResponseError, TaggedDataframe};
+/// let input = "\
+/// ;result;table;_time;_value;_field\r\n\
+/// ;_result;0;2021-03-04T17:00:00Z;28.4;temperature\r\n\
+/// \r\n";
+///
+/// let dialect = Dialect {
+///     annotations: vec![],
+///     delimiter: b';',
+///     ..Dialect::default()
+/// };
+/// let dataframes: Vec<TaggedDataframe<TimeSeries>> = from_str_with_dialect(input, &dialect)?;
+/// let (series, tags) = &dataframes[0];
+///
+/// assert!(tags.is_empty());
+/// assert_eq!(
+///     series.field("temperature"),
+///     Some(&[(Utc.with_ymd_and_hms(2021, 3, 4, 17, 0, 0).unwrap(), Value::Float(28.4))][..]),
+/// );
+/// # Ok::<(), ResponseError>(())
+/// ```
+pub fn from_str_with_dialect<DF, E>(input: &str, dialect: &Dialect) -> ResponseResult<Vec<TaggedDataframe<DF>>>
+where
+    DF: FromQueryResult<Error = E>,
+    E: Into<ResponseError>,
+{
+    parse_groups(input, dialect, true)?
+        .into_iter()
+        .map(|(key, group)| {
+            let tags = key.tags.into_iter().collect();
+            Ok((group.into_dataframe()?, tags))
+        })
+        .collect()
+}
 
-        println!("Columns: {:?}", columns);
+/// Parse an annotated CSV response returned from InfluxDB to a single
+/// dataframe
+///
+/// This is the same as [`from_str`], but all tables are merged in a single
+/// dataframe, named after the `_measurement` column of the first row, so
+/// every column must have a value at every instant in the index.
+///
+/// ```
+/// # use chrono::{TimeZone, Utc};
+/// # use rinfluxdb_types::{TimeSeries, Value};
+/// # use rinfluxdb_flux::{from_str_merged, ResponseError};
+/// let input = "\
+/// #datatype,string,long,dateTime:RFC3339,double,string,string\r\n\
+/// #group,false,false,false,false,true,true\r\n\
+/// #default,_result,,,,,\r\n\
+/// ,result,table,_time,_value,_field,_measurement\r\n\
+/// ,,0,2021-03-04T17:00:00Z,28.4,temperature,environment\r\n\
+/// ,,0,2021-03-04T18:00:00Z,29.2,temperature,environment\r\n\
+/// ,,1,2021-03-04T17:00:00Z,41,humidity,environment\r\n\
+/// ,,1,2021-03-04T18:00:00Z,37,humidity,environment\r\n\
+/// \r\n";
+///
+/// let series: TimeSeries = from_str_merged(input)?;
+///
+/// assert_eq!(
+///     series.field("humidity"),
+///     Some(&[
+///         (Utc.with_ymd_and_hms(2021, 3, 4, 17, 0, 0).unwrap(), Value::Float(41.0)),
+///         (Utc.with_ymd_and_hms(2021, 3, 4, 18, 0, 0).unwrap(), Value::Float(37.0)),
+///     ][..]),
+/// );
+/// # Ok::<(), ResponseError>(())
+/// ```
+pub fn from_str_merged<DF, E>(input: &str) -> ResponseResult<DF>
+where
+    DF: FromQueryResult<Error = E>,
+    E: Into<ResponseError>,
+{
+    from_str_merged_with_dialect(input, &Dialect::default())
+}
+
+/// Parse a CSV response in a specific dialect returned from InfluxDB to a
+/// single dataframe
+///
+/// This is the same as [`from_str_merged`], but for responses to queries
+/// that requested a dialect other than the default one.
+pub fn from_str_merged_with_dialect<DF, E>(input: &str, dialect: &Dialect) -> ResponseResult<DF>
+where
+    DF: FromQueryResult<Error = E>,
+    E: Into<ResponseError>,
+{
+    parse_groups(input, dialect, false)?
+        .into_iter()
+        .next()
+        .map(|(_, group)| group)
+        .unwrap_or_default()
+        .into_dataframe()
+}
+
+/// Parse the rows of all tables in a response, and group them
+///
+/// If `by_group_key` is false, all rows end up in a single group.
+fn parse_groups(input: &str, dialect: &Dialect, by_group_key: bool) -> Result<IndexMap<GroupKey, Group>, ResponseError> {
+    let mut csv = CsvReaderBuilder::new()
+        .delimiter(dialect.delimiter)
+        .comment(None)
+        .has_headers(false)
+        .flexible(true)
+        .from_reader(input.as_bytes());
+
+    let mut groups: IndexMap<GroupKey, Group> = IndexMap::new();
+    let mut schema = Schema::default();
 
-        let mut index: Vec<DateTime<Utc>> = Vec::new();
-        let mut values: Vec<f64> = Vec::new();
+    for record in csv.records() {
+        let record = record?;
 
-        for result in rows {
-            let record = result?;
-            let pairs = columns.iter().zip(record.into_iter().skip(1));
-            for (column, field) in pairs {
-                println!("{}: {} (grouping? {})", column.0, field, column.2);
-                if column.0 == "_time" {
-                    let instant = field.parse()?;
-                    index.push(instant);
+        match record.get(0) {
+            Some(annotation) if annotation.starts_with('#') => {
+                if schema.columns.is_some() {
+                    schema = Schema::default();
                 }
+                schema.annotate(annotation, &record);
+            }
+            _ if dialect.header && (schema.columns.is_none() || is_header(&record)) => {
+                // Without annotations, a header row is the only separator
+                // between tables
+                if schema.columns.is_some() {
+                    schema = Schema::default();
+                }
+                schema.columns = Some(record.iter().map(str::to_owned).collect());
+            }
+            _ if schema.is_error() => return Err(schema.parse_error(&record)),
+            _ => {
+                let row = schema.parse_row(&record)?;
+                let key = if by_group_key {
+                    GroupKey {
+                        measurement: row.measurement.clone(),
+                        tags: row.tags,
+                    }
+                } else {
+                    GroupKey::default()
+                };
+                let group = groups.entry(key).or_default();
 
-                if column.0 == "_value" {
-                    let value = field.parse()?;
-                    values.push(value);
+                if group.name.is_none() {
+                    group.name = row.measurement;
                 }
 
-                if !column.0.starts_with('_') {}
+                let values = group.rows.entry(row.time).or_default();
+                for (column, value) in row.values {
+                    group.columns.entry(column.clone()).or_default();
+                    if values.insert(column.clone(), value).is_some() {
+                        return Err(ResponseError::ValueError(format!(
+                            "multiple values for column {} at {}",
+                            column, row.time,
+                        )));
+                    }
+                }
+            }
+        }
+    }
+
+    Ok(groups)
+}
+
+/// The measurement and the sorted group key values shared by tables
+#[derive(Debug, Default, PartialEq, Eq, Hash)]
+struct GroupKey {
+    measurement: Option<String>,
+    tags: Vec<(String, String)>,
+}
+
+/// Rows of tables merged in a single dataframe
+#[derive(Debug, Default)]
+struct Group {
+    name: Option<String>,
+    rows: BTreeMap<DateTime<Utc>, HashMap<String, Value>>,
+    columns: Columns,
+}
+
+impl Group {
+    fn into_dataframe<DF, E>(mut self) -> ResponseResult<DF>
+    where
+        DF: FromQueryResult<Error = E>,
+        E: Into<ResponseError>,
+    {
+        let index: Vec<DateTime<Utc>> = self.rows.keys().cloned().collect();
+
+        for (time, mut values) in self.rows {
+            for (column, column_values) in self.columns.iter_mut() {
+                let value = values.remove(column).ok_or_else(|| {
+                    ResponseError::ValueError(format!("missing value for column {} at {}", column, time))
+                })?;
+                column_values.push(value);
+            }
+        }
+
+        let dataframe = DF::from_query_result(self.name.unwrap_or_default(), index, self.columns, None)
+            .map_err(|error| error.into())?;
+
+        Ok(dataframe)
+    }
+}
+
+/// Check whether a record is the header row of a table or of an error table
+fn is_header(record: &StringRecord) -> bool {
+    (record.get(1) == Some("result") && record.get(2) == Some("table"))
+        || (record.get(1) == Some("error") && record.get(2) == Some("reference"))
+}
+
+/// Detect the type of a value when the data type is not known
+fn infer_value(cell: &str) -> Value {
+    match cell {
+        "" => Value::Null,
+        "true" => Value::Boolean(true),
+        "false" => Value::Boolean(false),
+        cell => cell
+            .parse()
+            .map(Value::Float)
+            .unwrap_or_else(|_| Value::String(cell.to_owned())),
+    }
+}
+
+/// Columns and annotations of a table
+#[derive(Debug, Default)]
+struct Schema {
+    data_types: Option<Vec<String>>,
+    group: Option<Vec<bool>>,
+    defaults: Option<Vec<String>>,
+    columns: Option<Vec<String>>,
+}
+
+/// A parsed row of a table
+struct Row {
+    measurement: Option<String>,
+    tags: Vec<(String, String)>,
+    time: DateTime<Utc>,
+    values: Vec<(String, Value)>,
+}
+
+/// Columns that are never stored as values
+const RESERVED_COLUMNS: &[&str] = &["", "result", "table", "_time", "_start", "_stop"];
+
+/// Group key columns that are not used as tags
+const NON_TAG_COLUMNS: &[&str] = &["_measurement", "_field", "_value"];
+
+impl Schema {
+    fn annotate(&mut self, annotation: &str, record: &StringRecord) {
+        let cells = record.iter().map(str::to_owned);
+        match annotation {
+            "#datatype" => self.data_types = Some(cells.collect()),
+            "#group" => self.group = Some(cells.map(|cell| cell == "true").collect()),
+            "#default" => self.defaults = Some(cells.collect()),
+            _ => {}
+        }
+    }
+
+    fn position(&self, name: &str) -> Option<usize> {
+        self.columns
+            .as_ref()
+            .and_then(|columns| columns.iter().position(|column| column == name))
+    }
+
+    fn is_group(&self, index: usize) -> bool {
+        self.group
+            .as_ref()
+            .and_then(|group| group.get(index).copied())
+            .unwrap_or(false)
+    }
+
+    fn cell<'a>(&'a self, record: &'a StringRecord, index: usize) -> &'a str {
+        match record.get(index) {
+            Some(cell) if !cell.is_empty() => cell,
+            _ => self
+                .defaults
+                .as_ref()
+                .and_then(|defaults| defaults.get(index))
+                .map(String::as_str)
+                .unwrap_or(""),
+        }
+    }
+
+    fn value(&self, record: &StringRecord, index: usize) -> Result<Value, ResponseError> {
+        let cell = self.cell(record, index);
+        let data_type = match &self.data_types {
+            Some(data_types) => data_types.get(index).map(String::as_str).unwrap_or("string"),
+            None => return Ok(infer_value(cell)),
+        };
+
+        let value = match data_type {
+            "string" => Value::String(cell.to_owned()),
+            "double" => Value::Float(cell.parse()?),
+            "long" => Value::Integer(cell.parse()?),
+            "unsignedLong" => Value::UnsignedInteger(cell.parse()?),
+            "boolean" => match cell {
+                "true" => Value::Boolean(true),
+                "false" => Value::Boolean(false),
+                _ => return Err(ResponseError::ValueError(format!("invalid boolean {}", cell))),
+            },
+            "dateTime:RFC3339" | "dateTime:RFC3339Nano" => {
+                Value::Timestamp(DateTime::parse_from_rfc3339(cell)?.with_timezone(&Utc))
+            }
+            data_type => {
+                return Err(ResponseError::ValueError(format!(
+                    "unsupported data type {}",
+                    data_type,
+                )))
             }
-            println!();
+        };
+
+        Ok(value)
+    }
+
+    /// Check whether the table reports an error instead of data
+    fn is_error(&self) -> bool {
+        self.position("error").is_some() && self.position("_time").is_none()
+    }
+
+    fn parse_error(&self, record: &StringRecord) -> ResponseError {
+        let message = self
+            .position("error")
+            .map(|index| self.cell(record, index).to_owned())
+            .unwrap_or_default();
+        let reference = self
+            .position("reference")
+            .and_then(|index| self.cell(record, index).parse().ok());
+        ResponseError::Server { message, reference }
+    }
+
+    fn parse_row(&self, record: &StringRecord) -> Result<Row, ResponseError> {
+        let columns = self.columns.as_ref().ok_or(ResponseError::Columns)?;
+
+        let time = self
+            .position("_time")
+            .ok_or_else(|| ResponseError::MissingColumn("_time".into()))?;
+        let time = DateTime::parse_from_rfc3339(self.cell(record, time))?.with_timezone(&Utc);
+
+        let measurement = self
+            .position("_measurement")
+            .map(|index| self.cell(record, index).to_owned());
+
+        let values = match (self.position("_field"), self.position("_value")) {
+            (Some(field), Some(value)) => {
+                vec![(self.cell(record, field).to_owned(), self.value(record, value)?)]
+            }
+            _ => columns
+                .iter()
+                .enumerate()
+                .filter(|(index, column)| {
+                    !RESERVED_COLUMNS.contains(&column.as_str()) && !self.is_group(*index)
+                })
+                .map(|(index, column)| Ok((column.clone(), self.value(record, index)?)))
+                .collect::<Result<_, ResponseError>>()?,
+        };
+
+        let mut tags: Vec<(String, String)> = columns
+            .iter()
+            .enumerate()
+            .filter(|(index, column)| {
+                self.is_group(*index)
+                    && !RESERVED_COLUMNS.contains(&column.as_str())
+                    && !NON_TAG_COLUMNS.contains(&column.as_str())
+            })
+            .map(|(index, column)| (column.clone(), self.cell(record, index).to_owned()))
+            .collect();
+        tags.sort();
+
+        Ok(Row {
+            measurement,
+            tags,
+            time,
+            values,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use chrono::TimeZone;
+
+    use rinfluxdb_types::TimeSeries;
+
+    #[test]
+    fn multiple_schemas() -> Result<(), ResponseError> {
+        let input = "\
+#datatype,string,long,dateTime:RFC3339,long,string,string\r
+#group,false,false,false,false,true,true\r
+#default,_result,,,,,\r
+,result,table,_time,_value,_field,_measurement\r
+,,0,2021-03-04T17:00:00Z,3,count,environment\r
+\r
+#datatype,string,long,dateTime:RFC3339,boolean,string\r
+#group,false,false,false,false,true\r
+#default,_result,,,,\r
+,result,table,_time,window,_measurement\r
+,,1,2021-03-04T17:00:00Z,true,environment\r
+\r
+";
+
+        let mut dataframes: Vec<TaggedDataframe<TimeSeries>> = from_str(input)?;
+        assert_eq!(dataframes.len(), 1);
+        let (series, tags) = dataframes.remove(0);
+
+        let time = Utc.with_ymd_and_hms(2021, 3, 4, 17, 0, 0).unwrap();
+        assert!(tags.is_empty());
+        assert_eq!(series.field("count"), Some(&[(time, Value::Integer(3))][..]));
+        assert_eq!(series.field("window"), Some(&[(time, Value::Boolean(true))][..]));
+        Ok(())
+    }
+
+    #[test]
+    fn default_values() -> Result<(), ResponseError> {
+        let input = "\
+#datatype,string,long,dateTime:RFC3339,double,string\r
+#group,false,false,false,false,true\r
+#default,_result,,,0,\r
+,result,table,_time,_value,_field\r
+,,0,2021-03-04T17:00:00Z,,temperature\r
+";
+
+        let series: TimeSeries = from_str_merged(input)?;
+
+        let time = Utc.with_ymd_and_hms(2021, 3, 4, 17, 0, 0).unwrap();
+        assert_eq!(series.field("temperature"), Some(&[(time, Value::Float(0.0))][..]));
+        Ok(())
+    }
+
+    #[test]
+    fn no_annotations() -> Result<(), ResponseError> {
+        let input = "\
+,result,table,_time,_value,_field\r
+,_result,0,2021-03-04T17:00:00Z,28.4,temperature\r
+\r
+,result,table,_time,open,note\r
+,_result,1,2021-03-04T17:00:00Z,true,\r
+";
+
+        let dialect = Dialect {
+            annotations: vec![],
+            ..Dialect::default()
+        };
+        let series: TimeSeries = from_str_merged_with_dialect(input, &dialect)?;
+
+        let time = Utc.with_ymd_and_hms(2021, 3, 4, 17, 0, 0).unwrap();
+        assert_eq!(series.field("temperature"), Some(&[(time, Value::Float(28.4))][..]));
+        assert_eq!(series.field("open"), Some(&[(time, Value::Boolean(true))][..]));
+        assert_eq!(series.field("note"), Some(&[][..]));
+        Ok(())
+    }
+
+    #[test]
+    fn no_header() {
+        let input = "\
+#datatype,string,long,dateTime:RFC3339,double,string\r
+,,0,2021-03-04T17:00:00Z,28.4,temperature\r
+";
+
+        let dialect = Dialect {
+            header: false,
+            ..Dialect::default()
+        };
+        let result: ResponseResult<Vec<TaggedDataframe<TimeSeries>>> = from_str_with_dialect(input, &dialect);
+
+        assert!(matches!(result, Err(ResponseError::Columns)));
+    }
+
+    #[test]
+    fn missing_values() {
+        let input = "\
+#datatype,string,long,dateTime:RFC3339,double,string\r
+#group,false,false,false,false,true\r
+#default,_result,,,,\r
+,result,table,_time,_value,_field\r
+,,0,2021-03-04T17:00:00Z,28.4,temperature\r
+,,1,2021-03-04T18:00:00Z,41,humidity\r
+";
+
+        let result: ResponseResult<TimeSeries> = from_str_merged(input);
+
+        assert!(matches!(result, Err(ResponseError::ValueError(_))));
+    }
+
+    #[test]
+    fn error_table() {
+        let input = "\
+#datatype,string,long,dateTime:RFC3339,double,string\r
+#group,false,false,false,false,true\r
+#default,_result,,,,\r
+,result,table,_time,_value,_field\r
+,,0,2021-03-04T17:00:00Z,28.4,temperature\r
+\r
+#datatype,string,string\r
+#group,true,true\r
+#default,,\r
+,error,reference\r
+,\"panic: runtime error: index out of range\",897\r
+";
+
+        let result: ResponseResult<TimeSeries> = from_str_merged(input);
+
+        match result {
+            Err(ResponseError::Server { message, reference }) => {
+                assert_eq!(message, "panic: runtime error: index out of range");
+                assert_eq!(reference, Some(897));
+            }
+            result => panic!("Unexpected result {:?}", result.map(|_| ())),
         }
     }
 
-    todo!()
+    #[test]
+    fn error_table_without_annotations() {
+        let input = "\
+,result,table,_time,_value,_field\r
+,_result,0,2021-03-04T17:00:00Z,28.4,temperature\r
+\r
+,error,reference\r
+,query timed out,\r
+";
+
+        let dialect = Dialect {
+            annotations: vec![],
+            ..Dialect::default()
+        };
+        let result: ResponseResult<Vec<TaggedDataframe<TimeSeries>>> = from_str_with_dialect(input, &dialect);
+
+        assert!(matches!(
+            result,
+            Err(ResponseError::Server { message, reference: None }) if message == "query timed out"
+        ));
+    }
+
+    #[test]
+    fn group_by_tags() -> Result<(), ResponseError> {
+        let input = "\
+#datatype,string,long,dateTime:RFC3339,dateTime:RFC3339,dateTime:RFC3339,double,string,string,string\r
+#group,false,false,true,true,false,false,true,true,true\r
+#default,_result,,,,,,,,\r
+,result,table,_start,_stop,_time,_value,_field,_measurement,room\r
+,,0,2021-03-04T00:00:00Z,2021-03-05T00:00:00Z,2021-03-04T17:00:00Z,28.4,temperature,environment,bedroom\r
+,,1,2021-03-04T00:00:00Z,2021-03-05T00:00:00Z,2021-03-04T18:00:00Z,22.1,temperature,environment,kitchen\r
+,,2,2021-03-04T00:00:00Z,2021-03-05T00:00:00Z,2021-03-04T17:00:00Z,41,humidity,environment,bedroom\r
+,,3,2021-03-04T00:00:00Z,2021-03-05T00:00:00Z,2021-03-04T17:00:00Z,3,count,system,\r
+";
+
+        let dataframes: Vec<TaggedDataframe<TimeSeries>> = from_str(input)?;
+
+        let first = Utc.with_ymd_and_hms(2021, 3, 4, 17, 0, 0).unwrap();
+        let second = Utc.with_ymd_and_hms(2021, 3, 4, 18, 0, 0).unwrap();
+        let tags: Vec<&str> = dataframes.iter().map(|(_, tags)| tags["room"].as_str()).collect();
+        assert_eq!(tags, vec!["bedroom", "kitchen", ""]);
+
+        let (bedroom, _) = &dataframes[0];
+        assert_eq!(bedroom.field("temperature"), Some(&[(first, Value::Float(28.4))][..]));
+        assert_eq!(bedroom.field("humidity"), Some(&[(first, Value::Float(41.0))][..]));
+
+        let (kitchen, _) = &dataframes[1];
+        assert_eq!(kitchen.field("temperature"), Some(&[(second, Value::Float(22.1))][..]));
+        assert_eq!(kitchen.field("humidity"), None);
+
+        let (system, _) = &dataframes[2];
+        assert_eq!(system.field("count"), Some(&[(first, Value::Float(3.0))][..]));
+        Ok(())
+    }
 }
@@ -0,0 +1,256 @@
+// Copyright Claudio Mattera 2021.
+// Distributed under the MIT License or Apache 2.0 License at your option.
+// See accompanying files License-MIT.txt and License-Apache-2.0, or online at
+// https://opensource.org/licenses/MIT
+// https://opensource.org/licenses/Apache-2.0
+
+//! Data types to manage tasks on InfluxDB 2.x servers
+//!
+//! Tasks are Flux scripts run periodically by the server, typically to
+//! downsample data.
+//! They are managed through the clients' methods, such as
+//! [`create_task`](super::blocking::Client::create_task) and
+//! [`tasks`](super::blocking::Client::tasks).
+
+use chrono::{DateTime, Utc};
+
+use serde::Deserialize;
+
+use serde_json::{json, Value as JsonValue};
+
+use rinfluxdb_types::Duration;
+
+use super::querybuilder::string_literal;
+
+/// When a task is run
+#[derive(Clone, Debug, PartialEq)]
+pub enum Schedule {
+    /// Run a task periodically
+    Every(Duration),
+
+    /// Run a task according to a Cron expression, such as `0 * * * *`
+    Cron(String),
+}
+
+/// A task to be created
+///
+/// ```
+/// # use rinfluxdb_types::Duration;
+/// # use rinfluxdb_flux::tasks::{NewTask, Schedule};
+/// let task = NewTask::new(
+///     "downsample",
+///     Schedule::Every(Duration::Hours(1)),
+///     r#"from(bucket: "house") |> range(start: -1h) |> mean() |> to(bucket: "house_1h")"#,
+/// )
+/// .offset(Duration::Minutes(5));
+///
+/// assert_eq!(
+///     task.script(),
+///     r#"option task = {name: "downsample", every: 1h, offset: 5m}
+///
+/// from(bucket: "house") |> range(start: -1h) |> mean() |> to(bucket: "house_1h")"#,
+/// );
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct NewTask {
+    name: String,
+    schedule: Schedule,
+    offset: Option<Duration>,
+    description: Option<String>,
+    flux: String,
+}
+
+impl NewTask {
+    /// Create a task running a Flux script according to a schedule
+    pub fn new<T, S>(name: T, schedule: Schedule, flux: S) -> Self
+    where
+        T: Into<String>,
+        S: Into<String>,
+    {
+        Self {
+            name: name.into(),
+            schedule,
+            offset: None,
+            description: None,
+            flux: flux.into(),
+        }
+    }
+
+    /// Delay each run, for instance to wait for late data
+    pub fn offset(mut self, offset: Duration) -> Self {
+        self.offset = Some(offset);
+        self
+    }
+
+    /// Set the task description
+    pub fn description<T>(mut self, description: T) -> Self
+    where
+        T: Into<String>,
+    {
+        self.description = Some(description.into());
+        self
+    }
+
+    /// Return the Flux script, preceded by the task options
+    pub fn script(&self) -> String {
+        let mut options = vec![format!("name: {}", string_literal(&self.name))];
+        match &self.schedule {
+            Schedule::Every(every) => options.push(format!("every: {}", every)),
+            Schedule::Cron(cron) => options.push(format!("cron: {}", string_literal(cron))),
+        }
+        if let Some(offset) = &self.offset {
+            options.push(format!("offset: {}", offset));
+        }
+        format!("option task = {{{}}}\n\n{}", options.join(", "), self.flux)
+    }
+
+    /// Return the body of the creation request
+    pub(crate) fn body(&self, organization: (&str, &str)) -> JsonValue {
+        let (key, organization) = organization;
+        let mut body = json!({
+            "flux": self.script(),
+            "status": TaskStatus::Active.as_str(),
+        });
+        body[key] = JsonValue::from(organization);
+        if let Some(description) = &self.description {
+            body["description"] = JsonValue::from(description.as_str());
+        }
+        body
+    }
+}
+
+/// Whether a task is run
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum TaskStatus {
+    /// The task is run according to its schedule
+    Active,
+
+    /// The task is not run
+    Inactive,
+}
+
+impl TaskStatus {
+    /// Return the name used by InfluxDB for this status
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TaskStatus::Active => "active",
+            TaskStatus::Inactive => "inactive",
+        }
+    }
+}
+
+/// A task existing on a server
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct Task {
+    /// Task identifier
+    pub id: String,
+
+    /// Identifier of the organization owning the task
+    #[serde(rename = "orgID")]
+    pub org_id: String,
+
+    /// Task name
+    pub name: String,
+
+    /// Task description
+    pub description: Option<String>,
+
+    /// Whether the task is run
+    pub status: TaskStatus,
+
+    /// Flux script, including the task options
+    pub flux: String,
+
+    /// Run period, if the task is run periodically
+    pub every: Option<String>,
+
+    /// Cron expression, if the task is run according to one
+    pub cron: Option<String>,
+
+    /// Delay of each run
+    pub offset: Option<String>,
+
+    /// Instant of the latest completed run
+    pub latest_completed: Option<DateTime<Utc>>,
+
+    /// Status of the latest run
+    pub last_run_status: Option<String>,
+}
+
+/// Status of a task run
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum RunStatus {
+    /// The run is scheduled but has not started yet
+    Scheduled,
+
+    /// The run is in progress
+    Started,
+
+    /// The run failed
+    Failed,
+
+    /// The run completed successfully
+    Success,
+
+    /// The run was canceled
+    Canceled,
+}
+
+/// A run of a task
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct Run {
+    /// Run identifier
+    pub id: String,
+
+    /// Identifier of the task
+    #[serde(rename = "taskID")]
+    pub task_id: String,
+
+    /// Run status
+    pub status: RunStatus,
+
+    /// Instant the run was scheduled for
+    pub scheduled_for: DateTime<Utc>,
+
+    /// Instant the run started
+    pub started_at: Option<DateTime<Utc>>,
+
+    /// Instant the run finished
+    pub finished_at: Option<DateTime<Utc>>,
+}
+
+/// A log message of a task
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+pub struct LogEvent {
+    /// Identifier of the run that emitted the message
+    #[serde(rename = "runID")]
+    pub run_id: Option<String>,
+
+    /// Instant of the message
+    pub time: DateTime<Utc>,
+
+    /// Message text
+    pub message: String,
+}
+
+/// Response to a request for listing tasks
+#[derive(Deserialize)]
+pub(crate) struct Tasks {
+    pub(crate) tasks: Vec<Task>,
+}
+
+/// Response to a request for listing runs
+#[derive(Deserialize)]
+pub(crate) struct Runs {
+    pub(crate) runs: Vec<Run>,
+}
+
+/// Response to a request for listing log messages
+#[derive(Deserialize)]
+pub(crate) struct Logs {
+    pub(crate) events: Vec<LogEvent>,
+}
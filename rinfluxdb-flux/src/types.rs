@@ -4,7 +4,16 @@
 // https://opensource.org/licenses/MIT
 // https://opensource.org/licenses/Apache-2.0
 
+use std::collections::HashMap;
+
 use super::ResponseError;
 
-/// The result of an entire InfluxQL query
+/// A map of tag names to values
+pub type TagsMap = HashMap<String, String>;
+
+/// A dataframe built from tables with the same group key, and the values of
+/// the group key columns used as tags
+pub type TaggedDataframe<DF> = (DF, TagsMap);
+
+/// The result of an entire Flux query
 pub type ResponseResult<DF> = Result<DF, ResponseError>;
@@ -4,11 +4,26 @@
 // https://opensource.org/licenses/MIT
 // https://opensource.org/licenses/Apache-2.0
 
+use std::collections::HashMap;
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+
 use thiserror::Error;
 
-use super::response::ResponseError;
+use reqwest::header::{HeaderMap, RETRY_AFTER};
+use reqwest::StatusCode;
+
+use rinfluxdb_types::{parse_retry_after, Columns, FromQueryResult, MetricsObserver, Ping, Rows, TimeSeries, TransportError, Value};
 
+use super::querybuilder::QueryBuilder;
+use super::query::Query;
+use super::response::{from_str, RawDataFrame, ResponseError};
+use super::{StatementResult, TaggedDataframe, TagsMap};
+
+#[cfg(feature = "client-async")]
 pub mod r#async;
+#[cfg(feature = "client-blocking")]
 pub mod blocking;
 
 /// An error occurred during interfacing with an InfluxDB server
@@ -18,10 +33,18 @@ pub enum ClientError {
     #[error("Reqwest error")]
     ReqwestError(#[from] reqwest::Error),
 
+    /// Error occurred while reading a response
+    #[error("IO error")]
+    IoError(#[from] std::io::Error),
+
     /// Error occurred while parsing a URL
     #[error("URL parse error")]
     UrlError(#[from] url::ParseError),
 
+    /// Error occurred within a custom transport
+    #[error("Transport error: {0}")]
+    TransportError(TransportError),
+
     /// Error occurred while parsing format
     #[error("Format parse error")]
     FormatError(#[from] ResponseError),
@@ -37,4 +60,509 @@ pub enum ClientError {
     /// An expected tag was missing
     #[error("Missing tag \"{0}\"")]
     ExpectedTagError(String),
+
+    /// The response contained more statements or dataframes than expected
+    #[error("Unexpected data in response")]
+    UnexpectedDataError,
+
+    /// Dataframes in the response could not be merged
+    #[error("Cannot merge dataframes: {0}")]
+    MergeError(String),
+
+    /// A tag value could not be decoded
+    #[error("Tag value error")]
+    TagValueError(#[from] rinfluxdb_types::TagValueError),
+
+    /// A row could not be deserialized
+    #[error("Deserialization error")]
+    DeserializeError(#[from] serde_json::Error),
+
+    /// Signing in did not return a session cookie
+    #[error("Missing session cookie")]
+    MissingSessionCookie,
+
+    /// Server is rejecting requests because too many were sent
+    #[error("Rate limited")]
+    RateLimited {
+        /// How long to wait before sending the next request, from header
+        /// `Retry-After`
+        retry_after: Option<Duration>,
+    },
+}
+
+/// The default value of header `User-Agent`, including the crate version
+const DEFAULT_USER_AGENT: &str = concat!("rinfluxdb/", env!("CARGO_PKG_VERSION"));
+
+fn user_agent(user_agent: Option<String>, application: Option<String>) -> String {
+    let user_agent = user_agent.unwrap_or_else(|| DEFAULT_USER_AGENT.to_string());
+    match application {
+        Some(application) => format!("{} {}", user_agent, application),
+        None => user_agent,
+    }
+}
+
+/// Check whether a client error was caused by missing or expired credentials
+fn is_unauthorized(error: &ClientError) -> bool {
+    match error {
+        ClientError::ReqwestError(error) => error.status() == Some(StatusCode::UNAUTHORIZED),
+        _ => false,
+    }
+}
+
+/// Parse header `Retry-After`, either as a number of seconds or as a date
+fn retry_after(headers: &HeaderMap) -> Option<Duration> {
+    parse_retry_after(headers.get(RETRY_AFTER)?.to_str().ok()?)
+}
+
+/// Return an error if a response asks the client to slow down
+///
+/// Status 429 always does, while status 503 does only with header
+/// `Retry-After`.
+fn rate_limited(status: StatusCode, headers: &HeaderMap) -> Option<ClientError> {
+    let is_rate_limited = status == StatusCode::TOO_MANY_REQUESTS
+        || (status == StatusCode::SERVICE_UNAVAILABLE && headers.contains_key(RETRY_AFTER));
+    if is_rate_limited {
+        Some(ClientError::RateLimited {
+            retry_after: retry_after(headers),
+        })
+    } else {
+        None
+    }
+}
+
+/// Check whether a client error is likely to be temporary
+///
+/// Errors returned by custom transports are always considered temporary.
+#[cfg(feature = "client-blocking")]
+fn is_transient_error(error: &ClientError) -> bool {
+    match error {
+        ClientError::ReqwestError(error) => is_transient(error),
+        ClientError::TransportError(_) => true,
+        _ => false,
+    }
+}
+
+/// Check whether a request error is likely to be temporary
+#[cfg(not(target_arch = "wasm32"))]
+fn is_transient(error: &reqwest::Error) -> bool {
+    error.is_connect() || error.is_timeout() || error.is_request()
+}
+
+/// Check whether a request error is likely to be temporary
+///
+/// Browsers do not report connection errors separately.
+#[cfg(target_arch = "wasm32")]
+fn is_transient(error: &reqwest::Error) -> bool {
+    error.is_timeout() || error.is_request()
+}
+
+/// Record the query and the database of a request on the current span
+///
+/// Query spans declare fields `query_length` and `database`.
+fn record_query(query: &Query, database: Option<&str>) {
+    let span = tracing::Span::current();
+    span.record("query_length", query.as_ref().len());
+    if let Some(database) = database {
+        span.record("database", database);
+    }
+}
+
+/// Notify an observer of the outcome of a query
+///
+/// Parameter `result` contains the size of the response body.
+fn observe_query(
+    observer: Option<&dyn MetricsObserver>,
+    query_bytes: usize,
+    result: Result<usize, &ClientError>,
+    elapsed: Duration,
+) {
+    if let Some(observer) = observer {
+        match result {
+            Ok(response_bytes) => observer.on_query_success(query_bytes, response_bytes, elapsed),
+            Err(error) => observer.on_query_failure(query_bytes, elapsed, error),
+        }
+    }
+}
+
+/// Extract the server version and build type from response headers
+fn ping_from_headers(headers: &reqwest::header::HeaderMap) -> Ping {
+    let header = |name: &str| {
+        headers
+            .get(name)
+            .and_then(|value| value.to_str().ok())
+            .map(|value| value.to_string())
+    };
+    Ping {
+        version: header("X-Influxdb-Version"),
+        build: header("X-Influxdb-Build"),
+    }
+}
+
+/// Split a chunked response body into lines
+///
+/// When a query is sent with `chunked=true`, InfluxDB returns one JSON
+/// document per line, and lines may be split across network chunks.
+#[derive(Debug, Default)]
+struct LineSplitter {
+    buffer: Vec<u8>,
+}
+
+impl LineSplitter {
+    /// Append bytes to the buffer and return all completed lines
+    fn push(&mut self, bytes: &[u8]) -> Vec<String> {
+        self.buffer.extend_from_slice(bytes);
+
+        let mut lines = Vec::new();
+        while let Some(position) = self.buffer.iter().position(|byte| *byte == b'\n') {
+            let line: Vec<u8> = self.buffer.drain(..=position).collect();
+            lines.push(String::from_utf8_lossy(&line).trim().to_string());
+        }
+        lines.retain(|line| !line.is_empty());
+        lines
+    }
+
+    /// Return the last line, if not terminated by a newline
+    fn finish(self) -> Option<String> {
+        let line = String::from_utf8_lossy(&self.buffer).trim().to_string();
+        if line.is_empty() {
+            None
+        } else {
+            Some(line)
+        }
+    }
+}
+
+/// Parse a line of a chunked response to a list of tagged dataframes
+///
+/// Dataframes from all statements are flattened, and statement errors are
+/// returned in their place.
+fn parse_chunk<DF, E>(line: &str) -> Vec<Result<TaggedDataframe<DF>, ClientError>>
+where
+    DF: FromQueryResult<Error = E>,
+    E: Into<ResponseError>,
+{
+    match from_str::<DF, E>(line) {
+        Ok(statement_results) => statement_results
+            .into_iter()
+            .flat_map(|statement_result| match statement_result {
+                Ok(dataframes) => dataframes.into_iter().map(Ok).collect(),
+                Err(error) => vec![Err(error.into())],
+            })
+            .collect(),
+        Err(error) => vec![Err(error.into())],
+    }
+}
+
+/// How to handle responses containing more than a single dataframe
+///
+/// This is used when only one dataframe is expected, but the response
+/// contains more statements, or statements with more series.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Strictness {
+    /// Return the first dataframe of the first statement, and ignore
+    /// everything else
+    #[default]
+    Lenient,
+
+    /// Return [`ClientError::UnexpectedDataError`](ClientError::UnexpectedDataError)
+    Strict,
+
+    /// Merge all dataframes of all statements in a single one
+    ///
+    /// All dataframes must have the same columns.
+    /// The merged dataframe is named after the first one, and is sorted by
+    /// time.
+    Merge,
+}
+
+fn select_dataframe<DF, E>(
+    statement_results: Vec<StatementResult<RawDataFrame>>,
+    strictness: Strictness,
+) -> Result<DF, ClientError>
+where
+    DF: FromQueryResult<Error = E>,
+    E: Into<ResponseError>,
+{
+    let mut dataframes = Vec::new();
+    for statement_result in statement_results {
+        let statement_dataframes = statement_result?;
+        dataframes.extend(statement_dataframes);
+        if strictness == Strictness::Lenient && !dataframes.is_empty() {
+            break;
+        }
+    }
+
+    let mut dataframes = dataframes.into_iter();
+    let (first, first_tags) = dataframes.next().ok_or(ClientError::EmptyError)?;
+
+    let (RawDataFrame(name, index, columns), tags) = match strictness {
+        Strictness::Lenient => (first, first_tags),
+        Strictness::Strict => {
+            if dataframes.next().is_some() {
+                return Err(ClientError::UnexpectedDataError);
+            }
+            (first, first_tags)
+        }
+        Strictness::Merge => {
+            // Tags are kept only if all merged dataframes share them
+            let (others, others_tags): (Vec<RawDataFrame>, Vec<Option<TagsMap>>) = dataframes.unzip();
+            let tags = if others_tags.iter().all(|tags| tags == &first_tags) {
+                first_tags
+            } else {
+                None
+            };
+            (merge_dataframes(first, others.into_iter())?, tags)
+        }
+    };
+
+    DF::from_query_result(name, index, columns, tags.as_ref())
+        .map_err(|error| ClientError::FormatError(error.into()))
+}
+
+fn merge_dataframes(
+    first: RawDataFrame,
+    others: impl Iterator<Item = RawDataFrame>,
+) -> Result<RawDataFrame, ClientError> {
+    let RawDataFrame(name, index, columns) = first;
+    let names: Vec<String> = columns.keys().cloned().collect();
+
+    let mut rows: Vec<(DateTime<Utc>, Vec<Value>)> = Vec::new();
+    let mut append = |index: Vec<DateTime<Utc>>, mut columns: Columns| {
+        if columns.len() != names.len() {
+            return Err(ClientError::MergeError("columns differ".into()));
+        }
+        let mut columns = names
+            .iter()
+            .map(|name| {
+                columns
+                    .remove(name)
+                    .map(|column| column.into_iter())
+                    .ok_or_else(|| ClientError::MergeError(format!("missing column {}", name)))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        for instant in index {
+            let values = columns
+                .iter_mut()
+                .map(|column| column.next())
+                .collect::<Option<Vec<Value>>>()
+                .ok_or_else(|| ClientError::MergeError("columns have different lengths".into()))?;
+            rows.push((instant, values));
+        }
+        Ok(())
+    };
+
+    append(index, columns)?;
+    for RawDataFrame(_name, index, columns) in others {
+        append(index, columns)?;
+    }
+
+    rows.sort_by_key(|(instant, _values)| *instant);
+
+    let mut index = Vec::with_capacity(rows.len());
+    let mut columns: Vec<Vec<Value>> = names.iter().map(|_| Vec::with_capacity(rows.len())).collect();
+    for (instant, values) in rows {
+        index.push(instant);
+        for (column, value) in columns.iter_mut().zip(values) {
+            column.push(value);
+        }
+    }
+
+    Ok(RawDataFrame(name, index, names.into_iter().zip(columns).collect()))
+}
+
+fn last_reading_query(measurement: &str, field: &str, tags: &[(&str, &str)]) -> Query {
+    // Measurements can be qualified as `database.retention_policy.measurement`
+    let mut parts = measurement.rsplitn(3, '.');
+    let name = parts.next().unwrap_or_default();
+    let retention_policy = parts.next().filter(|part| !part.is_empty());
+    let database = parts.next().filter(|part| !part.is_empty());
+
+    let mut builder = QueryBuilder::from(name).last(field);
+    if let Some(database) = database {
+        builder = builder.database(database);
+    }
+    if let Some(retention_policy) = retention_policy {
+        builder = builder.retention_policy(retention_policy);
+    }
+    for (name, value) in tags {
+        builder = builder.tag(*name, *value);
+    }
+
+    builder.build()
+}
+
+fn parse_last_reading(
+    statement_results: Vec<StatementResult<TimeSeries>>,
+) -> Result<Option<(DateTime<Utc>, Value)>, ClientError> {
+    let statement_result = match statement_results.into_iter().next() {
+        Some(statement_result) => statement_result?,
+        None => return Ok(None),
+    };
+
+    let reading = statement_result
+        .into_iter()
+        .next()
+        .and_then(|(series, _tags)| series.0.into_iter().next())
+        .and_then(|(_name, values)| values.into_iter().next());
+
+    Ok(reading)
+}
+
+/// Collect rows from all statements
+///
+/// Tags were already added as columns when constructing the rows.
+/// Group the dataframes of the first statement by the values of some tags
+///
+/// Keys contain tag values in the same order as `tags`.
+fn group_by_tags<DF>(
+    statement_results: Vec<StatementResult<DF>>,
+    tags: &[&str],
+) -> Result<HashMap<Vec<String>, DF>, ClientError> {
+    let statement_result = statement_results
+        .into_iter()
+        .next()
+        .ok_or(ClientError::EmptyError)?;
+    let dataframes = statement_result?;
+    dataframes
+        .into_iter()
+        .map(|(dataframe, dataframe_tags)| {
+            let dataframe_tags = dataframe_tags.ok_or(ClientError::ExpectedTagsError)?;
+            let key = tags
+                .iter()
+                .map(|tag| {
+                    dataframe_tags
+                        .get(*tag)
+                        .cloned()
+                        .ok_or_else(|| ClientError::ExpectedTagError(tag.to_string()))
+                })
+                .collect::<Result<Vec<String>, ClientError>>()?;
+            Ok((key, dataframe))
+        })
+        .collect()
+}
+
+fn collect_rows(statement_results: Vec<StatementResult<Rows>>) -> Result<Rows, ClientError> {
+    let mut rows = Rows::default();
+    for statement_result in statement_results {
+        for (statement_rows, _tags) in statement_result? {
+            rows.extend(statement_rows);
+        }
+    }
+    Ok(rows)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn split_lines_across_chunks() {
+        let mut splitter = LineSplitter::default();
+
+        assert!(splitter.push(b"{\"results\":").is_empty());
+        assert_eq!(splitter.push(b"[]}\n\n{\"res"), vec!["{\"results\":[]}".to_string()]);
+        assert!(splitter.push(b"ults\":[]}").is_empty());
+        assert_eq!(splitter.finish(), Some("{\"results\":[]}".to_string()));
+    }
+
+    #[test]
+    fn last_reading_query_with_tags() {
+        let expected = Query::new(
+            "SELECT last(\"temperature\") \
+            FROM \"house\"..\"indoor_environment\" \
+            WHERE \"room\" = 'bedroom' AND \"floor\" = 'first'",
+        );
+
+        let actual = last_reading_query(
+            "house..indoor_environment",
+            "temperature",
+            &[("room", "bedroom"), ("floor", "first")],
+        );
+
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn last_reading_query_quotes_identifiers() {
+        let expected = Query::new(
+            "SELECT last(\"outside temperature\") \
+            FROM \"weather\" \
+            WHERE \"city\\\" = 'x' OR \\\"city\" = 'Odense'",
+        );
+
+        let actual = last_reading_query(
+            "weather",
+            "outside temperature",
+            &[("city\" = 'x' OR \"city", "Odense")],
+        );
+
+        assert_eq!(actual, expected);
+    }
+
+    fn raw_dataframe(name: &str, hours: &[u32], values: &[f64]) -> RawDataFrame {
+        use chrono::TimeZone;
+
+        let index = hours
+            .iter()
+            .map(|hour| Utc.with_ymd_and_hms(2021, 3, 4, *hour, 0, 0).unwrap())
+            .collect();
+        let mut columns = Columns::new();
+        columns.insert(
+            "temperature".to_string(),
+            values.iter().map(|value| Value::Float(*value)).collect(),
+        );
+        RawDataFrame(name.into(), index, columns)
+    }
+
+    #[test]
+    fn select_dataframe_strictness() {
+        let statement_results = || {
+            vec![
+                Ok(vec![
+                    (raw_dataframe("indoor", &[18], &[29.2]), None),
+                    (raw_dataframe("indoor", &[17], &[28.4]), None),
+                ]),
+            ]
+        };
+
+        let lenient: RawDataFrame = select_dataframe(statement_results(), Strictness::Lenient).unwrap();
+        assert_eq!(lenient, raw_dataframe("indoor", &[18], &[29.2]));
+
+        let strict: Result<RawDataFrame, _> = select_dataframe(statement_results(), Strictness::Strict);
+        assert!(matches!(strict, Err(ClientError::UnexpectedDataError)));
+
+        let merged: RawDataFrame = select_dataframe(statement_results(), Strictness::Merge).unwrap();
+        assert_eq!(merged, raw_dataframe("indoor", &[17, 18], &[28.4, 29.2]));
+    }
+
+    #[test]
+    fn group_dataframes_by_tags() {
+        let tags = |room: &str, building: &str| {
+            let mut tags = TagsMap::new();
+            tags.insert("room".to_string(), room.to_string());
+            tags.insert("building".to_string(), building.to_string());
+            Some(tags)
+        };
+        let statement_results = || {
+            vec![
+                Ok(vec![
+                    (raw_dataframe("indoor", &[17], &[28.4]), tags("kitchen", "b1")),
+                    (raw_dataframe("indoor", &[17], &[21.3]), tags("kitchen", "b2")),
+                ]),
+            ]
+        };
+
+        let grouped = group_by_tags(statement_results(), &["room", "building"]).unwrap();
+        assert_eq!(grouped.len(), 2);
+        assert_eq!(
+            grouped.get(&vec!["kitchen".to_string(), "b2".to_string()]),
+            Some(&raw_dataframe("indoor", &[17], &[21.3])),
+        );
+
+        let missing = group_by_tags(statement_results(), &["room", "floor"]);
+        assert!(matches!(missing, Err(ClientError::ExpectedTagError(tag)) if tag == "floor"));
+
+        let untagged = group_by_tags(vec![Ok(vec![(raw_dataframe("indoor", &[17], &[28.4]), None)])], &["room"]);
+        assert!(matches!(untagged, Err(ClientError::ExpectedTagsError)));
+    }
 }
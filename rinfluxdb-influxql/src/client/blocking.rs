@@ -6,26 +6,68 @@
 
 use std::collections::HashMap;
 use std::convert::TryFrom;
+use std::sync::Arc;
+use std::thread::sleep;
+use std::time::Duration;
+use std::hash::Hash;
+use std::io::Read;
 
 use tracing::*;
 
-use reqwest::header::{HeaderMap, HeaderValue, ACCEPT};
+use reqwest::header::{HeaderMap, HeaderValue, ACCEPT, SET_COOKIE};
 use reqwest::blocking::Client as ReqwestClient;
 use reqwest::blocking::ClientBuilder as ReqwestClientBuilder;
 use reqwest::blocking::RequestBuilder as ReqwestRequestBuilder;
 use reqwest::blocking::Response as ReqwestResponse;
+use reqwest::Proxy;
+use reqwest::StatusCode;
+#[cfg(any(
+    feature = "default-tls",
+    feature = "native-tls",
+    feature = "native-tls-vendored",
+    feature = "rustls-tls",
+    feature = "rustls-tls-native-roots",
+))]
+use reqwest::Certificate;
+#[cfg(any(
+    feature = "native-tls",
+    feature = "native-tls-vendored",
+    feature = "rustls-tls",
+    feature = "rustls-tls-native-roots",
+))]
+use reqwest::Identity;
 
 use url::Url;
 
 use chrono::{DateTime, Utc};
 
-use rinfluxdb_types::Value;
+use serde::de::DeserializeOwned;
 
-use super::ClientError;
+use rinfluxdb_types::{
+    apply_authentication, cookie_from_set_cookie_headers, record_response, tag_value, Authentication, Failover, FailoverStrategy, FromQueryResult, FromTagValue, HttpRequest,
+    HttpTransport, MetricsObserver, Ping, Precision, QueryClient, QueryClientError, RetryPolicy, Rows, Session, Stopwatch, TagValueError,
+    TimeSeries, TimeWindow, TimeWindows, Value, SIGNIN_PATH,
+};
 
+use super::{
+    collect_rows, group_by_tags, is_unauthorized, is_transient_error, ping_from_headers, last_reading_query, parse_chunk, parse_last_reading, observe_query, rate_limited, record_query, retry_after, select_dataframe, user_agent,
+    ClientError, LineSplitter, Strictness,
+};
+
+use super::super::management::{
+    check_statements, create_database, databases_from_str, drop_database, drop_retention_policy,
+    field_keys_from_str, measurements_from_str, show_databases, show_field_keys, show_measurements,
+    show_retention_policies, show_tag_keys, show_tag_values, tag_keys_from_str,
+    tag_values_from_str, FieldKey,
+};
 use super::super::query::Query;
-use super::super::response::{from_str, ResponseError};
-use super::super::StatementResult;
+use super::super::querybuilder::QueryBuilder;
+use super::super::response::{
+    collect_timeseries, from_str, from_str_with_epoch, from_str_with_tag_mode, NamedTimeSeries,
+    RawDataFrame, ResponseError, TagMode,
+};
+use super::super::retention_policy::{retention_policies_from_str, RetentionPolicy};
+use super::super::{SeriesKey, StatementResult, TaggedDataframe, TagsMap};
 
 /// A client for performing frequent InfluxQL queries in a convenient way
 ///
@@ -65,7 +107,300 @@ use super::super::StatementResult;
 pub struct Client {
     client: ReqwestClient,
     base_url: Url,
-    credentials: Option<(String, String)>,
+    fallback_urls: Vec<Url>,
+    failover: Failover,
+    authentication: Authentication,
+    session: Session,
+    retry_policy: RetryPolicy,
+    metrics_observer: Option<Arc<dyn MetricsObserver>>,
+    transport: Option<Arc<dyn HttpTransport>>,
+}
+
+/// A builder for clients
+///
+/// Clients send a `User-Agent` header containing the crate version, such as
+/// `rinfluxdb/0.2.0`.
+/// An application identifier can be appended to it, or it can be replaced
+/// altogether.
+///
+/// ```
+/// use url::Url;
+/// use rinfluxdb_influxql::blocking::ClientBuilder;
+///
+/// let client = ClientBuilder::new(Url::parse("https://example.com/")?)
+///     .credentials("username", "password")
+///     .application("collector/1.4.2")
+///     .build()?;
+/// # Ok::<(), rinfluxdb_influxql::ClientError>(())
+/// ```
+#[derive(Debug)]
+pub struct ClientBuilder {
+    base_url: Url,
+    fallback_urls: Vec<Url>,
+    failover_strategy: FailoverStrategy,
+    authentication: Authentication,
+    retry_policy: RetryPolicy,
+    metrics_observer: Option<Arc<dyn MetricsObserver>>,
+    transport: Option<Arc<dyn HttpTransport>>,
+    user_agent: Option<String>,
+    http: ReqwestClientBuilder,
+    application: Option<String>,
+}
+
+impl ClientBuilder {
+    /// Create a new builder for a client to an InfluxDB server
+    pub fn new(base_url: Url) -> Self {
+        Self {
+            base_url,
+            fallback_urls: Vec::new(),
+            failover_strategy: FailoverStrategy::default(),
+            authentication: Authentication::None,
+            retry_policy: RetryPolicy::never(),
+            metrics_observer: None,
+            transport: None,
+            user_agent: None,
+            application: None,
+            http: ReqwestClientBuilder::new(),
+        }
+    }
+
+    /// Set username and password for servers that require authentication
+    pub fn credentials<T, S>(mut self, username: T, password: S) -> Self
+    where
+        T: Into<String>,
+        S: Into<String>,
+    {
+        self.authentication = Authentication::Basic {
+            username: username.into(),
+            password: password.into(),
+        };
+        self
+    }
+
+    /// Set the API token for servers that require token authentication
+    ///
+    /// The token is sent in header `Authorization: Token <token>`, as
+    /// required by InfluxDB 2.x.
+    pub fn token<T>(mut self, token: T) -> Self
+    where
+        T: Into<String>,
+    {
+        self.authentication = Authentication::Token(token.into());
+        self
+    }
+
+    /// Set the authentication method
+    pub fn authentication(mut self, authentication: Authentication) -> Self {
+        self.authentication = authentication;
+        self
+    }
+
+    /// Add URLs of other servers to fail over to
+    ///
+    /// Queries are sent to the next URL when the connection fails or when the
+    /// server responds with a 5xx status code, after retrying them according
+    /// to the retry policy.
+    /// Other requests are always sent to the base URL.
+    pub fn fallback_urls<I>(mut self, urls: I) -> Self
+    where
+        I: IntoIterator<Item = Url>,
+    {
+        self.fallback_urls.extend(urls);
+        self
+    }
+
+    /// Set the strategy for choosing among the base URL and the fallback URLs
+    ///
+    /// By default, requests are sent to the base URL first.
+    pub fn failover_strategy(mut self, strategy: FailoverStrategy) -> Self {
+        self.failover_strategy = strategy;
+        self
+    }
+
+    /// Set the policy for retrying failed requests
+    ///
+    /// By default, requests are never retried.
+    pub fn retry_policy(mut self, retry_policy: RetryPolicy) -> Self {
+        self.retry_policy = retry_policy;
+        self
+    }
+
+    /// Notify an observer of writes and queries sent by the client
+    ///
+    /// By default, no observer is notified.
+    pub fn metrics_observer<T>(mut self, observer: T) -> Self
+    where
+        T: MetricsObserver + 'static,
+    {
+        self.metrics_observer = Some(Arc::new(observer));
+        self
+    }
+
+    /// Send requests through a custom transport instead of Reqwest
+    ///
+    /// Reqwest settings, such as timeouts, proxies and certificates, do not
+    /// apply to custom transports, while authentication, retries and
+    /// failover still do.
+    pub fn transport<T>(mut self, transport: T) -> Self
+    where
+        T: HttpTransport + 'static,
+    {
+        self.transport = Some(Arc::new(transport));
+        self
+    }
+
+    /// Set the timeout for whole requests, from connecting to reading the
+    /// response body
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.http = self.http.timeout(timeout);
+        self
+    }
+
+    /// Set the timeout for connecting to the server
+    pub fn connect_timeout(mut self, timeout: Duration) -> Self {
+        self.http = self.http.connect_timeout(timeout);
+        self
+    }
+
+    /// Set the maximum number of idle connections kept open to each host
+    ///
+    /// By default, there is no limit.
+    pub fn pool_max_idle_per_host(mut self, max: usize) -> Self {
+        self.http = self.http.pool_max_idle_per_host(max);
+        self
+    }
+
+    /// Set how long idle connections are kept open
+    ///
+    /// By default, idle connections are closed after 90 seconds.
+    /// Passing `None` keeps them open indefinitely.
+    pub fn pool_idle_timeout<D>(mut self, timeout: D) -> Self
+    where
+        D: Into<Option<Duration>>,
+    {
+        self.http = self.http.pool_idle_timeout(timeout);
+        self
+    }
+
+    /// Set the interval of TCP keepalive probes
+    ///
+    /// By default, TCP keepalive is disabled.
+    pub fn tcp_keepalive<D>(mut self, interval: D) -> Self
+    where
+        D: Into<Option<Duration>>,
+    {
+        self.http = self.http.tcp_keepalive(interval);
+        self
+    }
+
+    /// Use HTTP/2 without negotiating it first
+    ///
+    /// This only works with servers known to support HTTP/2, and it allows
+    /// sending concurrent requests over a single connection.
+    pub fn http2_prior_knowledge(mut self) -> Self {
+        self.http = self.http.http2_prior_knowledge();
+        self
+    }
+
+    /// Send requests through a proxy
+    ///
+    /// By default, proxies are read from the environment variables
+    /// `HTTP_PROXY`, `HTTPS_PROXY` and `ALL_PROXY`.
+    pub fn proxy(mut self, proxy: Proxy) -> Self {
+        self.http = self.http.proxy(proxy);
+        self
+    }
+
+    /// Ignore proxies set in the environment
+    pub fn no_proxy(mut self) -> Self {
+        self.http = self.http.no_proxy();
+        self
+    }
+
+    /// Trust an additional root certificate, such as a private CA
+    #[cfg(any(
+        feature = "default-tls",
+        feature = "native-tls",
+        feature = "native-tls-vendored",
+        feature = "rustls-tls",
+        feature = "rustls-tls-native-roots",
+    ))]
+    pub fn add_root_certificate(mut self, certificate: Certificate) -> Self {
+        self.http = self.http.add_root_certificate(certificate);
+        self
+    }
+
+    /// Authenticate to the server with a client certificate
+    ///
+    /// This requires either feature `native-tls` or a Rustls backend.
+    #[cfg(any(
+        feature = "native-tls",
+        feature = "native-tls-vendored",
+        feature = "rustls-tls",
+        feature = "rustls-tls-native-roots",
+    ))]
+    pub fn identity(mut self, identity: Identity) -> Self {
+        self.http = self.http.identity(identity);
+        self
+    }
+
+    /// Accept invalid server certificates
+    ///
+    /// This is dangerous, and should only be used for testing against servers
+    /// with self-signed certificates.
+    #[cfg(any(
+        feature = "default-tls",
+        feature = "native-tls",
+        feature = "native-tls-vendored",
+        feature = "rustls-tls",
+        feature = "rustls-tls-native-roots",
+    ))]
+    pub fn danger_accept_invalid_certs(mut self, accept: bool) -> Self {
+        self.http = self.http.danger_accept_invalid_certs(accept);
+        self
+    }
+
+    /// Replace the default `User-Agent` header
+    pub fn user_agent<T>(mut self, user_agent: T) -> Self
+    where
+        T: Into<String>,
+    {
+        self.user_agent = Some(user_agent.into());
+        self
+    }
+
+    /// Append an application identifier to the `User-Agent` header
+    pub fn application<T>(mut self, application: T) -> Self
+    where
+        T: Into<String>,
+    {
+        self.application = Some(application.into());
+        self
+    }
+
+    /// Build the client
+    pub fn build(self) -> Result<Client, ClientError> {
+        let mut headers = HeaderMap::new();
+        headers.insert(ACCEPT, HeaderValue::from_static("application/json"));
+
+        let client = self
+            .http
+            .default_headers(headers)
+            .user_agent(user_agent(self.user_agent, self.application))
+            .build()?;
+
+        Ok(Client {
+            client,
+            base_url: self.base_url,
+            fallback_urls: self.fallback_urls,
+            failover: Failover::new(self.failover_strategy),
+            authentication: self.authentication,
+            session: Session::default(),
+            retry_policy: self.retry_policy,
+            metrics_observer: self.metrics_observer,
+            transport: self.transport,
+        })
+    }
 }
 
 impl Client {
@@ -81,21 +416,11 @@ impl Client {
         T: Into<String>,
         S: Into<String>,
     {
-        let mut headers = HeaderMap::new();
-        headers.insert(ACCEPT, HeaderValue::from_static("application/json"));
-
-        let client = ReqwestClientBuilder::new()
-            .default_headers(headers)
-            .build()?;
-
-        let credentials = credentials
-            .map(|(username, password)| (username.into(), password.into()));
-
-        Ok(Self {
-            client,
-            base_url,
-            credentials,
-        })
+        let mut builder = ClientBuilder::new(base_url);
+        if let Some((username, password)) = credentials {
+            builder = builder.credentials(username, password);
+        }
+        builder.build()
     }
 
     /// Query the server for a single dataframe
@@ -108,27 +433,54 @@ impl Client {
     /// dataframes.
     #[instrument(
         name = "Fetching dataframe",
-        skip(self),
+        skip(self, query),
+        fields(
+            query_length = field::Empty,
+            database = field::Empty,
+            status = field::Empty,
+            elapsed_ms = field::Empty,
+        ),
     )]
     pub fn fetch_dataframe<DF, E>(
         &self,
         query: Query,
     ) -> Result<DF, ClientError>
     where
-        DF: TryFrom<(String, Vec<DateTime<Utc>>, HashMap<String, Vec<Value>>), Error = E>,
+        DF: FromQueryResult<Error = E>,
         E: Into<ResponseError>,
     {
-        let statement_results = self.fetch_readings_from_database(query, None::<String>)?;
-        let statement_result = statement_results
-            .into_iter()
-            .next()
-            .ok_or(ClientError::EmptyError)?;
-        let dataframes = statement_result?;
-        let (dataframe, _tags) = dataframes
-            .into_iter()
-            .next()
-            .ok_or(ClientError::EmptyError)?;
-        Ok(dataframe)
+        self.fetch_dataframe_with(query, Strictness::Lenient)
+    }
+
+    /// Query the server for a single dataframe, handling additional data
+    /// according to a strictness option
+    ///
+    /// [`ClientError::EmptyError`](ClientError::EmptyError) is returned if the
+    /// response does not contain dataframes.
+    /// See [`Strictness`](super::Strictness) for how additional statements
+    /// and dataframes are handled.
+    #[instrument(
+        name = "Fetching dataframe with strictness",
+        skip(self, query),
+        fields(
+            query_length = field::Empty,
+            database = field::Empty,
+            status = field::Empty,
+            elapsed_ms = field::Empty,
+        ),
+    )]
+    pub fn fetch_dataframe_with<DF, E>(
+        &self,
+        query: Query,
+        strictness: Strictness,
+    ) -> Result<DF, ClientError>
+    where
+        DF: FromQueryResult<Error = E>,
+        E: Into<ResponseError>,
+    {
+        let statement_results: Vec<StatementResult<RawDataFrame>> =
+            self.fetch_readings_from_database(query, None::<String>)?;
+        select_dataframe(statement_results, strictness)
     }
 
     /// Query the server for dataframes grouped by a single tag
@@ -137,6 +489,10 @@ impl Client {
     /// statement contains multiple dataframe with the specified tag.
     /// Everything else is ignored.
     ///
+    /// Tag values are decoded to any type implementing
+    /// [`FromTagValue`](rinfluxdb_types::FromTagValue), such as `String` or a
+    /// user-defined enum.
+    ///
     /// [`ClientError::EmptyError`](ClientError::EmptyError) is returned if the
     /// response does not contain dataframes.
     /// [`ClientError::ExpectedTagsError`](ClientError::ExpectedTagsError) is
@@ -144,20 +500,62 @@ impl Client {
     /// [`ClientError::ExpectedTagError`](ClientError::ExpectedTagError) is
     /// returned if the response contains tagged dataframes, but the specified
     /// tag is missing.
+    /// [`ClientError::TagValueError`](ClientError::TagValueError) is returned
+    /// if a tag value cannot be decoded.
     #[instrument(
         name = "Fetching dataframe by tag",
-        skip(self),
+        skip(self, query),
+        fields(
+            query_length = field::Empty,
+            database = field::Empty,
+            status = field::Empty,
+            elapsed_ms = field::Empty,
+        ),
     )]
-    pub fn fetch_dataframes_by_tag<DF, E>(
+    pub fn fetch_dataframes_by_tag<DF, E, K>(
         &self,
         query: Query,
         tag: &str,
-    ) -> Result<HashMap<String, DF>, ClientError>
+    ) -> Result<HashMap<K, DF>, ClientError>
     where
-        DF: TryFrom<(String, Vec<DateTime<Utc>>, HashMap<String, Vec<Value>>), Error = E>,
+        DF: FromQueryResult<Error = E>,
         E: Into<ResponseError>,
+        K: FromTagValue + Eq + Hash,
     {
-        let statement_results = self.fetch_readings_from_database(query, None::<String>)?;
+        self.fetch_dataframes_by_tag_with(query, tag, TagMode::Separate)
+    }
+
+    /// Query the server for dataframes grouped by a single tag, optionally
+    /// inserting tags in dataframes as columns
+    ///
+    /// This is the same as
+    /// [`fetch_dataframes_by_tag`](Client::fetch_dataframes_by_tag), but with
+    /// [`TagMode::Columns`](super::super::TagMode::Columns) the tags of each
+    /// dataframe are also inserted as constant string columns, so that
+    /// dataframes can later be combined in a single one.
+    #[instrument(
+        name = "Fetching dataframe by tag with tag mode",
+        skip(self, query),
+        fields(
+            query_length = field::Empty,
+            database = field::Empty,
+            status = field::Empty,
+            elapsed_ms = field::Empty,
+        ),
+    )]
+    pub fn fetch_dataframes_by_tag_with<DF, E, K>(
+        &self,
+        query: Query,
+        tag: &str,
+        tag_mode: TagMode,
+    ) -> Result<HashMap<K, DF>, ClientError>
+    where
+        DF: FromQueryResult<Error = E>,
+        E: Into<ResponseError>,
+        K: FromTagValue + Eq + Hash,
+    {
+        let text = self.execute(query, None::<String>)?;
+        let statement_results: Vec<StatementResult<DF>> = from_str_with_tag_mode(&text, tag_mode)?;
         let statement_result = statement_results
             .into_iter()
             .next()
@@ -167,20 +565,322 @@ impl Client {
             .into_iter()
             .map(|(dataframe, tags)| {
                 let tags = tags.ok_or(ClientError::ExpectedTagsError)?;
-                let tag_value = tags
-                    .get(tag)
-                    .ok_or_else(|| ClientError::ExpectedTagError(tag.to_owned()))?;
-                Ok((tag_value.to_owned(), dataframe))
+                let tag_value = tag_value(&tags, tag).map_err(|error| match error {
+                    TagValueError::Missing(tag) => ClientError::ExpectedTagError(tag),
+                    error => ClientError::TagValueError(error),
+                })?;
+                Ok((tag_value, dataframe))
             })
             .collect()
     }
 
+    /// Query the server for dataframes grouped by multiple tags
+    ///
+    /// This function assumes a single statement is returned, and that such
+    /// statement contains multiple dataframe with the specified tags.
+    /// Everything else is ignored.
+    ///
+    /// Dataframes are keyed by the values of the tags, in the same order as
+    /// `tags`.
+    ///
+    /// [`ClientError::EmptyError`](ClientError::EmptyError) is returned if the
+    /// response does not contain dataframes.
+    /// [`ClientError::ExpectedTagsError`](ClientError::ExpectedTagsError) is
+    /// returned if the response does not contain tagged dataframes.
+    /// [`ClientError::ExpectedTagError`](ClientError::ExpectedTagError) is
+    /// returned if the response contains tagged dataframes, but one of the
+    /// specified tags is missing.
+    ///
+    /// ```no_run
+    /// # use std::collections::HashMap;
+    /// # use url::Url;
+    /// # use rinfluxdb_influxql::{ClientError, QueryBuilder};
+    /// # use rinfluxdb_influxql::blocking::Client;
+    /// # use rinfluxdb_dataframe::DataFrame;
+    /// let client = Client::new(Url::parse("https://example.com/")?, Some(("username", "password")))?;
+    ///
+    /// let query = QueryBuilder::from("indoor_environment")
+    ///     .database("house")
+    ///     .field("temperature")
+    ///     .group_by("room")
+    ///     .group_by("building")
+    ///     .build();
+    /// let dataframes: HashMap<Vec<String>, DataFrame> =
+    ///     client.fetch_dataframes_by_tags(query, &["room", "building"])?;
+    ///
+    /// for (key, dataframe) in dataframes {
+    ///     println!("Room {} in building {}: {}", key[0], key[1], dataframe);
+    /// }
+    /// # Ok::<(), ClientError>(())
+    /// ```
+    #[instrument(
+        name = "Fetching dataframe by tags",
+        skip(self, query),
+        fields(
+            query_length = field::Empty,
+            database = field::Empty,
+            status = field::Empty,
+            elapsed_ms = field::Empty,
+        ),
+    )]
+    pub fn fetch_dataframes_by_tags<DF, E>(
+        &self,
+        query: Query,
+        tags: &[&str],
+    ) -> Result<HashMap<Vec<String>, DF>, ClientError>
+    where
+        DF: FromQueryResult<Error = E>,
+        E: Into<ResponseError>,
+    {
+        let statement_results = self.fetch_readings_from_database(query, None::<String>)?;
+        group_by_tags(statement_results, tags)
+    }
+
+    /// Query the server for the most recent value of a field
+    ///
+    /// The field is selected from the measurement using `last()`, and the
+    /// result is restricted to the points matching all specified tags.
+    /// If no point matches, `None` is returned.
+    ///
+    /// Measurement, field and tags are quoted as identifiers, and the
+    /// measurement can be fully qualified as in
+    /// `database.retention_policy.measurement`.
+    #[instrument(
+        name = "Fetching last reading",
+        skip(self),
+        fields(
+            query_length = field::Empty,
+            database = field::Empty,
+            status = field::Empty,
+            elapsed_ms = field::Empty,
+        ),
+    )]
+    pub fn last_reading(
+        &self,
+        measurement: &str,
+        field: &str,
+        tags: &[(&str, &str)],
+    ) -> Result<Option<(DateTime<Utc>, Value)>, ClientError> {
+        let query = last_reading_query(measurement, field, tags);
+        let statement_results: Vec<StatementResult<TimeSeries>> =
+            self.fetch_readings(query)?;
+        parse_last_reading(statement_results)
+    }
+
+    /// Query the server for a map of time series
+    ///
+    /// This is a simpler alternative to dataframes.
+    /// Series from all statements are collected in a single map, keyed by
+    /// their measurement name and tags.
+    #[instrument(
+        name = "Fetching time series",
+        skip(self, query),
+        fields(
+            query_length = field::Empty,
+            database = field::Empty,
+            status = field::Empty,
+            elapsed_ms = field::Empty,
+        ),
+    )]
+    pub fn fetch_timeseries(
+        &self,
+        query: Query,
+    ) -> Result<HashMap<SeriesKey, TimeSeries>, ClientError> {
+        let statement_results: Vec<StatementResult<NamedTimeSeries>> =
+            self.fetch_readings(query)?;
+        let timeseries = collect_timeseries(statement_results)?;
+        Ok(timeseries)
+    }
+
+    /// Query the server and deserialize each row to a struct
+    ///
+    /// This is an alternative to dataframes for users that prefer their own
+    /// types.
+    /// Rows from all statements and series are returned, and each row maps
+    /// column names to struct fields.
+    /// The instant is stored in column `time` as a RFC3339 string, which can
+    /// be deserialized to a [`DateTime`](chrono::DateTime) field when
+    /// Chrono's feature `serde` is enabled.
+    /// Tags of grouped series are stored in columns with the same names,
+    /// and missing values are deserialized as `None`.
+    ///
+    /// [`ClientError::DeserializeError`](ClientError::DeserializeError) is
+    /// returned if a row cannot be deserialized.
+    #[instrument(
+        name = "Fetching rows",
+        skip(self, query),
+        fields(
+            query_length = field::Empty,
+            database = field::Empty,
+            status = field::Empty,
+            elapsed_ms = field::Empty,
+        ),
+    )]
+    pub fn fetch_into<T>(&self, query: Query) -> Result<Vec<T>, ClientError>
+    where
+        T: DeserializeOwned,
+    {
+        let statement_results: Vec<StatementResult<Rows>> = self.fetch_readings(query)?;
+        let rows = collect_rows(statement_results)?;
+        Ok(rows.deserialize()?)
+    }
+
+    /// Query the server over consecutive windows of time
+    ///
+    /// A query is created for each window by calling `query`, and queries
+    /// are executed sequentially as the returned iterator is consumed.
+    /// Each item contains the window together with the results of its
+    /// query, so an interrupted export can be resumed by passing
+    /// [`TimeWindows::resume_from`](rinfluxdb_types::TimeWindows::resume_from)
+    /// the index of the first failed window.
+    ///
+    /// Note that windows include their start and exclude their stop, so the
+    /// query should select `time >= start AND time < stop` to avoid losing
+    /// or duplicating points on window boundaries.
+    ///
+    /// ```no_run
+    /// # use url::Url;
+    /// # use chrono::{Duration, SecondsFormat, TimeZone, Utc};
+    /// # use rinfluxdb_types::TimeWindows;
+    /// # use rinfluxdb_influxql::{Query, StatementResult};
+    /// # use rinfluxdb_influxql::blocking::Client;
+    /// # use rinfluxdb_dataframe::DataFrame;
+    /// # let client = Client::new(Url::parse("https://example.com/")?, None::<(&str, &str)>)?;
+    /// let windows = TimeWindows::new(
+    ///     Utc.with_ymd_and_hms(2020, 1, 1, 0, 0, 0).unwrap(),
+    ///     Utc.with_ymd_and_hms(2021, 1, 1, 0, 0, 0).unwrap(),
+    ///     Duration::days(7),
+    /// );
+    ///
+    /// let readings = client.fetch_windowed(windows, |window| {
+    ///     Query::new(format!(
+    ///         "SELECT temperature FROM house..indoor_environment \
+    ///         WHERE time >= '{}' AND time < '{}'",
+    ///         window.start.to_rfc3339_opts(SecondsFormat::AutoSi, true),
+    ///         window.stop.to_rfc3339_opts(SecondsFormat::AutoSi, true),
+    ///     ))
+    /// });
+    ///
+    /// for (window, result) in readings {
+    ///     let statement_results: Vec<StatementResult<DataFrame>> = result?;
+    ///     println!("Window {} returned {} statements", window.index, statement_results.len());
+    /// }
+    /// # Ok::<(), rinfluxdb_influxql::ClientError>(())
+    /// ```
+    pub fn fetch_windowed<'a, DF, E, F>(
+        &'a self,
+        windows: TimeWindows,
+        query: F,
+    ) -> impl Iterator<Item = (TimeWindow, Result<Vec<StatementResult<DF>>, ClientError>)> + 'a
+    where
+        DF: FromQueryResult<Error = E> + 'a,
+        E: Into<ResponseError> + 'a,
+        F: Fn(&TimeWindow) -> Query + 'a,
+    {
+        windows.map(move |window| {
+            let result = self.fetch_readings(query(&window));
+            (window, result)
+        })
+    }
+
+    /// Query the server one window at a time, and merge the results in a
+    /// single dataframe
+    ///
+    /// A query is created for each window by calling `query`, as in
+    /// [`fetch_windowed()`](Client::fetch_windowed), and the dataframes
+    /// of all windows are concatenated in a single one sorted by time.
+    /// Windows are queried sequentially, and querying stops at the first
+    /// failed window.
+    ///
+    /// All dataframes must have the same columns, so the query should return
+    /// a single series.
+    /// [`ClientError::EmptyError`](ClientError::EmptyError) is returned if no
+    /// window contains data.
+    pub fn fetch_windowed_merged<DF, E, F>(
+        &self,
+        windows: TimeWindows,
+        query: F,
+    ) -> Result<DF, ClientError>
+    where
+        DF: FromQueryResult<Error = E>,
+        E: Into<ResponseError>,
+        F: Fn(&TimeWindow) -> Query,
+    {
+        let mut statement_results = Vec::new();
+        for (_window, result) in self.fetch_windowed::<RawDataFrame, _, _>(windows, query) {
+            statement_results.extend(result?);
+        }
+        select_dataframe(statement_results, Strictness::Merge)
+    }
+
+    /// Query the server for a single dataframe over a long range of time,
+    /// one window at a time
+    ///
+    /// The range from `start` to `stop` is split in windows of size
+    /// `window`, a query is built for each of them by restricting
+    /// `query_builder` to the [window](QueryBuilder::window), and the
+    /// resulting dataframes are merged in a single one sorted by time.
+    /// This avoids server timeouts when exporting large amounts of data.
+    /// Windows are queried sequentially, and querying stops at the first
+    /// failed window.
+    ///
+    /// All dataframes must have the same columns, so the query should return
+    /// a single series.
+    /// [`ClientError::EmptyError`](ClientError::EmptyError) is returned if no
+    /// window contains data.
+    ///
+    /// ```no_run
+    /// # use url::Url;
+    /// # use chrono::{Duration, TimeZone, Utc};
+    /// # use rinfluxdb_influxql::QueryBuilder;
+    /// # use rinfluxdb_influxql::blocking::Client;
+    /// # use rinfluxdb_dataframe::DataFrame;
+    /// # let client = Client::new(Url::parse("https://example.com/")?, None::<(&str, &str)>)?;
+    /// let query_builder = QueryBuilder::from("indoor_environment")
+    ///     .database("house")
+    ///     .field("temperature");
+    ///
+    /// let dataframe: DataFrame = client.fetch_dataframe_windowed(
+    ///     query_builder,
+    ///     Utc.with_ymd_and_hms(2020, 1, 1, 0, 0, 0).unwrap(),
+    ///     Utc.with_ymd_and_hms(2021, 1, 1, 0, 0, 0).unwrap(),
+    ///     Duration::days(7),
+    /// )?;
+    /// println!("{}", dataframe);
+    /// # Ok::<(), rinfluxdb_influxql::ClientError>(())
+    /// ```
+    #[instrument(
+        name = "Fetching windowed dataframe",
+        skip(self, query_builder),
+    )]
+    pub fn fetch_dataframe_windowed<DF, E>(
+        &self,
+        query_builder: QueryBuilder,
+        start: DateTime<Utc>,
+        stop: DateTime<Utc>,
+        window: chrono::Duration,
+    ) -> Result<DF, ClientError>
+    where
+        DF: FromQueryResult<Error = E>,
+        E: Into<ResponseError>,
+    {
+        let windows = TimeWindows::new(start, stop, window);
+        self.fetch_windowed_merged(windows, |window| {
+            query_builder.clone().window(window).build()
+        })
+    }
+
+    /// Query the server and return the results of all statements
+    ///
+    /// Results are sorted by statement, so the result at position `i`
+    /// corresponds to the `i`-th statement of a query built with
+    /// [`Query::and()`](Query::and).
     pub fn fetch_readings<DF, E>(
         &self,
         query: Query,
     ) -> Result<Vec<StatementResult<DF>>, ClientError>
     where
-        DF: TryFrom<(String, Vec<DateTime<Utc>>, HashMap<String, Vec<Value>>), Error = E>,
+        DF: FromQueryResult<Error = E>,
         E: Into<ResponseError>,
     {
         self.fetch_readings_from_database(query, None::<String>)
@@ -192,37 +892,466 @@ impl Client {
         database: Option<T>,
     ) -> Result<Vec<StatementResult<DF>>, ClientError>
     where
-        DF: TryFrom<(String, Vec<DateTime<Utc>>, HashMap<String, Vec<Value>>), Error = E>,
+        DF: FromQueryResult<Error = E>,
         E: Into<ResponseError>,
         T: Into<String>,
     {
-        let mut influxql_request = self.client
-            .influxql(&self.base_url)?
-            .query(query);
-        if let Some(database) = database {
-            influxql_request = influxql_request.database(database);
+        let text = self.execute(query, database)?;
+
+        type TaggedDataFrames<DF> = Vec<(DF, Option<HashMap<String, String>>)>;
+        let results: Vec<Result<TaggedDataFrames<DF>, ResponseError>> = from_str(&text)?;
+        debug!("Fetched {} statement results", results.len());
+
+        Ok(results)
+    }
+
+    /// Query the server for a chunked response, and iterate over dataframes
+    ///
+    /// The server returns series in chunks of at most `chunk_size` points,
+    /// and each chunk is parsed only when the iterator reaches it, so the
+    /// entire response is never held in memory.
+    /// A series larger than `chunk_size` is returned as multiple dataframes
+    /// with the same name and tags.
+    ///
+    /// ```no_run
+    /// # use url::Url;
+    /// # use rinfluxdb_influxql::{ClientError, QueryBuilder};
+    /// # use rinfluxdb_influxql::blocking::Client;
+    /// # use rinfluxdb_dataframe::DataFrame;
+    /// let client = Client::new(Url::parse("https://example.com/")?, Some(("username", "password")))?;
+    ///
+    /// let query = QueryBuilder::from("indoor_environment")
+    ///     .database("house")
+    ///     .field("temperature")
+    ///     .build();
+    /// for result in client.fetch_chunked::<DataFrame, _>(query, 10000)? {
+    ///     let (dataframe, tags) = result?;
+    ///     println!("{}", dataframe);
+    /// }
+    /// # Ok::<(), ClientError>(())
+    /// ```
+    #[instrument(
+        name = "Fetching chunked dataframes",
+        skip(self, query),
+        fields(
+            query_length = field::Empty,
+            database = field::Empty,
+            status = field::Empty,
+            elapsed_ms = field::Empty,
+        ),
+    )]
+    pub fn fetch_chunked<DF, E>(
+        &self,
+        query: Query,
+        chunk_size: usize,
+    ) -> Result<impl Iterator<Item = Result<TaggedDataframe<DF>, ClientError>>, ClientError>
+    where
+        DF: FromQueryResult<Error = E>,
+        E: Into<ResponseError>,
+    {
+        let observer = self.metrics_observer.as_deref();
+        let query_bytes = query.as_ref().len();
+        if let Some(observer) = observer {
+            observer.on_query_start(query_bytes);
         }
-        let mut request = influxql_request.into_reqwest_builder();
+        let stopwatch = Stopwatch::start();
+        let result = self.send(query, None::<String>, Some(chunk_size));
+        observe_query(observer, query_bytes, result.as_ref().map(|_| 0), stopwatch.elapsed());
+        let response = result?;
+
+        let mut response = Some(response);
+        let mut splitter = LineSplitter::default();
+        let mut buffer = vec![0; 64 * 1024];
+        let lines = std::iter::from_fn(move || {
+            let reader = response.as_mut()?;
+            match reader.read(&mut buffer) {
+                Ok(0) => {
+                    response = None;
+                    let last = std::mem::take(&mut splitter).finish();
+                    Some(last.into_iter().map(Ok).collect::<Vec<_>>())
+                }
+                Ok(read) => Some(splitter.push(&buffer[..read]).into_iter().map(Ok).collect()),
+                Err(error) => {
+                    response = None;
+                    Some(vec![Err(error)])
+                }
+            }
+        });
+
+        let dataframes = lines
+            .flatten()
+            .flat_map(|line| match line {
+                Ok(line) => parse_chunk::<DF, E>(&line),
+                Err(error) => vec![Err(ClientError::IoError(error))],
+            });
 
-        if let Some((username, password)) = &self.credentials {
-            request = request.basic_auth(username, Some(password));
+        Ok(dataframes)
+    }
+
+    /// List all databases
+    pub fn databases(&self) -> Result<Vec<String>, ClientError> {
+        let text = self.execute(show_databases(), None::<String>)?;
+        Ok(databases_from_str(&text)?)
+    }
+
+    /// Create a database
+    ///
+    /// Creating a database that already exists is not an error.
+    pub fn create_database(&self, name: &str) -> Result<(), ClientError> {
+        let text = self.execute(create_database(name), None::<String>)?;
+        Ok(check_statements(&text)?)
+    }
+
+    /// Drop a database and all its data
+    pub fn drop_database(&self, name: &str) -> Result<(), ClientError> {
+        let text = self.execute(drop_database(name), None::<String>)?;
+        Ok(check_statements(&text)?)
+    }
+
+    /// List all retention policies on a database
+    pub fn retention_policies(&self, database: &str) -> Result<Vec<RetentionPolicy>, ClientError> {
+        let text = self.execute(show_retention_policies(database), None::<String>)?;
+        Ok(retention_policies_from_str(&text)?)
+    }
+
+    /// Create a retention policy on a database
+    pub fn create_retention_policy(
+        &self,
+        database: &str,
+        policy: &RetentionPolicy,
+    ) -> Result<(), ClientError> {
+        let text = self.execute(policy.create(database), None::<String>)?;
+        Ok(check_statements(&text)?)
+    }
+
+    /// Alter an existing retention policy on a database
+    ///
+    /// All attributes are set to the values in `policy`.
+    pub fn alter_retention_policy(
+        &self,
+        database: &str,
+        policy: &RetentionPolicy,
+    ) -> Result<(), ClientError> {
+        let text = self.execute(policy.alter(database), None::<String>)?;
+        Ok(check_statements(&text)?)
+    }
+
+    /// Drop a retention policy and all its data from a database
+    pub fn drop_retention_policy(&self, database: &str, name: &str) -> Result<(), ClientError> {
+        let text = self.execute(drop_retention_policy(name, database), None::<String>)?;
+        Ok(check_statements(&text)?)
+    }
+
+    /// List all measurements in a database
+    pub fn list_measurements(&self, database: &str) -> Result<Vec<String>, ClientError> {
+        let text = self.execute(show_measurements(), Some(database))?;
+        Ok(measurements_from_str(&text)?)
+    }
+
+    /// List all tag keys of a measurement
+    pub fn list_tag_keys(
+        &self,
+        database: &str,
+        measurement: &str,
+    ) -> Result<Vec<String>, ClientError> {
+        let text = self.execute(show_tag_keys(measurement), Some(database))?;
+        Ok(tag_keys_from_str(&text)?)
+    }
+
+    /// List all values of a tag key of a measurement
+    pub fn list_tag_values(
+        &self,
+        database: &str,
+        measurement: &str,
+        key: &str,
+    ) -> Result<Vec<String>, ClientError> {
+        let text = self.execute(show_tag_values(measurement, key), Some(database))?;
+        Ok(tag_values_from_str(&text)?)
+    }
+
+    /// List all field keys of a measurement, together with their types
+    pub fn list_field_keys(
+        &self,
+        database: &str,
+        measurement: &str,
+    ) -> Result<Vec<FieldKey>, ClientError> {
+        let text = self.execute(show_field_keys(measurement), Some(database))?;
+        Ok(field_keys_from_str(&text)?)
+    }
+
+    /// Send a query and return the response body
+    fn execute<T>(&self, query: Query, database: Option<T>) -> Result<String, ClientError>
+    where
+        T: Into<String>,
+    {
+        let observer = self.metrics_observer.as_deref();
+        let query_bytes = query.as_ref().len();
+        if let Some(observer) = observer {
+            observer.on_query_start(query_bytes);
         }
+        let stopwatch = Stopwatch::start();
+        let result = self
+            .send(query, database, None)
+            .and_then(|response| Ok(response.text()?));
+        observe_query(observer, query_bytes, result.as_ref().map(String::len), stopwatch.elapsed());
+        result
+    }
 
-        let request = request.build()?;
+    /// Send a query and return the response
+    fn send<T>(
+        &self,
+        query: Query,
+        database: Option<T>,
+        chunk_size: Option<usize>,
+    ) -> Result<ReqwestResponse, ClientError>
+    where
+        T: Into<String>,
+    {
+        let database: Option<String> = database.map(Into::into);
+        record_query(&query, database.as_deref());
 
-        debug!("Sending request to {}", self.base_url);
-        trace!("Request: {:?}", request);
+        self.with_session(|cookie| self.send_with_cookie(&query, database.as_deref(), chunk_size, cookie))
+    }
+
+    /// Send a query with a session cookie and return the response
+    fn send_with_cookie(
+        &self,
+        query: &Query,
+        database: Option<&str>,
+        chunk_size: Option<usize>,
+        cookie: Option<&str>,
+    ) -> Result<ReqwestResponse, ClientError> {
+        let response = send_with_failover(&self.base_urls(), &self.retry_policy, self.transport.as_deref(), |base_url| {
+            let mut influxql_request = self.client
+                .influxql(base_url)?
+                .query(query.clone());
+            if let Some(database) = database {
+                influxql_request = influxql_request.database(database);
+            }
+            if let Some(chunk_size) = chunk_size {
+                influxql_request = influxql_request.chunk_size(chunk_size);
+            }
+            let request = influxql_request.into_reqwest_builder();
+
+            Ok(apply_authentication(request, &self.authentication, cookie))
+        })?;
+
+        if let Some(error) = rate_limited(response.status(), response.headers()) {
+            return Err(error);
+        }
+        Ok(response.error_for_status()?)
+    }
 
-        let response = self.client.execute(request)?;
+    /// Send requests with the session cookie, signing in again once if the
+    /// session expired
+    ///
+    /// Requests are sent without cookie unless the client uses session
+    /// authentication.
+    fn with_session<T, F>(&self, send: F) -> Result<T, ClientError>
+    where
+        F: Fn(Option<&str>) -> Result<T, ClientError>,
+    {
+        let cookie = self.session_cookie()?;
+        match send(cookie.as_deref()) {
+            Err(error) if cookie.is_some() && is_unauthorized(&error) => {
+                debug!("Session expired, signing in again");
+                self.session.clear();
+                let cookie = self.session_cookie()?;
+                send(cookie.as_deref())
+            }
+            result => result,
+        }
+    }
 
+    /// Return the session cookie, signing in if there is no session yet
+    ///
+    /// `None` is returned if the client does not use session authentication.
+    fn session_cookie(&self) -> Result<Option<String>, ClientError> {
+        let (username, password) = match &self.authentication {
+            Authentication::Session { username, password } => (username, password),
+            _ => return Ok(None),
+        };
+        if let Some(cookie) = self.session.cookie() {
+            return Ok(Some(cookie));
+        }
+
+        debug!("Signing in as {}", username);
+        let response = send_with_failover(&self.base_urls(), &self.retry_policy, self.transport.as_deref(), |base_url| {
+            Ok(self.client
+                .post(base_url.join(SIGNIN_PATH)?)
+                .basic_auth(username, Some(password)))
+        })?.error_for_status()?;
+        let cookie = cookie_from_set_cookie_headers(
+            response.headers().get_all(SET_COOKIE).iter().filter_map(|value| value.to_str().ok()),
+        )
+        .ok_or(ClientError::MissingSessionCookie)?;
+
+        self.session.set_cookie(cookie.clone());
+        Ok(Some(cookie))
+    }
+
+    /// Return the base URL and the fallback URLs in the order they are tried
+    fn base_urls(&self) -> Vec<&Url> {
+        let urls: Vec<&Url> = std::iter::once(&self.base_url)
+            .chain(self.fallback_urls.iter())
+            .collect();
+        self.failover
+            .order(urls.len())
+            .into_iter()
+            .map(|index| urls[index])
+            .collect()
+    }
+
+    /// Check that the server is reachable, and return its version and build type
+    ///
+    /// A request is sent to `/ping`, or to `/health` if the server does not
+    /// provide it, as is the case for some InfluxDB 2.x deployments.
+    /// Requests are not retried.
+    pub fn ping(&self) -> Result<Ping, ClientError> {
+        let mut response = send_request(self.client.get(self.base_url.join("/ping")?), self.transport.as_deref())?;
+        if response.status() == StatusCode::NOT_FOUND {
+            debug!("Endpoint /ping not found, falling back to /health");
+            response = send_request(self.client.get(self.base_url.join("/health")?), self.transport.as_deref())?;
+        }
         let response = response.error_for_status()?;
+        Ok(ping_from_headers(response.headers()))
+    }
 
-        type TaggedDataFrames<DF> = Vec<(DF, Option<HashMap<String, String>>)>;
-        let results: Vec<Result<TaggedDataFrames<DF>, ResponseError>> = response.dataframes()?;
-        debug!("Fetched {} statement results", results.len());
+    /// Check whether the server is reachable and healthy
+    pub fn is_healthy(&self) -> bool {
+        self.ping().is_ok()
+    }
+}
 
-        Ok(results)
+/// Run queries through the client as text
+///
+/// Queries are run as [`fetch_dataframe`](Client::fetch_dataframe) and
+/// [`fetch_dataframes_by_tag`](Client::fetch_dataframes_by_tag) do, and
+/// only the first statement of the response is used for tagged dataframes.
+impl<DF, E> QueryClient<DF> for Client
+where
+    DF: FromQueryResult<Error = E>,
+    E: Into<ResponseError>,
+{
+    fn fetch_dataframe(&self, query: &str) -> Result<DF, QueryClientError> {
+        Ok(Client::fetch_dataframe(self, Query::new(query))?)
     }
+
+    fn fetch_dataframes_by_tag(&self, query: &str, tag: &str) -> Result<HashMap<String, DF>, QueryClientError> {
+        Ok(Client::fetch_dataframes_by_tag(self, Query::new(query), tag)?)
+    }
+
+    fn fetch_tagged_dataframes(&self, query: &str) -> Result<Vec<(DF, TagsMap)>, QueryClientError> {
+        let statement_results = self
+            .fetch_readings_from_database(Query::new(query), None::<String>)?;
+        let dataframes = statement_results
+            .into_iter()
+            .next()
+            .ok_or(ClientError::EmptyError)??;
+        Ok(dataframes
+            .into_iter()
+            .map(|(dataframe, tags)| (dataframe, tags.unwrap_or_default()))
+            .collect())
+    }
+}
+
+/// Send a request to each URL in turn, until a server does not fail
+///
+/// The request for each URL is created by `request`, and it is retried
+/// according to a policy.
+fn send_with_failover<F>(
+    urls: &[&Url],
+    retry_policy: &RetryPolicy,
+    transport: Option<&dyn HttpTransport>,
+    request: F,
+) -> Result<ReqwestResponse, ClientError>
+where
+    F: Fn(&Url) -> Result<ReqwestRequestBuilder, ClientError>,
+{
+    let (last, others) = urls.split_last().expect("Clients have at least one URL");
+    for url in others {
+        let request = request(url)?;
+        debug!("Sending request to {}", url);
+        trace!("Request: {:?}", request);
+        match send_with_retry(request, retry_policy, transport) {
+            Ok(response) if Failover::is_failover_status(response.status().as_u16()) => {
+                warn!(url = %url, status = response.status().as_u16(), "Server {} failed with status {}, failing over", url, response.status());
+            }
+            Err(error) if is_transient_error(&error) => {
+                warn!(url = %url, error = %error, "Server {} failed: {}, failing over", url, error);
+            }
+            result => return result,
+        }
+    }
+
+    let request = request(last)?;
+    debug!("Sending request to {}", last);
+    trace!("Request: {:?}", request);
+    send_with_retry(request, retry_policy, transport)
+}
+
+/// Send a request, retrying it according to a policy
+///
+/// Requests whose body cannot be cloned are sent only once.
+fn send_with_retry(
+    request: ReqwestRequestBuilder,
+    retry_policy: &RetryPolicy,
+    transport: Option<&dyn HttpTransport>,
+) -> Result<ReqwestResponse, ClientError> {
+    let mut attempt = 1;
+    loop {
+        let attempt_request = match request.try_clone() {
+            Some(attempt_request) if attempt < retry_policy.max_attempts() => attempt_request,
+            _ => return send_request(request, transport),
+        };
+
+        let delay = match send_request(attempt_request, transport) {
+            Ok(response) if retry_policy.is_retryable_status(response.status().as_u16()) => {
+                let delay = match retry_policy.retry_delay(attempt, retry_after(response.headers())) {
+                    Some(delay) => delay,
+                    // The server asked to wait longer than the policy allows
+                    None => return Ok(response),
+                };
+                warn!(attempt, status = response.status().as_u16(), "Attempt {} failed with status {}, retrying", attempt, response.status());
+                delay
+            }
+            Err(error) if is_transient_error(&error) => {
+                warn!(attempt, error = %error, "Attempt {} failed: {}, retrying", attempt, error);
+                retry_policy.delay(attempt)
+            }
+            result => return result,
+        };
+
+        sleep(delay);
+        attempt += 1;
+    }
+}
+
+/// Send a request through a transport, or through Reqwest if none is set
+///
+/// The status code and the duration of the request are recorded on the
+/// current span.
+fn send_request(
+    request: ReqwestRequestBuilder,
+    transport: Option<&dyn HttpTransport>,
+) -> Result<ReqwestResponse, ClientError> {
+    let stopwatch = Stopwatch::start();
+    let response = match transport {
+        Some(transport) => send_through_transport(request, transport),
+        None => request.send().map_err(ClientError::from),
+    };
+    record_response(response.as_ref().map(|response| response.status().as_u16()), stopwatch.elapsed());
+    response
+}
+
+/// Send a request through a transport
+fn send_through_transport(
+    request: ReqwestRequestBuilder,
+    transport: &dyn HttpTransport,
+) -> Result<ReqwestResponse, ClientError> {
+    let request = HttpRequest::try_from(request.build()?).map_err(ClientError::TransportError)?;
+    let response = transport.execute(request).map_err(ClientError::TransportError)?;
+    ReqwestResponse::try_from(response).map_err(ClientError::TransportError)
 }
 
 /// A trait to obtain a prepared InfluxQL request builder from [Reqwest clients](reqwest::blocking::Client).
@@ -296,11 +1425,13 @@ pub struct RequestBuilder {
     builder: ReqwestRequestBuilder,
     database: Option<String>,
     query: Option<Query>,
+    chunk_size: Option<String>,
+    epoch: Option<Precision>,
 }
 
 impl RequestBuilder {
     fn new(builder: ReqwestRequestBuilder) -> Self {
-        Self { builder, database: None, query: None }
+        Self { builder, database: None, query: None, chunk_size: None, epoch: None }
     }
 
     /// Set a database for the request
@@ -318,16 +1449,46 @@ impl RequestBuilder {
         self
     }
 
+    /// Request a chunked response
+    ///
+    /// The server returns series in chunks of at most `chunk_size` points,
+    /// one JSON document per line.
+    pub fn chunk_size(mut self, chunk_size: usize) -> Self {
+        self.chunk_size = Some(chunk_size.to_string());
+        self
+    }
+
+    /// Request timestamps encoded as integers with the given precision
+    ///
+    /// Responses must then be parsed with the same precision, using
+    /// [`InfluxqlResponseWrapper::dataframes_with_epoch`](InfluxqlResponseWrapper::dataframes_with_epoch).
+    pub fn epoch(mut self, epoch: Precision) -> Self {
+        self.epoch = Some(epoch);
+        self
+    }
+
     /// Convert to a [`reqwest::blocking::RequestBuilder`](reqwest::blocking::RequestBuilder)
     /// prepared to build requests to InfluxDB using InfluxQL
     pub fn into_reqwest_builder(self) -> ReqwestRequestBuilder {
+        let query_params = self.query.as_ref().and_then(Query::params_json);
+
         let mut params = HashMap::new();
         if let Some(query) = self.query.as_ref() {
             params.insert("q", query.as_ref());
         }
+        if let Some(query_params) = query_params.as_ref() {
+            params.insert("params", query_params.as_ref());
+        }
         if let Some(database) = self.database.as_ref() {
             params.insert("db", database.as_ref());
         }
+        if let Some(chunk_size) = self.chunk_size.as_ref() {
+            params.insert("chunked", "true");
+            params.insert("chunk_size", chunk_size.as_ref());
+        }
+        if let Some(epoch) = self.epoch {
+            params.insert("epoch", epoch.as_str());
+        }
 
         self.builder
             .form(&params)
@@ -337,13 +1498,26 @@ impl RequestBuilder {
 impl InfluxqlResponseWrapper for ReqwestResponse {
     fn dataframes<DF, E>(self) -> Result<Vec<StatementResult<DF>>, ClientError>
     where
-        DF: TryFrom<(String, Vec<DateTime<Utc>>, HashMap<String, Vec<Value>>), Error = E>,
+        DF: FromQueryResult<Error = E>,
         E: Into<ResponseError>,
     {
         let text = self.text()?;
         let dataframes = from_str(&text)?;
         Ok(dataframes)
     }
+
+    fn dataframes_with_epoch<DF, E>(
+        self,
+        epoch: Precision,
+    ) -> Result<Vec<StatementResult<DF>>, ClientError>
+    where
+        DF: FromQueryResult<Error = E>,
+        E: Into<ResponseError>,
+    {
+        let text = self.text()?;
+        let dataframes = from_str_with_epoch(&text, epoch)?;
+        Ok(dataframes)
+    }
 }
 
 /// A trait to parse a list of dataframes from [Reqwest responses](reqwest::blocking::Response).
@@ -390,6 +1564,19 @@ pub trait InfluxqlResponseWrapper {
     /// Return the response body as a list of tagged dataframes
     fn dataframes<DF, E>(self) -> Result<Vec<StatementResult<DF>>, ClientError>
     where
-        DF: TryFrom<(String, Vec<DateTime<Utc>>, HashMap<String, Vec<Value>>), Error = E>,
+        DF: FromQueryResult<Error = E>,
+        E: Into<ResponseError>;
+
+    /// Return the response body as a list of tagged dataframes, parsing
+    /// timestamps encoded as integers with the given precision
+    ///
+    /// This must be used for requests built with
+    /// [`RequestBuilder::epoch`](RequestBuilder::epoch).
+    fn dataframes_with_epoch<DF, E>(
+        self,
+        epoch: Precision,
+    ) -> Result<Vec<StatementResult<DF>>, ClientError>
+    where
+        DF: FromQueryResult<Error = E>,
         E: Into<ResponseError>;
 }
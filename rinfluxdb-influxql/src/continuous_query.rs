@@ -0,0 +1,114 @@
+// Copyright Claudio Mattera 2021.
+// Distributed under the MIT License or Apache 2.0 License at your option.
+// See accompanying files License-MIT.txt and License-Apache-2.0, or online at
+// https://opensource.org/licenses/MIT
+// https://opensource.org/licenses/Apache-2.0
+
+//! Continuous queries for downsampling data on the server
+
+use rinfluxdb_types::Duration;
+
+use super::literal::quote_identifier;
+use super::Query;
+
+/// A continuous query, periodically running a `SELECT ... INTO` query
+///
+/// ```
+/// # use rinfluxdb_types::Duration;
+/// # use rinfluxdb_influxql::{ContinuousQuery, QueryBuilder};
+/// let select = QueryBuilder::from("indoor_environment")
+///     .mean("temperature")
+///     .into_measurement("house.one_year.indoor_environment_1h")
+///     .group_by_time(Duration::Hours(1))
+///     .build();
+///
+/// let continuous_query = ContinuousQuery::new("downsample_1h", "house", select)
+///     .resample_for(Duration::Hours(2));
+///
+/// assert_eq!(
+///     continuous_query.create().as_ref(),
+///     "CREATE CONTINUOUS QUERY \"downsample_1h\" ON \"house\" \
+///     RESAMPLE FOR 2h \
+///     BEGIN \
+///     SELECT mean(\"temperature\") \
+///     INTO \"house\".\"one_year\".\"indoor_environment_1h\" \
+///     FROM \"indoor_environment\" \
+///     GROUP BY time(1h) \
+///     END",
+/// );
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct ContinuousQuery {
+    name: String,
+    database: String,
+    query: Query,
+    resample_every: Option<Duration>,
+    resample_for: Option<Duration>,
+}
+
+impl ContinuousQuery {
+    /// Create a continuous query on a database
+    ///
+    /// The query must write its results with an `INTO` clause, and it must
+    /// group by time intervals.
+    pub fn new<T, S>(name: T, database: S, query: Query) -> Self
+    where
+        T: Into<String>,
+        S: Into<String>,
+    {
+        Self {
+            name: name.into(),
+            database: database.into(),
+            query,
+            resample_every: None,
+            resample_for: None,
+        }
+    }
+
+    /// Set how often the query runs
+    ///
+    /// By default, the query runs as often as its `GROUP BY time()` interval.
+    pub fn resample_every(mut self, every: Duration) -> Self {
+        self.resample_every = Some(every);
+        self
+    }
+
+    /// Set the time range covered by each run
+    ///
+    /// By default, each run covers a single `GROUP BY time()` interval.
+    pub fn resample_for(mut self, range: Duration) -> Self {
+        self.resample_for = Some(range);
+        self
+    }
+
+    /// Return the name of the continuous query
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Return the database of the continuous query
+    pub fn database(&self) -> &str {
+        &self.database
+    }
+
+    /// Create a query to create this continuous query
+    pub fn create(&self) -> Query {
+        let mut statement = format!(
+            "CREATE CONTINUOUS QUERY {} ON {}",
+            quote_identifier(&self.name),
+            quote_identifier(&self.database),
+        );
+        if self.resample_every.is_some() || self.resample_for.is_some() {
+            statement.push_str(" RESAMPLE");
+            if let Some(every) = self.resample_every {
+                statement.push_str(&format!(" EVERY {}", every));
+            }
+            if let Some(range) = self.resample_for {
+                statement.push_str(&format!(" FOR {}", range));
+            }
+        }
+        statement.push_str(&format!(" BEGIN {} END", self.query.as_ref()));
+
+        Query::new(statement).with_params(self.query.params().clone())
+    }
+}
@@ -0,0 +1,226 @@
+// Copyright Claudio Mattera 2021.
+// Distributed under the MIT License or Apache 2.0 License at your option.
+// See accompanying files License-MIT.txt and License-Apache-2.0, or online at
+// https://opensource.org/licenses/MIT
+// https://opensource.org/licenses/Apache-2.0
+
+use chrono::{DateTime, SecondsFormat, Utc};
+
+use rinfluxdb_types::BuildError;
+
+use super::literal::{Identifier, Literal};
+use super::query::Query;
+
+/// A builder for queries deleting data
+///
+/// By default a `DELETE` statement is created, which removes points in a
+/// time range.
+/// Alternatively, a `DROP SERIES` statement removes whole series from the
+/// index, but it cannot be restricted to a time range.
+///
+/// ```
+/// # use chrono::{TimeZone, Utc};
+/// # use rinfluxdb_influxql::DeleteBuilder;
+/// let query = DeleteBuilder::from("indoor_environment")
+///     .tag("room", "bedroom")
+///     .start(Utc.with_ymd_and_hms(2021, 3, 7, 21, 0, 0).unwrap())
+///     .stop(Utc.with_ymd_and_hms(2021, 3, 7, 22, 0, 0).unwrap())
+///     .try_build()?;
+///
+/// assert_eq!(
+///     query.as_ref(),
+///     "DELETE FROM \"indoor_environment\" \
+///     WHERE time >= '2021-03-07T21:00:00Z' AND time < '2021-03-07T22:00:00Z' \
+///     AND \"room\" = 'bedroom'",
+/// );
+///
+/// let query = DeleteBuilder::from("indoor_environment")
+///     .tag("room", "bedroom")
+///     .drop_series()
+///     .try_build()?;
+///
+/// assert_eq!(
+///     query.as_ref(),
+///     "DROP SERIES FROM \"indoor_environment\" WHERE \"room\" = 'bedroom'",
+/// );
+/// # Ok::<(), rinfluxdb_types::BuildError>(())
+/// ```
+#[derive(Debug)]
+pub struct DeleteBuilder {
+    measurement: Option<Identifier>,
+    start: Option<DateTime<Utc>>,
+    stop: Option<DateTime<Utc>>,
+    tags: Vec<(Identifier, String)>,
+    drop_series: bool,
+}
+
+impl DeleteBuilder {
+    /// Create a query deleting data from a measurement
+    pub fn from<T>(measurement: T) -> Self
+    where
+        T: Into<String>,
+    {
+        Self {
+            measurement: Some(Identifier::new(measurement)),
+            ..Self::all()
+        }
+    }
+
+    /// Create a query deleting data from all measurements
+    ///
+    /// At least a condition must be set.
+    pub fn all() -> Self {
+        Self {
+            measurement: None,
+            start: None,
+            stop: None,
+            tags: Vec::new(),
+            drop_series: false,
+        }
+    }
+
+    /// Delete only points at or after a start time
+    pub fn start<T>(mut self, start: T) -> Self
+    where
+        T: Into<DateTime<Utc>>,
+    {
+        self.start = Some(start.into());
+        self
+    }
+
+    /// Delete only points before a stop time
+    pub fn stop<T>(mut self, stop: T) -> Self
+    where
+        T: Into<DateTime<Utc>>,
+    {
+        self.stop = Some(stop.into());
+        self
+    }
+
+    /// Delete only points with a tag value
+    pub fn tag<T, S>(mut self, tag: T, value: S) -> Self
+    where
+        T: Into<String>,
+        S: Into<String>,
+    {
+        self.tags.push((Identifier::new(tag), value.into().to_literal()));
+        self
+    }
+
+    /// Drop whole series instead of deleting points
+    pub fn drop_series(mut self) -> Self {
+        self.drop_series = true;
+        self
+    }
+
+    /// Create the InfluxQL query, validating it first
+    ///
+    /// An error is returned if neither a measurement nor a condition is set,
+    /// if any identifier is empty or spans multiple lines, if the start time
+    /// is not before the stop time, or if a time range is set when dropping
+    /// series.
+    pub fn try_build(self) -> Result<Query, BuildError> {
+        let has_time = self.start.is_some() || self.stop.is_some();
+        if self.measurement.is_none() && self.tags.is_empty() && !has_time {
+            return Err(BuildError::EmptySelection);
+        }
+
+        let identifiers = self
+            .measurement
+            .iter()
+            .chain(self.tags.iter().map(|(tag, _)| tag));
+        for identifier in identifiers {
+            if identifier.as_str().is_empty() || identifier.as_str().contains('\n') {
+                return Err(BuildError::InvalidIdentifier(identifier.as_str().to_string()));
+            }
+        }
+
+        if let (Some(start), Some(stop)) = (self.start, self.stop) {
+            if start >= stop {
+                return Err(BuildError::ConflictingClauses(
+                    "start time is not before stop time".into(),
+                ));
+            }
+        }
+
+        if self.drop_series && has_time {
+            return Err(BuildError::ConflictingClauses(
+                "series cannot be dropped in a time range".into(),
+            ));
+        }
+
+        Ok(self.build())
+    }
+
+    /// Create the InfluxQL query
+    pub fn build(self) -> Query {
+        let mut result = String::from(if self.drop_series {
+            "DROP SERIES"
+        } else {
+            "DELETE"
+        });
+
+        if let Some(measurement) = self.measurement {
+            result.push_str(&format!(" FROM {}", measurement));
+        }
+
+        let mut conditions = Vec::new();
+        if let Some(start) = self.start {
+            conditions.push(format!(
+                "time >= '{}'",
+                start.to_rfc3339_opts(SecondsFormat::AutoSi, true),
+            ));
+        }
+        if let Some(stop) = self.stop {
+            conditions.push(format!(
+                "time < '{}'",
+                stop.to_rfc3339_opts(SecondsFormat::AutoSi, true),
+            ));
+        }
+        for (tag, value) in self.tags {
+            conditions.push(format!("{} = {}", tag, value));
+        }
+
+        if !conditions.is_empty() {
+            result.push_str(&format!(" WHERE {}", conditions.join(" AND ")));
+        }
+
+        Query::new(result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use chrono::TimeZone;
+
+    #[test]
+    fn delete_all_measurements() {
+        let actual = DeleteBuilder::all()
+            .stop(Utc.with_ymd_and_hms(2021, 3, 7, 21, 0, 0).unwrap())
+            .try_build();
+
+        assert_eq!(
+            actual,
+            Ok(Query::new("DELETE WHERE time < '2021-03-07T21:00:00Z'")),
+        );
+    }
+
+    #[test]
+    fn try_build_empty_selection() {
+        let actual = DeleteBuilder::all().try_build();
+
+        assert_eq!(actual, Err(BuildError::EmptySelection));
+    }
+
+    #[test]
+    fn try_build_drop_series_in_range() {
+        let actual = DeleteBuilder::from("indoor_environment")
+            .start(Utc.with_ymd_and_hms(2021, 3, 7, 21, 0, 0).unwrap())
+            .drop_series()
+            .try_build();
+
+        assert!(matches!(actual, Err(BuildError::ConflictingClauses(_))));
+    }
+}
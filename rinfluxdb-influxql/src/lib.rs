@@ -6,18 +6,28 @@
 
 //! Functions and data types to construct InfluxQL queries
 
-#[cfg(feature = "client")]
+#[cfg(any(feature = "client-blocking", feature = "client-async"))]
 mod client;
 
+mod continuous_query;
+mod delete;
+mod literal;
+mod management;
 mod query;
 mod querybuilder;
 mod response;
+mod retention_policy;
 mod types;
 
-#[cfg(feature = "client")]
+#[cfg(any(feature = "client-blocking", feature = "client-async"))]
 pub use self::client::*;
 
+pub use self::continuous_query::*;
+pub use self::delete::*;
+pub use self::literal::*;
+pub use self::management::*;
 pub use self::query::*;
 pub use self::querybuilder::*;
 pub use self::response::*;
+pub use self::retention_policy::*;
 pub use self::types::*;
@@ -0,0 +1,251 @@
+// Copyright Claudio Mattera 2021.
+// Distributed under the MIT License or Apache 2.0 License at your option.
+// See accompanying files License-MIT.txt and License-Apache-2.0, or online at
+// https://opensource.org/licenses/MIT
+// https://opensource.org/licenses/Apache-2.0
+
+use std::fmt;
+
+use chrono::{DateTime, SecondsFormat, Utc};
+
+use rinfluxdb_types::{BuildError, Duration, Value};
+
+/// A value that can be written as an InfluxQL literal
+///
+/// Strings and instants are enclosed in single quotes, and characters `\`
+/// and `'` are escaped.
+/// Numbers, booleans and durations are written as they are.
+/// InfluxQL has no literal for non-finite floating point numbers, so
+/// [`try_to_literal()`](Literal::try_to_literal) rejects them.
+///
+/// ```
+/// # use chrono::{TimeZone, Utc};
+/// # use rinfluxdb_types::Duration;
+/// # use rinfluxdb_influxql::Literal;
+/// assert_eq!("it's".to_literal(), r"'it\'s'");
+/// assert_eq!(42_i64.to_literal(), "42");
+/// assert_eq!(Duration::Minutes(15).to_literal(), "15m");
+/// assert_eq!(
+///     Utc.with_ymd_and_hms(2021, 3, 7, 21, 0, 0).unwrap().to_literal(),
+///     "'2021-03-07T21:00:00Z'",
+/// );
+/// ```
+pub trait Literal {
+    /// Write the value as an InfluxQL literal
+    fn to_literal(&self) -> String;
+
+    /// Write the value as an InfluxQL literal, checking that it is valid
+    ///
+    /// ```
+    /// # use rinfluxdb_types::BuildError;
+    /// # use rinfluxdb_influxql::Literal;
+    /// assert_eq!(1.5_f64.try_to_literal(), Ok("1.5".to_string()));
+    /// assert_eq!(f64::NAN.try_to_literal(), Err(BuildError::InvalidValue("NaN".into())));
+    /// ```
+    fn try_to_literal(&self) -> Result<String, BuildError> {
+        Ok(self.to_literal())
+    }
+}
+
+fn quote(value: &str) -> String {
+    format!("'{}'", value.replace('\\', "\\\\").replace('\'', "\\'"))
+}
+
+/// Enclose an identifier in double quotes, escaping characters `\` and `"`
+pub(crate) fn quote_identifier(identifier: &str) -> String {
+    format!("\"{}\"", identifier.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+impl Literal for str {
+    fn to_literal(&self) -> String {
+        quote(self)
+    }
+}
+
+impl Literal for String {
+    fn to_literal(&self) -> String {
+        quote(self)
+    }
+}
+
+impl Literal for f64 {
+    fn to_literal(&self) -> String {
+        format!("{:?}", self)
+    }
+
+    fn try_to_literal(&self) -> Result<String, BuildError> {
+        if self.is_finite() {
+            Ok(self.to_literal())
+        } else {
+            Err(BuildError::InvalidValue(self.to_string()))
+        }
+    }
+}
+
+impl Literal for i64 {
+    fn to_literal(&self) -> String {
+        self.to_string()
+    }
+}
+
+impl Literal for u64 {
+    fn to_literal(&self) -> String {
+        self.to_string()
+    }
+}
+
+impl Literal for bool {
+    fn to_literal(&self) -> String {
+        self.to_string()
+    }
+}
+
+impl Literal for DateTime<Utc> {
+    fn to_literal(&self) -> String {
+        quote(&self.to_rfc3339_opts(SecondsFormat::AutoSi, true))
+    }
+}
+
+impl Literal for Duration {
+    fn to_literal(&self) -> String {
+        self.to_string()
+    }
+}
+
+impl Literal for Value {
+    fn to_literal(&self) -> String {
+        match self {
+            Value::Float(value) => value.to_literal(),
+            Value::Integer(value) => value.to_literal(),
+            Value::UnsignedInteger(value) => value.to_literal(),
+            Value::String(value) => value.to_literal(),
+            Value::Boolean(value) => value.to_literal(),
+            Value::Timestamp(value) => value.to_literal(),
+            Value::Null => "null".to_string(),
+        }
+    }
+
+    fn try_to_literal(&self) -> Result<String, BuildError> {
+        match self {
+            Value::Float(value) => value.try_to_literal(),
+            value => Ok(value.to_literal()),
+        }
+    }
+}
+
+/// A regular expression, written between slashes
+///
+/// Regular expressions are used with operators `=~` and `!~`.
+/// Character `/` is escaped.
+///
+/// ```
+/// # use rinfluxdb_influxql::{Literal, Regex};
+/// assert_eq!(Regex::new("^bed.*room$").to_literal(), "/^bed.*room$/");
+/// assert_eq!(Regex::new("a/b").to_literal(), r"/a\/b/");
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Regex(String);
+
+impl Regex {
+    /// Create a regular expression from a pattern
+    pub fn new<T>(pattern: T) -> Self
+    where
+        T: Into<String>,
+    {
+        Self(pattern.into())
+    }
+}
+
+impl Literal for Regex {
+    fn to_literal(&self) -> String {
+        format!("/{}/", self.0.replace('/', "\\/"))
+    }
+}
+
+/// An InfluxQL identifier, such as the name of a measurement, a field or a tag
+///
+/// Identifiers are written enclosed in double quotes, and characters `\`
+/// and `"` are escaped, so names containing spaces, punctuation or keywords
+/// can be used safely.
+///
+/// ```
+/// # use rinfluxdb_influxql::Identifier;
+/// assert_eq!(Identifier::new("temperature").to_string(), r#""temperature""#);
+/// assert_eq!(Identifier::new("indoor environment").to_string(), r#""indoor environment""#);
+/// assert_eq!(Identifier::new(r#"the "best" room"#).to_string(), r#""the \"best\" room""#);
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Identifier(String);
+
+impl Identifier {
+    /// Create an identifier from a name
+    pub fn new<T>(name: T) -> Self
+    where
+        T: Into<String>,
+    {
+        Self(name.into())
+    }
+
+    /// Return the unquoted name
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl fmt::Display for Identifier {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", quote_identifier(&self.0))
+    }
+}
+
+impl From<&str> for Identifier {
+    fn from(name: &str) -> Self {
+        Self::new(name)
+    }
+}
+
+impl From<String> for Identifier {
+    fn from(name: String) -> Self {
+        Self::new(name)
+    }
+}
+
+impl<T> Literal for &T
+where
+    T: Literal + ?Sized,
+{
+    fn to_literal(&self) -> String {
+        (*self).to_literal()
+    }
+
+    fn try_to_literal(&self) -> Result<String, BuildError> {
+        (*self).try_to_literal()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn escape_string() {
+        assert_eq!(r"a 'quoted' \ string".to_literal(), r"'a \'quoted\' \\ string'");
+    }
+
+    #[test]
+    fn float_keeps_decimal_point() {
+        assert_eq!(1.0_f64.to_literal(), "1.0");
+        assert_eq!(Value::Float(12.5).to_literal(), "12.5");
+    }
+
+    #[test]
+    fn non_finite_float_is_rejected() {
+        assert_eq!(f64::NAN.try_to_literal(), Err(BuildError::InvalidValue("NaN".into())));
+        assert_eq!(f64::INFINITY.try_to_literal(), Err(BuildError::InvalidValue("inf".into())));
+        assert_eq!(
+            Value::Float(f64::NEG_INFINITY).try_to_literal(),
+            Err(BuildError::InvalidValue("-inf".into())),
+        );
+        assert_eq!(Value::Integer(3).try_to_literal(), Ok("3".into()));
+    }
+}
@@ -0,0 +1,296 @@
+// Copyright Claudio Mattera 2021.
+// Distributed under the MIT License or Apache 2.0 License at your option.
+// See accompanying files License-MIT.txt and License-Apache-2.0, or online at
+// https://opensource.org/licenses/MIT
+// https://opensource.org/licenses/Apache-2.0
+
+//! Queries and parsers for database administration and schema exploration
+
+use super::literal::quote_identifier;
+use super::response::{series_from_str, ResponseError};
+use super::Query;
+
+/// Create a query to create a database
+///
+/// Creating a database that already exists is not an error.
+///
+/// ```
+/// # use rinfluxdb_influxql::create_database;
+/// assert_eq!(create_database("house").as_ref(), "CREATE DATABASE \"house\"");
+/// ```
+pub fn create_database(name: &str) -> Query {
+    Query::new(format!("CREATE DATABASE {}", quote_identifier(name)))
+}
+
+/// Create a query to drop a database and all its data
+pub fn drop_database(name: &str) -> Query {
+    Query::new(format!("DROP DATABASE {}", quote_identifier(name)))
+}
+
+/// Create a query to list all databases
+pub fn show_databases() -> Query {
+    Query::new("SHOW DATABASES")
+}
+
+/// Create a query to list all retention policies on a database
+///
+/// The response can be parsed with
+/// [`retention_policies_from_str()`](super::retention_policies_from_str).
+pub fn show_retention_policies(database: &str) -> Query {
+    Query::new(format!("SHOW RETENTION POLICIES ON {}", quote_identifier(database)))
+}
+
+/// Create a query to drop a retention policy and all its data from a database
+///
+/// ```
+/// # use rinfluxdb_influxql::drop_retention_policy;
+/// assert_eq!(
+///     drop_retention_policy("one_week", "house").as_ref(),
+///     "DROP RETENTION POLICY \"one_week\" ON \"house\"",
+/// );
+/// ```
+pub fn drop_retention_policy(name: &str, database: &str) -> Query {
+    Query::new(format!(
+        "DROP RETENTION POLICY {} ON {}",
+        quote_identifier(name),
+        quote_identifier(database),
+    ))
+}
+
+/// Create a query to list all continuous queries on all databases
+pub fn show_continuous_queries() -> Query {
+    Query::new("SHOW CONTINUOUS QUERIES")
+}
+
+/// Create a query to drop a continuous query from a database
+///
+/// ```
+/// # use rinfluxdb_influxql::drop_continuous_query;
+/// assert_eq!(
+///     drop_continuous_query("downsample_1h", "house").as_ref(),
+///     "DROP CONTINUOUS QUERY \"downsample_1h\" ON \"house\"",
+/// );
+/// ```
+pub fn drop_continuous_query(name: &str, database: &str) -> Query {
+    Query::new(format!(
+        "DROP CONTINUOUS QUERY {} ON {}",
+        quote_identifier(name),
+        quote_identifier(database),
+    ))
+}
+
+/// Create a query to list all measurements in a database
+///
+/// The database is selected by the request.
+pub fn show_measurements() -> Query {
+    Query::new("SHOW MEASUREMENTS")
+}
+
+/// Create a query to list all tag keys of a measurement
+pub fn show_tag_keys(measurement: &str) -> Query {
+    Query::new(format!("SHOW TAG KEYS FROM {}", quote_identifier(measurement)))
+}
+
+/// Create a query to list all values of a tag key of a measurement
+///
+/// ```
+/// # use rinfluxdb_influxql::show_tag_values;
+/// assert_eq!(
+///     show_tag_values("indoor_environment", "room").as_ref(),
+///     "SHOW TAG VALUES FROM \"indoor_environment\" WITH KEY = \"room\"",
+/// );
+/// ```
+pub fn show_tag_values(measurement: &str, key: &str) -> Query {
+    Query::new(format!(
+        "SHOW TAG VALUES FROM {} WITH KEY = {}",
+        quote_identifier(measurement),
+        quote_identifier(key),
+    ))
+}
+
+/// Create a query to list all field keys of a measurement
+pub fn show_field_keys(measurement: &str) -> Query {
+    Query::new(format!("SHOW FIELD KEYS FROM {}", quote_identifier(measurement)))
+}
+
+/// A field key and its type, as returned by a `SHOW FIELD KEYS` statement
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FieldKey {
+    /// The name of the field
+    pub name: String,
+
+    /// The type of the field, such as `float`, `integer`, `string` or
+    /// `boolean`
+    pub field_type: String,
+}
+
+/// Parse the JSON response to a `SHOW DATABASES` statement
+///
+/// ```
+/// # use rinfluxdb_influxql::{databases_from_str, ResponseError};
+/// let input = r#"{
+///     "results": [
+///         {
+///             "statement_id": 0,
+///             "series": [
+///                 {
+///                     "name": "databases",
+///                     "columns": ["name"],
+///                     "values": [["_internal"], ["house"]]
+///                 }
+///             ]
+///         }
+///     ]
+/// }"#;
+///
+/// let databases = databases_from_str(input)?;
+///
+/// assert_eq!(databases, vec!["_internal".to_string(), "house".to_string()]);
+/// # Ok::<(), ResponseError>(())
+/// ```
+pub fn databases_from_str(input: &str) -> Result<Vec<String>, ResponseError> {
+    column_from_str(input, "name")
+}
+
+/// Parse the JSON response to a `SHOW MEASUREMENTS` statement
+pub fn measurements_from_str(input: &str) -> Result<Vec<String>, ResponseError> {
+    column_from_str(input, "name")
+}
+
+/// Parse the JSON response to a `SHOW TAG KEYS` statement
+pub fn tag_keys_from_str(input: &str) -> Result<Vec<String>, ResponseError> {
+    column_from_str(input, "tagKey")
+}
+
+/// Parse the JSON response to a `SHOW TAG VALUES` statement
+pub fn tag_values_from_str(input: &str) -> Result<Vec<String>, ResponseError> {
+    column_from_str(input, "value")
+}
+
+/// Parse the JSON response to a `SHOW FIELD KEYS` statement
+///
+/// ```
+/// # use rinfluxdb_influxql::{field_keys_from_str, FieldKey, ResponseError};
+/// let input = r#"{
+///     "results": [
+///         {
+///             "statement_id": 0,
+///             "series": [
+///                 {
+///                     "name": "indoor_environment",
+///                     "columns": ["fieldKey", "fieldType"],
+///                     "values": [["humidity", "float"], ["occupied", "boolean"]]
+///                 }
+///             ]
+///         }
+///     ]
+/// }"#;
+///
+/// let field_keys = field_keys_from_str(input)?;
+///
+/// assert_eq!(
+///     field_keys[1],
+///     FieldKey { name: "occupied".into(), field_type: "boolean".into() },
+/// );
+/// # Ok::<(), ResponseError>(())
+/// ```
+pub fn field_keys_from_str(input: &str) -> Result<Vec<FieldKey>, ResponseError> {
+    let names = column_from_str(input, "fieldKey")?;
+    let field_types = column_from_str(input, "fieldType")?;
+    let field_keys = names
+        .into_iter()
+        .zip(field_types)
+        .map(|(name, field_type)| FieldKey { name, field_type })
+        .collect();
+    Ok(field_keys)
+}
+
+/// Collect the string values of a column from all series of all statements
+fn column_from_str(input: &str, column: &str) -> Result<Vec<String>, ResponseError> {
+    let mut values = Vec::new();
+    for statement in series_from_str(input)? {
+        for series in statement? {
+            let position = series
+                .columns
+                .iter()
+                .position(|name| name == column)
+                .ok_or_else(|| ResponseError::ValueError(format!("missing column {}", column)))?;
+            for row in series.values {
+                let value = row
+                    .get(position)
+                    .and_then(|value| value.as_str())
+                    .ok_or_else(|| {
+                        ResponseError::ValueError(format!("{} is not a string", column))
+                    })?;
+                values.push(value.to_string());
+            }
+        }
+    }
+    Ok(values)
+}
+
+/// Check that no statement in a JSON response failed
+///
+/// This is used for responses to statements that do not return data, such
+/// as `CREATE DATABASE`.
+#[cfg(any(feature = "client-blocking", feature = "client-async"))]
+pub(crate) fn check_statements(input: &str) -> Result<(), ResponseError> {
+    for statement in series_from_str(input)? {
+        statement?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tag_values() -> Result<(), ResponseError> {
+        let input = r#"{
+            "results": [
+                {
+                    "statement_id": 0,
+                    "series": [
+                        {
+                            "name": "indoor_environment",
+                            "columns": ["key", "value"],
+                            "values": [["room", "bedroom"], ["room", "kitchen"]]
+                        }
+                    ]
+                }
+            ]
+        }"#;
+
+        assert_eq!(tag_values_from_str(input)?, vec!["bedroom".to_string(), "kitchen".to_string()]);
+        assert!(matches!(tag_keys_from_str(input), Err(ResponseError::ValueError(_))));
+        Ok(())
+    }
+
+    #[test]
+    fn empty_measurement() -> Result<(), ResponseError> {
+        let input = r#"{"results": [{"statement_id": 0}]}"#;
+
+        assert!(field_keys_from_str(input)?.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(any(feature = "client-blocking", feature = "client-async"))]
+    fn statement_error() {
+        let input = r#"{
+            "results": [
+                {
+                    "statement_id": 0,
+                    "error": "database not found: house"
+                }
+            ]
+        }"#;
+
+        assert!(matches!(
+            check_statements(input),
+            Err(ResponseError::StatementError(_)),
+        ));
+        assert!(check_statements(r#"{"results": [{"statement_id": 0}]}"#).is_ok());
+    }
+}
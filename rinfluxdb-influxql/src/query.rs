@@ -4,6 +4,15 @@
 // https://opensource.org/licenses/MIT
 // https://opensource.org/licenses/Apache-2.0
 
+use std::collections::BTreeMap;
+use std::fmt;
+
+use serde_json::Value as JsonValue;
+
+use rinfluxdb_types::BuildError;
+
+use super::literal::Literal;
+
 /// An InfluxQL query
 ///
 /// A query such as
@@ -11,8 +20,15 @@
 /// * `SELECT temperature, humidity FROM house..indoor_environment`
 /// * `SELECT temperature, humidity FROM house..indoor_environment WHERE time > now() - 1`
 /// * `SELECT temperature, humidity FROM house..indoor_environment GROUP BY room`
-#[derive(Debug, PartialEq)]
-pub struct Query(String);
+///
+/// With feature `serde`, queries can be serialized and deserialized along
+/// with their parameters, e.g. to store them in configuration files.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Query {
+    text: String,
+    params: BTreeMap<String, JsonValue>,
+}
 
 impl Query {
     /// Create a query from a string-like object
@@ -20,12 +36,249 @@ impl Query {
     where
         T: Into<String>,
     {
-        Self(query.into())
+        Self {
+            text: query.into(),
+            params: BTreeMap::new(),
+        }
+    }
+
+    /// Attach parameters to be bound by the server
+    ///
+    /// Parameters are sent in the `params` field of the request as a JSON
+    /// object, and InfluxDB replaces each `$name` placeholder with the
+    /// corresponding value.
+    /// Values are never interpolated in the query text, so they cannot alter
+    /// the query structure.
+    /// Instants can be passed as RFC3339 strings.
+    ///
+    /// This requires InfluxDB 1.6 or later, otherwise [`bind()`](Query::bind)
+    /// can be used to substitute parameters on the client.
+    ///
+    /// ```
+    /// # use rinfluxdb_influxql::Query;
+    /// let query = Query::new("SELECT temperature FROM indoor_environment WHERE room = $room AND temperature > $value")
+    ///     .with_params(vec![("room", "kid's bedroom")])
+    ///     .with_params(vec![("value", 20.5)]);
+    ///
+    /// assert_eq!(
+    ///     query.as_ref(),
+    ///     "SELECT temperature FROM indoor_environment WHERE room = $room AND temperature > $value",
+    /// );
+    /// assert_eq!(
+    ///     query.params_json().as_deref(),
+    ///     Some(r#"{"room":"kid's bedroom","value":20.5}"#),
+    /// );
+    /// ```
+    pub fn with_params<I, K, V>(mut self, params: I) -> Self
+    where
+        I: IntoIterator<Item = (K, V)>,
+        K: Into<String>,
+        V: Into<JsonValue>,
+    {
+        self.params.extend(
+            params
+                .into_iter()
+                .map(|(name, value)| (name.into(), value.into())),
+        );
+        self
+    }
+
+    /// Append another query as a separate statement
+    ///
+    /// Statements are separated by `;` and executed in order by InfluxDB,
+    /// which returns one result per statement.
+    /// The result at position `i` corresponds to the `i`-th statement,
+    /// though results after a failed statement may be missing.
+    /// Parameters of both queries are merged.
+    ///
+    /// ```
+    /// # use rinfluxdb_influxql::{Query, QueryBuilder};
+    /// let query = QueryBuilder::from("indoor_environment")
+    ///     .mean("temperature")
+    ///     .build()
+    ///     .and(Query::new("SELECT max(humidity) FROM indoor_environment"));
+    ///
+    /// assert_eq!(
+    ///     query.as_ref(),
+    ///     "SELECT mean(\"temperature\") FROM \"indoor_environment\"; \
+    ///     SELECT max(humidity) FROM indoor_environment",
+    /// );
+    /// ```
+    pub fn and(mut self, other: Query) -> Self {
+        let text = self.text.trim_end().trim_end_matches(';');
+        self.text = format!("{}; {}", text, other.text.trim_start());
+        self.params.extend(other.params);
+        self
+    }
+
+    /// Return the parameters attached to the query
+    pub fn params(&self) -> &BTreeMap<String, JsonValue> {
+        &self.params
+    }
+
+    /// Return the parameters as a JSON object, or `None` if there are none
+    pub fn params_json(&self) -> Option<String> {
+        if self.params.is_empty() {
+            None
+        } else {
+            serde_json::to_string(&self.params).ok()
+        }
+    }
+
+    /// Replace a parameter with a value
+    ///
+    /// All occurrences of `$name` are replaced with the value written as an
+    /// InfluxQL literal, i.e. strings are quoted and escaped, and instants
+    /// are formatted as RFC3339 strings.
+    /// Occurrences within quoted strings and identifiers, and within regular
+    /// expressions following operators `=~` and `!~`, are left untouched.
+    ///
+    /// The substitution is performed on the client, so this works with any
+    /// version of InfluxDB.
+    ///
+    /// An error is returned if the value has no InfluxQL literal, such as a
+    /// non-finite floating point number.
+    ///
+    /// ```
+    /// # use chrono::{TimeZone, Utc};
+    /// # use rinfluxdb_influxql::Query;
+    /// let query = Query::new("SELECT temperature FROM indoor_environment WHERE room = $room AND time > $start")
+    ///     .bind("room", "kid's bedroom")?
+    ///     .bind("start", Utc.with_ymd_and_hms(2021, 3, 7, 21, 0, 0).unwrap())?;
+    ///
+    /// assert_eq!(
+    ///     query.as_ref(),
+    ///     r"SELECT temperature FROM indoor_environment WHERE room = 'kid\'s bedroom' AND time > '2021-03-07T21:00:00Z'",
+    /// );
+    /// # Ok::<(), rinfluxdb_types::BuildError>(())
+    /// ```
+    pub fn bind<T>(self, name: &str, value: T) -> Result<Self, BuildError>
+    where
+        T: Literal,
+    {
+        let literal = value.try_to_literal()?;
+
+        let mut result = String::with_capacity(self.text.len());
+        let mut quote: Option<char> = None;
+        let mut escaped = false;
+        let mut chars = self.text.char_indices().peekable();
+
+        while let Some((_, c)) = chars.next() {
+            match quote {
+                Some(_) if escaped => escaped = false,
+                Some(_) if c == '\\' => escaped = true,
+                Some(q) if c == q => quote = None,
+                Some(_) => {}
+                None if c == '\'' || c == '"' => quote = Some(c),
+                None if c == '/' && is_regex_operator(&result) => quote = Some(c),
+                None if c == '$' => {
+                    let mut parameter = String::new();
+                    while let Some(&(_, next)) = chars.peek() {
+                        if next.is_ascii_alphanumeric() || next == '_' {
+                            parameter.push(next);
+                            chars.next();
+                        } else {
+                            break;
+                        }
+                    }
+
+                    if parameter == name {
+                        result.push_str(&literal);
+                    } else {
+                        result.push('$');
+                        result.push_str(&parameter);
+                    }
+                    continue;
+                }
+                None => {}
+            }
+            result.push(c);
+        }
+
+        Ok(Self {
+            text: result,
+            params: self.params,
+        })
     }
 }
 
+/// Check whether a query text ends with a regular expression operator
+fn is_regex_operator(text: &str) -> bool {
+    let text = text.trim_end();
+    text.ends_with("=~") || text.ends_with("!~")
+}
+
 impl AsRef<str> for Query {
     fn as_ref(&self) -> &str {
-        self.0.as_ref()
+        self.text.as_ref()
+    }
+}
+
+impl fmt::Display for Query {
+    /// Format the query text, without its parameters
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.text)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn and_merges_params() {
+        let query = Query::new("SELECT * FROM a WHERE x = $x;")
+            .with_params(vec![("x", 1)])
+            .and(Query::new("SELECT * FROM b WHERE y = $y").with_params(vec![("y", "b")]));
+
+        assert_eq!(query.as_ref(), "SELECT * FROM a WHERE x = $x; SELECT * FROM b WHERE y = $y");
+        assert_eq!(query.params_json().as_deref(), Some(r#"{"x":1,"y":"b"}"#));
+    }
+
+    #[test]
+    fn bind_all_occurrences() {
+        let query = Query::new("SELECT * FROM m WHERE a = $value OR b = $value").bind("value", 42_i64);
+
+        assert_eq!(query, Ok(Query::new("SELECT * FROM m WHERE a = 42 OR b = 42")));
+    }
+
+    #[test]
+    fn bind_ignores_longer_names() {
+        let query = Query::new("SELECT * FROM m WHERE host = $host AND hostname = $hostname")
+            .bind("host", "server");
+
+        assert_eq!(
+            query,
+            Ok(Query::new("SELECT * FROM m WHERE host = 'server' AND hostname = $hostname")),
+        );
+    }
+
+    #[test]
+    fn bind_ignores_quoted_text() {
+        let query = Query::new(r#"SELECT "$value" FROM m WHERE a = '\'$value' AND b = $value"#)
+            .bind("value", true);
+
+        assert_eq!(
+            query,
+            Ok(Query::new(r#"SELECT "$value" FROM m WHERE a = '\'$value' AND b = true"#)),
+        );
+    }
+
+    #[test]
+    fn bind_ignores_regular_expressions() {
+        let query = Query::new(r"SELECT * FROM m WHERE host =~ /^$host\/$/ AND a / $host > 1")
+            .bind("host", 2_i64);
+
+        assert_eq!(
+            query,
+            Ok(Query::new(r"SELECT * FROM m WHERE host =~ /^$host\/$/ AND a / 2 > 1")),
+        );
+    }
+
+    #[test]
+    fn bind_rejects_non_finite_floats() {
+        let query = Query::new("SELECT * FROM m WHERE a > $value").bind("value", f64::NAN);
+
+        assert_eq!(query, Err(BuildError::InvalidValue("NaN".into())));
     }
 }
@@ -4,38 +4,200 @@
 // https://opensource.org/licenses/MIT
 // https://opensource.org/licenses/Apache-2.0
 
-use std::fmt::Write;
+use std::convert::TryFrom;
+use std::fmt::{self, Write};
 
-use chrono::{DateTime, SecondsFormat, Utc};
+use chrono::SecondsFormat;
 
+use rinfluxdb_types::{BuildError, Duration, InstantOrDuration, TimeWindow};
+
+use super::literal::{Identifier, Literal};
 use super::query::Query;
 
+/// A comparison operator in a `WHERE` clause
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Op {
+    /// Equal to, `=`
+    Eq,
+
+    /// Not equal to, `!=`
+    NotEq,
+
+    /// Matches a regular expression, `=~`
+    Matches,
+
+    /// Does not match a regular expression, `!~`
+    NotMatches,
+
+    /// Greater than, `>`
+    Gt,
+
+    /// Greater than or equal to, `>=`
+    GtEq,
+
+    /// Less than, `<`
+    Lt,
+
+    /// Less than or equal to, `<=`
+    LtEq,
+}
+
+impl Op {
+    fn as_str(&self) -> &'static str {
+        match self {
+            Op::Eq => "=",
+            Op::NotEq => "!=",
+            Op::Matches => "=~",
+            Op::NotMatches => "!~",
+            Op::Gt => ">",
+            Op::GtEq => ">=",
+            Op::Lt => "<",
+            Op::LtEq => "<=",
+        }
+    }
+
+    fn is_regex(&self) -> bool {
+        matches!(self, Op::Matches | Op::NotMatches)
+    }
+}
+
+/// An InfluxQL aggregation or selector function
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Aggregate {
+    /// Number of non-null values, `COUNT()`
+    Count,
+
+    /// Number of distinct values, `COUNT(DISTINCT())`
+    CountDistinct,
+
+    /// Arithmetic mean, `MEAN()`
+    Mean,
+
+    /// Median, `MEDIAN()`
+    Median,
+
+    /// Most frequent value, `MODE()`
+    Mode,
+
+    /// Difference between maximum and minimum, `SPREAD()`
+    Spread,
+
+    /// Standard deviation, `STDDEV()`
+    Stddev,
+
+    /// Sum, `SUM()`
+    Sum,
+
+    /// Value with the oldest timestamp, `FIRST()`
+    First,
+
+    /// Value with the newest timestamp, `LAST()`
+    Last,
+
+    /// Maximum, `MAX()`
+    Max,
+
+    /// Minimum, `MIN()`
+    Min,
+}
+
+impl Aggregate {
+    fn apply(&self, field: &Identifier) -> String {
+        let function = match self {
+            Aggregate::Count => "count",
+            Aggregate::CountDistinct => return format!("count(distinct({}))", field),
+            Aggregate::Mean => "mean",
+            Aggregate::Median => "median",
+            Aggregate::Mode => "mode",
+            Aggregate::Spread => "spread",
+            Aggregate::Stddev => "stddev",
+            Aggregate::Sum => "sum",
+            Aggregate::First => "first",
+            Aggregate::Last => "last",
+            Aggregate::Max => "max",
+            Aggregate::Min => "min",
+        };
+        format!("{}({})", function, field)
+    }
+}
+
+/// How to fill time intervals without data in `GROUP BY time()` queries
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Fill {
+    /// Report null values, `fill(null)`
+    Null,
+
+    /// Omit intervals without data, `fill(none)`
+    None,
+
+    /// Report the value from the previous interval, `fill(previous)`
+    Previous,
+
+    /// Interpolate linearly, `fill(linear)`
+    Linear,
+
+    /// Report a fixed value, `fill(<value>)`
+    Value(f64),
+}
+
+impl Fill {
+    fn to_clause(self) -> String {
+        match self {
+            Fill::Null => "fill(null)".to_string(),
+            Fill::None => "fill(none)".to_string(),
+            Fill::Previous => "fill(previous)".to_string(),
+            Fill::Linear => "fill(linear)".to_string(),
+            Fill::Value(value) => format!("fill({})", value),
+        }
+    }
+}
+
+/// The target of the `FROM` clause
+#[derive(Clone)]
+enum Source {
+    /// A measurement, optionally in a database and retention policy
+    Measurement(Identifier),
+
+    /// The results of another query
+    Subquery(Query),
+}
+
 /// A builder for InfluxQL queries
 ///
+/// Measurements, fields, tags, databases and retention policies are written
+/// as [quoted identifiers](Identifier), so names containing spaces or
+/// matching InfluxQL keywords can be used.
+///
 /// ```
 /// # use rinfluxdb_influxql::QueryBuilder;
 /// # use chrono::{TimeZone, Utc};
 /// let query = QueryBuilder::from("indoor_environment")
 ///     .field("temperature")
 ///     .field("humidity")
-///     .start(Utc.ymd(2021, 3, 7).and_hms(21, 0, 0))
+///     .start(Utc.with_ymd_and_hms(2021, 3, 7, 21, 0, 0).unwrap())
 ///     .build();
 ///
 /// assert_eq!(
 ///     query.as_ref(),
-///     "SELECT temperature, humidity \
-///     FROM indoor_environment \
+///     "SELECT \"temperature\", \"humidity\" \
+///     FROM \"indoor_environment\" \
 ///     WHERE time > '2021-03-07T21:00:00Z'",
 /// );
 /// ```
+#[derive(Clone)]
 pub struct QueryBuilder {
-    measurement: String,
-    database: Option<String>,
-    retention_policy: Option<String>,
-    fields: Vec<String>,
-    start: Option<DateTime<Utc>>,
-    stop: Option<DateTime<Utc>>,
-    groups: Vec<String>,
+    source: Source,
+    into: Option<String>,
+    database: Option<Identifier>,
+    retention_policy: Option<Identifier>,
+    fields: Vec<(Option<Aggregate>, Identifier)>,
+    start: Option<InstantOrDuration>,
+    start_included: bool,
+    stop: Option<InstantOrDuration>,
+    conditions: Vec<(Identifier, Op, String)>,
+    interval: Option<Duration>,
+    groups: Vec<Identifier>,
+    fill: Option<Fill>,
 }
 
 impl QueryBuilder {
@@ -46,14 +208,45 @@ impl QueryBuilder {
     where
         T: Into<String>,
     {
+        Self::from_source(Source::Measurement(Identifier::new(measurement)))
+    }
+
+    /// Create a query selecting from the results of another query
+    ///
+    /// This sets a subquery in the `FROM` clause.
+    /// Parameters attached to the inner query are attached to the outer
+    /// query.
+    ///
+    /// ```
+    /// # use rinfluxdb_influxql::{Query, QueryBuilder};
+    /// let inner = Query::new("SELECT 100 - \"idle\" AS \"usage\" FROM \"cpu\"");
+    /// let query = QueryBuilder::from_subquery(inner)
+    ///     .mean("usage")
+    ///     .build();
+    ///
+    /// assert_eq!(
+    ///     query.as_ref(),
+    ///     "SELECT mean(\"usage\") FROM (SELECT 100 - \"idle\" AS \"usage\" FROM \"cpu\")",
+    /// );
+    /// ```
+    pub fn from_subquery(inner: Query) -> Self {
+        Self::from_source(Source::Subquery(inner))
+    }
+
+    fn from_source(source: Source) -> Self {
         Self {
-            measurement: measurement.into(),
+            source,
+            into: None,
             database: None,
             retention_policy: None,
             fields: Vec::new(),
             start: None,
+            start_included: false,
             stop: None,
+            conditions: Vec::new(),
+            interval: None,
             groups: Vec::new(),
+            fill: None,
         }
     }
 
@@ -61,11 +254,12 @@ impl QueryBuilder {
     ///
     /// This sets the database in the `FROM` clause:
     /// `database.retention_policy.measurement`.
+    /// It cannot be used when selecting from a subquery.
     pub fn database<T>(mut self, database: T) -> Self
     where
         T: Into<String>,
     {
-        self.database = Some(database.into());
+        self.database = Some(Identifier::new(database));
         self
     }
 
@@ -73,11 +267,46 @@ impl QueryBuilder {
     ///
     /// This sets the retention policy in the `FROM` clause:
     /// `database.retention_policy.measurement`.
+    /// It cannot be used when selecting from a subquery.
     pub fn retention_policy<T>(mut self, retention_policy: T) -> Self
     where
         T: Into<String>,
     {
-        self.retention_policy = Some(retention_policy.into());
+        self.retention_policy = Some(Identifier::new(retention_policy));
+        self
+    }
+
+    /// Write the query results to a measurement
+    ///
+    /// This sets the `INTO` clause.
+    /// The target is either `measurement`, `retention_policy.measurement` or
+    /// `database.retention_policy.measurement`, where the retention policy
+    /// can be left empty to use the default one.
+    /// The measurement can be the backreference `:MEASUREMENT`, which writes
+    /// to measurements with the same names as the queried ones.
+    ///
+    /// ```
+    /// # use rinfluxdb_influxql::QueryBuilder;
+    /// # use rinfluxdb_types::Duration;
+    /// let query = QueryBuilder::from("indoor_environment")
+    ///     .mean("temperature")
+    ///     .into_measurement("house.one_year.indoor_environment_5m")
+    ///     .group_by_time(Duration::Minutes(5))
+    ///     .build();
+    ///
+    /// assert_eq!(
+    ///     query.as_ref(),
+    ///     "SELECT mean(\"temperature\") \
+    ///     INTO \"house\".\"one_year\".\"indoor_environment_5m\" \
+    ///     FROM \"indoor_environment\" \
+    ///     GROUP BY time(5m)",
+    /// );
+    /// ```
+    pub fn into_measurement<T>(mut self, target: T) -> Self
+    where
+        T: Into<String>,
+    {
+        self.into = Some(target.into());
         self
     }
 
@@ -86,44 +315,417 @@ impl QueryBuilder {
     where
         T: Into<String>,
     {
-        self.fields.push(field.into());
+        self.fields.push((None, Identifier::new(field)));
         self
     }
 
+    /// Add an aggregation of a field to the query
+    ///
+    /// Results columns are named after the function, such as `mean`.
+    ///
+    /// ```
+    /// # use rinfluxdb_influxql::{Aggregate, Fill, QueryBuilder};
+    /// # use rinfluxdb_types::Duration;
+    /// let query = QueryBuilder::from("indoor_environment")
+    ///     .aggregate(Aggregate::Mean, "temperature")
+    ///     .max("humidity")
+    ///     .group_by_time(Duration::Minutes(5))
+    ///     .group_by("room")
+    ///     .fill(Fill::Null)
+    ///     .build();
+    ///
+    /// assert_eq!(
+    ///     query.as_ref(),
+    ///     "SELECT mean(\"temperature\"), max(\"humidity\") \
+    ///     FROM \"indoor_environment\" \
+    ///     GROUP BY time(5m), \"room\" fill(null)",
+    /// );
+    /// ```
+    pub fn aggregate<T>(mut self, aggregate: Aggregate, field: T) -> Self
+    where
+        T: Into<String>,
+    {
+        self.fields.push((Some(aggregate), Identifier::new(field)));
+        self
+    }
+
+    /// Add the count of a field to the query
+    pub fn count<T>(self, field: T) -> Self
+    where
+        T: Into<String>,
+    {
+        self.aggregate(Aggregate::Count, field)
+    }
+
+    /// Add the mean of a field to the query
+    pub fn mean<T>(self, field: T) -> Self
+    where
+        T: Into<String>,
+    {
+        self.aggregate(Aggregate::Mean, field)
+    }
+
+    /// Add the median of a field to the query
+    pub fn median<T>(self, field: T) -> Self
+    where
+        T: Into<String>,
+    {
+        self.aggregate(Aggregate::Median, field)
+    }
+
+    /// Add the sum of a field to the query
+    pub fn sum<T>(self, field: T) -> Self
+    where
+        T: Into<String>,
+    {
+        self.aggregate(Aggregate::Sum, field)
+    }
+
+    /// Add the maximum of a field to the query
+    pub fn max<T>(self, field: T) -> Self
+    where
+        T: Into<String>,
+    {
+        self.aggregate(Aggregate::Max, field)
+    }
+
+    /// Add the minimum of a field to the query
+    pub fn min<T>(self, field: T) -> Self
+    where
+        T: Into<String>,
+    {
+        self.aggregate(Aggregate::Min, field)
+    }
+
+    /// Add the first value of a field to the query
+    pub fn first<T>(self, field: T) -> Self
+    where
+        T: Into<String>,
+    {
+        self.aggregate(Aggregate::First, field)
+    }
+
+    /// Add the last value of a field to the query
+    pub fn last<T>(self, field: T) -> Self
+    where
+        T: Into<String>,
+    {
+        self.aggregate(Aggregate::Last, field)
+    }
+
     /// Restrict query results to a start time
+    ///
+    /// The start time can be an instant, or a duration relative to the
+    /// current time.
+    ///
+    /// ```
+    /// # use rinfluxdb_influxql::QueryBuilder;
+    /// # use rinfluxdb_types::Duration;
+    /// let query = QueryBuilder::from("indoor_environment")
+    ///     .field("temperature")
+    ///     .start(Duration::Hours(-1))
+    ///     .stop(Duration::Minutes(-5))
+    ///     .build();
+    ///
+    /// assert_eq!(
+    ///     query.as_ref(),
+    ///     "SELECT \"temperature\" FROM \"indoor_environment\" \
+    ///     WHERE time > now() - 1h AND time < now() - 5m",
+    /// );
+    /// ```
     pub fn start<T>(mut self, start: T) -> Self
     where
-        T: Into<DateTime<Utc>>,
+        T: Into<InstantOrDuration>,
     {
         self.start = Some(start.into());
+        self.start_included = false;
         self
     }
 
     /// Restrict query results to a stop time
+    ///
+    /// The stop time can be an instant, or a duration relative to the
+    /// current time.
     pub fn stop<T>(mut self, stop: T) -> Self
     where
-        T: Into<DateTime<Utc>>,
+        T: Into<InstantOrDuration>,
     {
         self.stop = Some(stop.into());
         self
     }
 
+    /// Restrict query results to a window of time
+    ///
+    /// Contrary to [`start()`](QueryBuilder::start), the start of the window
+    /// is included, so consecutive windows select every point exactly once.
+    ///
+    /// ```
+    /// # use rinfluxdb_influxql::QueryBuilder;
+    /// # use rinfluxdb_types::TimeWindow;
+    /// # use chrono::{TimeZone, Utc};
+    /// let window = TimeWindow {
+    ///     index: 0,
+    ///     start: Utc.with_ymd_and_hms(2021, 3, 7, 21, 0, 0).unwrap(),
+    ///     stop: Utc.with_ymd_and_hms(2021, 3, 8, 21, 0, 0).unwrap(),
+    /// };
+    /// let query = QueryBuilder::from("indoor_environment")
+    ///     .field("temperature")
+    ///     .window(&window)
+    ///     .build();
+    ///
+    /// assert_eq!(
+    ///     query.as_ref(),
+    ///     "SELECT \"temperature\" FROM \"indoor_environment\" \
+    ///     WHERE time >= '2021-03-07T21:00:00Z' AND time < '2021-03-08T21:00:00Z'",
+    /// );
+    /// ```
+    pub fn window(mut self, window: &TimeWindow) -> Self {
+        self.start = Some(window.start.into());
+        self.start_included = true;
+        self.stop = Some(window.stop.into());
+        self
+    }
+
+    /// Restrict query results to a tag value
+    ///
+    /// This is a shortcut for `r#where(tag, Op::Eq, value)`.
+    ///
+    /// ```
+    /// # use rinfluxdb_influxql::QueryBuilder;
+    /// let query = QueryBuilder::from("indoor_environment")
+    ///     .field("temperature")
+    ///     .tag("room", "kid's bedroom")
+    ///     .build();
+    ///
+    /// assert_eq!(
+    ///     query.as_ref(),
+    ///     r#"SELECT "temperature" FROM "indoor_environment" WHERE "room" = 'kid\'s bedroom'"#,
+    /// );
+    /// ```
+    pub fn tag<T, S>(self, tag: T, value: S) -> Self
+    where
+        T: Into<String>,
+        S: Into<String>,
+    {
+        self.r#where(tag, Op::Eq, value.into())
+    }
+
+    /// Restrict query results to a condition on a tag or field
+    ///
+    /// The value is written as an InfluxQL literal, and regular expressions
+    /// are written between slashes.
+    /// Conditions are combined with `AND`.
+    ///
+    /// ```
+    /// # use rinfluxdb_influxql::{Op, QueryBuilder, Regex};
+    /// let query = QueryBuilder::from("indoor_environment")
+    ///     .field("temperature")
+    ///     .r#where("room", Op::Matches, Regex::new("^bed"))
+    ///     .r#where("temperature", Op::GtEq, 20.5)
+    ///     .build();
+    ///
+    /// assert_eq!(
+    ///     query.as_ref(),
+    ///     "SELECT \"temperature\" FROM \"indoor_environment\" \
+    ///     WHERE \"room\" =~ /^bed/ AND \"temperature\" >= 20.5",
+    /// );
+    /// ```
+    pub fn r#where<T, L>(mut self, name: T, op: Op, value: L) -> Self
+    where
+        T: Into<String>,
+        L: Literal,
+    {
+        self.conditions.push((Identifier::new(name), op, value.to_literal()));
+        self
+    }
+
+    /// Group by time intervals
+    ///
+    /// This adds `time(<interval>)` to the `GROUP BY` clause, and requires
+    /// all fields to be aggregated.
+    pub fn group_by_time(mut self, interval: Duration) -> Self {
+        self.interval = Some(interval);
+        self
+    }
+
+    /// Set how to fill time intervals without data
+    ///
+    /// This requires grouping by time intervals, and a fixed value must be
+    /// finite.
+    pub fn fill(mut self, fill: Fill) -> Self {
+        self.fill = Some(fill);
+        self
+    }
+
     /// Group by a tag
     pub fn group_by<T>(mut self, tag: T) -> Self
     where
         T: Into<String>,
     {
-        self.groups.push(tag.into());
+        self.groups.push(Identifier::new(tag));
         self
     }
 
+    /// Create the InfluxQL query, validating it first
+    ///
+    /// An error is returned if the measurement is empty, if any identifier
+    /// is empty or spans multiple lines, if the start time is not before
+    /// the stop time, or if the fill value is not finite.
+    ///
+    /// ```
+    /// # use rinfluxdb_influxql::QueryBuilder;
+    /// # use rinfluxdb_types::{BuildError, Duration};
+    /// # use chrono::{TimeZone, Utc};
+    /// let result = QueryBuilder::from("indoor_environment")
+    ///     .start(Utc.with_ymd_and_hms(2021, 3, 7, 22, 0, 0).unwrap())
+    ///     .stop(Utc.with_ymd_and_hms(2021, 3, 7, 21, 0, 0).unwrap())
+    ///     .try_build();
+    ///
+    /// assert!(matches!(result, Err(BuildError::ConflictingClauses(_))));
+    /// ```
+    pub fn try_build(self) -> Result<Query, BuildError> {
+        let measurement = match &self.source {
+            Source::Measurement(measurement) => {
+                if measurement.as_str().is_empty() {
+                    return Err(BuildError::EmptySelection);
+                }
+                Some(measurement)
+            }
+            Source::Subquery(inner) => {
+                if inner.as_ref().trim().is_empty() {
+                    return Err(BuildError::EmptySelection);
+                }
+                if self.database.is_some() || self.retention_policy.is_some() {
+                    return Err(BuildError::ConflictingClauses(
+                        "database and retention policy cannot be set for subqueries".into(),
+                    ));
+                }
+                None
+            }
+        };
+
+        let identifiers = measurement
+            .into_iter()
+            .chain(self.database.iter())
+            .chain(self.retention_policy.iter())
+            .chain(self.fields.iter().map(|(_, field)| field))
+            .chain(self.conditions.iter().map(|(name, _, _)| name))
+            .chain(self.groups.iter());
+        for identifier in identifiers {
+            if identifier.as_str().is_empty() || identifier.as_str().contains('\n') {
+                return Err(BuildError::InvalidIdentifier(identifier.as_str().to_string()));
+            }
+        }
+
+        if let Some(into) = &self.into {
+            let parts: Vec<&str> = into.split('.').collect();
+            let measurement = parts.last().copied().unwrap_or_default();
+            if parts.len() > 3 || measurement.is_empty() || into.contains('\n') {
+                return Err(BuildError::InvalidIdentifier(into.clone()));
+            }
+        }
+
+        for time in self.start.iter().chain(self.stop.iter()) {
+            if let InstantOrDuration::Duration(Duration::Infinity) = time {
+                return Err(BuildError::InvalidDuration(Duration::Infinity.to_string()));
+            }
+        }
+
+        if let (Some(start), Some(stop)) = (&self.start, &self.stop) {
+            if !is_before(start, stop) {
+                return Err(BuildError::ConflictingClauses(
+                    "start time is not before stop time".into(),
+                ));
+            }
+        }
+
+        for (name, op, value) in &self.conditions {
+            if op.is_regex() != value.starts_with('/') {
+                return Err(BuildError::ConflictingClauses(format!(
+                    "operator {} cannot be used with value {} for {}",
+                    op.as_str(),
+                    value,
+                    name,
+                )));
+            }
+        }
+
+        let aggregates = self.fields.iter().filter(|(aggregate, _)| aggregate.is_some()).count();
+        if aggregates > 0 && aggregates < self.fields.len() {
+            return Err(BuildError::ConflictingClauses(
+                "aggregated and non-aggregated fields are mixed".into(),
+            ));
+        }
+
+        if let Some(interval) = self.interval {
+            if !interval.is_positive() {
+                return Err(BuildError::InvalidDuration(interval.to_string()));
+            }
+            if aggregates == 0 {
+                return Err(BuildError::ConflictingClauses(
+                    "grouping by time requires aggregated fields".into(),
+                ));
+            }
+        } else if self.fill.is_some() {
+            return Err(BuildError::ConflictingClauses(
+                "fill requires grouping by time".into(),
+            ));
+        }
+
+        if let Some(Fill::Value(value)) = self.fill {
+            if !value.is_finite() {
+                return Err(BuildError::InvalidValue(value.to_string()));
+            }
+        }
+
+        Ok(self.build())
+    }
+
+    /// Return the measurement in the `FROM` clause
+    ///
+    /// Queries selecting from a subquery have no measurement.
+    ///
+    /// ```
+    /// # use rinfluxdb_influxql::{Aggregate, QueryBuilder};
+    /// let builder = QueryBuilder::from("indoor_environment")
+    ///     .field("temperature")
+    ///     .aggregate(Aggregate::Mean, "humidity");
+    ///
+    /// assert_eq!(builder.measurement(), Some("indoor_environment"));
+    /// assert_eq!(
+    ///     builder.fields().collect::<Vec<_>>(),
+    ///     vec![(None, "temperature"), (Some(Aggregate::Mean), "humidity")],
+    /// );
+    /// ```
+    pub fn measurement(&self) -> Option<&str> {
+        match &self.source {
+            Source::Measurement(measurement) => Some(measurement.as_str()),
+            Source::Subquery(_) => None,
+        }
+    }
+
+    /// Return the selected fields, with their aggregation functions
+    pub fn fields(&self) -> impl Iterator<Item = (Option<Aggregate>, &str)> {
+        self.fields.iter().map(|(aggregate, field)| (*aggregate, field.as_str()))
+    }
+
+    /// Create the InfluxQL query and return its text
+    ///
+    /// Parameters attached to subqueries are discarded.
+    pub fn build_string(self) -> String {
+        self.build().to_string()
+    }
+
     /// Create the InfluxQL query
     pub fn build(self) -> Query {
         let mut result = String::new();
 
         write!(&mut result, "SELECT ").unwrap();
 
-        let mut fields = self.fields.into_iter();
+        let mut fields = self.fields.into_iter().map(|(aggregate, field)| match aggregate {
+            Some(aggregate) => aggregate.apply(&field),
+            None => field.to_string(),
+        });
 
         // TODO: Return error if vecs have not expected number of arguments
         let first_field = fields.next();
@@ -137,79 +739,132 @@ impl QueryBuilder {
             None => write!(&mut result, "*").unwrap(),
         }
 
-        match (self.database, self.retention_policy) {
-            (Some(database), Some(retention_policy)) => write!(
-                &mut result,
-                " FROM {}.{}.{}",
-                database,
-                retention_policy,
-                self.measurement,
-            )
-            .unwrap(),
-            (Some(database), None) => write!(
-                &mut result,
-                " FROM {}..{}",
-                database,
-                self.measurement,
-            )
-            .unwrap(),
-            (None, Some(retention_policy)) => write!(
-                &mut result,
-                " FROM .{}.{}",
-                retention_policy,
-                self.measurement,
-            )
-            .unwrap(),
-            (None, None) => write!(&mut result, " FROM {}", self.measurement).unwrap(),
+        if let Some(into) = &self.into {
+            write!(&mut result, " INTO {}", into_target(into)).unwrap();
         }
 
-        if self.start.is_some() || self.stop.is_some() {
-            write!(&mut result, " WHERE").unwrap();
-
-            match (self.start, self.stop) {
-                (Some(start), Some(stop)) => write!(
-                    &mut result,
-                    " time > '{}' AND time < '{}'",
-                    start.to_rfc3339_opts(SecondsFormat::AutoSi, true),
-                    stop.to_rfc3339_opts(SecondsFormat::AutoSi, true),
-                )
-                .unwrap(),
-                (Some(start), None) => write!(
-                    &mut result,
-                    " time > '{}'",
-                    start.to_rfc3339_opts(SecondsFormat::AutoSi, true),
-                )
-                .unwrap(),
-                (None, Some(stop)) => write!(
-                    &mut result,
-                    " time < '{}'",
-                    stop.to_rfc3339_opts(SecondsFormat::AutoSi, true),
-                )
-                .unwrap(),
-                (None, None) => unreachable!(),
+        let mut params = None;
+        match self.source {
+            Source::Measurement(measurement) => {
+                match (self.database, self.retention_policy) {
+                    (Some(database), Some(retention_policy)) => write!(
+                        &mut result,
+                        " FROM {}.{}.{}",
+                        database,
+                        retention_policy,
+                        measurement,
+                    )
+                    .unwrap(),
+                    (Some(database), None) => write!(
+                        &mut result,
+                        " FROM {}..{}",
+                        database,
+                        measurement,
+                    )
+                    .unwrap(),
+                    (None, Some(retention_policy)) => write!(
+                        &mut result,
+                        " FROM .{}.{}",
+                        retention_policy,
+                        measurement,
+                    )
+                    .unwrap(),
+                    (None, None) => write!(&mut result, " FROM {}", measurement).unwrap(),
+                }
+            }
+            Source::Subquery(inner) => {
+                write!(&mut result, " FROM ({})", inner.as_ref()).unwrap();
+                params = Some(inner.params().clone());
             }
         }
 
-        if !self.groups.is_empty() {
-            write!(&mut result, " GROUP BY").unwrap();
+        let mut conditions = Vec::new();
+        if let Some(start) = self.start {
+            let op = if self.start_included { ">=" } else { ">" };
+            conditions.push(format!("time {} {}", op, time_expression(&start)));
+        }
+        if let Some(stop) = self.stop {
+            conditions.push(format!("time < {}", time_expression(&stop)));
+        }
+        for (name, op, value) in self.conditions {
+            conditions.push(format!("{} {} {}", name, op.as_str(), value));
+        }
 
-            let mut group = self.groups.into_iter();
+        if !conditions.is_empty() {
+            write!(&mut result, " WHERE {}", conditions.join(" AND ")).unwrap();
+        }
 
-            // TODO: Return error if vecs have not expected number of arguments
-            let first_group = group.next();
-            match first_group {
-                Some(first_group) => {
-                    write!(&mut result, " {}", first_group).unwrap();
-                    for group in group {
-                        write!(&mut result, ", {}", group).unwrap();
-                    }
-                }
-                None => unreachable!(),
-            }
+        let groups: Vec<String> = self
+            .interval
+            .map(|interval| format!("time({})", interval))
+            .into_iter()
+            .chain(self.groups.iter().map(Identifier::to_string))
+            .collect();
+        if !groups.is_empty() {
+            write!(&mut result, " GROUP BY {}", groups.join(", ")).unwrap();
+        }
 
+        if let Some(fill) = self.fill {
+            write!(&mut result, " {}", fill.to_clause()).unwrap();
         }
 
-        Query::new(result)
+        let query = Query::new(result);
+        match params {
+            Some(params) => query.with_params(params),
+            None => query,
+        }
+    }
+}
+
+impl fmt::Display for QueryBuilder {
+    /// Format the text of the query that would be built
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.clone().build())
+    }
+}
+
+/// Format the target of an `INTO` clause, quoting each part but the
+/// `:MEASUREMENT` backreference
+fn into_target(target: &str) -> String {
+    let parts: Vec<String> = target
+        .splitn(3, '.')
+        .map(|part| match part {
+            "" => String::new(),
+            ":MEASUREMENT" => part.to_string(),
+            part => Identifier::new(part).to_string(),
+        })
+        .collect();
+    parts.join(".")
+}
+
+/// Format an instant, or a duration relative to `now()`
+fn time_expression(time: &InstantOrDuration) -> String {
+    match time {
+        InstantOrDuration::Instant(instant) => {
+            format!("'{}'", instant.to_rfc3339_opts(SecondsFormat::AutoSi, true))
+        }
+        InstantOrDuration::Duration(duration) => match duration.checked_neg() {
+            Some(negated) if negated.is_positive() => format!("now() - {}", negated),
+            _ if duration.is_positive() => format!("now() + {}", duration),
+            _ => "now()".to_string(),
+        },
+    }
+}
+
+/// Check whether a time comes before another
+///
+/// An instant and a relative time cannot be compared, so they are assumed
+/// to be in the right order.
+fn is_before(start: &InstantOrDuration, stop: &InstantOrDuration) -> bool {
+    match (start, stop) {
+        (InstantOrDuration::Instant(start), InstantOrDuration::Instant(stop)) => start < stop,
+        (InstantOrDuration::Duration(start), InstantOrDuration::Duration(stop)) => {
+            match (chrono::Duration::try_from(*start), chrono::Duration::try_from(*stop)) {
+                (Ok(start), Ok(stop)) => start < stop,
+                _ => true,
+            }
+        }
+        _ => true,
     }
 }
 
@@ -217,13 +872,15 @@ impl QueryBuilder {
 mod tests {
     use super::*;
 
-    use chrono::TimeZone;
+    use chrono::{TimeZone, Utc};
+
+    use super::super::literal::Regex;
 
     #[test]
     fn simple_query() {
         let expected = Query::new(
-            "SELECT temperature, humidity \
-            FROM indoor_environment",
+            "SELECT \"temperature\", \"humidity\" \
+            FROM \"indoor_environment\"",
         );
 
         let actual = QueryBuilder::from("indoor_environment")
@@ -234,11 +891,46 @@ mod tests {
         assert_eq!(actual, expected);
     }
 
+    #[test]
+    fn display_builder() {
+        let builder = QueryBuilder::from_subquery(
+            QueryBuilder::from("indoor_environment")
+                .field("temperature")
+                .build(),
+        )
+        .mean("temperature");
+
+        assert_eq!(builder.measurement(), None);
+        assert_eq!(
+            builder.to_string(),
+            "SELECT mean(\"temperature\") \
+            FROM (SELECT \"temperature\" FROM \"indoor_environment\")",
+        );
+        assert_eq!(builder.clone().build_string(), builder.build().to_string());
+    }
+
+    #[test]
+    fn try_build_invalid_identifier() {
+        let actual = QueryBuilder::from("indoor_environment")
+            .field("temperature")
+            .field("")
+            .try_build();
+
+        assert_eq!(actual, Err(BuildError::InvalidIdentifier("".into())));
+    }
+
+    #[test]
+    fn try_build_empty_selection() {
+        let actual = QueryBuilder::from("").try_build();
+
+        assert_eq!(actual, Err(BuildError::EmptySelection));
+    }
+
     #[test]
     fn simple_query_all_fields() {
         let expected = Query::new(
             "SELECT * \
-            FROM indoor_environment",
+            FROM \"indoor_environment\"",
         );
 
         let actual = QueryBuilder::from("indoor_environment")
@@ -250,33 +942,125 @@ mod tests {
     #[test]
     fn simple_query_with_range() {
         let expected = Query::new(
-            "SELECT temperature, humidity \
-            FROM indoor_environment \
+            "SELECT \"temperature\", \"humidity\" \
+            FROM \"indoor_environment\" \
             WHERE time > '2021-03-07T21:00:00Z' AND time < '2021-03-07T22:00:00Z'",
         );
 
         let actual = QueryBuilder::from("indoor_environment")
             .field("temperature")
             .field("humidity")
-            .start(Utc.ymd(2021, 3, 7).and_hms(21, 0, 0))
-            .stop(Utc.ymd(2021, 3, 7).and_hms(22, 0, 0))
+            .start(Utc.with_ymd_and_hms(2021, 3, 7, 21, 0, 0).unwrap())
+            .stop(Utc.with_ymd_and_hms(2021, 3, 7, 22, 0, 0).unwrap())
             .build();
 
         assert_eq!(actual, expected);
     }
 
+    #[test]
+    fn query_from_subquery() {
+        let expected = Query::new(
+            "SELECT max(\"mean\") \
+            FROM (SELECT mean(\"temperature\") FROM \"indoor_environment\" \
+            WHERE \"room\" = 'bedroom' GROUP BY time(5m)) \
+            WHERE time > now() - 1h",
+        );
+
+        let inner = QueryBuilder::from("indoor_environment")
+            .mean("temperature")
+            .tag("room", "bedroom")
+            .group_by_time(Duration::Minutes(5))
+            .build();
+        let actual = QueryBuilder::from_subquery(inner)
+            .max("mean")
+            .start(Duration::Hours(-1))
+            .try_build();
+
+        assert_eq!(actual, Ok(expected));
+
+        let inner = Query::new("SELECT \"usage_idle\" FROM \"cpu\" WHERE \"host\" = $host")
+            .with_params(vec![("host", "server")]);
+        let actual = QueryBuilder::from_subquery(inner).mean("usage_idle").build();
+        assert_eq!(actual.params_json().as_deref(), Some(r#"{"host":"server"}"#));
+
+        let with_database = QueryBuilder::from_subquery(Query::new("SELECT * FROM \"cpu\""))
+            .database("telegraf")
+            .try_build();
+        assert!(matches!(with_database, Err(BuildError::ConflictingClauses(_))));
+    }
+
+    #[test]
+    fn query_into_measurement() {
+        let query = |target: &str| {
+            QueryBuilder::from("cpu")
+                .max("usage")
+                .into_measurement(target)
+                .try_build()
+                .map(|query| query.as_ref().to_string())
+        };
+
+        assert_eq!(
+            query("cpu_max"),
+            Ok("SELECT max(\"usage\") INTO \"cpu_max\" FROM \"cpu\"".to_string()),
+        );
+        assert_eq!(
+            query("one_year.cpu_max"),
+            Ok("SELECT max(\"usage\") INTO \"one_year\".\"cpu_max\" FROM \"cpu\"".to_string()),
+        );
+        assert_eq!(
+            query("telegraf..:MEASUREMENT"),
+            Ok("SELECT max(\"usage\") INTO \"telegraf\"..:MEASUREMENT FROM \"cpu\"".to_string()),
+        );
+        assert_eq!(
+            query("telegraf.one_year."),
+            Err(BuildError::InvalidIdentifier("telegraf.one_year.".into())),
+        );
+        assert_eq!(
+            query("a.b.c.d"),
+            Err(BuildError::InvalidIdentifier("a.b.c.d".into())),
+        );
+    }
+
+    #[test]
+    fn query_with_relative_times() {
+        let expected = Query::new(
+            "SELECT \"temperature\" \
+            FROM \"indoor_environment\" \
+            WHERE time > now() - 1h30m AND time < now()",
+        );
+
+        let actual = QueryBuilder::from("indoor_environment")
+            .field("temperature")
+            .start(Duration::Minutes(-90))
+            .stop(Duration::Seconds(0))
+            .try_build();
+
+        assert_eq!(actual, Ok(expected));
+
+        let reversed = QueryBuilder::from("indoor_environment")
+            .start(Duration::Minutes(-5))
+            .stop(Duration::Hours(-1))
+            .try_build();
+        assert!(matches!(reversed, Err(BuildError::ConflictingClauses(_))));
+
+        let infinite = QueryBuilder::from("indoor_environment")
+            .start(Duration::Infinity)
+            .try_build();
+        assert_eq!(infinite, Err(BuildError::InvalidDuration("inf".into())));
+    }
+
     #[test]
     fn simple_query_with_start() {
         let expected = Query::new(
-            "SELECT temperature, humidity \
-            FROM indoor_environment \
+            "SELECT \"temperature\", \"humidity\" \
+            FROM \"indoor_environment\" \
             WHERE time > '2021-03-07T21:00:00Z'",
         );
 
         let actual = QueryBuilder::from("indoor_environment")
             .field("temperature")
             .field("humidity")
-            .start(Utc.ymd(2021, 3, 7).and_hms(21, 0, 0))
+            .start(Utc.with_ymd_and_hms(2021, 3, 7, 21, 0, 0).unwrap())
             .build();
 
         assert_eq!(actual, expected);
@@ -285,36 +1069,138 @@ mod tests {
     #[test]
     fn simple_query_with_stop() {
         let expected = Query::new(
-            "SELECT temperature, humidity \
-            FROM indoor_environment \
+            "SELECT \"temperature\", \"humidity\" \
+            FROM \"indoor_environment\" \
             WHERE time < '2021-03-07T22:00:00Z'",
         );
 
         let actual = QueryBuilder::from("indoor_environment")
             .field("temperature")
             .field("humidity")
-            .stop(Utc.ymd(2021, 3, 7).and_hms(22, 0, 0))
+            .stop(Utc.with_ymd_and_hms(2021, 3, 7, 22, 0, 0).unwrap())
             .build();
 
         assert_eq!(actual, expected);
     }
 
+    #[test]
+    fn query_with_range_and_conditions() {
+        let expected = Query::new(
+            "SELECT \"temperature\" \
+            FROM \"indoor_environment\" \
+            WHERE time > '2021-03-07T21:00:00Z' AND time < '2021-03-07T22:00:00Z' \
+            AND \"room\" = 'bedroom' AND \"sensor\" != 'broken' AND \"room\" !~ /kitchen/",
+        );
+
+        let actual = QueryBuilder::from("indoor_environment")
+            .field("temperature")
+            .start(Utc.with_ymd_and_hms(2021, 3, 7, 21, 0, 0).unwrap())
+            .stop(Utc.with_ymd_and_hms(2021, 3, 7, 22, 0, 0).unwrap())
+            .tag("room", "bedroom")
+            .r#where("sensor", Op::NotEq, "broken")
+            .r#where("room", Op::NotMatches, Regex::new("kitchen"))
+            .try_build();
+
+        assert_eq!(actual, Ok(expected));
+    }
+
+    #[test]
+    fn try_build_regex_without_pattern() {
+        let actual = QueryBuilder::from("indoor_environment")
+            .r#where("room", Op::Matches, "bedroom")
+            .try_build();
+
+        assert!(matches!(actual, Err(BuildError::ConflictingClauses(_))));
+    }
+
+    #[test]
+    fn query_with_aggregates_and_fill_value() {
+        let expected = Query::new(
+            "SELECT count(distinct(\"room\")), last(\"temperature\") \
+            FROM \"indoor_environment\" \
+            WHERE time > '2021-03-07T21:00:00Z' \
+            GROUP BY time(1h) fill(-1.5)",
+        );
+
+        let actual = QueryBuilder::from("indoor_environment")
+            .aggregate(Aggregate::CountDistinct, "room")
+            .last("temperature")
+            .start(Utc.with_ymd_and_hms(2021, 3, 7, 21, 0, 0).unwrap())
+            .group_by_time(Duration::Hours(1))
+            .fill(Fill::Value(-1.5))
+            .try_build();
+
+        assert_eq!(actual, Ok(expected));
+    }
+
+    #[test]
+    fn try_build_invalid_aggregations() {
+        let mixed = QueryBuilder::from("indoor_environment")
+            .field("temperature")
+            .mean("humidity")
+            .try_build();
+        assert!(matches!(mixed, Err(BuildError::ConflictingClauses(_))));
+
+        let without_aggregates = QueryBuilder::from("indoor_environment")
+            .field("temperature")
+            .group_by_time(Duration::Minutes(5))
+            .try_build();
+        assert!(matches!(without_aggregates, Err(BuildError::ConflictingClauses(_))));
+
+        let fill_without_interval = QueryBuilder::from("indoor_environment")
+            .mean("temperature")
+            .fill(Fill::Previous)
+            .try_build();
+        assert!(matches!(fill_without_interval, Err(BuildError::ConflictingClauses(_))));
+
+        let empty_interval = QueryBuilder::from("indoor_environment")
+            .mean("temperature")
+            .group_by_time(Duration::Minutes(0))
+            .try_build();
+        assert_eq!(empty_interval, Err(BuildError::InvalidDuration("0m".into())));
+
+        let non_finite_fill = QueryBuilder::from("indoor_environment")
+            .mean("temperature")
+            .group_by_time(Duration::Minutes(5))
+            .fill(Fill::Value(f64::NAN))
+            .try_build();
+        assert_eq!(non_finite_fill, Err(BuildError::InvalidValue("NaN".into())));
+    }
+
     #[test]
     fn simple_query_with_stop_and_groups() {
         let expected = Query::new(
-            "SELECT temperature, humidity \
-            FROM indoor_environment \
+            "SELECT \"temperature\", \"humidity\" \
+            FROM \"indoor_environment\" \
             WHERE time < '2021-03-07T22:00:00Z' \
-            GROUP BY room",
+            GROUP BY \"room\"",
         );
 
         let actual = QueryBuilder::from("indoor_environment")
             .field("temperature")
             .field("humidity")
-            .stop(Utc.ymd(2021, 3, 7).and_hms(22, 0, 0))
+            .stop(Utc.with_ymd_and_hms(2021, 3, 7, 22, 0, 0).unwrap())
             .group_by("room")
             .build();
 
         assert_eq!(actual, expected);
     }
+
+    #[test]
+    fn query_with_unusual_identifiers() {
+        let expected = Query::new(
+            r#"SELECT mean("select"), mean("my \"quoted\" field") FROM "my db"."one day"."indoor environment" GROUP BY time(5m), "room name""#,
+        );
+
+        let actual = QueryBuilder::from("indoor environment")
+            .database("my db")
+            .retention_policy("one day")
+            .mean("select")
+            .mean(r#"my "quoted" field"#)
+            .group_by_time(Duration::Minutes(5))
+            .group_by("room name")
+            .try_build();
+
+        assert_eq!(actual, Ok(expected));
+    }
 }
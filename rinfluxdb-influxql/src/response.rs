@@ -9,7 +9,7 @@
 use std::collections::HashMap;
 use std::convert::{TryFrom, TryInto};
 
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, TimeZone, Utc};
 
 use serde::Deserialize;
 
@@ -18,9 +18,9 @@ use serde_json::Value as JsonValue;
 
 use thiserror::Error;
 
-use rinfluxdb_types::Value;
+use rinfluxdb_types::{Columns, FromQueryResult, Precision, TimeSeries, Value};
 
-use super::{ResponseResult, StatementResult};
+use super::{ResponseResult, SeriesKey, SeriesOutcome, StatementOutcome, StatementResult};
 
 type Tags = HashMap<String, String>;
 
@@ -52,6 +52,22 @@ pub enum ResponseError {
     DataFrameError(#[from] rinfluxdb_types::DataFrameError),
 }
 
+/// How the tags of series grouped by tags are passed to dataframes
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum TagMode {
+    /// Return tags alongside dataframes
+    #[default]
+    Separate,
+
+    /// Also insert each tag in its dataframe as a column of strings
+    ///
+    /// Tag columns are appended after the other columns, sorted by tag name.
+    /// A tag is not inserted if the series already has a column with the
+    /// same name.
+    /// This allows merging dataframes of different groups in a single one.
+    Columns,
+}
+
 #[derive(Debug, Deserialize, PartialEq)]
 enum Response {
     #[serde(rename = "results")]
@@ -66,14 +82,19 @@ struct IndexedOutcome {
     statement_id: u32,
     series: Option<Vec<Series>>,
     error: Option<String>,
+    #[serde(default)]
+    partial: bool,
 }
 
 #[derive(Debug, Deserialize, PartialEq)]
-struct Series {
-    name: String,
-    columns: Vec<String>,
-    values: Vec<Vec<JsonValue>>,
-    tags: Option<Tags>,
+pub(crate) struct Series {
+    pub(crate) name: Option<String>,
+    pub(crate) columns: Vec<String>,
+    #[serde(default)]
+    pub(crate) values: Vec<Vec<JsonValue>>,
+    pub(crate) tags: Option<Tags>,
+    #[serde(default)]
+    pub(crate) partial: bool,
 }
 
 impl TryFrom<Response> for Vec<IndexedOutcome> {
@@ -150,10 +171,11 @@ impl TryFrom<IndexedOutcome> for Vec<Series> {
 ///
 /// This function is agnostics on the actual return type.
 /// The only constraint is that it can be constructed from a string, a list of
-/// instants, namely the index, and a map of lists of values, namely the columns.
+/// instants, namely the index, a map of lists of values, namely the columns,
+/// and optionally the tags of the series.
 ///
 /// I.e. the return type must implement trait
-/// `TryFrom<(String, Vec<DateTime<Utc>>, HashMap<String, Vec<Value>>), Error = E>`,
+/// [`FromQueryResult<Error = E>`](rinfluxdb_types::FromQueryResult),
 /// where `E` must implement trait `Into<ResponseError>`.
 ///
 ///
@@ -161,23 +183,26 @@ impl TryFrom<IndexedOutcome> for Vec<Series> {
 ///
 /// ```
 /// # use std::collections::HashMap;
-/// # use chrono::{DateTime, Utc};
+/// # use chrono::{DateTime, TimeZone, Utc};
 /// # use rinfluxdb_influxql::{from_str, ResponseError};
-/// # use rinfluxdb_types::Value;
+/// # use rinfluxdb_types::{Columns, Value};
 ///
-/// use std::convert::{TryFrom, TryInto};
+/// use rinfluxdb_types::FromQueryResult;
 ///
 /// struct DummyDataFrame {
 ///     name: String,
 ///     index: Vec<DateTime<Utc>>,
-///     columns: HashMap<String, Vec<Value>>
+///     columns: Columns
 /// }
 ///
-/// impl TryFrom<(String, Vec<DateTime<Utc>>, HashMap<String, Vec<Value>>)> for DummyDataFrame {
+/// impl FromQueryResult for DummyDataFrame {
 ///     type Error = ResponseError;
 ///
-///     fn try_from(
-///         (name, index, columns): (String, Vec<DateTime<Utc>>, HashMap<String, Vec<Value>>),
+///     fn from_query_result(
+///         name: String,
+///         index: Vec<DateTime<Utc>>,
+///         columns: Columns,
+///         _tags: Option<&HashMap<String, String>>,
 ///     ) -> Result<Self, Self::Error> {
 ///         if columns.len() > 0 {
 ///             Ok(Self { name, index, columns })
@@ -232,63 +257,359 @@ impl TryFrom<IndexedOutcome> for Vec<Series> {
 /// }
 /// # Ok::<(), ResponseError>(())
 /// ```
+///
+/// Timestamps encoded as integers are interpreted as nanoseconds since
+/// epoch; use [`from_str_with_epoch`](from_str_with_epoch) for other
+/// precisions.
 pub fn from_str<DF, E>(input: &str) -> ResponseResult<DF>
 where
-    DF: TryFrom<(String, Vec<DateTime<Utc>>, HashMap<String, Vec<Value>>), Error = E>,
+    DF: FromQueryResult<Error = E>,
+    E: Into<ResponseError>,
+{
+    from_str_with_epoch(input, Precision::Nanoseconds)
+}
+
+/// Parse a JSON response with timestamps encoded as integers
+///
+/// InfluxDB returns timestamps as integers when a query is sent with the
+/// `epoch` parameter, in which case they must be interpreted with the
+/// same precision.
+/// Timestamps encoded as RFC3339 strings are still accepted.
+///
+/// ```
+/// # use chrono::{TimeZone, Utc};
+/// # use rinfluxdb_types::{Precision, TimeSeries};
+/// # use rinfluxdb_influxql::{from_str_with_epoch, ResponseError};
+/// let input = r#"{
+///     "results": [
+///         {
+///             "statement_id": 0,
+///             "series": [
+///                 {
+///                     "name": "environment",
+///                     "columns": ["time","temperature"],
+///                     "values":[[1614877200000,28.4]]
+///                 }
+///             ]
+///         }
+///     ]
+/// }"#;
+///
+/// let statements = from_str_with_epoch::<TimeSeries, _>(input, Precision::Milliseconds)?;
+/// let (series, _tags) = statements.into_iter().next().unwrap()?.remove(0);
+///
+/// let (instant, _value) = &series.field("temperature").unwrap()[0];
+/// assert_eq!(*instant, Utc.with_ymd_and_hms(2021, 3, 4, 17, 0, 0).unwrap());
+/// # Ok::<(), ResponseError>(())
+/// ```
+pub fn from_str_with_epoch<DF, E>(input: &str, epoch: Precision) -> ResponseResult<DF>
+where
+    DF: FromQueryResult<Error = E>,
+    E: Into<ResponseError>,
+{
+    let outcomes = outcomes_from_str_with_epoch::<DF, E>(input, epoch)?;
+    let dataframes = outcomes
+        .into_iter()
+        .map(StatementOutcome::into_statement_result)
+        .collect();
+
+    Ok(dataframes)
+}
+
+/// Parse a JSON response, optionally inserting tags in dataframes as columns
+///
+/// This is the same as [`from_str`](from_str), but with
+/// [`TagMode::Columns`](TagMode::Columns) the tags of each series are also
+/// inserted in its dataframe as constant string columns.
+///
+/// ```
+/// # use rinfluxdb_types::{Record, Value};
+/// # use rinfluxdb_influxql::{from_str_with_tag_mode, ResponseError, TagMode};
+/// let input = r#"{
+///     "results": [
+///         {
+///             "statement_id": 0,
+///             "series": [
+///                 {
+///                     "name": "environment",
+///                     "columns": ["time","temperature"],
+///                     "values":[["2021-03-04T17:00:00Z",28.4]],
+///                     "tags": {
+///                         "room": "bedroom"
+///                     }
+///                 }
+///             ]
+///         }
+///     ]
+/// }"#;
+///
+/// let statements = from_str_with_tag_mode::<Vec<Record>, _>(input, TagMode::Columns)?;
+/// let (records, _tags) = statements.into_iter().next().unwrap()?.remove(0);
+///
+/// assert_eq!(records[0].values.get("room"), Some(&Value::String("bedroom".into())));
+/// # Ok::<(), ResponseError>(())
+/// ```
+pub fn from_str_with_tag_mode<DF, E>(input: &str, tag_mode: TagMode) -> ResponseResult<DF>
+where
+    DF: FromQueryResult<Error = E>,
+    E: Into<ResponseError>,
+{
+    let outcomes = parse_outcomes::<DF, E>(input, Precision::Nanoseconds, tag_mode)?;
+    let dataframes = outcomes
+        .into_iter()
+        .map(StatementOutcome::into_statement_result)
+        .collect();
+
+    Ok(dataframes)
+}
+
+/// Parse a JSON response to a list of statement outcomes
+///
+/// This is a richer alternative to [`from_str`](from_str), which also
+/// returns the metadata of statements and series: the statement id, whether
+/// a statement or a series is partial, i.e. continued in the next chunk of
+/// a chunked response, and the original order of the columns.
+///
+/// ```
+/// # use rinfluxdb_types::TimeSeries;
+/// # use rinfluxdb_influxql::{outcomes_from_str, ResponseError};
+/// let input = r#"{
+///     "results": [
+///         {
+///             "statement_id": 0,
+///             "series": [
+///                 {
+///                     "name": "environment",
+///                     "columns": ["time","temperature","humidity"],
+///                     "values":[["2021-03-04T17:00:00Z",28.4,41.0]],
+///                     "partial": true
+///                 }
+///             ],
+///             "partial": true
+///         }
+///     ]
+/// }"#;
+///
+/// let outcomes = outcomes_from_str::<TimeSeries, _>(input)?;
+/// let outcome = &outcomes[0];
+/// assert_eq!(outcome.statement_id, 0);
+/// assert!(outcome.partial);
+///
+/// let series = &outcome.series.as_ref().unwrap()[0];
+/// assert_eq!(series.columns, vec!["temperature", "humidity"]);
+/// assert!(series.partial);
+/// # Ok::<(), ResponseError>(())
+/// ```
+pub fn outcomes_from_str<DF, E>(input: &str) -> Result<Vec<StatementOutcome<DF>>, ResponseError>
+where
+    DF: FromQueryResult<Error = E>,
+    E: Into<ResponseError>,
+{
+    outcomes_from_str_with_epoch(input, Precision::Nanoseconds)
+}
+
+/// Parse a JSON response with timestamps encoded as integers to a list of
+/// statement outcomes
+///
+/// See [`outcomes_from_str`](outcomes_from_str) and
+/// [`from_str_with_epoch`](from_str_with_epoch).
+pub fn outcomes_from_str_with_epoch<DF, E>(
+    input: &str,
+    epoch: Precision,
+) -> Result<Vec<StatementOutcome<DF>>, ResponseError>
+where
+    DF: FromQueryResult<Error = E>,
+    E: Into<ResponseError>,
+{
+    parse_outcomes(input, epoch, TagMode::Separate)
+}
+
+fn parse_outcomes<DF, E>(
+    input: &str,
+    epoch: Precision,
+    tag_mode: TagMode,
+) -> Result<Vec<StatementOutcome<DF>>, ResponseError>
+where
+    DF: FromQueryResult<Error = E>,
     E: Into<ResponseError>,
 {
     let response: Response = json_from_str(input)?;
-    let results: Vec<IndexedOutcome> = response.try_into()?;
+    let mut results: Vec<IndexedOutcome> = response.try_into()?;
+    results.sort_by_key(|outcome| outcome.statement_id);
 
-    let dataframes = results
+    let outcomes = results
         .into_iter()
-        // .sorted_by(|IndexedOutcome{statement_id, ..}| statement_id)
         .map(|outcome| {
+            let statement_id = outcome.statement_id;
+            let partial = outcome.partial;
             let serieses: Result<Vec<Series>, ResponseError> = outcome.try_into();
-            serieses.and_then(|serieses| {
-                let dataframes = parse_serieses::<DF, E>(serieses)?;
-                Ok(dataframes)
-            })
+            let series = serieses.and_then(|serieses| parse_serieses::<DF, E>(serieses, epoch, tag_mode));
+            StatementOutcome {
+                statement_id,
+                partial,
+                series,
+            }
         })
         .collect();
 
-    Ok(dataframes)
+    Ok(outcomes)
+}
+
+/// Parse a JSON response returned from InfluxDB to a map of time series
+///
+/// This is a simpler alternative to [`from_str`](from_str) for users that do
+/// not need dataframes.
+/// Series from all statements are collected in a single map, keyed by their
+/// measurement name and tags.
+/// Series with the same key are merged together.
+///
+/// ```
+/// # use rinfluxdb_influxql::{timeseries_from_str, ResponseError};
+/// # use rinfluxdb_types::Value;
+/// let input = r#"{
+///     "results": [
+///         {
+///             "statement_id": 0,
+///             "series": [
+///                 {
+///                     "name": "environment",
+///                     "columns": ["time","temperature"],
+///                     "values":[
+///                         ["2021-03-04T17:00:00Z",28.4],
+///                         ["2021-03-04T18:00:00Z",29.2]
+///                     ],
+///                     "tags": {
+///                         "room": "bedroom"
+///                     }
+///                 }
+///             ]
+///         }
+///     ]
+/// }"#;
+///
+/// let timeseries = timeseries_from_str(input)?;
+/// assert_eq!(timeseries.len(), 1);
+///
+/// let ((measurement, tags), series) = timeseries.into_iter().next().unwrap();
+/// assert_eq!(measurement, "environment");
+/// assert_eq!(tags.get("room"), Some(&"bedroom".to_string()));
+/// assert_eq!(series.field("temperature").map(|values| values.len()), Some(2));
+/// # Ok::<(), ResponseError>(())
+/// ```
+pub fn timeseries_from_str(input: &str) -> Result<HashMap<SeriesKey, TimeSeries>, ResponseError> {
+    let statements: Vec<StatementResult<NamedTimeSeries>> = from_str(input)?;
+    collect_timeseries(statements)
+}
+
+/// The raw content of a series, before conversion to a dataframe
+#[cfg(any(feature = "client-blocking", feature = "client-async"))]
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct RawDataFrame(
+    pub(crate) String,
+    pub(crate) Vec<DateTime<Utc>>,
+    pub(crate) Columns,
+);
+
+#[cfg(any(feature = "client-blocking", feature = "client-async"))]
+impl FromQueryResult for RawDataFrame {
+    type Error = ResponseError;
+
+    fn from_query_result(
+        name: String,
+        index: Vec<DateTime<Utc>>,
+        columns: Columns,
+        _tags: Option<&Tags>,
+    ) -> Result<Self, Self::Error> {
+        Ok(Self(name, index, columns))
+    }
+}
+
+/// A time series that also keeps the name of its measurement
+pub(crate) struct NamedTimeSeries(String, TimeSeries);
+
+impl FromQueryResult for NamedTimeSeries {
+    type Error = ResponseError;
+
+    fn from_query_result(
+        name: String,
+        index: Vec<DateTime<Utc>>,
+        columns: Columns,
+        tags: Option<&Tags>,
+    ) -> Result<Self, Self::Error> {
+        let series = TimeSeries::from_query_result(name.clone(), index, columns, tags)?;
+        Ok(Self(name, series))
+    }
+}
+
+pub(crate) fn collect_timeseries(
+    statements: Vec<StatementResult<NamedTimeSeries>>,
+) -> Result<HashMap<SeriesKey, TimeSeries>, ResponseError> {
+    let mut map: HashMap<SeriesKey, TimeSeries> = HashMap::new();
+
+    for statement in statements {
+        for (NamedTimeSeries(name, series), tags) in statement? {
+            let tags = tags.unwrap_or_default().into_iter().collect();
+            map.entry((name, tags)).or_default().extend(series);
+        }
+    }
+
+    Ok(map)
+}
+
+/// Parse a JSON response returned from InfluxDB to a list of raw series
+///
+/// This is used for responses that do not contain time series, such as the
+/// ones to `SHOW` statements.
+pub(crate) fn series_from_str(input: &str) -> Result<Vec<Result<Vec<Series>, ResponseError>>, ResponseError> {
+    let response: Response = json_from_str(input)?;
+    let mut results: Vec<IndexedOutcome> = response.try_into()?;
+    results.sort_by_key(|outcome| outcome.statement_id);
+    let serieses = results
+        .into_iter()
+        .map(|outcome| outcome.try_into())
+        .collect();
+    Ok(serieses)
 }
 
-fn parse_serieses<DF, E>(serieses: Vec<Series>) -> StatementResult<DF>
+fn parse_serieses<DF, E>(
+    serieses: Vec<Series>,
+    epoch: Precision,
+    tag_mode: TagMode,
+) -> Result<Vec<SeriesOutcome<DF>>, ResponseError>
 where
-    DF: TryFrom<(String, Vec<DateTime<Utc>>, HashMap<String, Vec<Value>>), Error = E>,
+    DF: FromQueryResult<Error = E>,
     E: Into<ResponseError>,
 {
     serieses
         .into_iter()
-        .map(parse_series::<DF, E>)
+        .map(|series| parse_series::<DF, E>(series, epoch, tag_mode))
         .collect()
 }
 
-fn parse_series<DF, E>(series: Series) -> Result<(DF, Option<Tags>), ResponseError>
+fn parse_series<DF, E>(
+    series: Series,
+    epoch: Precision,
+    tag_mode: TagMode,
+) -> Result<SeriesOutcome<DF>, ResponseError>
 where
-    DF: TryFrom<(String, Vec<DateTime<Utc>>, HashMap<String, Vec<Value>>), Error = E>,
+    DF: FromQueryResult<Error = E>,
     E: Into<ResponseError>,
 {
-    let name: String = series.name;
+    let name: String = series.name.unwrap_or_default();
     let mut index: Vec<DateTime<Utc>> = vec![];
-    let mut data: HashMap<String, Vec<Value>> = HashMap::new();
+    let mut data: Columns = Columns::new();
 
     for column_name in series.columns.iter().skip(1) {
         data.insert(column_name.clone(), vec![]);
     }
 
     for row in series.values {
-        let instant = row[0]
-            .as_str()
-            .ok_or_else(|| ResponseError::ValueError("index is not encoded as string".into()))?;
-        let instant = instant.parse::<DateTime<Utc>>()?;
+        let instant = parse_instant(&row[0], epoch)?;
         index.push(instant);
 
         for (column_name, value) in series.columns.iter().skip(1).zip(&row[1..]) {
             let value = match value {
-                JsonValue::Null => Err(ResponseError::ValueError("value is null".into())),
+                JsonValue::Null => Ok(Value::Null),
                 JsonValue::Bool(boolean) => Ok(Value::Boolean(*boolean)),
                 JsonValue::Number(ref number) if number.is_i64() => Ok(Value::Integer(number.as_i64().unwrap())),
                 JsonValue::Number(ref number) if number.is_u64() => Ok(Value::UnsignedInteger(number.as_u64().unwrap())),
@@ -302,10 +623,39 @@ where
         }
     }
 
-    let dataframe = DF::try_from((name, index, data))
+    if let (TagMode::Columns, Some(tags)) = (tag_mode, &series.tags) {
+        let mut tags: Vec<(&String, &String)> = tags.iter().collect();
+        tags.sort();
+        for (tag, value) in tags {
+            data.entry(tag.clone())
+                .or_insert_with(|| vec![Value::String(value.clone()); index.len()]);
+        }
+    }
+
+    let dataframe = DF::from_query_result(name, index, data, series.tags.as_ref())
         .map_err(|e| e.into())?;
 
-    Ok((dataframe, series.tags))
+    let columns = series.columns.into_iter().skip(1).collect();
+
+    Ok(SeriesOutcome {
+        dataframe,
+        tags: series.tags,
+        columns,
+        partial: series.partial,
+    })
+}
+
+/// Parse an instant encoded either as RFC3339 string or as integer
+fn parse_instant(value: &JsonValue, epoch: Precision) -> Result<DateTime<Utc>, ResponseError> {
+    match value {
+        JsonValue::String(instant) => Ok(instant.parse::<DateTime<Utc>>()?),
+        JsonValue::Number(number) => number
+            .as_i64()
+            .and_then(|instant| instant.checked_mul(epoch.nanoseconds()))
+            .map(|nanoseconds| Utc.timestamp_nanos(nanoseconds))
+            .ok_or_else(|| ResponseError::ValueError(format!("index {} is out of range", number))),
+        _ => Err(ResponseError::ValueError("index is not encoded as string or integer".into())),
+    }
 }
 
 #[cfg(test)]
@@ -318,20 +668,25 @@ mod tests {
 
     use chrono::TimeZone;
 
+    use rinfluxdb_types::Record;
+
     type TaggedDataFrames = Vec<(DummyDataFrame, Option<Tags>)>;
 
     #[derive(Debug, PartialEq)]
     struct DummyDataFrame {
         name: String,
         index: Vec<DateTime<Utc>>,
-        columns: HashMap<String, Vec<Value>>,
+        columns: Columns,
     }
 
-    impl TryFrom<(String, Vec<DateTime<Utc>>, HashMap<String, Vec<Value>>)> for DummyDataFrame {
+    impl FromQueryResult for DummyDataFrame {
         type Error = ResponseError;
 
-        fn try_from(
-            (name, index, columns): (String, Vec<DateTime<Utc>>, HashMap<String, Vec<Value>>),
+        fn from_query_result(
+            name: String,
+            index: Vec<DateTime<Utc>>,
+            columns: Columns,
+            _tags: Option<&Tags>,
         ) -> Result<Self, Self::Error> {
             Ok(Self { name, index, columns })
         }
@@ -366,6 +721,7 @@ mod tests {
                     statement_id: 12,
                     series: None,
                     error: Some("database not found: mydb1".into()),
+                    partial: false,
                 }
             ]
         );
@@ -406,22 +762,25 @@ mod tests {
                     series: Some(
                         vec![
                             Series {
-                                name: "mymeas".into(),
+                                name: Some("mymeas".into()),
                                 columns: vec!["time".into(), "myfield".into(), "mytag1".into(), "mytag2".into()],
                                 values: vec![
                                     vec![JsonValue::String("2017-03-01T00:16:18Z".into()), JsonValue::Number(JsonNumber::from_f64(33.1).unwrap()), JsonValue::Null, JsonValue::Null],
                                     vec![JsonValue::String("2017-03-01T00:17:18Z".into()), JsonValue::Number(JsonNumber::from_f64(12.4).unwrap()), JsonValue::String("12".into()), JsonValue::String("14".into())],
                                 ],
                                 tags: None,
+                                partial: false,
                             }
                         ]
                     ),
                     error: None,
+                    partial: false,
                 },
                 IndexedOutcome {
                     statement_id: 12,
                     series: None,
                     error: Some("Other error".into()),
+                    partial: false,
                 }
             ]
         );
@@ -457,17 +816,19 @@ mod tests {
                     series: Some(
                         vec![
                             Series {
-                                name: "mymeas".into(),
+                                name: Some("mymeas".into()),
                                 columns: vec!["time".into(), "myfield".into(), "mytag1".into(), "mytag2".into()],
                                 values: vec![
                                     vec![JsonValue::String("2017-03-01T00:16:18Z".into()), JsonValue::Number(JsonNumber::from_f64(33.1).unwrap()), JsonValue::Null, JsonValue::Null],
                                     vec![JsonValue::String("2017-03-01T00:17:18Z".into()), JsonValue::Number(JsonNumber::from_f64(12.4).unwrap()), JsonValue::String("12".into()), JsonValue::String("14".into())],
                                 ],
                                 tags: None,
+                                partial: false,
                             }
                         ]
                     ),
                     error: None,
+                    partial: false,
                 }
             ]
         );
@@ -498,8 +859,8 @@ mod tests {
         }"#;
         let mut expected = DummyDataFrame {
             name: "mymeas".into(),
-            index: vec![Utc.ymd(2017, 3, 1).and_hms(0, 16, 18), Utc.ymd(2017, 3, 1).and_hms(0, 17, 18)],
-            columns: HashMap::new(),
+            index: vec![Utc.with_ymd_and_hms(2017, 3, 1, 0, 16, 18).unwrap(), Utc.with_ymd_and_hms(2017, 3, 1, 0, 17, 18).unwrap()],
+            columns: Columns::new(),
         };
         expected.columns.insert("myfield1".into(), vec![Value::Float(33.1), Value::Float(12.4)]);
         expected.columns.insert("myfield2".into(), vec![Value::Float(12.5), Value::Float(12.7)]);
@@ -516,6 +877,62 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn parse_ok_to_dataframe_with_nulls() -> Result<(), ResponseError> {
+        let input = r#"{
+            "results": [
+                {
+                    "statement_id": 0,
+                    "series": [
+                        {
+                            "name":"mymeas",
+                            "columns": ["time","myfield1","myfield2"],
+                            "values": [
+                                ["2017-03-01T00:16:18Z",33.1,null],["2017-03-01T00:17:18Z",null,12.7]
+                            ]
+                        }
+                    ]
+                }
+            ]
+        }"#;
+        let mut expected = DummyDataFrame {
+            name: "mymeas".into(),
+            index: vec![Utc.with_ymd_and_hms(2017, 3, 1, 0, 16, 18).unwrap(), Utc.with_ymd_and_hms(2017, 3, 1, 0, 17, 18).unwrap()],
+            columns: Columns::new(),
+        };
+        expected.columns.insert("myfield1".into(), vec![Value::Float(33.1), Value::Null]);
+        expected.columns.insert("myfield2".into(), vec![Value::Null, Value::Float(12.7)]);
+
+        let actual_response: Vec<Result<TaggedDataFrames, ResponseError>> = from_str(input)?;
+        let actual_dataframes: TaggedDataFrames = actual_response.into_iter().next().ok_or_else(|| ResponseError::ValueError("empty list".into()))??;
+
+        let (actual_dataframe, _): (DummyDataFrame, Option<Tags>) = actual_dataframes.into_iter().next().ok_or_else(|| ResponseError::ValueError("empty list".into()))?;
+
+        assert_eq!(actual_dataframe, expected);
+
+        Ok(())
+    }
+
+    #[test]
+    fn parse_epoch_instants() {
+        let instant = |value: JsonValue, epoch| parse_instant(&value, epoch).ok();
+
+        assert_eq!(
+            instant(JsonValue::from(1614877200), Precision::Seconds),
+            Some(Utc.with_ymd_and_hms(2021, 3, 4, 17, 0, 0).unwrap()),
+        );
+        assert_eq!(
+            instant(JsonValue::from(1614877200000000000_i64), Precision::Nanoseconds),
+            Some(Utc.with_ymd_and_hms(2021, 3, 4, 17, 0, 0).unwrap()),
+        );
+        assert_eq!(
+            instant(JsonValue::from("2021-03-04T17:00:00Z"), Precision::Milliseconds),
+            Some(Utc.with_ymd_and_hms(2021, 3, 4, 17, 0, 0).unwrap()),
+        );
+        assert_eq!(instant(JsonValue::from(1614877200000_i64), Precision::Hours), None);
+        assert_eq!(instant(JsonValue::from(true), Precision::Seconds), None);
+    }
+
     #[test]
     fn parse_ok_to_empty_dataframe() -> Result<(), ResponseError> {
         let input = r#"{
@@ -557,8 +974,8 @@ mod tests {
         }"#;
         let mut expected_dataframe = DummyDataFrame {
             name: "mymeas".into(),
-            index: vec![Utc.ymd(2017, 3, 1).and_hms(0, 16, 18), Utc.ymd(2017, 3, 1).and_hms(0, 17, 18)],
-            columns: HashMap::new(),
+            index: vec![Utc.with_ymd_and_hms(2017, 3, 1, 0, 16, 18).unwrap(), Utc.with_ymd_and_hms(2017, 3, 1, 0, 17, 18).unwrap()],
+            columns: Columns::new(),
         };
         expected_dataframe.columns.insert("myfield1".into(), vec![Value::Float(33.1), Value::Float(12.4)]);
         expected_dataframe.columns.insert("myfield2".into(), vec![Value::Float(12.5), Value::Float(12.7)]);
@@ -578,4 +995,174 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn parse_statements_in_order() -> Result<(), ResponseError> {
+        let input = r#"{
+            "results": [
+                {
+                    "statement_id": 1,
+                    "error": "Second statement failed"
+                },
+                {
+                    "statement_id": 0,
+                    "series": [
+                        {
+                            "name": "mymeas",
+                            "columns": ["time", "myfield"],
+                            "values": [["2017-03-01T00:16:18Z", 33.1]]
+                        }
+                    ]
+                }
+            ]
+        }"#;
+
+        let actual: Vec<Result<TaggedDataFrames, ResponseError>> = from_str(input)?;
+
+        assert_eq!(actual.len(), 2);
+        assert!(matches!(&actual[0], Ok(dataframes) if dataframes.len() == 1));
+        assert!(matches!(&actual[1], Err(ResponseError::StatementError(_))));
+
+        Ok(())
+    }
+
+    #[test]
+    fn parse_outcomes_with_metadata() -> Result<(), ResponseError> {
+        let input = r#"{
+            "results": [
+                {
+                    "statement_id": 1,
+                    "error": "Second statement failed"
+                },
+                {
+                    "statement_id": 0,
+                    "series": [
+                        {
+                            "name": "mymeas",
+                            "columns": ["time", "zfield", "afield"],
+                            "values": [["2017-03-01T00:16:18Z", 33.1, 12.5]],
+                            "tags": {
+                                "room": "bedroom"
+                            },
+                            "partial": true
+                        }
+                    ],
+                    "partial": true
+                }
+            ]
+        }"#;
+
+        let actual: Vec<StatementOutcome<DummyDataFrame>> = outcomes_from_str(input)?;
+
+        assert_eq!(actual.len(), 2);
+
+        assert_eq!(actual[0].statement_id, 0);
+        assert!(actual[0].partial);
+        let series = actual[0].series.as_ref().map_err(|_| ResponseError::ValueError("statement failed".into()))?;
+        assert_eq!(series.len(), 1);
+        assert_eq!(series[0].columns, vec!["zfield".to_string(), "afield".to_string()]);
+        assert!(series[0].partial);
+        assert_eq!(series[0].tags.as_ref().and_then(|tags| tags.get("room")), Some(&"bedroom".to_string()));
+
+        assert_eq!(actual[1].statement_id, 1);
+        assert!(!actual[1].partial);
+        assert!(matches!(&actual[1].series, Err(ResponseError::StatementError(_))));
+
+        Ok(())
+    }
+
+    #[test]
+    fn parse_preserves_column_order() -> Result<(), ResponseError> {
+        let input = r#"{
+            "results": [
+                {
+                    "statement_id": 0,
+                    "series": [
+                        {
+                            "name": "mymeas",
+                            "columns": ["time", "zfield", "afield", "mfield"],
+                            "values": [["2017-03-01T00:16:18Z", 33.1, 12.5, 8.0]]
+                        }
+                    ]
+                }
+            ]
+        }"#;
+
+        let actual: Vec<Result<TaggedDataFrames, ResponseError>> = from_str(input)?;
+        let actual_dataframes: TaggedDataFrames = actual.into_iter().next().ok_or_else(|| ResponseError::ValueError("empty list".into()))??;
+        let (actual_dataframe, _) = actual_dataframes.into_iter().next().ok_or_else(|| ResponseError::ValueError("empty list".into()))?;
+
+        let names: Vec<&str> = actual_dataframe.columns.keys().map(String::as_str).collect();
+        assert_eq!(names, vec!["zfield", "afield", "mfield"]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn parse_passes_tags_to_dataframe() -> Result<(), ResponseError> {
+        let input = r#"{
+            "results": [
+                {
+                    "statement_id": 0,
+                    "series": [
+                        {
+                            "name": "mymeas",
+                            "columns": ["time", "myfield"],
+                            "values": [["2017-03-01T00:16:18Z", 33.1]],
+                            "tags": {
+                                "room": "bedroom"
+                            }
+                        }
+                    ]
+                }
+            ]
+        }"#;
+
+        let actual: Vec<StatementResult<Vec<Record>>> = from_str(input)?;
+        let (records, _) = actual.into_iter().next().ok_or_else(|| ResponseError::ValueError("empty list".into()))??.remove(0);
+
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].tags.get("room"), Some(&"bedroom".to_string()));
+
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(any(feature = "client-blocking", feature = "client-async"))]
+    fn parse_tags_as_columns() -> Result<(), ResponseError> {
+        let input = r#"{
+            "results": [
+                {
+                    "statement_id": 0,
+                    "series": [
+                        {
+                            "name": "mymeas",
+                            "columns": ["time", "myfield", "room"],
+                            "values": [
+                                ["2017-03-01T00:16:18Z", 33.1, "kitchen"],
+                                ["2017-03-01T00:17:18Z", 34.1, "kitchen"]
+                            ],
+                            "tags": {
+                                "room": "bedroom",
+                                "building": "b1"
+                            }
+                        }
+                    ]
+                }
+            ]
+        }"#;
+
+        let actual: Vec<StatementResult<RawDataFrame>> = from_str_with_tag_mode(input, TagMode::Columns)?;
+        let (RawDataFrame(_, _, columns), tags) = actual.into_iter().next().ok_or_else(|| ResponseError::ValueError("empty list".into()))??.remove(0);
+
+        let names: Vec<&str> = columns.keys().map(String::as_str).collect();
+        assert_eq!(names, vec!["myfield", "room", "building"]);
+        assert_eq!(
+            columns.get("building"),
+            Some(&vec![Value::String("b1".into()), Value::String("b1".into())]),
+        );
+        assert_eq!(columns.get("room").map(|column| &column[0]), Some(&Value::String("kitchen".into())));
+        assert_eq!(tags.and_then(|tags| tags.get("room").cloned()), Some("bedroom".to_string()));
+
+        Ok(())
+    }
 }
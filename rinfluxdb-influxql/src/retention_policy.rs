@@ -0,0 +1,277 @@
+// Copyright Claudio Mattera 2021.
+// Distributed under the MIT License or Apache 2.0 License at your option.
+// See accompanying files License-MIT.txt and License-Apache-2.0, or online at
+// https://opensource.org/licenses/MIT
+// https://opensource.org/licenses/Apache-2.0
+
+//! Typed representation of InfluxDB retention policies
+
+use std::collections::HashMap;
+use std::convert::TryFrom;
+
+use serde_json::Value as JsonValue;
+
+use rinfluxdb_types::Duration;
+
+use super::literal::quote_identifier;
+use super::response::{series_from_str, ResponseError, Series};
+use super::Query;
+
+/// A retention policy
+///
+/// The same type is returned when parsing the result of a
+/// `SHOW RETENTION POLICIES` statement, and used to create `CREATE RETENTION
+/// POLICY` and `ALTER RETENTION POLICY` statements.
+///
+/// ```
+/// # use rinfluxdb_types::Duration;
+/// # use rinfluxdb_influxql::RetentionPolicy;
+/// let policy = RetentionPolicy {
+///     name: "one_week".into(),
+///     duration: Duration::Days(7),
+///     shard_duration: Some(Duration::Days(1)),
+///     replication: 1,
+///     default: true,
+/// };
+///
+/// assert_eq!(
+///     policy.create("house").as_ref(),
+///     "CREATE RETENTION POLICY \"one_week\" ON \"house\" DURATION 7d REPLICATION 1 SHARD DURATION 1d DEFAULT",
+/// );
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RetentionPolicy {
+    /// The name of the retention policy
+    pub name: String,
+
+    /// How long data is kept, possibly infinite
+    pub duration: Duration,
+
+    /// The time range covered by each shard group, or `None` for the
+    /// server's default
+    pub shard_duration: Option<Duration>,
+
+    /// How many copies of the data are stored in the cluster
+    pub replication: u32,
+
+    /// Whether this is the default retention policy of the database
+    pub default: bool,
+}
+
+impl RetentionPolicy {
+    /// Create a query to create this retention policy on a database
+    pub fn create(&self, database: &str) -> Query {
+        Query::new(self.statement("CREATE", database))
+    }
+
+    /// Create a query to alter an existing retention policy on a database
+    ///
+    /// All attributes are set to the values in this retention policy.
+    pub fn alter(&self, database: &str) -> Query {
+        Query::new(self.statement("ALTER", database))
+    }
+
+    fn statement(&self, verb: &str, database: &str) -> String {
+        let mut statement = format!(
+            "{} RETENTION POLICY {} ON {} DURATION {} REPLICATION {}",
+            verb,
+            quote_identifier(&self.name),
+            quote_identifier(database),
+            self.duration,
+            self.replication,
+        );
+        if let Some(shard_duration) = self.shard_duration {
+            statement.push_str(&format!(" SHARD DURATION {}", shard_duration));
+        }
+        if self.default {
+            statement.push_str(" DEFAULT");
+        }
+        statement
+    }
+}
+
+/// Parse the JSON response to a `SHOW RETENTION POLICIES` statement
+///
+/// Durations are normalized to the largest unit that represents them
+/// exactly, up to hours, since that is how InfluxDB reports them.
+/// A duration of zero is parsed as an infinite duration.
+///
+/// ```
+/// # use rinfluxdb_types::Duration;
+/// # use rinfluxdb_influxql::{retention_policies_from_str, ResponseError};
+/// let input = r#"{
+///     "results": [
+///         {
+///             "statement_id": 0,
+///             "series": [
+///                 {
+///                     "columns": ["name","duration","shardGroupDuration","replicaN","default"],
+///                     "values": [
+///                         ["autogen","0s","168h0m0s",1,true],
+///                         ["one_day","24h0m0s","1h0m0s",1,false]
+///                     ]
+///                 }
+///             ]
+///         }
+///     ]
+/// }"#;
+///
+/// let policies = retention_policies_from_str(input)?;
+///
+/// assert_eq!(policies.len(), 2);
+/// assert_eq!(policies[0].name, "autogen");
+/// assert_eq!(policies[0].duration, Duration::Infinity);
+/// assert_eq!(policies[0].shard_duration, Some(Duration::Hours(168)));
+/// assert!(policies[0].default);
+/// assert_eq!(policies[1].duration, Duration::Hours(24));
+/// # Ok::<(), ResponseError>(())
+/// ```
+pub fn retention_policies_from_str(input: &str) -> Result<Vec<RetentionPolicy>, ResponseError> {
+    let mut policies = Vec::new();
+    for statement in series_from_str(input)? {
+        for series in statement? {
+            policies.extend(parse_series(series)?);
+        }
+    }
+    Ok(policies)
+}
+
+fn parse_series(series: Series) -> Result<Vec<RetentionPolicy>, ResponseError> {
+    let positions: HashMap<&str, usize> = series
+        .columns
+        .iter()
+        .enumerate()
+        .map(|(i, column)| (column.as_str(), i))
+        .collect();
+
+    let position = |name: &str| {
+        positions
+            .get(name)
+            .copied()
+            .ok_or_else(|| ResponseError::ValueError(format!("missing column {}", name)))
+    };
+
+    let name = position("name")?;
+    let duration = position("duration")?;
+    let shard_duration = position("shardGroupDuration")?;
+    let replication = position("replicaN")?;
+    let default = position("default")?;
+
+    series
+        .values
+        .iter()
+        .map(|row| {
+            let cell = |i: usize| {
+                row.get(i)
+                    .ok_or_else(|| ResponseError::ValueError("row is too short".into()))
+            };
+
+            let name = cell(name)?
+                .as_str()
+                .ok_or_else(|| ResponseError::ValueError("name is not a string".into()))?
+                .to_string();
+            let duration = match parse_duration(cell(duration)?)? {
+                Duration::Seconds(0) => Duration::Infinity,
+                duration => duration,
+            };
+            let shard_duration = match parse_duration(cell(shard_duration)?)? {
+                Duration::Seconds(0) => None,
+                duration => Some(duration),
+            };
+            let replication = cell(replication)?
+                .as_u64()
+                .and_then(|replication| u32::try_from(replication).ok())
+                .ok_or_else(|| {
+                    ResponseError::ValueError("replication is not a valid integer".into())
+                })?;
+            let default = cell(default)?
+                .as_bool()
+                .ok_or_else(|| ResponseError::ValueError("default is not a boolean".into()))?;
+
+            Ok(RetentionPolicy {
+                name,
+                duration,
+                shard_duration,
+                replication,
+                default,
+            })
+        })
+        .collect()
+}
+
+/// Parse a duration in the format used by InfluxDB, such as `168h0m0s`
+fn parse_duration(value: &JsonValue) -> Result<Duration, ResponseError> {
+    let text = value
+        .as_str()
+        .ok_or_else(|| ResponseError::ValueError("duration is not a string".into()))?;
+    text.parse()
+        .map_err(|_| ResponseError::ValueError(format!("invalid duration {}", text)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn alter_without_shard_duration() {
+        let policy = RetentionPolicy {
+            name: "forever".into(),
+            duration: Duration::Infinity,
+            shard_duration: None,
+            replication: 2,
+            default: false,
+        };
+
+        assert_eq!(
+            policy.alter("house").as_ref(),
+            "ALTER RETENTION POLICY \"forever\" ON \"house\" DURATION inf REPLICATION 2",
+        );
+    }
+
+    #[test]
+    fn durations() {
+        let parse = |text: &str| parse_duration(&JsonValue::String(text.into())).ok();
+
+        assert_eq!(parse("168h0m0s"), Some(Duration::Hours(168)));
+        assert_eq!(parse("0h30m0s"), Some(Duration::Minutes(30)));
+        assert_eq!(parse("1m30s"), Some(Duration::Seconds(90)));
+        assert_eq!(parse("0s"), Some(Duration::Seconds(0)));
+        assert_eq!(parse("12"), None);
+        assert_eq!(parse("1d"), Some(Duration::Hours(24)));
+        assert_eq!(parse("1x"), None);
+        assert_eq!(parse(""), None);
+    }
+
+    #[test]
+    fn round_trip() -> Result<(), ResponseError> {
+        let input = r#"{
+            "results": [
+                {
+                    "statement_id": 0,
+                    "series": [
+                        {
+                            "columns": ["name","duration","shardGroupDuration","replicaN","default"],
+                            "values": [["one_day","24h0m0s","1h0m0s",1,true]]
+                        }
+                    ]
+                }
+            ]
+        }"#;
+
+        let policies = retention_policies_from_str(input)?;
+        let expected = RetentionPolicy {
+            name: "one_day".into(),
+            duration: Duration::Hours(24),
+            shard_duration: Some(Duration::Hours(1)),
+            replication: 1,
+            default: true,
+        };
+
+        assert_eq!(policies, vec![expected]);
+        assert_eq!(
+            policies[0].create("house").as_ref(),
+            "CREATE RETENTION POLICY \"one_day\" ON \"house\" DURATION 24h REPLICATION 1 SHARD DURATION 1h DEFAULT",
+        );
+        Ok(())
+    }
+}
@@ -4,13 +4,18 @@
 // https://opensource.org/licenses/MIT
 // https://opensource.org/licenses/Apache-2.0
 
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 
 use super::ResponseError;
 
 /// A set of tags and tag values
 pub type TagsMap = HashMap<String, String>;
 
+/// A measurement name accompanied by a set of tags and tag values
+///
+/// Tags are stored in a sorted map, so that keys can be hashed and compared.
+pub type SeriesKey = (String, BTreeMap<String, String>);
+
 /// A dataframe accompanied by a set of tags and tag values
 pub type TaggedDataframe<DF> = (DF, Option<TagsMap>);
 
@@ -19,3 +24,52 @@ pub type StatementResult<DF> = Result<Vec<TaggedDataframe<DF>>, ResponseError>;
 
 /// The result of an entire InfluxQL query
 pub type ResponseResult<DF> = Result<Vec<StatementResult<DF>>, ResponseError>;
+
+/// A dataframe accompanied by its tags and by the metadata of its series
+#[derive(Clone, Debug, PartialEq)]
+pub struct SeriesOutcome<DF> {
+    /// The dataframe
+    pub dataframe: DF,
+
+    /// The tags of the series, if the statement grouped by tags
+    pub tags: Option<TagsMap>,
+
+    /// The names of the columns in the order returned by InfluxDB,
+    /// excluding the time column
+    pub columns: Vec<String>,
+
+    /// Whether the series is continued in the next chunk
+    pub partial: bool,
+}
+
+impl<DF> SeriesOutcome<DF> {
+    /// Discard the metadata and return the dataframe and its tags
+    pub fn into_tagged_dataframe(self) -> TaggedDataframe<DF> {
+        (self.dataframe, self.tags)
+    }
+}
+
+/// The outcome of an individual statement, accompanied by its metadata
+#[derive(Debug)]
+pub struct StatementOutcome<DF> {
+    /// The index of the statement in the query
+    pub statement_id: u32,
+
+    /// Whether the statement result is continued in the next chunk
+    pub partial: bool,
+
+    /// The series returned by the statement, or its error
+    pub series: Result<Vec<SeriesOutcome<DF>>, ResponseError>,
+}
+
+impl<DF> StatementOutcome<DF> {
+    /// Discard the metadata and return the tagged dataframes
+    pub fn into_statement_result(self) -> StatementResult<DF> {
+        self.series.map(|series| {
+            series
+                .into_iter()
+                .map(SeriesOutcome::into_tagged_dataframe)
+                .collect()
+        })
+    }
+}
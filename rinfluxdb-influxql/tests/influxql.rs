@@ -4,7 +4,8 @@
 // https://opensource.org/licenses/MIT
 // https://opensource.org/licenses/Apache-2.0
 
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
+use std::time::{Duration as StdDuration, Instant};
 
 use httpmock::Method::POST;
 use httpmock::MockServer;
@@ -15,7 +16,15 @@ use url::Url;
 
 use rinfluxdb_dataframe::DataFrame;
 use rinfluxdb_influxql::blocking::Client as InfluxqlClient;
+use rinfluxdb_influxql::blocking::ClientBuilder as InfluxqlClientBuilder;
+use rinfluxdb_influxql::ClientError;
+use rinfluxdb_influxql::Query;
 use rinfluxdb_influxql::QueryBuilder as InfluxqlQueryBuilder;
+use rinfluxdb_influxql::StatementResult;
+use rinfluxdb_types::{
+    FailoverStrategy, HttpRequest, HttpResponse, HttpTransport, Precision, QueryClient, RetryPolicy,
+    TimeSeries, TimeWindows, TransportError, Value,
+};
 
 use std::io::stderr;
 
@@ -24,9 +33,9 @@ use tracing_log::LogTracer;
 use tracing_subscriber::fmt as subscriber_fmt;
 use tracing_subscriber::{layer::SubscriberExt, EnvFilter, Registry};
 
-use chrono::{TimeZone, Utc};
+use chrono::{Duration, TimeZone, Utc};
 
-use std::sync::Once;
+use std::sync::{Mutex, Once};
 
 static INIT: Once = Once::new();
 
@@ -90,7 +99,7 @@ fn influxql_client_simple_query() -> Result<()> {
     let query = InfluxqlQueryBuilder::from("indoor_environment")
         .field("temperature")
         .database("house")
-        .start(Utc.ymd(2021, 3, 7).and_hms(21, 0, 0))
+        .start(Utc.with_ymd_and_hms(2021, 3, 7, 21, 0, 0).unwrap())
         .build();
 
     let _dataframe: DataFrame = client.fetch_dataframe(query)?;
@@ -152,7 +161,7 @@ fn influxql_client_tagged_query() -> Result<()> {
     let query = InfluxqlQueryBuilder::from("indoor_environment")
         .field("temperature")
         .database("house")
-        .start(Utc.ymd(2021, 3, 7).and_hms(21, 0, 0))
+        .start(Utc.with_ymd_and_hms(2021, 3, 7, 21, 0, 0).unwrap())
         .group_by("room")
         .build();
 
@@ -166,3 +175,880 @@ fn influxql_client_tagged_query() -> Result<()> {
 
     Ok(())
 }
+
+#[test]
+fn influxql_client_query_client() -> Result<()> {
+    setup_logging();
+
+    let server = MockServer::start();
+
+    let result = r#"{
+        "results": [
+            {
+                "statement_id": 0,
+                "series": [
+                    {
+                        "name": "indoor_environment",
+                        "columns": ["time","temperature"],
+                        "values": [["2021-03-04T17:00:00Z",28.4]],
+                        "tags": {"room": "bedroom"}
+                    },
+                    {
+                        "name": "indoor_environment",
+                        "columns": ["time","temperature"],
+                        "values": [["2021-03-04T17:00:00Z",21.1]],
+                        "tags": {"room": "entrance"}
+                    }
+                ]
+            }
+        ]
+    }"#;
+
+    let hello_mock = server.mock(|when, then| {
+        when.method(POST)
+            .path("/query");
+        then.status(200)
+            .header("Content-Type", "application/json")
+            .body(result);
+    });
+
+    let client: Box<dyn QueryClient<DataFrame>> =
+        Box::new(InfluxqlClient::new(Url::parse(&server.base_url())?, None::<(&str, &str)>)?);
+
+    let query = "SELECT temperature FROM indoor_environment GROUP BY room";
+    let tagged_dataframes = client.fetch_dataframes_by_tag(query, "room").map_err(anyhow::Error::msg)?;
+    let dataframes = client.fetch_tagged_dataframes(query).map_err(anyhow::Error::msg)?;
+
+    hello_mock.assert_hits(2);
+
+    assert!(tagged_dataframes.contains_key("bedroom"));
+    assert!(tagged_dataframes.contains_key("entrance"));
+    assert_eq!(dataframes.len(), 2);
+    assert_eq!(dataframes[0].1["room"], "bedroom");
+
+    Ok(())
+}
+
+#[test]
+fn influxql_client_windowed_query() -> Result<()> {
+    setup_logging();
+
+    let server = MockServer::start();
+
+    let result = r#"{
+        "results": [
+            {
+                "statement_id": 0,
+                "series": [
+                    {
+                        "name": "indoor_environment",
+                        "columns": ["time","temperature"],
+                        "values":[
+                            ["2021-03-04T17:00:00Z",28.4]
+                        ]
+                    }
+                ]
+            }
+        ]
+    }"#;
+
+    let hello_mock = server.mock(|when, then| {
+        when.method(POST)
+            .path("/query")
+            .header("Accept", "application/json");
+        then.status(200)
+            .header("Content-Type", "application/json")
+            .body(result);
+    });
+
+    let client = InfluxqlClient::new(Url::parse(&server.base_url())?, None::<(&str, &str)>)?;
+
+    let windows = TimeWindows::new(
+        Utc.with_ymd_and_hms(2021, 3, 1, 0, 0, 0).unwrap(),
+        Utc.with_ymd_and_hms(2021, 3, 4, 0, 0, 0).unwrap(),
+        Duration::days(1),
+    )
+    .resume_from(1);
+
+    let indices: Vec<usize> = client
+        .fetch_windowed(windows, |_window| {
+            InfluxqlQueryBuilder::from("indoor_environment")
+                .field("temperature")
+                .build()
+        })
+        .map(|(window, result)| {
+            let _statements: Vec<StatementResult<DataFrame>> = result?;
+            Ok(window.index)
+        })
+        .collect::<Result<_>>()?;
+
+    hello_mock.assert_hits(2);
+
+    assert_eq!(indices, vec![1, 2]);
+
+    Ok(())
+}
+
+#[test]
+fn influxql_client_dataframe_windowed_query() -> Result<()> {
+    setup_logging();
+
+    let server = MockServer::start();
+
+    let window_result = |instant: &str, value: f64| {
+        format!(
+            r#"{{
+                "results": [
+                    {{
+                        "statement_id": 0,
+                        "series": [
+                            {{
+                                "name": "indoor_environment",
+                                "columns": ["time","temperature"],
+                                "values":[
+                                    ["{}",{}]
+                                ]
+                            }}
+                        ]
+                    }}
+                ]
+            }}"#,
+            instant,
+            value,
+        )
+    };
+
+    let first_mock = server.mock(|when, then| {
+        when.method(POST)
+            .path("/query")
+            .body_contains("time+%3E%3D+%272021-03-01T00%3A00%3A00Z%27");
+        then.status(200)
+            .header("Content-Type", "application/json")
+            .body(window_result("2021-03-01T17:00:00Z", 21.5));
+    });
+
+    let second_mock = server.mock(|when, then| {
+        when.method(POST)
+            .path("/query")
+            .body_contains("time+%3E%3D+%272021-03-02T00%3A00%3A00Z%27");
+        then.status(200)
+            .header("Content-Type", "application/json")
+            .body(window_result("2021-03-02T17:00:00Z", 22.5));
+    });
+
+    let client = InfluxqlClient::new(Url::parse(&server.base_url())?, None::<(&str, &str)>)?;
+
+    let query_builder = InfluxqlQueryBuilder::from("indoor_environment")
+        .field("temperature");
+
+    let dataframe: DataFrame = client.fetch_dataframe_windowed(
+        query_builder,
+        Utc.with_ymd_and_hms(2021, 3, 1, 0, 0, 0).unwrap(),
+        Utc.with_ymd_and_hms(2021, 3, 3, 0, 0, 0).unwrap(),
+        Duration::days(1),
+    )?;
+
+    first_mock.assert();
+    second_mock.assert();
+
+    assert_eq!(dataframe.name(), "indoor_environment");
+    assert_eq!(
+        dataframe.index(),
+        &[
+            Utc.with_ymd_and_hms(2021, 3, 1, 17, 0, 0).unwrap(),
+            Utc.with_ymd_and_hms(2021, 3, 2, 17, 0, 0).unwrap(),
+        ],
+    );
+
+    Ok(())
+}
+
+#[test]
+fn influxql_client_windowed_merged_query() -> Result<()> {
+    setup_logging();
+
+    let server = MockServer::start();
+
+    let window_result = |instant: &str, value: f64| {
+        format!(
+            r#"{{
+                "results": [
+                    {{
+                        "statement_id": 0,
+                        "series": [
+                            {{
+                                "name": "indoor_environment",
+                                "columns": ["time","temperature"],
+                                "values":[
+                                    ["{}",{}]
+                                ]
+                            }}
+                        ]
+                    }}
+                ]
+            }}"#,
+            instant,
+            value,
+        )
+    };
+
+    let first_mock = server.mock(|when, then| {
+        when.method(POST)
+            .path("/query")
+            .body_contains("window+0");
+        then.status(200)
+            .header("Content-Type", "application/json")
+            .body(window_result("2021-03-01T17:00:00Z", 21.5));
+    });
+
+    let second_mock = server.mock(|when, then| {
+        when.method(POST)
+            .path("/query")
+            .body_contains("window+1");
+        then.status(200)
+            .header("Content-Type", "application/json")
+            .body(window_result("2021-03-02T17:00:00Z", 22.5));
+    });
+
+    let client = InfluxqlClient::new(Url::parse(&server.base_url())?, None::<(&str, &str)>)?;
+
+    let windows = TimeWindows::new(
+        Utc.with_ymd_and_hms(2021, 3, 1, 0, 0, 0).unwrap(),
+        Utc.with_ymd_and_hms(2021, 3, 3, 0, 0, 0).unwrap(),
+        Duration::days(1),
+    );
+
+    let dataframe: DataFrame = client.fetch_windowed_merged(windows, |window| {
+        Query::new(format!("SELECT temperature FROM indoor_environment -- window {}", window.index))
+    })?;
+
+    first_mock.assert();
+    second_mock.assert();
+
+    assert_eq!(dataframe.name(), "indoor_environment");
+    assert_eq!(
+        dataframe.index(),
+        &[
+            Utc.with_ymd_and_hms(2021, 3, 1, 17, 0, 0).unwrap(),
+            Utc.with_ymd_and_hms(2021, 3, 2, 17, 0, 0).unwrap(),
+        ],
+    );
+
+    Ok(())
+}
+
+#[test]
+fn influxql_client_timeseries_query() -> Result<()> {
+    setup_logging();
+
+    let server = MockServer::start();
+
+    let result = r#"{
+        "results": [
+            {
+                "statement_id": 0,
+                "series": [
+                    {
+                        "name": "indoor_environment",
+                        "columns": ["time","temperature"],
+                        "values":[
+                            ["2021-03-04T17:00:00Z",28.4],
+                            ["2021-03-04T18:00:00Z",29.2]
+                        ],
+                        "tags": {
+                            "room": "bedroom"
+                        }
+                    },
+                    {
+                        "name": "indoor_environment",
+                        "columns": ["time","temperature"],
+                        "values":[
+                            ["2021-03-04T17:00:00Z",21.1]
+                        ],
+                        "tags": {
+                            "room": "entrance"
+                        }
+                    }
+                ]
+            }
+        ]
+    }"#;
+
+    let hello_mock = server.mock(|when, then| {
+        when.method(POST)
+            .path("/query")
+            .header("Accept", "application/json");
+        then.status(200)
+            .header("Content-Type", "application/json")
+            .body(result);
+    });
+
+    let client = InfluxqlClient::new(Url::parse(&server.base_url())?, None::<(&str, &str)>)?;
+
+    let query = InfluxqlQueryBuilder::from("indoor_environment")
+        .field("temperature")
+        .database("house")
+        .group_by("room")
+        .build();
+
+    let timeseries = client.fetch_timeseries(query)?;
+
+    hello_mock.assert();
+
+    let mut tags = BTreeMap::new();
+    tags.insert("room".to_string(), "entrance".to_string());
+    let series = &timeseries[&("indoor_environment".to_string(), tags)];
+
+    assert_eq!(timeseries.len(), 2);
+    assert_eq!(
+        series.field("temperature"),
+        Some(&[(Utc.with_ymd_and_hms(2021, 3, 4, 17, 0, 0).unwrap(), Value::Float(21.1))][..]),
+    );
+
+    Ok(())
+}
+
+#[test]
+fn influxql_client_last_reading() -> Result<()> {
+    setup_logging();
+
+    let server = MockServer::start();
+
+    let result = r#"{
+        "results": [
+            {
+                "statement_id": 0,
+                "series": [
+                    {
+                        "name": "indoor_environment",
+                        "columns": ["time","last"],
+                        "values":[
+                            ["2021-03-04T18:00:00Z",29.2]
+                        ]
+                    }
+                ]
+            }
+        ]
+    }"#;
+
+    let hello_mock = server.mock(|when, then| {
+        when.method(POST)
+            .path("/query")
+            .body_contains("q=SELECT+last%28%22temperature%22%29+FROM+%22indoor_environment%22");
+        then.status(200)
+            .header("Content-Type", "application/json")
+            .body(result);
+    });
+
+    let client = InfluxqlClient::new(Url::parse(&server.base_url())?, None::<(&str, &str)>)?;
+
+    let reading = client.last_reading("indoor_environment", "temperature", &[("room", "bedroom")])?;
+
+    hello_mock.assert();
+
+    assert_eq!(
+        reading,
+        Some((Utc.with_ymd_and_hms(2021, 3, 4, 18, 0, 0).unwrap(), Value::Float(29.2))),
+    );
+
+    Ok(())
+}
+
+#[test]
+fn influxql_client_query_params() -> Result<()> {
+    setup_logging();
+
+    let server = MockServer::start();
+
+    let result = r#"{
+        "results": [
+            {
+                "statement_id": 0,
+                "series": [
+                    {
+                        "name": "indoor_environment",
+                        "columns": ["time","temperature"],
+                        "values":[
+                            ["2021-03-04T18:00:00Z",29.2]
+                        ]
+                    }
+                ]
+            }
+        ]
+    }"#;
+
+    let hello_mock = server.mock(|when, then| {
+        when.method(POST)
+            .path("/query")
+            .body_contains("q=SELECT+temperature+FROM+indoor_environment+WHERE+room+%3D+%24room")
+            .body_contains("params=%7B%22room%22%3A%22bedroom%27+OR+1%3D1%22%7D");
+        then.status(200)
+            .header("Content-Type", "application/json")
+            .body(result);
+    });
+
+    let client = InfluxqlClient::new(Url::parse(&server.base_url())?, None::<(&str, &str)>)?;
+
+    let query = Query::new("SELECT temperature FROM indoor_environment WHERE room = $room")
+        .with_params(vec![("room", "bedroom' OR 1=1")]);
+    let _dataframe: DataFrame = client.fetch_dataframe(query)?;
+
+    hello_mock.assert();
+
+    Ok(())
+}
+
+#[test]
+fn influxql_client_last_reading_empty() -> Result<()> {
+    setup_logging();
+
+    let server = MockServer::start();
+
+    let hello_mock = server.mock(|when, then| {
+        when.method(POST)
+            .path("/query");
+        then.status(200)
+            .header("Content-Type", "application/json")
+            .body(r#"{"results": [{"statement_id": 0}]}"#);
+    });
+
+    let client = InfluxqlClient::new(Url::parse(&server.base_url())?, None::<(&str, &str)>)?;
+
+    let reading = client.last_reading("indoor_environment", "temperature", &[])?;
+
+    hello_mock.assert();
+
+    assert_eq!(reading, None);
+
+    Ok(())
+}
+
+#[test]
+fn influxql_client_user_agent() -> Result<()> {
+    setup_logging();
+
+    let server = MockServer::start();
+
+    let hello_mock = server.mock(|when, then| {
+        when.method(POST)
+            .path("/query")
+            .header("User-Agent", concat!("rinfluxdb/", env!("CARGO_PKG_VERSION"), " collector/1.4.2"));
+        then.status(200)
+            .header("Content-Type", "application/json")
+            .body(r#"{"results": [{"statement_id": 0}]}"#);
+    });
+
+    let client = InfluxqlClientBuilder::new(Url::parse(&server.base_url())?)
+        .application("collector/1.4.2")
+        .build()?;
+
+    let reading = client.last_reading("indoor_environment", "temperature", &[])?;
+
+    hello_mock.assert();
+
+    assert_eq!(reading, None);
+
+    Ok(())
+}
+
+#[test]
+fn influxql_client_retry() -> Result<()> {
+    setup_logging();
+
+    let server = MockServer::start();
+
+    let hello_mock = server.mock(|when, then| {
+        when.method(POST)
+            .path("/query");
+        then.status(503)
+            .body("");
+    });
+
+    let client = InfluxqlClientBuilder::new(Url::parse(&server.base_url())?)
+        .retry_policy(
+            RetryPolicy::new(3)
+                .backoff(StdDuration::from_millis(1), StdDuration::from_millis(1)),
+        )
+        .build()?;
+
+    let result = client.last_reading("indoor_environment", "temperature", &[]);
+
+    hello_mock.assert_hits(3);
+
+    assert!(result.is_err());
+
+    Ok(())
+}
+
+#[test]
+fn influxql_client_failover() -> Result<()> {
+    setup_logging();
+
+    let primary = MockServer::start();
+    let fallback = MockServer::start();
+
+    let primary_mock = primary.mock(|when, then| {
+        when.method(POST)
+            .path("/query");
+        then.status(503)
+            .body("");
+    });
+
+    let fallback_mock = fallback.mock(|when, then| {
+        when.method(POST)
+            .path("/query")
+            .body_contains("db=house");
+        then.status(200)
+            .header("Content-Type", "application/json")
+            .body(r#"{"results": [{"statement_id": 0}]}"#);
+    });
+
+    let client = InfluxqlClientBuilder::new(Url::parse(&primary.base_url())?)
+        .fallback_urls(vec![Url::parse(&fallback.base_url())?])
+        .failover_strategy(FailoverStrategy::PrimaryWithFallback)
+        .build()?;
+
+    let query = Query::new("SELECT temperature FROM indoor_environment");
+    let _statements: Vec<StatementResult<DataFrame>> =
+        client.fetch_readings_from_database(query, Some("house"))?;
+
+    primary_mock.assert();
+    fallback_mock.assert();
+
+    Ok(())
+}
+
+#[test]
+fn influxql_client_management() -> Result<()> {
+    setup_logging();
+
+    let server = MockServer::start();
+
+    let create_mock = server.mock(|when, then| {
+        when.method(POST)
+            .path("/query")
+            .body_contains("q=CREATE+DATABASE+%22house%22");
+        then.status(200)
+            .header("Content-Type", "application/json")
+            .body(r#"{"results": [{"statement_id": 0}]}"#);
+    });
+
+    let drop_mock = server.mock(|when, then| {
+        when.method(POST)
+            .path("/query")
+            .body_contains("q=DROP+RETENTION+POLICY+%22one_day%22+ON+%22house%22");
+        then.status(200)
+            .header("Content-Type", "application/json")
+            .body(r#"{"results": [{"statement_id": 0, "error": "retention policy not found: one_day"}]}"#);
+    });
+
+    let show_mock = server.mock(|when, then| {
+        when.method(POST)
+            .path("/query")
+            .body_contains("q=SHOW+RETENTION+POLICIES+ON+%22house%22");
+        then.status(200)
+            .header("Content-Type", "application/json")
+            .body(r#"{
+                "results": [
+                    {
+                        "statement_id": 0,
+                        "series": [
+                            {
+                                "columns": ["name","duration","shardGroupDuration","replicaN","default"],
+                                "values": [["autogen","0s","168h0m0s",1,true]]
+                            }
+                        ]
+                    }
+                ]
+            }"#);
+    });
+
+    let client = InfluxqlClient::new(Url::parse(&server.base_url())?, None::<(&str, &str)>)?;
+
+    client.create_database("house")?;
+    let dropped = client.drop_retention_policy("house", "one_day");
+    let policies = client.retention_policies("house")?;
+
+    create_mock.assert();
+    drop_mock.assert();
+    show_mock.assert();
+
+    assert!(dropped.is_err());
+    assert_eq!(policies.len(), 1);
+    assert_eq!(policies[0].name, "autogen");
+    assert!(policies[0].default);
+
+    Ok(())
+}
+
+#[test]
+fn influxql_client_schema_exploration() -> Result<()> {
+    setup_logging();
+
+    let server = MockServer::start();
+
+    let tag_values_mock = server.mock(|when, then| {
+        when.method(POST)
+            .path("/query")
+            .body_contains("q=SHOW+TAG+VALUES+FROM+%22indoor_environment%22+WITH+KEY+%3D+%22room%22")
+            .body_contains("db=house");
+        then.status(200)
+            .header("Content-Type", "application/json")
+            .body(r#"{
+                "results": [
+                    {
+                        "statement_id": 0,
+                        "series": [
+                            {
+                                "name": "indoor_environment",
+                                "columns": ["key", "value"],
+                                "values": [["room", "bedroom"], ["room", "kitchen"]]
+                            }
+                        ]
+                    }
+                ]
+            }"#);
+    });
+
+    let client = InfluxqlClient::new(Url::parse(&server.base_url())?, None::<(&str, &str)>)?;
+
+    let values = client.list_tag_values("house", "indoor_environment", "room")?;
+
+    tag_values_mock.assert();
+
+    assert_eq!(values, vec!["bedroom".to_string(), "kitchen".to_string()]);
+
+    Ok(())
+}
+
+#[test]
+fn influxql_client_chunked_query() -> Result<()> {
+    setup_logging();
+
+    let server = MockServer::start();
+
+    let result = concat!(
+        r#"{"results":[{"statement_id":0,"series":[{"name":"indoor_environment","columns":["time","temperature"],"values":[["2021-03-04T17:00:00Z",28.4],["2021-03-04T18:00:00Z",29.2]]}],"partial":true}]}"#,
+        "\n",
+        r#"{"results":[{"statement_id":0,"series":[{"name":"indoor_environment","columns":["time","temperature"],"values":[["2021-03-04T19:00:00Z",29.5]]}]}]}"#,
+        "\n",
+    );
+
+    let hello_mock = server.mock(|when, then| {
+        when.method(POST)
+            .path("/query")
+            .body_contains("chunked=true")
+            .body_contains("chunk_size=2");
+        then.status(200)
+            .header("Content-Type", "application/json")
+            .body(result);
+    });
+
+    let client = InfluxqlClient::new(Url::parse(&server.base_url())?, None::<(&str, &str)>)?;
+
+    let query = InfluxqlQueryBuilder::from("indoor_environment")
+        .field("temperature")
+        .build();
+    let dataframes = client
+        .fetch_chunked::<DataFrame, _>(query, 2)?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    hello_mock.assert();
+
+    assert_eq!(dataframes.len(), 2);
+
+    Ok(())
+}
+
+#[test]
+fn influxql_request_epoch() -> Result<()> {
+    use rinfluxdb_influxql::blocking::{InfluxqlClientWrapper, InfluxqlResponseWrapper};
+
+    setup_logging();
+
+    let server = MockServer::start();
+
+    let result = r#"{"results":[{"statement_id":0,"series":[{"name":"indoor_environment","columns":["time","temperature"],"values":[[1614877200000,28.4],[1614880800000,29.2]]}]}]}"#;
+
+    let hello_mock = server.mock(|when, then| {
+        when.method(POST)
+            .path("/query")
+            .body_contains("epoch=ms");
+        then.status(200)
+            .header("Content-Type", "application/json")
+            .body(result);
+    });
+
+    let client = reqwest::blocking::Client::new();
+    let request = client
+        .influxql(&Url::parse(&server.base_url())?)?
+        .database("house")
+        .query(Query::new("SELECT temperature FROM indoor_environment"))
+        .epoch(Precision::Milliseconds)
+        .into_reqwest_builder()
+        .build()?;
+    let response = client.execute(request)?;
+
+    let statements: Vec<StatementResult<TimeSeries>> =
+        response.dataframes_with_epoch(Precision::Milliseconds)?;
+
+    hello_mock.assert();
+
+    let (series, _tags) = statements.into_iter().next().unwrap()?.remove(0);
+    let instants: Vec<_> = series
+        .field("temperature")
+        .unwrap()
+        .iter()
+        .map(|(instant, _)| *instant)
+        .collect();
+    assert_eq!(
+        instants,
+        vec![
+            Utc.with_ymd_and_hms(2021, 3, 4, 17, 0, 0).unwrap(),
+            Utc.with_ymd_and_hms(2021, 3, 4, 18, 0, 0).unwrap(),
+        ],
+    );
+
+    Ok(())
+}
+
+#[test]
+fn influxql_client_fetch_into() -> Result<()> {
+    #[derive(Debug, PartialEq, serde::Deserialize)]
+    struct Reading {
+        time: chrono::DateTime<Utc>,
+        room: String,
+        temperature: Option<f64>,
+    }
+
+    setup_logging();
+
+    let server = MockServer::start();
+
+    let result = r#"{"results":[{"statement_id":0,"series":[{"name":"indoor_environment","tags":{"room":"bedroom"},"columns":["time","temperature"],"values":[["2021-03-04T17:00:00Z",28.4],["2021-03-04T18:00:00Z",null]]},{"name":"indoor_environment","tags":{"room":"kitchen"},"columns":["time","temperature"],"values":[["2021-03-04T17:00:00Z",23.1]]}]}]}"#;
+
+    let hello_mock = server.mock(|when, then| {
+        when.method(POST)
+            .path("/query");
+        then.status(200)
+            .header("Content-Type", "application/json")
+            .body(result);
+    });
+
+    let client = InfluxqlClient::new(Url::parse(&server.base_url())?, None::<(&str, &str)>)?;
+
+    let query = Query::new("SELECT temperature FROM indoor_environment GROUP BY room");
+    let readings: Vec<Reading> = client.fetch_into(query)?;
+
+    hello_mock.assert();
+
+    assert_eq!(
+        readings,
+        vec![
+            Reading {
+                time: Utc.with_ymd_and_hms(2021, 3, 4, 17, 0, 0).unwrap(),
+                room: "bedroom".into(),
+                temperature: Some(28.4),
+            },
+            Reading {
+                time: Utc.with_ymd_and_hms(2021, 3, 4, 18, 0, 0).unwrap(),
+                room: "bedroom".into(),
+                temperature: None,
+            },
+            Reading {
+                time: Utc.with_ymd_and_hms(2021, 3, 4, 17, 0, 0).unwrap(),
+                room: "kitchen".into(),
+                temperature: Some(23.1),
+            },
+        ],
+    );
+
+    Ok(())
+}
+
+#[derive(Debug, Default)]
+struct CannedTransport {
+    responses: Mutex<Vec<HttpResponse>>,
+    requests: Mutex<Vec<HttpRequest>>,
+}
+
+impl HttpTransport for CannedTransport {
+    fn execute(&self, request: HttpRequest) -> Result<HttpResponse, TransportError> {
+        self.requests.lock().unwrap().push(request);
+        self.responses.lock().unwrap().pop().ok_or_else(|| "no more responses".into())
+    }
+}
+
+#[test]
+fn influxql_client_custom_transport() -> Result<()> {
+    setup_logging();
+
+    let result = r#"{"results":[{"statement_id":0,"series":[{"name":"indoor_environment","columns":["time","temperature"],"values":[["2021-03-04T17:00:00Z",28.4]]}]}]}"#;
+
+    let transport = std::sync::Arc::new(CannedTransport::default());
+    transport.responses.lock().unwrap().push(HttpResponse::new(200, result));
+    transport.responses.lock().unwrap().push(HttpResponse::new(503, ""));
+
+    let client = InfluxqlClientBuilder::new(Url::parse("https://example.com/")?)
+        .credentials("username", "password")
+        .retry_policy(RetryPolicy::new(3).backoff(StdDuration::from_millis(1), StdDuration::from_millis(1)))
+        .transport(transport.clone())
+        .build()?;
+
+    let query = Query::new("SELECT temperature FROM indoor_environment");
+    let dataframe: DataFrame = client.fetch_dataframe(query)?;
+
+    assert_eq!(dataframe.index().len(), 1);
+
+    let requests = transport.requests.lock().unwrap();
+    assert_eq!(requests.len(), 2);
+    assert_eq!(requests[1].method, "POST");
+    assert_eq!(requests[1].url, "https://example.com/query");
+    assert!(requests[1].header("authorization").is_some());
+    assert_eq!(
+        String::from_utf8(requests[1].body.clone())?,
+        "q=SELECT+temperature+FROM+indoor_environment",
+    );
+
+    Ok(())
+}
+
+#[test]
+fn influxql_client_retry_after() -> Result<()> {
+    setup_logging();
+
+    let result = r#"{"results":[{"statement_id":0,"series":[{"name":"indoor_environment","columns":["time","temperature"],"values":[["2021-03-04T17:00:00Z",28.4]]}]}]}"#;
+
+    let transport = std::sync::Arc::new(CannedTransport::default());
+    transport.responses.lock().unwrap().push(HttpResponse::new(503, "").with_header("Retry-After", "2"));
+    transport.responses.lock().unwrap().push(HttpResponse::new(200, result));
+    transport.responses.lock().unwrap().push(HttpResponse::new(429, "").with_header("Retry-After", "1"));
+
+    let client = InfluxqlClientBuilder::new(Url::parse("https://example.com/")?)
+        .retry_policy(RetryPolicy::new(2).backoff(StdDuration::from_millis(1), StdDuration::from_secs(2)))
+        .transport(transport.clone())
+        .build()?;
+
+    let start = Instant::now();
+    let query = Query::new("SELECT temperature FROM indoor_environment");
+    let dataframe: DataFrame = client.fetch_dataframe(query)?;
+
+    assert_eq!(dataframe.index().len(), 1);
+    assert!(start.elapsed() >= StdDuration::from_secs(1));
+
+    let client = InfluxqlClientBuilder::new(Url::parse("https://example.com/")?)
+        .transport(transport.clone())
+        .build()?;
+
+    let query = Query::new("SELECT temperature FROM indoor_environment");
+    let result: Result<DataFrame, _> = client.fetch_dataframe(query);
+
+    match result {
+        Err(ClientError::RateLimited { retry_after }) => {
+            assert_eq!(retry_after, Some(StdDuration::from_secs(2)));
+        }
+        result => panic!("Did not receive expected error: {:?}", result),
+    }
+
+    Ok(())
+}
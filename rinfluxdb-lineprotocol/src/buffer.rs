@@ -0,0 +1,257 @@
+// Copyright Claudio Mattera 2021.
+// Distributed under the MIT License or Apache 2.0 License at your option.
+// See accompanying files License-MIT.txt and License-Apache-2.0, or online at
+// https://opensource.org/licenses/MIT
+// https://opensource.org/licenses/Apache-2.0
+
+use std::collections::VecDeque;
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use thiserror::Error;
+
+use tracing::*;
+
+use super::Line;
+
+/// What to do when a line is pushed to a full buffer
+///
+/// Buffers fill up when lines are produced faster than they can be sent,
+/// for instance when the server is down or is rate limiting writes.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub enum OverflowPolicy {
+    /// Reject the new line and hand it back to the caller
+    ///
+    /// This applies backpressure to the producer, which must send the
+    /// buffered lines before pushing again.
+    #[default]
+    Block,
+
+    /// Discard the oldest buffered line to make room for the new one
+    DropOldest,
+
+    /// Discard the new line
+    DropNewest,
+
+    /// Append the new line to a file in line protocol format
+    ///
+    /// The file is created if it does not exist.
+    Spill(PathBuf),
+}
+
+/// Counters of lines that did not fit in a buffer
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct BufferMetrics {
+    /// Number of lines discarded because the buffer was full
+    pub dropped: u64,
+
+    /// Number of lines written to the spill file because the buffer was full
+    pub spilled: u64,
+}
+
+/// An error occurred while pushing a line to a buffer
+#[derive(Error, Debug)]
+pub enum OverflowError {
+    /// The buffer is full and its policy is to block
+    ///
+    /// The rejected line is returned, so it can be pushed again once the
+    /// buffer has been drained.
+    #[error("Buffer is full")]
+    Full(Box<Line>),
+
+    /// Error occurred while writing to the spill file
+    #[error("Spill file error")]
+    SpillError(#[from] std::io::Error),
+}
+
+/// A bounded buffer of lines with a policy for handling overflows
+///
+/// ```
+/// # use rinfluxdb_lineprotocol::{LineBuffer, LineBuilder, OverflowPolicy};
+/// let mut buffer = LineBuffer::new(2, OverflowPolicy::DropOldest);
+///
+/// for value in 0..5i64 {
+///     let line = LineBuilder::new("measurement")
+///         .insert_field("value", value)
+///         .build();
+///     buffer.push(line)?;
+/// }
+///
+/// assert_eq!(buffer.len(), 2);
+/// assert_eq!(buffer.metrics().dropped, 3);
+///
+/// let lines = buffer.drain();
+/// assert_eq!(lines[0].to_string(), "measurement value=3i");
+/// assert_eq!(lines[1].to_string(), "measurement value=4i");
+/// assert!(buffer.is_empty());
+/// # Ok::<(), rinfluxdb_lineprotocol::OverflowError>(())
+/// ```
+#[derive(Debug)]
+pub struct LineBuffer {
+    lines: VecDeque<Line>,
+    capacity: usize,
+    policy: OverflowPolicy,
+    metrics: BufferMetrics,
+}
+
+impl LineBuffer {
+    /// Create a new buffer holding up to `capacity` lines
+    pub fn new(capacity: usize, policy: OverflowPolicy) -> Self {
+        Self {
+            lines: VecDeque::with_capacity(capacity),
+            capacity,
+            policy,
+            metrics: BufferMetrics::default(),
+        }
+    }
+
+    /// Push a line to the buffer, applying the overflow policy if it is full
+    pub fn push(&mut self, line: Line) -> Result<(), OverflowError> {
+        if self.lines.len() < self.capacity {
+            self.lines.push_back(line);
+            return Ok(());
+        }
+
+        match &self.policy {
+            OverflowPolicy::Block => {
+                return Err(OverflowError::Full(Box::new(line)));
+            }
+            OverflowPolicy::DropOldest => {
+                self.lines.pop_front();
+                if self.capacity > 0 {
+                    self.lines.push_back(line);
+                }
+                self.metrics.dropped += 1;
+            }
+            OverflowPolicy::DropNewest => {
+                self.metrics.dropped += 1;
+            }
+            OverflowPolicy::Spill(path) => {
+                spill(path, &line)?;
+                self.metrics.spilled += 1;
+            }
+        }
+        debug!("Buffer overflow, {:?}", self.metrics);
+
+        Ok(())
+    }
+
+    /// Remove and return all buffered lines, oldest first
+    pub fn drain(&mut self) -> Vec<Line> {
+        self.lines.drain(..).collect()
+    }
+
+    /// Return the buffered lines, oldest first
+    pub fn lines(&self) -> impl Iterator<Item = &Line> {
+        self.lines.iter()
+    }
+
+    /// Return the buffered lines as a slice, oldest first
+    pub fn make_contiguous(&mut self) -> &[Line] {
+        self.lines.make_contiguous()
+    }
+
+    /// Remove all buffered lines
+    pub fn clear(&mut self) {
+        self.lines.clear();
+    }
+
+    /// Return the number of buffered lines
+    pub fn len(&self) -> usize {
+        self.lines.len()
+    }
+
+    /// Check whether the buffer is empty
+    pub fn is_empty(&self) -> bool {
+        self.lines.is_empty()
+    }
+
+    /// Check whether the buffer is full
+    pub fn is_full(&self) -> bool {
+        self.lines.len() >= self.capacity
+    }
+
+    /// Return the maximal number of buffered lines
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Return the overflow policy
+    pub fn policy(&self) -> &OverflowPolicy {
+        &self.policy
+    }
+
+    /// Return the counters of dropped and spilled lines
+    pub fn metrics(&self) -> BufferMetrics {
+        self.metrics
+    }
+}
+
+fn spill(path: &Path, line: &Line) -> std::io::Result<()> {
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+    writeln!(file, "{}", line)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::fs::{read_to_string, remove_file};
+
+    use super::super::LineBuilder;
+
+    fn line(value: i64) -> Line {
+        LineBuilder::new("measurement")
+            .insert_field("value", value)
+            .build()
+    }
+
+    #[test]
+    fn block_returns_line() {
+        let mut buffer = LineBuffer::new(1, OverflowPolicy::Block);
+
+        buffer.push(line(1)).unwrap();
+        match buffer.push(line(2)) {
+            Err(OverflowError::Full(rejected)) => assert_eq!(*rejected, line(2)),
+            other => panic!("Unexpected result {:?}", other),
+        }
+
+        assert_eq!(buffer.drain(), vec![line(1)]);
+        assert_eq!(buffer.metrics(), BufferMetrics::default());
+        buffer.push(line(2)).unwrap();
+    }
+
+    #[test]
+    fn drop_newest() {
+        let mut buffer = LineBuffer::new(2, OverflowPolicy::DropNewest);
+
+        for value in 0..4 {
+            buffer.push(line(value)).unwrap();
+        }
+
+        assert_eq!(buffer.drain(), vec![line(0), line(1)]);
+        assert_eq!(buffer.metrics().dropped, 2);
+    }
+
+    #[test]
+    fn spill_to_file() {
+        let path = std::env::temp_dir().join(format!(
+            "rinfluxdb-spill-{}.txt",
+            std::process::id()
+        ));
+        let _ = remove_file(&path);
+
+        let mut buffer = LineBuffer::new(1, OverflowPolicy::Spill(path.clone()));
+        for value in 0..3 {
+            buffer.push(line(value)).unwrap();
+        }
+
+        let spilled = read_to_string(&path).unwrap();
+        remove_file(&path).unwrap();
+
+        assert_eq!(buffer.drain(), vec![line(0)]);
+        assert_eq!(buffer.metrics().spilled, 2);
+        assert_eq!(spilled, "measurement value=1i\nmeasurement value=2i\n");
+    }
+}
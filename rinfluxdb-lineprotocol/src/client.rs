@@ -4,14 +4,38 @@
 // https://opensource.org/licenses/MIT
 // https://opensource.org/licenses/Apache-2.0
 
+use std::time::Duration;
+
+use reqwest::header::{HeaderMap, RETRY_AFTER};
+use reqwest::StatusCode;
+
 use serde::Deserialize;
 
 use serde_json::from_str;
 
 use thiserror::Error;
 
+use url::Url;
+
+use rinfluxdb_types::{parse_retry_after, MetricsObserver, Ping, Precision, TransportError};
+
+use super::{Line, OverflowError, ParseError, UnsignedPolicy, ValidationError};
+
+#[cfg(feature = "client-async")]
 pub mod r#async;
+#[cfg(feature = "client-blocking")]
 pub mod blocking;
+#[cfg(all(feature = "client-async", not(target_arch = "wasm32")))]
+mod ingestion;
+mod rate_limit;
+mod sink;
+
+#[cfg(all(feature = "client-async", not(target_arch = "wasm32")))]
+pub use self::ingestion::{IngestHandle, IngestionService, IngestionServiceBuilder};
+pub use self::rate_limit::RateLimit;
+pub use self::sink::FileSink;
+
+use self::rate_limit::RateLimiter;
 
 /// An error occurred during interfacing with an InfluxDB server
 #[derive(Error, Debug)]
@@ -24,6 +48,10 @@ pub enum ClientError {
     #[error("URL parse error")]
     UrlError(#[from] url::ParseError),
 
+    /// Error occurred within a custom transport
+    #[error("Transport error: {0}")]
+    TransportError(TransportError),
+
     /// Specified a field with conflicting type
     #[error("Field type conflict")]
     FieldTypeConflict,
@@ -32,29 +60,806 @@ pub enum ClientError {
     #[error("Database not found")]
     DatabaseNotFound,
 
+    /// Some points were written, while others were rejected
+    #[error("Partial write")]
+    PartialWrite(#[from] PartialWriteError),
+
+    /// A write was split in multiple requests, and one of them failed after
+    /// others were sent
+    ///
+    /// Lines before position `sent.lines` were written, and the following
+    /// ones were not, except for those accepted in a partial write.
+    #[error("Write interrupted after {} lines", sent.lines)]
+    WriteInterrupted {
+        /// The requests sent before the failure
+        sent: WriteSummary,
+
+        /// The error of the failed request
+        #[source]
+        error: Box<ClientError>,
+    },
+
+    /// Some points were rejected because they are older than the retention
+    /// policy duration
+    #[error("Points beyond retention policy")]
+    PointsBeyondRetentionPolicy {
+        /// The number of rejected points
+        dropped: Option<u64>,
+    },
+
+    /// Some points were rejected because they would create new series in a
+    /// database that has reached its maximum number of series
+    #[error("Maximum number of series per database exceeded")]
+    MaxSeriesPerDatabaseExceeded {
+        /// The number of rejected points
+        dropped: Option<u64>,
+    },
+
+    /// Request body is larger than the maximum allowed by the server
+    #[error("Request entity too large")]
+    RequestEntityTooLarge,
+
+    /// Credentials are missing or not valid
+    #[error("Unauthorized")]
+    Unauthorized,
+
+    /// Server is rejecting requests because too many were sent
+    #[error("Rate limited")]
+    RateLimited {
+        /// How long to wait before sending the next request, from header
+        /// `Retry-After`
+        retry_after: Option<Duration>,
+    },
+
+    /// Precision is not supported by the server
+    #[error("Unsupported precision {0:?}")]
+    UnsupportedPrecision(Precision),
+
+    /// Error occurred while buffering lines
+    #[error("Buffer overflow error")]
+    OverflowError(#[from] OverflowError),
+
+    /// Error occurred while reading or writing a file
+    #[error("I/O error")]
+    IoError(#[from] std::io::Error),
+
+    /// Error occurred while parsing buffered lines
+    #[error("Line protocol parse error")]
+    ParseError(#[from] ParseError),
+
+    /// A line cannot be formatted, and no lines were sent
+    #[error("Invalid line {index}: {error}")]
+    InvalidLine {
+        /// The position of the line
+        index: usize,
+
+        /// The problem found in the line
+        error: ValidationError,
+    },
+
+    /// A file sink contains invalid data
+    #[error("Invalid buffered data: {0}")]
+    InvalidBuffer(String),
+
+    /// The ingestion service was stopped, and no longer accepts lines
+    #[error("Ingestion service stopped")]
+    IngestionStopped,
+
     /// Unknown error
     #[error("Unknown error")]
     Unknown,
 }
 
-fn parse_error(text: &str) -> ClientError {
+/// Some lines in a batch were rejected by the server
+///
+/// InfluxDB reports the lines it could not parse either by quoting them, as
+/// in `unable to parse 'cpu value=': missing field value`, or by their line
+/// number, as in `line 2: no field values found`.
+/// Either way, they are matched to their position in the batch, so that
+/// callers can drop or fix only the rejected lines instead of sending the
+/// whole batch again.
+#[derive(Error, Clone, Debug, PartialEq, Eq)]
+#[error("{reason}")]
+pub struct PartialWriteError {
+    /// The position of the first rejected line in the batch, if the server
+    /// identified it
+    pub failed_index: Option<usize>,
+
+    /// The positions of all rejected lines identified by the server, sorted
+    pub rejected_indices: Vec<usize>,
+
+    /// The error message returned by the server
+    pub reason: String,
+
+    /// The number of rejected points, if the server reported it
+    pub dropped: Option<u64>,
+}
+
+impl PartialWriteError {
+    fn new(reason: String) -> Self {
+        Self {
+            failed_index: None,
+            rejected_indices: Vec::new(),
+            dropped: dropped_points(&reason),
+            reason,
+        }
+    }
+
+    /// Match the lines reported by the server to their positions in a
+    /// request payload
+    fn locate(mut self, payload: &str) -> Self {
+        let lines: Vec<&str> = payload.lines().collect();
+
+        let mut indices: Vec<usize> = quoted_lines(&self.reason)
+            .filter_map(|quoted| lines.iter().position(|line| *line == quoted))
+            .chain(
+                numbered_lines(&self.reason)
+                    .filter(|number| (1..=lines.len()).contains(number))
+                    .map(|number| number - 1),
+            )
+            .collect();
+        indices.sort_unstable();
+        indices.dedup();
+
+        if self.dropped.is_none() {
+            self.dropped = written_points(&self.reason)
+                .map(|written| (lines.len() as u64).saturating_sub(written));
+        }
+        self.failed_index = indices.first().copied();
+        self.rejected_indices = indices;
+        self
+    }
+}
+
+/// The default value of header `User-Agent`, including the crate version
+const DEFAULT_USER_AGENT: &str = concat!("rinfluxdb/", env!("CARGO_PKG_VERSION"));
+
+/// The default number of lines sent together by batch writers
+const DEFAULT_BATCH_SIZE: usize = 5000;
+
+/// The default number of lines kept by batch writers when sending fails
+const DEFAULT_BUFFER_CAPACITY: usize = 50000;
+
+fn user_agent(user_agent: Option<String>, application: Option<String>) -> String {
+    let user_agent = user_agent.unwrap_or_else(|| DEFAULT_USER_AGENT.to_string());
+    match application {
+        Some(application) => format!("{} {}", user_agent, application),
+        None => user_agent,
+    }
+}
+
+/// The destination of written data
+///
+/// InfluxDB 1.x stores data in databases and retention policies, while
+/// InfluxDB 2.x stores data in buckets owned by organizations.
+///
+/// A string is converted to a database with its default retention policy.
+///
+/// ```
+/// # use rinfluxdb_lineprotocol::WriteTarget;
+/// assert_eq!(
+///     WriteTarget::from("house"),
+///     WriteTarget::V1 { database: "house".into(), retention_policy: None },
+/// );
+/// assert_eq!(
+///     WriteTarget::v2("my-org", "house"),
+///     WriteTarget::V2 { organization: "my-org".into(), bucket: "house".into() },
+/// );
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum WriteTarget {
+    /// A database and retention policy in InfluxDB 1.x, written through
+    /// endpoint `/write`
+    V1 {
+        /// The database
+        database: String,
+
+        /// The retention policy, or `None` for the default one
+        retention_policy: Option<String>,
+    },
+
+    /// A bucket in InfluxDB 2.x, written through endpoint `/api/v2/write`
+    V2 {
+        /// The organization name or ID
+        organization: String,
+
+        /// The bucket name or ID
+        bucket: String,
+    },
+}
+
+impl WriteTarget {
+    /// Create a target for a database with its default retention policy
+    pub fn v1<T>(database: T) -> Self
+    where
+        T: Into<String>,
+    {
+        WriteTarget::V1 {
+            database: database.into(),
+            retention_policy: None,
+        }
+    }
+
+    /// Create a target for a bucket in an organization
+    pub fn v2<T, S>(organization: T, bucket: S) -> Self
+    where
+        T: Into<String>,
+        S: Into<String>,
+    {
+        WriteTarget::V2 {
+            organization: organization.into(),
+            bucket: bucket.into(),
+        }
+    }
+}
+
+impl From<&str> for WriteTarget {
+    fn from(database: &str) -> Self {
+        WriteTarget::v1(database)
+    }
+}
+
+impl From<String> for WriteTarget {
+    fn from(database: String) -> Self {
+        WriteTarget::v1(database)
+    }
+}
+
+impl From<&WriteTarget> for WriteTarget {
+    fn from(target: &WriteTarget) -> Self {
+        target.clone()
+    }
+}
+
+/// A summary of the requests sent to write lines
+///
+/// Clients split lines in multiple requests when they exceed the limits set
+/// with [`ClientBuilder::max_request_lines`](crate::blocking::ClientBuilder::max_request_lines)
+/// or [`ClientBuilder::max_request_bytes`](crate::blocking::ClientBuilder::max_request_bytes).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct WriteSummary {
+    /// The number of requests sent
+    pub requests: usize,
+
+    /// The number of lines written
+    pub lines: usize,
+
+    /// The total size of the request bodies
+    pub bytes: usize,
+}
+
+/// The number of nodes that must confirm a write in InfluxDB Enterprise
+/// clusters
+///
+/// ```
+/// # use rinfluxdb_lineprotocol::Consistency;
+/// assert_eq!(Consistency::Quorum.as_str(), "quorum");
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Consistency {
+    /// The write succeeds if any node accepts it, even only as a hinted
+    /// handoff
+    Any,
+
+    /// The write succeeds if one node writes it
+    One,
+
+    /// The write succeeds if a majority of nodes write it
+    Quorum,
+
+    /// The write succeeds only if all nodes write it
+    All,
+}
+
+impl Consistency {
+    /// Return the value of parameter `consistency` for this level
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Consistency::Any => "any",
+            Consistency::One => "one",
+            Consistency::Quorum => "quorum",
+            Consistency::All => "all",
+        }
+    }
+}
+
+/// Create the URL of the write endpoint for a target
+///
+/// Parameter `precision` is omitted for nanoseconds, which is the default.
+/// InfluxDB 2.x does not support minute and hour precisions, and ignores
+/// consistency, which is only used by InfluxDB Enterprise.
+fn write_url(
+    base_url: &Url,
+    target: &WriteTarget,
+    precision: Precision,
+    consistency: Option<Consistency>,
+) -> Result<Url, ClientError> {
+    let url = match target {
+        WriteTarget::V1 { database, retention_policy } => {
+            let mut url = base_url.join("/write")?;
+            {
+                let mut query = url.query_pairs_mut();
+                query.append_pair("db", database);
+                if let Some(retention_policy) = retention_policy {
+                    query.append_pair("rp", retention_policy);
+                }
+                if precision != Precision::Nanoseconds {
+                    query.append_pair("precision", precision.as_str());
+                }
+                if let Some(consistency) = consistency {
+                    query.append_pair("consistency", consistency.as_str());
+                }
+            }
+            url
+        }
+        WriteTarget::V2 { organization, bucket } => {
+            let precision = match precision {
+                Precision::Nanoseconds => "ns",
+                Precision::Microseconds => "us",
+                Precision::Milliseconds => "ms",
+                Precision::Seconds => "s",
+                Precision::Minutes | Precision::Hours => {
+                    return Err(ClientError::UnsupportedPrecision(precision));
+                }
+            };
+            let mut url = base_url.join("/api/v2/write")?;
+            url.query_pairs_mut()
+                .append_pair("org", organization)
+                .append_pair("bucket", bucket)
+                .append_pair("precision", precision);
+            url
+        }
+    };
+    Ok(url)
+}
+
+/// Join lines in a request body, with timestamps in a precision
+fn write_payload(lines: &[Line], precision: Precision) -> String {
+    let strings: Vec<String> = lines
+        .iter()
+        .map(|line| line.with_precision(precision).to_string())
+        .collect();
+    strings.join("\n")
+}
+
+/// Consecutive lines sent in a single request
+#[derive(Debug, PartialEq)]
+struct Batch {
+    /// The position of the first line of the batch
+    start: usize,
+
+    /// The number of lines in the batch
+    lines: usize,
+
+    /// The request body
+    payload: String,
+}
+
+/// Split lines in request bodies of bounded number of lines and size
+///
+/// A line larger than `max_bytes` is sent alone in its own request.
+/// No lines result in a single empty request.
+fn split_payload(
+    lines: &[Line],
+    precision: Precision,
+    unsigned: UnsignedPolicy,
+    max_lines: Option<usize>,
+    max_bytes: Option<usize>,
+) -> Result<Vec<Batch>, ClientError> {
+    let mut batches = Vec::new();
+    let mut current = Batch { start: 0, lines: 0, payload: String::new() };
+    for (index, line) in lines.iter().enumerate() {
+        let line = line
+            .to_line_protocol(precision, unsigned)
+            .map_err(|error| ClientError::InvalidLine { index, error })?;
+        let is_full = current.lines > 0
+            && (max_lines.is_some_and(|max_lines| current.lines >= max_lines)
+                || max_bytes.is_some_and(|max_bytes| current.payload.len() + 1 + line.len() > max_bytes));
+        if is_full {
+            let next = Batch { start: index, lines: 0, payload: String::new() };
+            batches.push(std::mem::replace(&mut current, next));
+        }
+        if current.lines > 0 {
+            current.payload.push('\n');
+        }
+        current.payload.push_str(&line);
+        current.lines += 1;
+    }
+    batches.push(current);
+    Ok(batches)
+}
+
+/// Record the target and the payload of a write on the current span
+///
+/// Write spans declare fields `database`, `measurement` and `payload_bytes`.
+/// The measurement is only recorded when all lines share it.
+fn record_write(target: &WriteTarget, lines: &[Line], payload_bytes: usize) {
+    let span = tracing::Span::current();
+    match target {
+        WriteTarget::V1 { database, .. } => span.record("database", database.as_str()),
+        WriteTarget::V2 { bucket, .. } => span.record("database", bucket.as_str()),
+    };
+    if let Some((first, rest)) = lines.split_first() {
+        if rest.iter().all(|line| line.measurement() == first.measurement()) {
+            span.record("measurement", first.measurement().as_str());
+        }
+    }
+    span.record("payload_bytes", payload_bytes);
+}
+
+/// Notify an observer of the outcome of a write
+fn observe_write(
+    observer: Option<&dyn MetricsObserver>,
+    lines: usize,
+    bytes: usize,
+    result: Result<&(), &ClientError>,
+    elapsed: Duration,
+) {
+    if let Some(observer) = observer {
+        match result {
+            Ok(()) => observer.on_write_success(lines, bytes, elapsed),
+            Err(error) => observer.on_write_failure(lines, bytes, elapsed, error),
+        }
+    }
+}
+
+/// Check whether a client error is likely to be temporary
+///
+/// Errors returned by custom transports are always considered temporary.
+#[cfg(feature = "client-blocking")]
+fn is_transient_error(error: &ClientError) -> bool {
+    match error {
+        ClientError::ReqwestError(error) => is_transient(error),
+        ClientError::TransportError(_) => true,
+        _ => false,
+    }
+}
+
+/// Check whether a request error is likely to be temporary
+#[cfg(not(target_arch = "wasm32"))]
+fn is_transient(error: &reqwest::Error) -> bool {
+    error.is_connect() || error.is_timeout() || error.is_request()
+}
+
+/// Check whether a request error is likely to be temporary
+///
+/// Browsers do not report connection errors separately.
+#[cfg(target_arch = "wasm32")]
+fn is_transient(error: &reqwest::Error) -> bool {
+    error.is_timeout() || error.is_request()
+}
+
+/// Extract the server version and build type from response headers
+fn ping_from_headers(headers: &reqwest::header::HeaderMap) -> Ping {
+    let header = |name: &str| {
+        headers
+            .get(name)
+            .and_then(|value| value.to_str().ok())
+            .map(|value| value.to_string())
+    };
+    Ping {
+        version: header("X-Influxdb-Version"),
+        build: header("X-Influxdb-Build"),
+    }
+}
+
+/// Parse an error response from a write request
+///
+/// InfluxDB 1.x returns errors as `{"error": "..."}`, while InfluxDB 2.x
+/// returns them as `{"code": "...", "message": "..."}`.
+fn parse_error(status: StatusCode, headers: &HeaderMap, text: &str) -> ClientError {
+    if let Some(error) = rate_limited(status, headers) {
+        return error;
+    }
+    match status {
+        StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN => return ClientError::Unauthorized,
+        StatusCode::PAYLOAD_TOO_LARGE => return ClientError::RequestEntityTooLarge,
+        _ => {}
+    }
+
     let response: Result<Response, _> = from_str(text);
     match response {
         Ok(response) => {
-            if response.error.starts_with("field type conflict") {
+            let error = response.error;
+            if error.contains("field type conflict") {
                 ClientError::FieldTypeConflict
-            } else if response.error.starts_with("database not found") {
+            } else if error.starts_with("database not found")
+                || (error.starts_with("bucket") && error.ends_with("not found"))
+            {
                 ClientError::DatabaseNotFound
+            } else if error.contains("points beyond retention policy") {
+                ClientError::PointsBeyondRetentionPolicy {
+                    dropped: dropped_points(&error),
+                }
+            } else if error.contains("max-series-per-database limit exceeded") {
+                ClientError::MaxSeriesPerDatabaseExceeded {
+                    dropped: dropped_points(&error),
+                }
+            } else if error.contains("partial write")
+                || error.contains("unable to parse")
+                || error.contains("failed to parse line protocol")
+            {
+                ClientError::PartialWrite(PartialWriteError::new(error))
             } else {
                 ClientError::Unknown
             }
         }
         Err(_) => ClientError::Unknown,
     }
+}
+
+/// Extract the number of rejected points from a partial write error, such
+/// as `partial write: points beyond retention policy dropped=3`
+fn dropped_points(error: &str) -> Option<u64> {
+    let (_, dropped) = error.rsplit_once("dropped=")?;
+    let digits: String = dropped.chars().take_while(char::is_ascii_digit).collect();
+    digits.parse().ok()
+}
+
+/// Extract the number of written points from an InfluxDB 2.x partial write
+/// error, such as `partial write error (2 written): ...`
+fn written_points(error: &str) -> Option<u64> {
+    let (_, written) = error.split_once("partial write error (")?;
+    let (written, _) = written.split_once(" written)")?;
+    written.parse().ok()
+}
+
+/// Iterate over the lines quoted in an error, such as
+/// `unable to parse 'cpu value=': missing field value`
+fn quoted_lines(error: &str) -> impl Iterator<Item = &str> {
+    error.split("unable to parse '").skip(1).filter_map(|rest| {
+        rest.find("': ").map(|end| &rest[..end])
+    })
+}
+
+/// Iterate over the line numbers in an error, such as
+/// `line 2: no field values found`
+fn numbered_lines(error: &str) -> impl Iterator<Item = usize> + '_ {
+    error.split("line ").skip(1).filter_map(|rest| {
+        let (number, _) = rest.split_once(':')?;
+        number.parse().ok()
+    })
+}
+
+/// Locate the rejected lines of a partial write in the request payload
+///
+/// Positions are shifted by `offset`, the position of the first line of the
+/// payload among all lines being written.
+fn locate_rejected_lines(error: ClientError, payload: &str, offset: usize) -> ClientError {
+    match error {
+        ClientError::PartialWrite(error) => {
+            let mut error = error.locate(payload);
+            error.failed_index = error.failed_index.map(|index| index + offset);
+            for index in &mut error.rejected_indices {
+                *index += offset;
+            }
+            ClientError::PartialWrite(error)
+        }
+        error => error,
+    }
+}
 
+/// Attach the requests already sent to an error interrupting a write
+///
+/// Errors in the first request are returned unchanged, since nothing was
+/// written.
+fn interrupt_write(error: ClientError, sent: WriteSummary) -> ClientError {
+    if sent.requests == 0 {
+        error
+    } else {
+        ClientError::WriteInterrupted {
+            sent,
+            error: Box::new(error),
+        }
+    }
+}
+
+/// Parse header `Retry-After`, either as a number of seconds or as a date
+fn retry_after(headers: &HeaderMap) -> Option<Duration> {
+    parse_retry_after(headers.get(RETRY_AFTER)?.to_str().ok()?)
+}
+
+/// Return an error if a response asks the client to slow down
+///
+/// Status 429 always does, while status 503 does only with header
+/// `Retry-After`.
+fn rate_limited(status: StatusCode, headers: &HeaderMap) -> Option<ClientError> {
+    let is_rate_limited = status == StatusCode::TOO_MANY_REQUESTS
+        || (status == StatusCode::SERVICE_UNAVAILABLE && headers.contains_key(RETRY_AFTER));
+    if is_rate_limited {
+        Some(ClientError::RateLimited {
+            retry_after: retry_after(headers),
+        })
+    } else {
+        None
+    }
 }
 
 #[derive(Debug, Deserialize)]
 struct Response {
+    /// Error message, in field `message` for InfluxDB 2.x
+    #[serde(alias = "message")]
     error: String,
 }
+
+#[cfg(test)]
+mod tests {
+    use reqwest::header::HeaderValue;
+
+    use super::*;
+
+    #[test]
+    fn parse_partial_write_v1() {
+        let error = parse_error(
+            StatusCode::BAD_REQUEST,
+            &HeaderMap::new(),
+            r#"{"error":"partial write: points beyond retention policy dropped=3"}"#,
+        );
+        assert!(matches!(
+            error,
+            ClientError::PointsBeyondRetentionPolicy { dropped: Some(3) },
+        ));
+
+        let error = parse_error(
+            StatusCode::BAD_REQUEST,
+            &HeaderMap::new(),
+            r#"{"error":"partial write: max-series-per-database limit exceeded: (1000000) dropped=1"}"#,
+        );
+        assert!(matches!(
+            error,
+            ClientError::MaxSeriesPerDatabaseExceeded { dropped: Some(1) },
+        ));
+
+        let error = parse_error(
+            StatusCode::BAD_REQUEST,
+            &HeaderMap::new(),
+            r#"{"error":"partial write: max-values-per-tag limit exceeded (100000/100000): measurement=\"cpu\" tag=\"host\" value=\"server\" dropped=2"}"#,
+        );
+        assert!(matches!(
+            error,
+            ClientError::PartialWrite(PartialWriteError { dropped: Some(2), .. }),
+        ));
+    }
+
+    #[test]
+    fn parse_partial_write_v2() {
+        let error = parse_error(
+            StatusCode::BAD_REQUEST,
+            &HeaderMap::new(),
+            r#"{"code":"invalid","message":"partial write error (2 written): unable to parse 'cpu value=': missing field value"}"#,
+        );
+        assert!(matches!(
+            error,
+            ClientError::PartialWrite(PartialWriteError { dropped: None, .. }),
+        ));
+    }
+
+    #[test]
+    fn locate_quoted_lines() {
+        let payload = "cpu value=1\ncpu value=\ncpu value=3\ncpu,host=a value=";
+        let error = parse_error(
+            StatusCode::BAD_REQUEST,
+            &HeaderMap::new(),
+            r#"{"error":"partial write: unable to parse 'cpu value=': missing field value\nunable to parse 'cpu,host=a value=': missing field value dropped=2"}"#,
+        );
+
+        match locate_rejected_lines(error, payload, 0) {
+            ClientError::PartialWrite(error) => {
+                assert_eq!(error.failed_index, Some(1));
+                assert_eq!(error.rejected_indices, vec![1, 3]);
+                assert_eq!(error.dropped, Some(2));
+            }
+            error => panic!("Unexpected error {:?}", error),
+        }
+    }
+
+    #[test]
+    fn locate_numbered_lines() {
+        let payload = "cpu value=1\ncpu value=2\ncpu\ncpu value=4";
+        let error = parse_error(
+            StatusCode::BAD_REQUEST,
+            &HeaderMap::new(),
+            r#"{"code":"invalid","message":"partial write error (3 written): failed to parse line protocol: errors encountered on line(s): line 3: expected field key"}"#,
+        );
+
+        match locate_rejected_lines(error, payload, 0) {
+            ClientError::PartialWrite(error) => {
+                assert_eq!(error.failed_index, Some(2));
+                assert_eq!(error.rejected_indices, vec![2]);
+                assert_eq!(error.dropped, Some(1));
+            }
+            error => panic!("Unexpected error {:?}", error),
+        }
+    }
+
+    fn lines(count: usize) -> Vec<Line> {
+        (0..count)
+            .map(|index| {
+                let mut line = Line::new("measurement");
+                line.insert_field("field", index as f64);
+                line
+            })
+            .collect()
+    }
+
+    #[test]
+    fn split_payload_by_lines() {
+        let lines = lines(4);
+        let batches = split_payload(&lines, Precision::Nanoseconds, UnsignedPolicy::Unchanged, Some(3), None).unwrap();
+
+        let starts: Vec<usize> = batches.iter().map(|batch| batch.start).collect();
+        let counts: Vec<usize> = batches.iter().map(|batch| batch.lines).collect();
+        assert_eq!(starts, vec![0, 3]);
+        assert_eq!(counts, vec![3, 1]);
+        assert_eq!(batches[0].payload, write_payload(&lines[..3], Precision::Nanoseconds));
+        assert_eq!(batches[1].payload, write_payload(&lines[3..], Precision::Nanoseconds));
+    }
+
+    #[test]
+    fn split_payload_by_bytes() {
+        let lines = lines(5);
+        let max_bytes = write_payload(&lines[..2], Precision::Nanoseconds).len();
+        let batches = split_payload(&lines, Precision::Nanoseconds, UnsignedPolicy::Unchanged, None, Some(max_bytes)).unwrap();
+
+        let starts: Vec<usize> = batches.iter().map(|batch| batch.start).collect();
+        assert_eq!(starts, vec![0, 2, 4]);
+        assert!(batches.iter().all(|batch| batch.payload.len() <= max_bytes));
+
+        // Lines larger than the limit are sent alone
+        let batches = split_payload(&lines, Precision::Nanoseconds, UnsignedPolicy::Unchanged, None, Some(1)).unwrap();
+        assert_eq!(batches.len(), 5);
+    }
+
+    #[test]
+    fn split_payload_invalid_line() {
+        let mut lines = lines(3);
+        lines[1].insert_field("counter", u64::MAX);
+
+        let result = split_payload(&lines, Precision::Nanoseconds, UnsignedPolicy::CheckedInteger, None, None);
+
+        assert!(matches!(
+            result,
+            Err(ClientError::InvalidLine { index: 1, error: ValidationError::UnsignedOutOfRange(_) }),
+        ));
+    }
+
+    #[test]
+    fn split_payload_empty() {
+        let batches = split_payload(&[], Precision::Nanoseconds, UnsignedPolicy::Unchanged, Some(3), Some(100)).unwrap();
+        assert_eq!(batches, vec![Batch { start: 0, lines: 0, payload: String::new() }]);
+    }
+
+    #[test]
+    fn parse_status_errors() {
+        let error = parse_error(
+            StatusCode::UNAUTHORIZED,
+            &HeaderMap::new(),
+            r#"{"code":"unauthorized","message":"unauthorized access"}"#,
+        );
+        assert!(matches!(error, ClientError::Unauthorized));
+
+        let error = parse_error(StatusCode::PAYLOAD_TOO_LARGE, &HeaderMap::new(), "");
+        assert!(matches!(error, ClientError::RequestEntityTooLarge));
+
+        let mut headers = HeaderMap::new();
+        headers.insert(RETRY_AFTER, HeaderValue::from_static("30"));
+        let error = parse_error(StatusCode::TOO_MANY_REQUESTS, &headers, "");
+        assert!(matches!(
+            error,
+            ClientError::RateLimited { retry_after: Some(duration) } if duration == Duration::from_secs(30),
+        ));
+
+        let error = parse_error(StatusCode::SERVICE_UNAVAILABLE, &headers, "");
+        assert!(matches!(error, ClientError::RateLimited { .. }));
+
+        let error = parse_error(StatusCode::SERVICE_UNAVAILABLE, &HeaderMap::new(), "");
+        assert!(matches!(error, ClientError::Unknown));
+    }
+
+    #[test]
+    fn parse_retry_after_date() {
+        let mut headers = HeaderMap::new();
+        headers.insert(RETRY_AFTER, HeaderValue::from_static("Wed, 21 Oct 2015 07:28:00 GMT"));
+        assert_eq!(retry_after(&headers), Some(Duration::from_secs(0)));
+    }
+}
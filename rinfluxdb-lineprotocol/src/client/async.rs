@@ -4,19 +4,58 @@
 // https://opensource.org/licenses/MIT
 // https://opensource.org/licenses/Apache-2.0
 
+use std::fs::remove_file;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
 use tracing::*;
 
+use reqwest::header::SET_COOKIE;
 use reqwest::Client as ReqwestClient;
 use reqwest::ClientBuilder as ReqwestClientBuilder;
 use reqwest::RequestBuilder as ReqwestRequestBuilder;
 use reqwest::Response as ReqwestResponse;
+#[cfg(not(target_arch = "wasm32"))]
+use reqwest::Proxy;
+use reqwest::StatusCode;
+#[cfg(any(
+    feature = "default-tls",
+    feature = "native-tls",
+    feature = "native-tls-vendored",
+    feature = "rustls-tls",
+    feature = "rustls-tls-native-roots",
+))]
+use reqwest::Certificate;
+#[cfg(any(
+    feature = "native-tls",
+    feature = "native-tls-vendored",
+    feature = "rustls-tls",
+    feature = "rustls-tls-native-roots",
+))]
+use reqwest::Identity;
+
+#[cfg(not(target_arch = "wasm32"))]
+use tokio::time::sleep;
+
+#[cfg(target_arch = "wasm32")]
+use gloo_timers::future::sleep;
 
 use url::Url;
 
+use rinfluxdb_types::{
+    apply_authentication, cookie_from_set_cookie_headers, record_response, Authentication, Failover, FailoverStrategy, MetricsObserver, Ping, Precision, RetryPolicy, Session,
+    Stopwatch, SIGNIN_PATH,
+};
+
 use async_trait::async_trait;
 
-use super::super::Line;
-use super::{parse_error, ClientError};
+use super::super::{normalize_lines, BufferMetrics, IntoLine, Line, LineBuffer, OverflowError, OverflowPolicy, UnsignedPolicy};
+use super::{
+    interrupt_write, is_transient, locate_rejected_lines, ping_from_headers, observe_write, parse_error, record_write, retry_after, split_payload, user_agent, write_payload, write_url, Batch, ClientError, Consistency,
+    RateLimit, RateLimiter, WriteSummary, WriteTarget, DEFAULT_BATCH_SIZE, DEFAULT_BUFFER_CAPACITY,
+};
+use super::sink::{read_batches, replay_path, take_batches};
 
 /// A client for sending data with Influx Line Protocol queries in a convenient
 /// way
@@ -26,7 +65,7 @@ use super::{parse_error, ClientError};
 /// use rinfluxdb_lineprotocol::LineBuilder;
 /// use rinfluxdb_lineprotocol::r#async::Client;
 ///
-/// # async_std::task::block_on(async {
+/// # async fn example() -> Result<(), rinfluxdb_lineprotocol::ClientError> {
 /// let client = Client::new(
 ///     Url::parse("https://example.com/")?,
 ///     Some(("username", "password")),
@@ -44,14 +83,376 @@ use super::{parse_error, ClientError};
 ///
 /// client.send("database", &lines).await?;
 /// # Ok::<(), rinfluxdb_lineprotocol::ClientError>(())
-/// # })?;
-/// # Ok::<(), rinfluxdb_lineprotocol::ClientError>(())
+/// # }
 /// ```
 #[derive(Debug)]
 pub struct Client {
     client: ReqwestClient,
     base_url: Url,
-    credentials: Option<(String, String)>,
+    fallback_urls: Vec<Url>,
+    failover: Failover,
+    authentication: Authentication,
+    session: Session,
+    retry_policy: RetryPolicy,
+    metrics_observer: Option<Arc<dyn MetricsObserver>>,
+    consistency: Option<Consistency>,
+    max_request_lines: Option<usize>,
+    max_request_bytes: Option<usize>,
+    rate_limiter: Option<RateLimiter>,
+    normalize: bool,
+    unsigned_policy: UnsignedPolicy,
+}
+
+/// A builder for clients
+///
+/// Clients send a `User-Agent` header containing the crate version, such as
+/// `rinfluxdb/0.2.0`.
+/// An application identifier can be appended to it, or it can be replaced
+/// altogether.
+///
+/// ```
+/// use url::Url;
+/// use rinfluxdb_lineprotocol::r#async::ClientBuilder;
+///
+/// let client = ClientBuilder::new(Url::parse("https://example.com/")?)
+///     .credentials("username", "password")
+///     .application("collector/1.4.2")
+///     .build()?;
+/// # Ok::<(), rinfluxdb_lineprotocol::ClientError>(())
+/// ```
+#[derive(Debug)]
+pub struct ClientBuilder {
+    base_url: Url,
+    fallback_urls: Vec<Url>,
+    failover_strategy: FailoverStrategy,
+    authentication: Authentication,
+    retry_policy: RetryPolicy,
+    metrics_observer: Option<Arc<dyn MetricsObserver>>,
+    consistency: Option<Consistency>,
+    max_request_lines: Option<usize>,
+    max_request_bytes: Option<usize>,
+    rate_limit: Option<RateLimit>,
+    normalize: bool,
+    unsigned_policy: UnsignedPolicy,
+    user_agent: Option<String>,
+    http: ReqwestClientBuilder,
+    application: Option<String>,
+}
+
+impl ClientBuilder {
+    /// Create a new builder for a client to an InfluxDB server
+    pub fn new(base_url: Url) -> Self {
+        Self {
+            base_url,
+            fallback_urls: Vec::new(),
+            failover_strategy: FailoverStrategy::default(),
+            authentication: Authentication::None,
+            retry_policy: RetryPolicy::never(),
+            metrics_observer: None,
+            consistency: None,
+            max_request_lines: None,
+            max_request_bytes: None,
+            rate_limit: None,
+            normalize: false,
+            unsigned_policy: UnsignedPolicy::default(),
+            user_agent: None,
+            application: None,
+            http: ReqwestClientBuilder::new(),
+        }
+    }
+
+    /// Set username and password for servers that require authentication
+    pub fn credentials<T, S>(mut self, username: T, password: S) -> Self
+    where
+        T: Into<String>,
+        S: Into<String>,
+    {
+        self.authentication = Authentication::Basic {
+            username: username.into(),
+            password: password.into(),
+        };
+        self
+    }
+
+    /// Set the API token for servers that require token authentication
+    ///
+    /// The token is sent in header `Authorization: Token <token>`, as
+    /// required by InfluxDB 2.x.
+    pub fn token<T>(mut self, token: T) -> Self
+    where
+        T: Into<String>,
+    {
+        self.authentication = Authentication::Token(token.into());
+        self
+    }
+
+    /// Set the authentication method
+    pub fn authentication(mut self, authentication: Authentication) -> Self {
+        self.authentication = authentication;
+        self
+    }
+
+    /// Add URLs of other servers to fail over to
+    ///
+    /// Writes are sent to the next URL when the connection fails or when the
+    /// server responds with a 5xx status code, after retrying them according
+    /// to the retry policy.
+    /// Other requests are always sent to the base URL.
+    pub fn fallback_urls<I>(mut self, urls: I) -> Self
+    where
+        I: IntoIterator<Item = Url>,
+    {
+        self.fallback_urls.extend(urls);
+        self
+    }
+
+    /// Set the strategy for choosing among the base URL and the fallback URLs
+    ///
+    /// By default, requests are sent to the base URL first.
+    pub fn failover_strategy(mut self, strategy: FailoverStrategy) -> Self {
+        self.failover_strategy = strategy;
+        self
+    }
+
+    /// Set the policy for retrying failed requests
+    ///
+    /// By default, requests are never retried.
+    pub fn retry_policy(mut self, retry_policy: RetryPolicy) -> Self {
+        self.retry_policy = retry_policy;
+        self
+    }
+
+    /// Notify an observer of writes and queries sent by the client
+    ///
+    /// By default, no observer is notified.
+    pub fn metrics_observer<T>(mut self, observer: T) -> Self
+    where
+        T: MetricsObserver + 'static,
+    {
+        self.metrics_observer = Some(Arc::new(observer));
+        self
+    }
+
+    /// Set the consistency level of writes to InfluxDB Enterprise clusters
+    ///
+    /// By default, the server's default consistency level is used.
+    /// It is ignored for InfluxDB 2.x targets.
+    pub fn consistency(mut self, consistency: Consistency) -> Self {
+        self.consistency = Some(consistency);
+        self
+    }
+
+    /// Set the maximal number of lines sent in a single request
+    ///
+    /// Lines are split in multiple requests when they exceed it.
+    /// By default, all lines are sent in a single request.
+    pub fn max_request_lines(mut self, max_request_lines: usize) -> Self {
+        self.max_request_lines = Some(max_request_lines.max(1));
+        self
+    }
+
+    /// Set the maximal size in bytes of the body of a single request
+    ///
+    /// Lines are split in multiple requests when they exceed it, but a
+    /// single line larger than the limit is still sent in its own request.
+    /// By default, all lines are sent in a single request.
+    pub fn max_request_bytes(mut self, max_request_bytes: usize) -> Self {
+        self.max_request_bytes = Some(max_request_bytes);
+        self
+    }
+
+    /// Set a limit on the rate of writes
+    ///
+    /// Each request waits until it fits within the limit before being sent,
+    /// so that bursts of writes do not exceed the rate limits of the server.
+    /// By default, writes are not limited.
+    pub fn rate_limit(mut self, rate_limit: RateLimit) -> Self {
+        self.rate_limit = Some(rate_limit);
+        self
+    }
+
+    /// Sort lines and merge lines for the same point before sending them
+    ///
+    /// See [`normalize_lines`](crate::normalize_lines) for details.
+    /// Line numbers in [`ClientError::PartialWrite`] refer to the normalized
+    /// lines.
+    /// By default, lines are sent as they are.
+    pub fn normalize(mut self, normalize: bool) -> Self {
+        self.normalize = normalize;
+        self
+    }
+
+    /// Set how to write unsigned integer fields
+    ///
+    /// Writes fail with [`ClientError::InvalidLine`] before sending any
+    /// request when a field is rejected by the policy.
+    /// By default, unsigned integers are written as they are.
+    pub fn unsigned_policy(mut self, unsigned_policy: UnsignedPolicy) -> Self {
+        self.unsigned_policy = unsigned_policy;
+        self
+    }
+
+    /// Set the timeout for whole requests, from connecting to reading the
+    /// response body
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.http = self.http.timeout(timeout);
+        self
+    }
+
+    /// Set the timeout for connecting to the server
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn connect_timeout(mut self, timeout: Duration) -> Self {
+        self.http = self.http.connect_timeout(timeout);
+        self
+    }
+
+    /// Set the maximum number of idle connections kept open to each host
+    ///
+    /// By default, there is no limit.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn pool_max_idle_per_host(mut self, max: usize) -> Self {
+        self.http = self.http.pool_max_idle_per_host(max);
+        self
+    }
+
+    /// Set how long idle connections are kept open
+    ///
+    /// By default, idle connections are closed after 90 seconds.
+    /// Passing `None` keeps them open indefinitely.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn pool_idle_timeout<D>(mut self, timeout: D) -> Self
+    where
+        D: Into<Option<Duration>>,
+    {
+        self.http = self.http.pool_idle_timeout(timeout);
+        self
+    }
+
+    /// Set the interval of TCP keepalive probes
+    ///
+    /// By default, TCP keepalive is disabled.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn tcp_keepalive<D>(mut self, interval: D) -> Self
+    where
+        D: Into<Option<Duration>>,
+    {
+        self.http = self.http.tcp_keepalive(interval);
+        self
+    }
+
+    /// Use HTTP/2 without negotiating it first
+    ///
+    /// This only works with servers known to support HTTP/2, and it allows
+    /// sending concurrent requests over a single connection.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn http2_prior_knowledge(mut self) -> Self {
+        self.http = self.http.http2_prior_knowledge();
+        self
+    }
+
+    /// Send requests through a proxy
+    ///
+    /// By default, proxies are read from the environment variables
+    /// `HTTP_PROXY`, `HTTPS_PROXY` and `ALL_PROXY`.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn proxy(mut self, proxy: Proxy) -> Self {
+        self.http = self.http.proxy(proxy);
+        self
+    }
+
+    /// Ignore proxies set in the environment
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn no_proxy(mut self) -> Self {
+        self.http = self.http.no_proxy();
+        self
+    }
+
+    /// Trust an additional root certificate, such as a private CA
+    #[cfg(any(
+        feature = "default-tls",
+        feature = "native-tls",
+        feature = "native-tls-vendored",
+        feature = "rustls-tls",
+        feature = "rustls-tls-native-roots",
+    ))]
+    pub fn add_root_certificate(mut self, certificate: Certificate) -> Self {
+        self.http = self.http.add_root_certificate(certificate);
+        self
+    }
+
+    /// Authenticate to the server with a client certificate
+    ///
+    /// This requires either feature `native-tls` or a Rustls backend.
+    #[cfg(any(
+        feature = "native-tls",
+        feature = "native-tls-vendored",
+        feature = "rustls-tls",
+        feature = "rustls-tls-native-roots",
+    ))]
+    pub fn identity(mut self, identity: Identity) -> Self {
+        self.http = self.http.identity(identity);
+        self
+    }
+
+    /// Accept invalid server certificates
+    ///
+    /// This is dangerous, and should only be used for testing against servers
+    /// with self-signed certificates.
+    #[cfg(any(
+        feature = "default-tls",
+        feature = "native-tls",
+        feature = "native-tls-vendored",
+        feature = "rustls-tls",
+        feature = "rustls-tls-native-roots",
+    ))]
+    pub fn danger_accept_invalid_certs(mut self, accept: bool) -> Self {
+        self.http = self.http.danger_accept_invalid_certs(accept);
+        self
+    }
+
+    /// Replace the default `User-Agent` header
+    pub fn user_agent<T>(mut self, user_agent: T) -> Self
+    where
+        T: Into<String>,
+    {
+        self.user_agent = Some(user_agent.into());
+        self
+    }
+
+    /// Append an application identifier to the `User-Agent` header
+    pub fn application<T>(mut self, application: T) -> Self
+    where
+        T: Into<String>,
+    {
+        self.application = Some(application.into());
+        self
+    }
+
+    /// Build the client
+    pub fn build(self) -> Result<Client, ClientError> {
+        let client = self
+            .http
+            .user_agent(user_agent(self.user_agent, self.application))
+            .build()?;
+
+        Ok(Client {
+            client,
+            base_url: self.base_url,
+            fallback_urls: self.fallback_urls,
+            failover: Failover::new(self.failover_strategy),
+            authentication: self.authentication,
+            session: Session::default(),
+            retry_policy: self.retry_policy,
+            metrics_observer: self.metrics_observer,
+            consistency: self.consistency,
+            max_request_lines: self.max_request_lines,
+            max_request_bytes: self.max_request_bytes,
+            rate_limiter: self.rate_limit.map(RateLimiter::new),
+            normalize: self.normalize,
+            unsigned_policy: self.unsigned_policy,
+        })
+    }
 }
 
 impl Client {
@@ -67,41 +468,617 @@ impl Client {
         T: Into<String>,
         S: Into<String>,
     {
-        let client = ReqwestClientBuilder::new()
-            .build()?;
+        let mut builder = ClientBuilder::new(base_url);
+        if let Some((username, password)) = credentials {
+            builder = builder.credentials(username, password);
+        }
+        builder.build()
+    }
 
-        let credentials = credentials
-            .map(|(username, password)| (username.into(), password.into()));
+    /// Sends data using the Influx Line Protocol
+    ///
+    /// The target can be a database name, or a [`WriteTarget`](crate::WriteTarget)
+    /// for retention policies or InfluxDB 2.x buckets.
+    ///
+    /// Lines are split in multiple requests if they exceed the limits set on
+    /// the builder, and requests are sent in order until one fails.
+    /// Lines sent in earlier requests are written even if a later request
+    /// fails, in which case the error is returned inside
+    /// [`ClientError::WriteInterrupted`] together with the requests already
+    /// sent.
+    /// Positions in [`PartialWriteError`](crate::PartialWriteError) refer to
+    /// all lines.
+    #[instrument(
+        name = "Sending data using the Influx Line Protocol",
+        skip(self, target, lines),
+        fields(
+            lines = lines.len(),
+            database = field::Empty,
+            measurement = field::Empty,
+            payload_bytes = field::Empty,
+            status = field::Empty,
+            elapsed_ms = field::Empty,
+        ),
+    )]
+    pub async fn send<T>(&self, target: T, lines: &[Line]) -> Result<WriteSummary, ClientError>
+    where
+        T: Into<WriteTarget>,
+    {
+        self.write(&target.into(), lines, Precision::Nanoseconds).await
+    }
 
-        Ok(Self {
-            client,
-            base_url,
-            credentials,
-        })
+    /// Sends data using the Influx Line Protocol to a retention policy
+    ///
+    /// If `retention_policy` is `None`, data is written to the default
+    /// retention policy of the database.
+    #[instrument(
+        name = "Sending data using the Influx Line Protocol",
+        skip(self, database, retention_policy, lines),
+        fields(
+            lines = lines.len(),
+            database = field::Empty,
+            measurement = field::Empty,
+            payload_bytes = field::Empty,
+            status = field::Empty,
+            elapsed_ms = field::Empty,
+        ),
+    )]
+    pub async fn send_to(
+        &self,
+        database: &str,
+        retention_policy: Option<&str>,
+        lines: &[Line],
+    ) -> Result<WriteSummary, ClientError> {
+        let target = WriteTarget::V1 {
+            database: database.to_string(),
+            retention_policy: retention_policy.map(String::from),
+        };
+        self.write(&target, lines, Precision::Nanoseconds).await
     }
 
-    /// Sends data using the Influx Line Protocol
+    /// Sends data using the Influx Line Protocol with timestamps in a
+    /// precision
+    ///
+    /// Timestamps are truncated to the precision, and the server is told to
+    /// interpret them accordingly.
     #[instrument(
         name = "Sending data using the Influx Line Protocol",
-        skip(self, database, lines),
+        skip(self, target, lines),
+        fields(
+            lines = lines.len(),
+            database = field::Empty,
+            measurement = field::Empty,
+            payload_bytes = field::Empty,
+            status = field::Empty,
+            elapsed_ms = field::Empty,
+        ),
     )]
-    pub async fn send(&self, database: &str, lines: &[Line]) -> Result<(), ClientError> {
-        let mut request = self.client
-                .line_protocol(&self.base_url, database, lines)?;
+    pub async fn send_with_precision<T>(
+        &self,
+        target: T,
+        lines: &[Line],
+        precision: Precision,
+    ) -> Result<WriteSummary, ClientError>
+    where
+        T: Into<WriteTarget>,
+    {
+        self.write(&target.into(), lines, precision).await
+    }
 
-        if let Some((username, password)) = &self.credentials {
-            request = request.basic_auth(username, Some(password));
+    async fn write(
+        &self,
+        target: &WriteTarget,
+        lines: &[Line],
+        precision: Precision,
+    ) -> Result<WriteSummary, ClientError> {
+        let normalized_lines;
+        let lines = if self.normalize {
+            normalized_lines = normalize_lines(lines, precision);
+            &normalized_lines
+        } else {
+            lines
+        };
+
+        let batches = split_payload(
+            lines,
+            precision,
+            self.unsigned_policy,
+            self.max_request_lines,
+            self.max_request_bytes,
+        )?;
+        let bytes = batches.iter().map(|batch| batch.payload.len()).sum();
+        record_write(target, lines, bytes);
+
+        debug!("Sending {} lines in {} requests", lines.len(), batches.len());
+
+        let mut summary = WriteSummary::default();
+        for batch in &batches {
+            if let Some(rate_limiter) = &self.rate_limiter {
+                let delay = rate_limiter.reserve(batch.lines);
+                if !delay.is_zero() {
+                    debug!("Waiting {:?} for rate limit", delay);
+                    sleep(delay).await;
+                }
+            }
+
+            let observer = self.metrics_observer.as_deref();
+            if let Some(observer) = observer {
+                observer.on_write_start(batch.lines, batch.payload.len());
+            }
+            let stopwatch = Stopwatch::start();
+            let result = self.post(target, batch, precision).await;
+            observe_write(observer, batch.lines, batch.payload.len(), result.as_ref(), stopwatch.elapsed());
+            if let Err(error) = result {
+                return Err(interrupt_write(error, summary));
+            }
+
+            summary.requests += 1;
+            summary.lines += batch.lines;
+            summary.bytes += batch.payload.len();
         }
 
-        debug!("Sending {} lines to {}", lines.len(), self.base_url);
-        trace!("Request: {:?}", request);
+        Ok(summary)
+    }
+
+    /// Send a batch of lines and check the response
+    ///
+    /// If the session expired, the client signs in again and sends the
+    /// batch once more.
+    async fn post(
+        &self,
+        target: &WriteTarget,
+        batch: &Batch,
+        precision: Precision,
+    ) -> Result<(), ClientError> {
+        let cookie = self.session_cookie().await?;
+        match self.post_with_cookie(target, batch, precision, cookie.as_deref()).await {
+            Err(ClientError::Unauthorized) if cookie.is_some() => {
+                debug!("Session expired, signing in again");
+                self.session.clear();
+                let cookie = self.session_cookie().await?;
+                self.post_with_cookie(target, batch, precision, cookie.as_deref()).await
+            }
+            result => result,
+        }
+    }
+
+    /// Send a batch of lines with a session cookie and check the response
+    async fn post_with_cookie(
+        &self,
+        target: &WriteTarget,
+        batch: &Batch,
+        precision: Precision,
+        cookie: Option<&str>,
+    ) -> Result<(), ClientError> {
+        let response = send_with_failover(&self.base_urls(), &self.retry_policy, |base_url| {
+            let url = write_url(base_url, target, precision, self.consistency)?;
+            let request = self.client
+                .post(url)
+                .body(batch.payload.clone());
+
+            Ok(apply_authentication(request, &self.authentication, cookie))
+        }).await?;
 
-        let response = request.send().await?;
+        response
+            .process_line_protocol_response().await
+            .map_err(|error| locate_rejected_lines(error, &batch.payload, batch.start))?;
 
+        Ok(())
+    }
+
+    /// Return the session cookie, signing in if there is no session yet
+    ///
+    /// `None` is returned if the client does not use session authentication.
+    async fn session_cookie(&self) -> Result<Option<String>, ClientError> {
+        let (username, password) = match &self.authentication {
+            Authentication::Session { username, password } => (username, password),
+            _ => return Ok(None),
+        };
+        if let Some(cookie) = self.session.cookie() {
+            return Ok(Some(cookie));
+        }
+
+        debug!("Signing in as {}", username);
+        let response = send_with_failover(&self.base_urls(), &self.retry_policy, |base_url| {
+            Ok(self.client
+                .post(base_url.join(SIGNIN_PATH)?)
+                .basic_auth(username, Some(password)))
+        }).await?;
+        let cookie = cookie_from_set_cookie_headers(
+            response.headers().get_all(SET_COOKIE).iter().filter_map(|value| value.to_str().ok()),
+        );
         response.process_line_protocol_response().await?;
 
+        let cookie = cookie.ok_or(ClientError::Unauthorized)?;
+        self.session.set_cookie(cookie.clone());
+        Ok(Some(cookie))
+    }
+
+    /// Return the base URL and the fallback URLs in the order they are tried
+    fn base_urls(&self) -> Vec<&Url> {
+        let urls: Vec<&Url> = std::iter::once(&self.base_url)
+            .chain(self.fallback_urls.iter())
+            .collect();
+        self.failover
+            .order(urls.len())
+            .into_iter()
+            .map(|index| urls[index])
+            .collect()
+    }
+
+    /// Check that the server is reachable, and return its version and build type
+    ///
+    /// A request is sent to `/ping`, or to `/health` if the server does not
+    /// provide it, as is the case for some InfluxDB 2.x deployments.
+    /// Requests are not retried.
+    pub async fn ping(&self) -> Result<Ping, ClientError> {
+        let mut response = send_request(self.client.get(self.base_url.join("/ping")?)).await?;
+        if response.status() == StatusCode::NOT_FOUND {
+            debug!("Endpoint /ping not found, falling back to /health");
+            response = send_request(self.client.get(self.base_url.join("/health")?)).await?;
+        }
+        let response = response.error_for_status()?;
+        Ok(ping_from_headers(response.headers()))
+    }
+
+    /// Check whether the server is reachable and healthy
+    pub async fn is_healthy(&self) -> bool {
+        self.ping().await.is_ok()
+    }
+}
+
+/// A builder for batch writers
+///
+/// By default, lines are sent in batches of 5000, without any time-based
+/// flushing, and up to 50000 lines are buffered when the server cannot be
+/// reached.
+#[derive(Debug)]
+pub struct BatchWriterBuilder<'a> {
+    client: &'a Client,
+    target: WriteTarget,
+    batch_size: usize,
+    flush_interval: Option<Duration>,
+    capacity: usize,
+    overflow_policy: OverflowPolicy,
+}
+
+impl<'a> BatchWriterBuilder<'a> {
+    /// Create a new builder for a batch writer to a target
+    ///
+    /// The target can be a database name, or a [`WriteTarget`](crate::WriteTarget)
+    /// for retention policies or InfluxDB 2.x buckets.
+    pub fn new<T>(client: &'a Client, target: T) -> Self
+    where
+        T: Into<WriteTarget>,
+    {
+        Self {
+            client,
+            target: target.into(),
+            batch_size: DEFAULT_BATCH_SIZE,
+            flush_interval: None,
+            capacity: DEFAULT_BUFFER_CAPACITY,
+            overflow_policy: OverflowPolicy::default(),
+        }
+    }
+
+    /// Set the retention policy lines are written to
+    ///
+    /// This has no effect on InfluxDB 2.x targets.
+    pub fn retention_policy<T>(mut self, retention_policy: T) -> Self
+    where
+        T: Into<String>,
+    {
+        if let WriteTarget::V1 { retention_policy: target_policy, .. } = &mut self.target {
+            *target_policy = Some(retention_policy.into());
+        }
+        self
+    }
+
+    /// Set the number of lines that triggers a flush
+    pub fn batch_size(mut self, batch_size: usize) -> Self {
+        self.batch_size = batch_size.max(1);
+        self
+    }
+
+    /// Set the maximal time between flushes
+    ///
+    /// The interval is checked when writing lines, no flush happens while
+    /// the writer is idle.
+    pub fn flush_interval(mut self, flush_interval: Duration) -> Self {
+        self.flush_interval = Some(flush_interval);
+        self
+    }
+
+    /// Set the maximal number of lines kept while the server cannot be reached
+    ///
+    /// The capacity is never smaller than the batch size.
+    pub fn capacity(mut self, capacity: usize) -> Self {
+        self.capacity = capacity;
+        self
+    }
+
+    /// Set what to do when the buffer is full
+    pub fn overflow_policy(mut self, overflow_policy: OverflowPolicy) -> Self {
+        self.overflow_policy = overflow_policy;
+        self
+    }
+
+    /// Build the batch writer
+    pub fn build(self) -> BatchWriter<'a> {
+        BatchWriter {
+            client: self.client,
+            target: self.target,
+            buffer: LineBuffer::new(self.capacity.max(self.batch_size), self.overflow_policy),
+            batch_size: self.batch_size,
+            flush_interval: self.flush_interval,
+            last_flush: Instant::now(),
+        }
+    }
+}
+
+/// A writer buffering lines and sending them in batches
+///
+/// Lines are sent when the batch size is reached, when the flush interval
+/// has elapsed, when calling [`flush()`](BatchWriter::flush), and when
+/// calling [`close()`](BatchWriter::close).
+/// If sending fails, lines are kept in the buffer and sent with the next
+/// batch; once the buffer is full the overflow policy applies.
+///
+/// Since sending is asynchronous, it cannot happen when the writer is
+/// dropped, so the writer must be closed with
+/// [`close()`](BatchWriter::close) once done.
+/// Lines still buffered when the writer is dropped without being closed are
+/// lost, and only reported in the log.
+///
+/// ```no_run
+/// # use std::time::Duration;
+/// # use url::Url;
+/// # use rinfluxdb_lineprotocol::LineBuilder;
+/// use rinfluxdb_lineprotocol::r#async::{BatchWriterBuilder, Client};
+///
+/// # async fn example() -> Result<(), rinfluxdb_lineprotocol::ClientError> {
+/// let client = Client::new(Url::parse("https://example.com/")?, Some(("username", "password")))?;
+///
+/// let mut writer = BatchWriterBuilder::new(&client, "database")
+///     .batch_size(100)
+///     .flush_interval(Duration::from_secs(10))
+///     .build();
+///
+/// for value in 0..1000 {
+///     writer.write(
+///         LineBuilder::new("measurement")
+///             .insert_field("field", value as f64),
+///     ).await?;
+/// }
+///
+/// writer.close().await?;
+/// # Ok::<(), rinfluxdb_lineprotocol::ClientError>(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct BatchWriter<'a> {
+    client: &'a Client,
+    target: WriteTarget,
+    buffer: LineBuffer,
+    batch_size: usize,
+    flush_interval: Option<Duration>,
+    last_flush: Instant,
+}
+
+impl<'a> BatchWriter<'a> {
+    /// Buffer a line, sending the batch if it is complete
+    ///
+    /// If the buffer is full and the overflow policy is to block, the
+    /// buffered lines are sent first.
+    /// If that fails, the line is returned inside
+    /// [`OverflowError::Full`](crate::OverflowError::Full).
+    /// Otherwise the line is buffered, and failures to send the batch are
+    /// logged, but not returned.
+    pub async fn write<T>(&mut self, line: T) -> Result<(), ClientError>
+    where
+        T: IntoLine,
+    {
+        match self.buffer.push(line.into_line()) {
+            Ok(()) => {}
+            Err(OverflowError::Full(line)) => {
+                if let Err(error) = self.flush().await {
+                    warn!("Could not flush full buffer: {}", error);
+                    return Err(OverflowError::Full(line).into());
+                }
+                self.buffer.push(*line)?;
+            }
+            Err(error) => return Err(error.into()),
+        }
+
+        if self.buffer.len() >= self.batch_size || self.is_interval_elapsed() {
+            // The line is already buffered, so it is sent with the next batch
+            if let Err(error) = self.flush().await {
+                warn!("Could not flush {} lines: {}", self.buffer.len(), error);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Send all buffered lines
+    pub async fn flush(&mut self) -> Result<(), ClientError> {
+        self.last_flush = Instant::now();
+        if self.buffer.is_empty() {
+            return Ok(());
+        }
+
+        self.client.send(&self.target, self.buffer.make_contiguous()).await?;
+        self.buffer.clear();
+
         Ok(())
     }
+
+    /// Send all buffered lines and close the writer
+    ///
+    /// If sending fails, the error is returned and the buffered lines are
+    /// discarded.
+    /// Call [`flush()`](BatchWriter::flush) first to retry sending them.
+    pub async fn close(mut self) -> Result<(), ClientError> {
+        let result = self.flush().await;
+        self.buffer.clear();
+        result
+    }
+
+    /// Return the number of buffered lines
+    pub fn len(&self) -> usize {
+        self.buffer.len()
+    }
+
+    /// Check whether the buffer is empty
+    pub fn is_empty(&self) -> bool {
+        self.buffer.is_empty()
+    }
+
+    /// Return the counters of dropped and spilled lines
+    pub fn metrics(&self) -> BufferMetrics {
+        self.buffer.metrics()
+    }
+
+    fn is_interval_elapsed(&self) -> bool {
+        self.flush_interval
+            .map(|interval| self.last_flush.elapsed() >= interval)
+            .unwrap_or(false)
+    }
+}
+
+impl<'a> Drop for BatchWriter<'a> {
+    fn drop(&mut self) {
+        if !self.buffer.is_empty() {
+            error!("Dropping batch writer with {} unsent lines, it was not closed", self.buffer.len());
+        }
+    }
+}
+
+/// Upload the lines buffered by a [`FileSink`](crate::FileSink)
+///
+/// The file is first renamed to a replay file, with extension `.replay`
+/// appended, so that lines appended to the file while it is being replayed
+/// are kept for the next replay.
+/// Lines are sent to their targets in batches, and the replay file is
+/// deleted once all of them have been sent.
+/// If sending fails, the replay file is left untouched and the next replay
+/// sends it before the file, including the lines that were already sent,
+/// which InfluxDB stores only once, since they have the same timestamps.
+///
+/// Return the number of lines sent.
+#[instrument(name = "Replaying buffered lines", skip(path, client))]
+pub async fn replay<P>(path: P, client: &Client) -> Result<usize, ClientError>
+where
+    P: AsRef<Path>,
+{
+    let path = path.as_ref();
+    let replay_path = replay_path(path);
+    let mut count = 0;
+
+    // A replay file is left by a failed replay, and its lines are older
+    if replay_path.exists() {
+        count += replay_file(&replay_path, client).await?;
+    }
+    if take_batches(path, &replay_path)? {
+        count += replay_file(&replay_path, client).await?;
+    }
+
+    debug!("Replayed {} lines from {}", count, path.display());
+    Ok(count)
+}
+
+/// Send the lines in a replay file, and delete it
+async fn replay_file(path: &Path, client: &Client) -> Result<usize, ClientError> {
+    let mut count = 0;
+    for (target, lines) in read_batches(path)? {
+        for batch in lines.chunks(DEFAULT_BATCH_SIZE) {
+            client.send(&target, batch).await?;
+            count += batch.len();
+        }
+    }
+    remove_file(path)?;
+    Ok(count)
+}
+
+/// Send a request to each URL in turn, until a server does not fail
+///
+/// The request for each URL is created by `request`, and it is retried
+/// according to a policy.
+async fn send_with_failover<F>(
+    urls: &[&Url],
+    retry_policy: &RetryPolicy,
+    request: F,
+) -> Result<ReqwestResponse, ClientError>
+where
+    F: Fn(&Url) -> Result<ReqwestRequestBuilder, ClientError>,
+{
+    let (last, others) = urls.split_last().expect("Clients have at least one URL");
+    for url in others {
+        let request = request(url)?;
+        debug!("Sending request to {}", url);
+        trace!("Request: {:?}", request);
+        match send_with_retry(request, retry_policy).await {
+            Ok(response) if Failover::is_failover_status(response.status().as_u16()) => {
+                warn!(url = %url, status = response.status().as_u16(), "Server {} failed with status {}, failing over", url, response.status());
+            }
+            Err(error) if is_transient(&error) => {
+                warn!(url = %url, error = %error, "Server {} failed: {}, failing over", url, error);
+            }
+            result => return Ok(result?),
+        }
+    }
+
+    let request = request(last)?;
+    debug!("Sending request to {}", last);
+    trace!("Request: {:?}", request);
+    Ok(send_with_retry(request, retry_policy).await?)
+}
+
+/// Send a request, retrying it according to a policy
+///
+/// Requests whose body cannot be cloned are sent only once.
+async fn send_with_retry(
+    request: ReqwestRequestBuilder,
+    retry_policy: &RetryPolicy,
+) -> Result<ReqwestResponse, reqwest::Error> {
+    let mut attempt = 1;
+    loop {
+        let attempt_request = match request.try_clone() {
+            Some(attempt_request) if attempt < retry_policy.max_attempts() => attempt_request,
+            _ => return send_request(request).await,
+        };
+
+        let delay = match send_request(attempt_request).await {
+            Ok(response) if retry_policy.is_retryable_status(response.status().as_u16()) => {
+                let delay = match retry_policy.retry_delay(attempt, retry_after(response.headers())) {
+                    Some(delay) => delay,
+                    // The server asked to wait longer than the policy allows
+                    None => return Ok(response),
+                };
+                warn!(attempt, status = response.status().as_u16(), "Attempt {} failed with status {}, retrying", attempt, response.status());
+                delay
+            }
+            Err(error) if is_transient(&error) => {
+                warn!(attempt, error = %error, "Attempt {} failed: {}, retrying", attempt, error);
+                retry_policy.delay(attempt)
+            }
+            result => return result,
+        };
+
+        sleep(delay).await;
+        attempt += 1;
+    }
+}
+
+/// Send a request through Reqwest
+///
+/// The status code and the duration of the request are recorded on the
+/// current span.
+async fn send_request(request: ReqwestRequestBuilder) -> Result<ReqwestResponse, reqwest::Error> {
+    let stopwatch = Stopwatch::start();
+    let response = request.send().await;
+    record_response(response.as_ref().map(|response| response.status().as_u16()), stopwatch.elapsed());
+    response
 }
 
 /// A trait to obtain a prepared Influx Line Protocol request builder from [Reqwest clients](reqwest::Client).
@@ -114,7 +1091,7 @@ impl Client {
 /// // Bring into scope the trait implementation
 /// use rinfluxdb_lineprotocol::r#async::InfluxLineClientWrapper;
 ///
-/// # async_std::task::block_on(async {
+/// # async fn example() -> Result<(), rinfluxdb_lineprotocol::ClientError> {
 /// // Create Reqwest client
 /// let client = reqwest::Client::new();
 ///
@@ -150,8 +1127,7 @@ impl Client {
 /// let response = client.execute(request).await?;
 ///
 /// # Ok::<(), rinfluxdb_lineprotocol::ClientError>(())
-/// # })?;
-/// # Ok::<(), rinfluxdb_lineprotocol::ClientError>(())
+/// # }
 /// ```
 pub trait InfluxLineClientWrapper {
     /// Create an Influx Line Protocol request builder
@@ -166,6 +1142,16 @@ pub trait InfluxLineClientWrapper {
         lines: &[Line],
     ) -> Result<Self::RequestBuilderType, ClientError>;
 
+    /// Create an Influx Line Protocol request builder with a consistency
+    /// level, for InfluxDB Enterprise clusters
+    fn line_protocol_with_consistency(
+        &self,
+        base_url: &Url,
+        database: &str,
+        lines: &[Line],
+        consistency: Consistency,
+    ) -> Result<Self::RequestBuilderType, ClientError>;
+
     /// The type of the resulting request builder
     ///
     /// This type is a parameter so the trait can be implemented for
@@ -183,12 +1169,30 @@ impl InfluxLineClientWrapper for ReqwestClient {
         database: &str,
         lines: &[Line],
     ) -> Result<ReqwestRequestBuilder, ClientError> {
-        let mut url = base_url.join("/write")?;
-        let query = "db=".to_string() + database;
-        url.set_query(Some(&query));
+        let url = write_url(base_url, &WriteTarget::v1(database), Precision::Nanoseconds, None)?;
+        let payload = write_payload(lines, Precision::Nanoseconds);
 
-        let strings: Vec<String> = lines.iter().map(|line| line.to_string()).collect();
-        let payload: String = strings.join("\n");
+        let builder = self
+            .post(url)
+            .body(payload);
+
+        Ok(builder)
+    }
+
+    fn line_protocol_with_consistency(
+        &self,
+        base_url: &Url,
+        database: &str,
+        lines: &[Line],
+        consistency: Consistency,
+    ) -> Result<ReqwestRequestBuilder, ClientError> {
+        let url = write_url(
+            base_url,
+            &WriteTarget::v1(database),
+            Precision::Nanoseconds,
+            Some(consistency),
+        )?;
+        let payload = write_payload(lines, Precision::Nanoseconds);
 
         let builder = self
             .post(url)
@@ -210,7 +1214,7 @@ impl InfluxLineClientWrapper for ReqwestClient {
 /// // Bring into scope the trait implementation
 /// use rinfluxdb_lineprotocol::r#async::InfluxLineResponseWrapper;
 ///
-/// # async_std::task::block_on(async {
+/// # async fn example() -> Result<(), rinfluxdb_lineprotocol::ClientError> {
 /// // Create Reqwest client
 /// let client = reqwest::Client::new();
 ///
@@ -249,24 +1253,27 @@ impl InfluxLineClientWrapper for ReqwestClient {
 /// response.process_line_protocol_response().await?;
 ///
 /// # Ok::<(), rinfluxdb_lineprotocol::ClientError>(())
-/// # })?;
-/// # Ok::<(), rinfluxdb_lineprotocol::ClientError>(())
+/// # }
 /// ```
-#[async_trait]
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
 pub trait InfluxLineResponseWrapper {
     /// Process the response, parsing potential errors
     async fn process_line_protocol_response(self) -> Result<(), ClientError>;
 }
 
-#[async_trait]
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
 impl InfluxLineResponseWrapper for ReqwestResponse {
     async fn process_line_protocol_response(self) -> Result<(), ClientError> {
         match self.error_for_status_ref() {
             Ok(_) => Ok(()),
             Err(_) => {
+                let status = self.status();
+                let headers = self.headers().clone();
                 let text = self.text().await?;
                 debug!("Response: \"{}\"", text);
-                let error = parse_error(&text);
+                let error = parse_error(status, &headers, &text);
                 Err(error)
             }
         }
@@ -0,0 +1,297 @@
+// Copyright Claudio Mattera 2021.
+// Distributed under the MIT License or Apache 2.0 License at your option.
+// See accompanying files License-MIT.txt and License-Apache-2.0, or online at
+// https://opensource.org/licenses/MIT
+// https://opensource.org/licenses/Apache-2.0
+
+use std::time::Duration;
+
+use tracing::*;
+
+use tokio::sync::{mpsc, oneshot};
+use tokio::task::JoinHandle;
+use tokio::time::{interval, MissedTickBehavior};
+
+use super::super::{IntoLine, Line, OverflowError, OverflowPolicy};
+use super::r#async::{BatchWriterBuilder, Client};
+use super::{ClientError, WriteTarget, DEFAULT_BATCH_SIZE, DEFAULT_BUFFER_CAPACITY};
+
+/// The default number of lines waiting in the channel
+const DEFAULT_CHANNEL_CAPACITY: usize = 10000;
+
+/// The default maximal time between flushes
+const DEFAULT_FLUSH_INTERVAL: Duration = Duration::from_secs(1);
+
+/// A message sent from handles to the worker task
+#[derive(Debug)]
+enum Message {
+    /// Buffer a line
+    Line(Line),
+
+    /// Send all buffered lines and report the result
+    Flush(oneshot::Sender<Result<(), ClientError>>),
+
+    /// Send all pending lines and stop
+    Shutdown,
+}
+
+/// A builder for ingestion services
+///
+/// By default, lines are sent in batches of 5000 or at least every second,
+/// up to 10000 lines wait in the channel, and up to 50000 lines are buffered
+/// when the server cannot be reached.
+#[derive(Debug)]
+pub struct IngestionServiceBuilder {
+    client: Client,
+    target: WriteTarget,
+    batch_size: usize,
+    flush_interval: Duration,
+    channel_capacity: usize,
+    capacity: usize,
+    overflow_policy: OverflowPolicy,
+}
+
+impl IngestionServiceBuilder {
+    /// Create a new builder for an ingestion service writing to a target
+    ///
+    /// The target can be a database name, or a [`WriteTarget`](crate::WriteTarget)
+    /// for retention policies or InfluxDB 2.x buckets.
+    pub fn new<T>(client: Client, target: T) -> Self
+    where
+        T: Into<WriteTarget>,
+    {
+        Self {
+            client,
+            target: target.into(),
+            batch_size: DEFAULT_BATCH_SIZE,
+            flush_interval: DEFAULT_FLUSH_INTERVAL,
+            channel_capacity: DEFAULT_CHANNEL_CAPACITY,
+            capacity: DEFAULT_BUFFER_CAPACITY,
+            overflow_policy: OverflowPolicy::default(),
+        }
+    }
+
+    /// Set the number of lines that triggers a flush
+    pub fn batch_size(mut self, batch_size: usize) -> Self {
+        self.batch_size = batch_size.max(1);
+        self
+    }
+
+    /// Set the maximal time between flushes
+    ///
+    /// Unlike batch writers, the service also flushes while idle.
+    pub fn flush_interval(mut self, flush_interval: Duration) -> Self {
+        self.flush_interval = flush_interval.max(Duration::from_millis(1));
+        self
+    }
+
+    /// Set the maximal number of lines waiting in the channel
+    ///
+    /// [`IngestHandle::push()`] waits when the channel is full.
+    pub fn channel_capacity(mut self, channel_capacity: usize) -> Self {
+        self.channel_capacity = channel_capacity.max(1);
+        self
+    }
+
+    /// Set the maximal number of lines kept while the server cannot be reached
+    ///
+    /// The capacity is never smaller than the batch size.
+    pub fn capacity(mut self, capacity: usize) -> Self {
+        self.capacity = capacity;
+        self
+    }
+
+    /// Set what to do when the buffer is full
+    pub fn overflow_policy(mut self, overflow_policy: OverflowPolicy) -> Self {
+        self.overflow_policy = overflow_policy;
+        self
+    }
+
+    /// Spawn the worker task on the current Tokio runtime
+    ///
+    /// # Panics
+    ///
+    /// Panics if called outside of a Tokio runtime.
+    pub fn spawn(self) -> IngestionService {
+        let (sender, receiver) = mpsc::channel(self.channel_capacity);
+        let task = tokio::spawn(run(self, receiver));
+        IngestionService { sender, task }
+    }
+}
+
+/// A background task owning a client and writing lines in batches
+///
+/// Lines are pushed through cheap [`IngestHandle`]s into a bounded channel,
+/// and a Tokio task buffers them and sends them in batches, when the batch
+/// size is reached and at every flush interval.
+/// Requests are retried according to the retry policy of the client, and
+/// lines that could not be sent are kept in the buffer and sent with the
+/// next batch; once the buffer is full the overflow policy applies.
+///
+/// [`shutdown()`](IngestionService::shutdown) sends all pending lines and
+/// stops the task.
+/// If the service is dropped instead, the task keeps running until all
+/// handles are dropped too, then it sends all pending lines and stops, as
+/// long as the runtime is still running.
+///
+/// ```no_run
+/// # use std::time::Duration;
+/// # use url::Url;
+/// # use rinfluxdb_lineprotocol::LineBuilder;
+/// use rinfluxdb_lineprotocol::r#async::Client;
+/// use rinfluxdb_lineprotocol::IngestionServiceBuilder;
+///
+/// # async fn example() -> Result<(), rinfluxdb_lineprotocol::ClientError> {
+/// let client = Client::new(Url::parse("https://example.com/")?, Some(("username", "password")))?;
+///
+/// let service = IngestionServiceBuilder::new(client, "database")
+///     .batch_size(100)
+///     .flush_interval(Duration::from_secs(10))
+///     .spawn();
+///
+/// let handle = service.handle();
+/// tokio::spawn(async move {
+///     for value in 0..1000 {
+///         let line = LineBuilder::new("measurement").insert_field("field", value as f64);
+///         if handle.push(line).await.is_err() {
+///             break;
+///         }
+///     }
+/// });
+///
+/// service.shutdown().await?;
+/// # Ok::<(), rinfluxdb_lineprotocol::ClientError>(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct IngestionService {
+    sender: mpsc::Sender<Message>,
+    task: JoinHandle<Result<(), ClientError>>,
+}
+
+impl IngestionService {
+    /// Return a new handle for pushing lines
+    pub fn handle(&self) -> IngestHandle {
+        IngestHandle {
+            sender: self.sender.clone(),
+        }
+    }
+
+    /// Send all pending lines and stop the worker task
+    ///
+    /// Lines pushed before shutting down are sent, while pushing lines
+    /// afterwards returns [`ClientError::IngestionStopped`].
+    /// The error of the last flush is returned, in which case the lines still
+    /// buffered are lost.
+    pub async fn shutdown(self) -> Result<(), ClientError> {
+        // The worker stops anyway if the channel was closed
+        let _ = self.sender.send(Message::Shutdown).await;
+        self.task.await.map_err(|error| {
+            error!("Ingestion task failed: {}", error);
+            ClientError::IngestionStopped
+        })?
+    }
+}
+
+/// A handle for pushing lines to an [`IngestionService`]
+///
+/// Handles are cheap to clone, and can be moved to other tasks.
+#[derive(Clone, Debug)]
+pub struct IngestHandle {
+    sender: mpsc::Sender<Message>,
+}
+
+impl IngestHandle {
+    /// Push a line, waiting if the channel is full
+    pub async fn push<T>(&self, line: T) -> Result<(), ClientError>
+    where
+        T: IntoLine,
+    {
+        self.sender
+            .send(Message::Line(line.into_line()))
+            .await
+            .map_err(|_| ClientError::IngestionStopped)
+    }
+
+    /// Push a line without waiting
+    ///
+    /// If the channel is full, the line is returned inside
+    /// [`OverflowError::Full`](crate::OverflowError::Full).
+    pub fn try_push<T>(&self, line: T) -> Result<(), ClientError>
+    where
+        T: IntoLine,
+    {
+        self.sender
+            .try_send(Message::Line(line.into_line()))
+            .map_err(|error| match error {
+                mpsc::error::TrySendError::Full(Message::Line(line)) => OverflowError::Full(Box::new(line)).into(),
+                _ => ClientError::IngestionStopped,
+            })
+    }
+
+    /// Send all lines pushed so far, and wait for the result
+    pub async fn flush(&self) -> Result<(), ClientError> {
+        let (reply, result) = oneshot::channel();
+        self.sender
+            .send(Message::Flush(reply))
+            .await
+            .map_err(|_| ClientError::IngestionStopped)?;
+        result.await.map_err(|_| ClientError::IngestionStopped)?
+    }
+}
+
+/// Receive lines and write them in batches until the service is stopped
+#[instrument(name = "Ingesting lines", skip(builder, receiver))]
+async fn run(
+    builder: IngestionServiceBuilder,
+    mut receiver: mpsc::Receiver<Message>,
+) -> Result<(), ClientError> {
+    let mut writer = BatchWriterBuilder::new(&builder.client, builder.target)
+        .batch_size(builder.batch_size)
+        .capacity(builder.capacity)
+        .overflow_policy(builder.overflow_policy)
+        .build();
+
+    let mut ticker = interval(builder.flush_interval);
+    ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+    loop {
+        tokio::select! {
+            message = receiver.recv() => match message {
+                Some(Message::Line(line)) => {
+                    if let Err(error) = writer.write(line).await {
+                        warn!("Could not write line: {}", error);
+                    }
+                }
+                Some(Message::Flush(reply)) => {
+                    let _ = reply.send(writer.flush().await);
+                }
+                Some(Message::Shutdown) | None => break,
+            },
+            _ = ticker.tick() => {
+                if let Err(error) = writer.flush().await {
+                    warn!("Could not flush lines: {}", error);
+                }
+            }
+        }
+    }
+
+    // Lines pushed before shutting down are still written
+    receiver.close();
+    while let Ok(message) = receiver.try_recv() {
+        match message {
+            Message::Line(line) => {
+                if let Err(error) = writer.write(line).await {
+                    warn!("Could not write line: {}", error);
+                }
+            }
+            Message::Flush(reply) => {
+                let _ = reply.send(writer.flush().await);
+            }
+            Message::Shutdown => {}
+        }
+    }
+
+    debug!("Flushing {} lines before stopping", writer.len());
+    writer.flush().await
+}
@@ -0,0 +1,205 @@
+// Copyright Claudio Mattera 2021.
+// Distributed under the MIT License or Apache 2.0 License at your option.
+// See accompanying files License-MIT.txt and License-Apache-2.0, or online at
+// https://opensource.org/licenses/MIT
+// https://opensource.org/licenses/Apache-2.0
+
+use std::sync::Mutex;
+use std::time::Duration;
+
+use rinfluxdb_types::Stopwatch;
+
+/// A limit on the rate of writes sent by a client
+///
+/// Limits are enforced with token buckets holding one second worth of
+/// capacity, so short bursts up to the limit are sent immediately.
+/// When a bucket is empty, blocking clients block and asynchronous clients
+/// wait until enough capacity is available, instead of sending requests
+/// that the server would reject.
+/// A request larger than the capacity of a bucket is sent when the bucket
+/// is full.
+///
+/// ```
+/// # use rinfluxdb_lineprotocol::RateLimit;
+/// let rate_limit = RateLimit::new()
+///     .points_per_second(5000.0)
+///     .requests_per_second(10.0);
+///
+/// assert_eq!(rate_limit.points(), Some(5000.0));
+/// assert_eq!(rate_limit.requests(), Some(10.0));
+/// ```
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct RateLimit {
+    points_per_second: Option<f64>,
+    requests_per_second: Option<f64>,
+}
+
+impl RateLimit {
+    /// Create a rate limit without any limit
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Limit the number of lines written per second
+    ///
+    /// Rates that are not positive are ignored.
+    pub fn points_per_second(mut self, points_per_second: f64) -> Self {
+        self.points_per_second = Some(points_per_second).filter(|rate| *rate > 0.0);
+        self
+    }
+
+    /// Limit the number of requests sent per second
+    ///
+    /// Rates that are not positive are ignored.
+    pub fn requests_per_second(mut self, requests_per_second: f64) -> Self {
+        self.requests_per_second = Some(requests_per_second).filter(|rate| *rate > 0.0);
+        self
+    }
+
+    /// Return the maximal number of lines written per second
+    pub fn points(&self) -> Option<f64> {
+        self.points_per_second
+    }
+
+    /// Return the maximal number of requests sent per second
+    pub fn requests(&self) -> Option<f64> {
+        self.requests_per_second
+    }
+}
+
+/// A token bucket refilled at a constant rate
+#[derive(Debug)]
+struct TokenBucket {
+    rate: f64,
+    tokens: f64,
+    updated: Duration,
+}
+
+impl TokenBucket {
+    /// Create a full bucket holding one second worth of tokens
+    fn new(rate: f64) -> Self {
+        Self {
+            rate,
+            tokens: rate,
+            updated: Duration::from_secs(0),
+        }
+    }
+
+    /// Take tokens from the bucket, and return how long to wait before using
+    /// them
+    ///
+    /// Tokens are taken even if the bucket does not contain enough of them,
+    /// so that later callers wait for the deficit to be refilled.
+    fn reserve(&mut self, tokens: f64, now: Duration) -> Duration {
+        let elapsed = now.saturating_sub(self.updated).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * self.rate).min(self.rate);
+        self.updated = now;
+
+        let needed = tokens.min(self.rate);
+        let wait = if self.tokens >= needed {
+            Duration::from_secs(0)
+        } else {
+            Duration::from_secs_f64((needed - self.tokens) / self.rate)
+        };
+        self.tokens -= tokens;
+        wait
+    }
+}
+
+/// The state of a rate limit, shared by all requests of a client
+#[derive(Debug)]
+pub(crate) struct RateLimiter {
+    stopwatch: Stopwatch,
+    points: Option<Mutex<TokenBucket>>,
+    requests: Option<Mutex<TokenBucket>>,
+}
+
+impl RateLimiter {
+    /// Create a limiter with full buckets
+    pub(crate) fn new(rate_limit: RateLimit) -> Self {
+        Self {
+            stopwatch: Stopwatch::start(),
+            points: rate_limit.points_per_second.map(TokenBucket::new).map(Mutex::new),
+            requests: rate_limit.requests_per_second.map(TokenBucket::new).map(Mutex::new),
+        }
+    }
+
+    /// Reserve capacity for a request, and return how long to wait before
+    /// sending it
+    pub(crate) fn reserve(&self, lines: usize) -> Duration {
+        self.reserve_at(lines, self.stopwatch.elapsed())
+    }
+
+    /// Reserve capacity for a request at a time since the limiter was created
+    fn reserve_at(&self, lines: usize, now: Duration) -> Duration {
+        let points = self.points.as_ref().map(|bucket| {
+            bucket
+                .lock()
+                .expect("Rate limiter lock is not poisoned")
+                .reserve(lines as f64, now)
+        });
+        let requests = self.requests.as_ref().map(|bucket| {
+            bucket
+                .lock()
+                .expect("Rate limiter lock is not poisoned")
+                .reserve(1.0, now)
+        });
+        points
+            .into_iter()
+            .chain(requests)
+            .max()
+            .unwrap_or_else(|| Duration::from_secs(0))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn burst_within_capacity() {
+        let limiter = RateLimiter::new(RateLimit::new().requests_per_second(2.0));
+        let now = Duration::from_secs(0);
+
+        assert_eq!(limiter.reserve_at(1, now), Duration::from_secs(0));
+        assert_eq!(limiter.reserve_at(1, now), Duration::from_secs(0));
+        assert_eq!(limiter.reserve_at(1, now), Duration::from_millis(500));
+        assert_eq!(limiter.reserve_at(1, now), Duration::from_secs(1));
+    }
+
+    #[test]
+    fn refill_over_time() {
+        let limiter = RateLimiter::new(RateLimit::new().points_per_second(100.0));
+
+        assert_eq!(limiter.reserve_at(100, Duration::from_secs(0)), Duration::from_secs(0));
+        assert_eq!(limiter.reserve_at(50, Duration::from_millis(500)), Duration::from_secs(0));
+        assert_eq!(limiter.reserve_at(50, Duration::from_millis(500)), Duration::from_millis(500));
+    }
+
+    #[test]
+    fn large_request_waits_for_full_bucket() {
+        let limiter = RateLimiter::new(RateLimit::new().points_per_second(100.0));
+
+        assert_eq!(limiter.reserve_at(300, Duration::from_secs(0)), Duration::from_secs(0));
+        assert_eq!(limiter.reserve_at(100, Duration::from_secs(1)), Duration::from_secs(2));
+    }
+
+    #[test]
+    fn slowest_limit_wins() {
+        let rate_limit = RateLimit::new()
+            .points_per_second(1000.0)
+            .requests_per_second(1.0);
+        let limiter = RateLimiter::new(rate_limit);
+        let now = Duration::from_secs(0);
+
+        assert_eq!(limiter.reserve_at(10, now), Duration::from_secs(0));
+        assert_eq!(limiter.reserve_at(10, now), Duration::from_secs(1));
+    }
+
+    #[test]
+    fn no_limit() {
+        let limiter = RateLimiter::new(RateLimit::new().points_per_second(0.0));
+
+        assert_eq!(limiter.reserve_at(1_000_000, Duration::from_secs(0)), Duration::from_secs(0));
+    }
+}
@@ -0,0 +1,296 @@
+// Copyright Claudio Mattera 2021.
+// Distributed under the MIT License or Apache 2.0 License at your option.
+// See accompanying files License-MIT.txt and License-Apache-2.0, or online at
+// https://opensource.org/licenses/MIT
+// https://opensource.org/licenses/Apache-2.0
+
+use std::ffi::OsString;
+use std::fs::{self, File, OpenOptions};
+use std::io::{ErrorKind, Read, Write};
+use std::path::{Path, PathBuf};
+
+use chrono::Utc;
+
+use tracing::*;
+
+use url::form_urlencoded;
+
+use super::super::{parse_lines, Line};
+use super::{write_payload, ClientError, WriteTarget};
+
+use rinfluxdb_types::Precision;
+
+/// Prefix of the comments storing the target of each batch
+const TARGET_PREFIX: &str = "# target ";
+
+/// The first bytes of gzip data
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+
+/// A sink appending lines to a local file
+///
+/// A file sink has the same `send()` interface as clients, but it appends
+/// lines to a file instead of sending them to a server.
+/// This allows buffering data durably while the server is unreachable, and
+/// uploading it later with [`blocking::replay()`](super::blocking::replay) or
+/// [`r#async::replay()`](super::r#async::replay).
+///
+/// Lines without a timestamp are given the current time, since they would
+/// otherwise be given the time of replay by the server.
+///
+/// ```no_run
+/// # use url::Url;
+/// # use rinfluxdb_lineprotocol::{FileSink, LineBuilder};
+/// # use rinfluxdb_lineprotocol::blocking::{replay, Client};
+/// let sink = FileSink::new("/var/lib/sensor/buffer.lp");
+///
+/// let line = LineBuilder::new("indoor_environment")
+///     .insert_field("temperature", 21.3)
+///     .build();
+/// sink.send("house", &[line])?;
+///
+/// // Later, when the server is reachable
+/// let client = Client::new(Url::parse("https://example.com/")?, Some(("username", "password")))?;
+/// let count = replay(sink.path(), &client)?;
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+#[derive(Clone, Debug)]
+pub struct FileSink {
+    path: PathBuf,
+
+    #[cfg(feature = "file-sink-gzip")]
+    gzip: bool,
+}
+
+impl FileSink {
+    /// Create a sink appending lines to a file
+    ///
+    /// The file is created if it does not exist.
+    pub fn new<P>(path: P) -> Self
+    where
+        P: Into<PathBuf>,
+    {
+        Self {
+            path: path.into(),
+
+            #[cfg(feature = "file-sink-gzip")]
+            gzip: false,
+        }
+    }
+
+    /// Compress appended lines with gzip
+    ///
+    /// Each call to `send()` appends a new gzip member to the file.
+    /// A file must be either entirely compressed or entirely uncompressed.
+    #[cfg(feature = "file-sink-gzip")]
+    pub fn gzip(mut self) -> Self {
+        self.gzip = true;
+        self
+    }
+
+    /// Return the path of the file
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Append lines to the file
+    ///
+    /// The target can be a database name, or a [`WriteTarget`](crate::WriteTarget)
+    /// for retention policies or InfluxDB 2.x buckets.
+    pub fn send<T>(&self, target: T, lines: &[Line]) -> Result<(), ClientError>
+    where
+        T: Into<WriteTarget>,
+    {
+        if lines.is_empty() {
+            return Ok(());
+        }
+
+        let now = Utc::now();
+        let lines: Vec<Line> = lines
+            .iter()
+            .map(|line| {
+                let mut line = line.clone();
+                if line.timestamp().is_none() {
+                    line.set_timestamp(now);
+                }
+                line
+            })
+            .collect();
+
+        let batch = format!(
+            "{}{}\n{}\n",
+            TARGET_PREFIX,
+            encode_target(&target.into()),
+            write_payload(&lines, Precision::Nanoseconds),
+        );
+
+        debug!("Appending {} lines to {}", lines.len(), self.path.display());
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        self.write_batch(file, batch.as_bytes())?;
+
+        Ok(())
+    }
+
+    fn write_batch(&self, mut file: File, batch: &[u8]) -> Result<(), std::io::Error> {
+        #[cfg(feature = "file-sink-gzip")]
+        if self.gzip {
+            let mut encoder = flate2::write::GzEncoder::new(file, flate2::Compression::default());
+            encoder.write_all(batch)?;
+            return encoder.finish()?.sync_data();
+        }
+
+        file.write_all(batch)?;
+        file.sync_data()
+    }
+}
+
+/// Read the batches appended to a file by a sink
+///
+/// Consecutive batches for the same target are merged.
+pub(crate) fn read_batches(path: &Path) -> Result<Vec<(WriteTarget, Vec<Line>)>, ClientError> {
+    let mut bytes = Vec::new();
+    File::open(path)?.read_to_end(&mut bytes)?;
+
+    let text = if bytes.starts_with(&GZIP_MAGIC) {
+        decompress(&bytes)?
+    } else {
+        String::from_utf8(bytes)
+            .map_err(|_| ClientError::InvalidBuffer("file is not valid UTF-8".into()))?
+    };
+
+    let mut batches: Vec<(WriteTarget, String)> = Vec::new();
+    for line in text.lines() {
+        if let Some(target) = line.strip_prefix(TARGET_PREFIX) {
+            let target = decode_target(target)?;
+            if batches.last().map(|(last, _)| last != &target).unwrap_or(true) {
+                batches.push((target, String::new()));
+            }
+        } else if let Some((_, batch)) = batches.last_mut() {
+            batch.push_str(line);
+            batch.push('\n');
+        } else if !line.trim().is_empty() {
+            return Err(ClientError::InvalidBuffer("line before any target".into()));
+        }
+    }
+
+    batches
+        .into_iter()
+        .map(|(target, batch)| Ok((target, parse_lines(&batch)?)))
+        .collect()
+}
+
+/// Return the path of the replay file of a file
+///
+/// The replay file holds the batches being replayed, and it is named after
+/// the file with extension `.replay` appended.
+pub(crate) fn replay_path(path: &Path) -> PathBuf {
+    let mut name = path.file_name().map(OsString::from).unwrap_or_default();
+    name.push(".replay");
+    path.with_file_name(name)
+}
+
+/// Move all batches from a file to its replay file
+///
+/// Renaming is atomic, so lines appended by a sink afterwards go to a new
+/// file and are not lost when the replay file is deleted.
+/// Return `false` if the file does not exist.
+pub(crate) fn take_batches(path: &Path, replay_path: &Path) -> Result<bool, ClientError> {
+    match fs::rename(path, replay_path) {
+        Ok(()) => Ok(true),
+        Err(error) if error.kind() == ErrorKind::NotFound => Ok(false),
+        Err(error) => Err(error.into()),
+    }
+}
+
+#[cfg(feature = "file-sink-gzip")]
+fn decompress(bytes: &[u8]) -> Result<String, ClientError> {
+    let mut text = String::new();
+    flate2::read::MultiGzDecoder::new(bytes).read_to_string(&mut text)?;
+    Ok(text)
+}
+
+#[cfg(not(feature = "file-sink-gzip"))]
+fn decompress(_bytes: &[u8]) -> Result<String, ClientError> {
+    Err(ClientError::InvalidBuffer(
+        "file is compressed, but feature file-sink-gzip is not enabled".into(),
+    ))
+}
+
+fn encode_target(target: &WriteTarget) -> String {
+    let mut serializer = form_urlencoded::Serializer::new(String::new());
+    match target {
+        WriteTarget::V1 { database, retention_policy } => {
+            serializer.append_pair("db", database);
+            if let Some(retention_policy) = retention_policy {
+                serializer.append_pair("rp", retention_policy);
+            }
+        }
+        WriteTarget::V2 { organization, bucket } => {
+            serializer.append_pair("org", organization);
+            serializer.append_pair("bucket", bucket);
+        }
+    }
+    serializer.finish()
+}
+
+fn decode_target(input: &str) -> Result<WriteTarget, ClientError> {
+    let mut database = None;
+    let mut retention_policy = None;
+    let mut organization = None;
+    let mut bucket = None;
+    for (key, value) in form_urlencoded::parse(input.as_bytes()) {
+        let value = Some(value.into_owned());
+        match key.as_ref() {
+            "db" => database = value,
+            "rp" => retention_policy = value,
+            "org" => organization = value,
+            "bucket" => bucket = value,
+            _ => {}
+        }
+    }
+
+    match (database, organization, bucket) {
+        (Some(database), None, None) => Ok(WriteTarget::V1 { database, retention_policy }),
+        (None, Some(organization), Some(bucket)) => Ok(WriteTarget::V2 { organization, bucket }),
+        _ => Err(ClientError::InvalidBuffer(format!("invalid target {}", input))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn target_round_trip() {
+        let targets = vec![
+            WriteTarget::v1("house"),
+            WriteTarget::V1 {
+                database: "house & garden".into(),
+                retention_policy: Some("one week".into()),
+            },
+            WriteTarget::v2("my-org", "house"),
+        ];
+
+        for target in targets {
+            assert_eq!(decode_target(&encode_target(&target)).ok(), Some(target));
+        }
+    }
+
+    #[test]
+    fn replay_path_appends_extension() {
+        assert_eq!(
+            replay_path(Path::new("/var/lib/sensor/buffer.lp")),
+            PathBuf::from("/var/lib/sensor/buffer.lp.replay"),
+        );
+    }
+
+    #[test]
+    fn invalid_target() {
+        assert!(matches!(
+            decode_target("org=my-org"),
+            Err(ClientError::InvalidBuffer(_)),
+        ));
+    }
+}
@@ -0,0 +1,137 @@
+// Copyright Claudio Mattera 2021.
+// Distributed under the MIT License or Apache 2.0 License at your option.
+// See accompanying files License-MIT.txt and License-Apache-2.0, or online at
+// https://opensource.org/licenses/MIT
+// https://opensource.org/licenses/Apache-2.0
+
+//! Escaping rules of the [InfluxDB line protocol](https://docs.influxdata.com/influxdb/v1.8/write_protocols/line_protocol_reference/#special-characters)
+//!
+//! * Measurements escape commas and spaces;
+//! * Tag keys, tag values and field keys escape commas, equal signs and
+//!   spaces;
+//! * String field values are enclosed in double quotes, and escape double
+//!   quotes and backslashes.
+//!
+//! Other backslashes are written as they are.
+//! Since a backslash followed by a special character is always read as an
+//! escape sequence, names ending with a backslash cannot be represented,
+//! and neither can tag values containing newlines.
+//!
+//! ```
+//! # use rinfluxdb_lineprotocol::escape;
+//! assert_eq!(escape::measurement("indoor environment,a=b"), r"indoor\ environment\,a=b");
+//! assert_eq!(escape::tag_value("a=b c"), r"a\=b\ c");
+//! assert_eq!(escape::string_field_value(r#"say "hi" \o/"#), r#""say \"hi\" \\o/""#);
+//! ```
+
+/// Characters escaped in measurements
+pub(crate) const MEASUREMENT_SPECIAL_CHARACTERS: [char; 2] = [',', ' '];
+
+/// Characters escaped in tag keys, tag values and field keys
+pub(crate) const KEY_SPECIAL_CHARACTERS: [char; 3] = [',', '=', ' '];
+
+/// Escape a measurement
+pub fn measurement(name: &str) -> String {
+    escape_characters(name, &MEASUREMENT_SPECIAL_CHARACTERS)
+}
+
+/// Escape a tag key
+pub fn tag_key(name: &str) -> String {
+    escape_characters(name, &KEY_SPECIAL_CHARACTERS)
+}
+
+/// Escape a tag value
+pub fn tag_value(value: &str) -> String {
+    escape_characters(value, &KEY_SPECIAL_CHARACTERS)
+}
+
+/// Escape a field key
+pub fn field_key(name: &str) -> String {
+    escape_characters(name, &KEY_SPECIAL_CHARACTERS)
+}
+
+/// Escape a string field value, and enclose it in double quotes
+pub fn string_field_value(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len() + 2);
+    escaped.push('"');
+    escaped.push_str(&escape_characters(value, &['"', '\\']));
+    escaped.push('"');
+    escaped
+}
+
+/// Prefix all occurrences of some characters with a backslash
+fn escape_characters(text: &str, special_characters: &[char]) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for character in text.chars() {
+        if special_characters.contains(&character) {
+            escaped.push('\\');
+        }
+        escaped.push(character);
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use quickcheck::TestResult;
+    use quickcheck_macros::quickcheck;
+
+    use super::super::{FieldValue, Line};
+
+    /// Check whether a name can be represented in line protocol
+    fn is_representable(name: &str) -> bool {
+        !name.is_empty() && !name.ends_with('\\') && !name.contains(&['\n', '\r'][..])
+    }
+
+    #[test]
+    fn escape_measurement() {
+        assert_eq!(measurement("a b,c=d"), r"a\ b\,c=d");
+        assert_eq!(measurement(r"a\b"), r"a\b");
+    }
+
+    #[test]
+    fn escape_keys() {
+        assert_eq!(tag_key("a b,c=d"), r"a\ b\,c\=d");
+        assert_eq!(tag_value("a b,c=d"), r"a\ b\,c\=d");
+        assert_eq!(field_key("a b,c=d"), r"a\ b\,c\=d");
+        assert_eq!(tag_value(r#"a"b"#), r#"a"b"#);
+    }
+
+    #[test]
+    fn escape_string_field_value() {
+        assert_eq!(string_field_value(r#"a "b""#), r#""a \"b\"""#);
+        assert_eq!(string_field_value(r"a\b"), r#""a\\b""#);
+        assert_eq!(string_field_value(r#"a\"b"#), r#""a\\\"b""#);
+    }
+
+    #[test]
+    fn round_trip_backslashes() {
+        let mut line = Line::new(r"a\=b\,c");
+        line.insert_tag(r"d\,e", r"f\ g");
+        line.insert_field(r"h\=i", FieldValue::String(r#"j\"k\"#.into()));
+
+        assert_eq!(Line::parse(&line.to_string()), Ok(line));
+    }
+
+    #[quickcheck]
+    fn round_trip_quickcheck(
+        measurement: String,
+        tag_key: String,
+        tag_value: String,
+        field_key: String,
+        field_value: String,
+    ) -> TestResult {
+        let names = [&measurement, &tag_key, &tag_value, &field_key];
+        if !names.iter().all(|name| is_representable(name)) {
+            return TestResult::discard();
+        }
+
+        let mut line = Line::new(measurement);
+        line.insert_tag(tag_key, tag_value);
+        line.insert_field(field_key, FieldValue::String(field_value));
+
+        TestResult::from_bool(Line::parse(&line.to_string()) == Ok(line))
+    }
+}
@@ -4,19 +4,25 @@
 // https://opensource.org/licenses/MIT
 // https://opensource.org/licenses/Apache-2.0
 
+use super::escape;
+
 /// Represent a field value
-#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+#[derive(Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(transparent))]
 pub struct FieldName(String);
 
 impl FieldName {
+    /// Return the unescaped field name
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
     /// Escape a field name to [InfluxDB line protocol](https://docs.influxdata.com/influxdb/v1.8/write_protocols/line_protocol_reference/)
     ///
-    /// The name is enclosed in double quotes, and characters ` `, `,` and `=` are escaped.
+    /// Characters ` `, `,` and `=` are escaped, see [`escape::field_key()`].
     pub fn escape_to_line_protocol(&self) -> String {
-        self.0
-            .replace(" ", "\\ ")
-            .replace(",", "\\,")
-            .replace("=", "\\=")
+        escape::field_key(&self.0)
     }
 }
 
@@ -4,10 +4,17 @@
 // https://opensource.org/licenses/MIT
 // https://opensource.org/licenses/Apache-2.0
 
+use ::std::convert::TryFrom;
+
 use ::chrono::{DateTime, Utc};
 
+use ::rinfluxdb_types::{Value, ValueError};
+
+use super::escape;
+
 /// Represent a field value
 #[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum FieldValue {
     /// Represent a floating point number field value
     Float(f64),
@@ -34,23 +41,24 @@ pub enum FieldValue {
 impl FieldValue {
     /// Escape a field value to [InfluxDB line protocol](https://docs.influxdata.com/influxdb/v1.8/write_protocols/line_protocol_reference/)
     ///
-    /// Numeric and boolean values are escaped as they are.
+    /// Floats and booleans are escaped as they are, while signed and unsigned
+    /// integers are followed by suffixes `i` and `u`, so that InfluxDB does not
+    /// store them as floats.
     /// Timestamps are converted to nanoseconds from epoch.
-    /// Strings are enclosed in double quotes, and characters `"` and `\` are escaped.
+    /// Strings are enclosed in double quotes, and characters `"` and `\` are
+    /// escaped, see [`escape::string_field_value()`].
     ///
     /// ```
     /// # use rinfluxdb_lineprotocol::FieldValue;
     /// let mut value = FieldValue::String("a string \"value\"".into());
-    /// assert_eq!(value.escape_to_line_protocol(), "\"a string \\\\\"value\\\\\"\"".to_string());
+    /// assert_eq!(value.escape_to_line_protocol(), "\"a string \\\"value\\\"\"".to_string());
     /// ```
     pub fn escape_to_line_protocol(&self) -> String {
         match self {
             FieldValue::Float(f) => format!("{}", f),
-            FieldValue::Integer(i) => format!("{}", i),
-            FieldValue::UnsignedInteger(u) => format!("{}", u),
-            FieldValue::String(s) => {
-                format!("\"{}\"", s.replace("\"", "\\\"").replace("\\", "\\\\"))
-            }
+            FieldValue::Integer(i) => format!("{}i", i),
+            FieldValue::UnsignedInteger(u) => format!("{}u", u),
+            FieldValue::String(s) => escape::string_field_value(s),
             FieldValue::Boolean(true) => "true".to_string(),
             FieldValue::Boolean(false) => "false".to_string(),
             FieldValue::Timestamp(ts) => format!("{}i", ts.timestamp_nanos()),
@@ -58,6 +66,27 @@ impl FieldValue {
     }
 }
 
+/// How to write unsigned integer fields
+///
+/// InfluxDB 1.x has no unsigned integer type, so unsigned integers larger
+/// than the largest signed integer cannot be stored as they are.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum UnsignedPolicy {
+    /// Write unsigned integers as they are
+    #[default]
+    Unchanged,
+
+    /// Write unsigned integers as signed integers, rejecting those that do
+    /// not fit
+    CheckedInteger,
+
+    /// Write unsigned integers as floats, possibly losing precision
+    Float,
+
+    /// Reject unsigned integers
+    Error,
+}
+
 impl From<&str> for FieldValue {
     fn from(s: &str) -> Self {
         Self::String(s.to_string())
@@ -100,6 +129,28 @@ impl From<DateTime<Utc>> for FieldValue {
     }
 }
 
+impl TryFrom<Value> for FieldValue {
+    type Error = ValueError;
+
+    /// Convert a value decoded from a query result to a field value
+    ///
+    /// Missing values cannot be written, and result in an error.
+    fn try_from(value: Value) -> Result<Self, Self::Error> {
+        match value {
+            Value::Float(value) => Ok(Self::Float(value)),
+            Value::Integer(value) => Ok(Self::Integer(value)),
+            Value::UnsignedInteger(value) => Ok(Self::UnsignedInteger(value)),
+            Value::String(value) => Ok(Self::String(value)),
+            Value::Boolean(value) => Ok(Self::Boolean(value)),
+            Value::Timestamp(value) => Ok(Self::Timestamp(value)),
+            Value::Null => Err(ValueError::TypeMismatch {
+                expected: "field value",
+                found: Value::Null,
+            }),
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -140,7 +191,7 @@ mod tests {
     fn escape_integer() {
         let value = Faker.fake::<i64>();
         let field_value = FieldValue::Integer(value);
-        let expected = value.to_string();
+        let expected = format!("{}i", value);
 
         assert_eq!(field_value.escape_to_line_protocol(), expected);
     }
@@ -149,7 +200,7 @@ mod tests {
     fn escape_integer_quickcheck(positive_integer: PositiveInteger) {
         let value = positive_integer.0;
         let field_value = FieldValue::Integer(value);
-        let expected = value.to_string();
+        let expected = format!("{}i", value);
 
         assert_eq!(field_value.escape_to_line_protocol(), expected);
     }
@@ -158,18 +209,25 @@ mod tests {
     fn escape_negative_integer() {
         let field_value = FieldValue::Integer(-55);
 
-        assert_eq!(field_value.escape_to_line_protocol(), "-55");
+        assert_eq!(field_value.escape_to_line_protocol(), "-55i");
     }
 
     #[quickcheck]
     fn escape_negative_integer_quickcheck(negative_integer: NegativeInteger) {
         let value = negative_integer.0;
         let field_value = FieldValue::Integer(value);
-        let expected = value.to_string();
+        let expected = format!("{}i", value);
 
         assert_eq!(field_value.escape_to_line_protocol(), expected);
     }
 
+    #[test]
+    fn escape_unsigned_integer() {
+        let field_value = FieldValue::UnsignedInteger(u64::MAX);
+
+        assert_eq!(field_value.escape_to_line_protocol(), "18446744073709551615u");
+    }
+
     #[test]
     fn escape_boolean() {
         let value = FieldValue::Boolean(true);
@@ -206,14 +264,14 @@ mod tests {
 
         assert_eq!(
             value.escape_to_line_protocol(),
-            "\"a string \\\\\"value\\\\\"\""
+            "\"a string \\\"value\\\"\""
         );
     }
 
     #[quickcheck]
     fn escape_string_quickcheck(value: String) {
         let field_value = FieldValue::String(value.clone());
-        let expected = format!("\"{}\"", value.replace("\"", "\\\"").replace("\\", "\\\\"));
+        let expected = format!("\"{}\"", value.replace("\\", "\\\\").replace("\"", "\\\""));
 
         assert_eq!(field_value.escape_to_line_protocol(), expected);
     }
@@ -0,0 +1,65 @@
+// Copyright Claudio Mattera 2021.
+// Distributed under the MIT License or Apache 2.0 License at your option.
+// See accompanying files License-MIT.txt and License-Apache-2.0, or online at
+// https://opensource.org/licenses/MIT
+// https://opensource.org/licenses/Apache-2.0
+
+use super::Line;
+use super::LineBuilder;
+
+/// A type that can be converted to a line in the Influx Line Protocol
+///
+/// Writers accept any type implementing this trait, so that application code
+/// can pass its own domain structs without building lines explicitly.
+///
+/// When feature `derive` is enabled, this trait can be derived for structs
+/// with named fields, using attributes `#[influx(measurement = "...")]`,
+/// `#[influx(tag)]`, `#[influx(timestamp)]`, `#[influx(rename = "...")]` and
+/// `#[influx(skip)]`.
+///
+/// ```
+/// # use chrono::{DateTime, TimeZone, Utc};
+/// # use rinfluxdb_lineprotocol::{IntoLine, Line, LineBuilder};
+/// struct Reading {
+///     room: String,
+///     temperature: f64,
+///     time: DateTime<Utc>,
+/// }
+///
+/// impl IntoLine for Reading {
+///     fn into_line(self) -> Line {
+///         LineBuilder::new("indoor_environment")
+///             .insert_tag("room", self.room)
+///             .insert_field("temperature", self.temperature)
+///             .set_timestamp(self.time)
+///             .build()
+///     }
+/// }
+///
+/// let reading = Reading {
+///     room: "bedroom".into(),
+///     temperature: 21.5,
+///     time: Utc.with_ymd_and_hms(2021, 3, 4, 17, 0, 0).unwrap(),
+/// };
+///
+/// assert_eq!(
+///     reading.into_line().to_string(),
+///     "indoor_environment,room=bedroom temperature=21.5 1614877200000000000",
+/// );
+/// ```
+pub trait IntoLine {
+    /// Convert to a line
+    fn into_line(self) -> Line;
+}
+
+impl IntoLine for Line {
+    fn into_line(self) -> Line {
+        self
+    }
+}
+
+impl IntoLine for LineBuilder {
+    fn into_line(self) -> Line {
+        self.build()
+    }
+}
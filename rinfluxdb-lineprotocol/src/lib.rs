@@ -6,24 +6,41 @@
 
 //! Data types for InfluxDB line protocol
 
-#[cfg(feature = "client")]
+#[cfg(any(feature = "client-blocking", feature = "client-async"))]
 mod client;
 
+mod buffer;
+pub mod escape;
 mod field_name;
 mod field_value;
+mod into_line;
 mod line;
 mod line_builder;
+mod lines;
 mod measurement;
+mod parse;
+mod schema;
 mod tag_name;
 mod tag_value;
+mod validate;
 
-#[cfg(feature = "client")]
+#[cfg(any(feature = "client-blocking", feature = "client-async"))]
 pub use self::client::*;
 
+pub use self::buffer::{BufferMetrics, LineBuffer, OverflowError, OverflowPolicy};
 pub use self::field_name::FieldName;
-pub use self::field_value::FieldValue;
-pub use self::line::Line;
+pub use self::field_value::{FieldValue, UnsignedPolicy};
+pub use self::into_line::IntoLine;
+#[cfg(feature = "derive")]
+pub use rinfluxdb_derive::IntoLine;
+pub use self::line::{normalize_lines, truncate_timestamps, Line, LineWithPrecision};
 pub use self::line_builder::LineBuilder;
+pub use self::lines::Lines;
 pub use self::measurement::Measurement;
+pub use self::parse::{parse_lines, ParseError};
+pub use self::schema::{
+    FieldHandle, FieldType, Schema, SchemaError, SchemaLineBuilder, SchemaType, TagHandle,
+};
 pub use self::tag_name::TagName;
 pub use self::tag_value::TagValue;
+pub use self::validate::{validate_lines, ValidationError};
@@ -4,24 +4,55 @@
 // https://opensource.org/licenses/MIT
 // https://opensource.org/licenses/Apache-2.0
 
-use ::std::collections::HashMap;
+use ::std::collections::BTreeMap;
+
+use ::std::convert::TryFrom;
 
 use ::std::fmt;
 
+use ::std::str::FromStr;
+
 use ::chrono::{DateTime, Utc};
 
+use ::rinfluxdb_types::Precision;
+
 use super::FieldName;
 use super::FieldValue;
+use super::UnsignedPolicy;
 use super::Measurement;
+use super::parse::{parse_line, ParseError};
 use super::TagName;
 use super::TagValue;
+use super::validate::{is_timestamp_in_range, ValidationError};
 
 /// A line in the Influx Line Protocol
+///
+/// Tags and fields are kept sorted by name, so lines are always formatted
+/// the same way, and tags are in the order recommended by InfluxDB for best
+/// write performance.
+///
+/// With feature `serde`, lines can be serialized and deserialized, e.g. to
+/// store them in a queue before sending them.
+///
+/// ```
+/// # #[cfg(feature = "serde")]
+/// # {
+/// # use rinfluxdb_lineprotocol::LineBuilder;
+/// let line = LineBuilder::new("location")
+///     .insert_tag("city", "Odense")
+///     .insert_field("latitude", 55.383333)
+///     .build();
+///
+/// let json = serde_json::to_string(&line).unwrap();
+/// assert_eq!(serde_json::from_str::<rinfluxdb_lineprotocol::Line>(&json).unwrap(), line);
+/// # }
+/// ```
 #[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Line {
     measurement: Measurement,
-    fields: HashMap<FieldName, FieldValue>,
-    tags: HashMap<TagName, TagValue>,
+    fields: BTreeMap<FieldName, FieldValue>,
+    tags: BTreeMap<TagName, TagValue>,
     timestamp: Option<DateTime<Utc>>,
 }
 
@@ -36,8 +67,8 @@ impl Line {
     pub fn new(measurement: impl Into<Measurement>) -> Self {
         Self {
             measurement: measurement.into(),
-            fields: HashMap::new(),
-            tags: HashMap::new(),
+            fields: BTreeMap::new(),
+            tags: BTreeMap::new(),
             timestamp: None,
         }
     }
@@ -53,6 +84,40 @@ impl Line {
         &self.measurement
     }
 
+    /// Set the measurement
+    ///
+    /// ```
+    /// # use rinfluxdb_lineprotocol::Line;
+    /// let mut line = Line::new("measurement");
+    /// line.set_measurement("other_measurement");
+    /// assert_eq!(line.measurement(), &"other_measurement".into());
+    /// ```
+    pub fn set_measurement(&mut self, measurement: impl Into<Measurement>) {
+        self.measurement = measurement.into();
+    }
+
+    /// Rename a field, replacing any field with the new name
+    ///
+    /// Return whether the field was found.
+    ///
+    /// ```
+    /// # use rinfluxdb_lineprotocol::Line;
+    /// let mut line = Line::new("measurement");
+    /// line.insert_field("temp", 21.5);
+    /// assert!(line.rename_field("temp", "temperature"));
+    /// assert_eq!(line.field("temp"), None);
+    /// assert_eq!(line.field("temperature"), Some(&21.5.into()));
+    /// ```
+    pub fn rename_field(&mut self, name: impl Into<FieldName>, new_name: impl Into<FieldName>) -> bool {
+        match self.fields.remove(&name.into()) {
+            Some(value) => {
+                self.fields.insert(new_name.into(), value);
+                true
+            }
+            None => false,
+        }
+    }
+
     /// Insert a field in the line
     ///
     /// ```
@@ -106,14 +171,36 @@ impl Line {
         self.tags.get(&name.into())
     }
 
+    /// Rename a tag, replacing any tag with the new name
+    ///
+    /// Return whether the tag was found.
+    ///
+    /// ```
+    /// # use rinfluxdb_lineprotocol::Line;
+    /// let mut line = Line::new("measurement");
+    /// line.insert_tag("town", "Odense");
+    /// assert!(line.rename_tag("town", "city"));
+    /// assert_eq!(line.tag("town"), None);
+    /// assert_eq!(line.tag("city"), Some(&"Odense".into()));
+    /// ```
+    pub fn rename_tag(&mut self, name: impl Into<TagName>, new_name: impl Into<TagName>) -> bool {
+        match self.tags.remove(&name.into()) {
+            Some(value) => {
+                self.tags.insert(new_name.into(), value);
+                true
+            }
+            None => false,
+        }
+    }
+
     /// Set the line timestamp
     ///
     /// ```
     /// # use rinfluxdb_lineprotocol::Line;
     /// # use chrono::{TimeZone, Utc};
     /// let mut line = Line::new("measurement");
-    /// line.set_timestamp(Utc.ymd(2014, 7, 8).and_hms(9, 10, 11));
-    /// assert_eq!(line.timestamp(), Some(&Utc.ymd(2014, 7, 8).and_hms(9, 10, 11)));
+    /// line.set_timestamp(Utc.with_ymd_and_hms(2014, 7, 8, 9, 10, 11).unwrap());
+    /// assert_eq!(line.timestamp(), Some(&Utc.with_ymd_and_hms(2014, 7, 8, 9, 10, 11).unwrap()));
     /// ```
     pub fn set_timestamp(&mut self, timestamp: DateTime<Utc>) {
         self.timestamp = Some(timestamp);
@@ -125,17 +212,312 @@ impl Line {
     /// # use rinfluxdb_lineprotocol::Line;
     /// # use chrono::{TimeZone, Utc};
     /// let mut line = Line::new("measurement");
-    /// line.set_timestamp(Utc.ymd(2014, 7, 8).and_hms(9, 10, 11));
-    /// assert_eq!(line.timestamp(), Some(&Utc.ymd(2014, 7, 8).and_hms(9, 10, 11)));
+    /// line.set_timestamp(Utc.with_ymd_and_hms(2014, 7, 8, 9, 10, 11).unwrap());
+    /// assert_eq!(line.timestamp(), Some(&Utc.with_ymd_and_hms(2014, 7, 8, 9, 10, 11).unwrap()));
     /// ```
     pub fn timestamp(&self) -> Option<&DateTime<Utc>> {
         self.timestamp.as_ref()
     }
+
+    /// Truncate the line timestamp to a precision
+    ///
+    /// Lines without timestamp are left untouched.
+    ///
+    /// ```
+    /// # use rinfluxdb_lineprotocol::Line;
+    /// # use rinfluxdb_types::Precision;
+    /// # use chrono::{TimeZone, Utc};
+    /// let mut line = Line::new("measurement");
+    /// line.set_timestamp(Utc.with_ymd_and_hms(2014, 7, 8, 9, 10, 11).unwrap() + chrono::Duration::milliseconds(500));
+    /// line.truncate_timestamp(Precision::Seconds);
+    /// assert_eq!(line.timestamp(), Some(&Utc.with_ymd_and_hms(2014, 7, 8, 9, 10, 11).unwrap()));
+    /// ```
+    pub fn truncate_timestamp(&mut self, precision: Precision) {
+        if let Some(timestamp) = self.timestamp {
+            self.timestamp = Some(precision.truncate(timestamp));
+        }
+    }
+
+    /// Format the line with its timestamp in units of a precision
+    ///
+    /// The timestamp is truncated to the precision.
+    /// Servers must be told the precision, otherwise they interpret
+    /// timestamps as nanoseconds.
+    ///
+    /// ```
+    /// # use rinfluxdb_lineprotocol::LineBuilder;
+    /// # use rinfluxdb_types::Precision;
+    /// # use chrono::{TimeZone, Utc};
+    /// let line = LineBuilder::new("measurement")
+    ///     .insert_field("field", 42.0)
+    ///     .set_timestamp(Utc.with_ymd_and_hms(2014, 7, 8, 9, 10, 11).unwrap() + chrono::Duration::milliseconds(500))
+    ///     .build();
+    ///
+    /// assert_eq!(
+    ///     line.with_precision(Precision::Seconds).to_string(),
+    ///     "measurement field=42 1404810611",
+    /// );
+    /// ```
+    pub fn with_precision(&self, precision: Precision) -> LineWithPrecision<'_> {
+        LineWithPrecision {
+            line: self,
+            precision,
+        }
+    }
+
+    /// Format the line with its timestamp in units of a precision, and its
+    /// unsigned integer fields according to a policy
+    ///
+    /// An error is returned if an unsigned integer field is rejected by the
+    /// policy.
+    ///
+    /// ```
+    /// # use rinfluxdb_lineprotocol::{LineBuilder, UnsignedPolicy, ValidationError};
+    /// # use rinfluxdb_types::Precision;
+    /// # use chrono::{TimeZone, Utc};
+    /// let line = LineBuilder::new("measurement")
+    ///     .insert_field("field", u64::MAX)
+    ///     .set_timestamp(Utc.with_ymd_and_hms(2014, 7, 8, 9, 10, 11).unwrap())
+    ///     .build();
+    ///
+    /// assert_eq!(
+    ///     line.to_line_protocol(Precision::Seconds, UnsignedPolicy::Unchanged)?,
+    ///     "measurement field=18446744073709551615u 1404810611",
+    /// );
+    /// assert_eq!(
+    ///     line.to_line_protocol(Precision::Seconds, UnsignedPolicy::Float)?,
+    ///     "measurement field=18446744073709552000 1404810611",
+    /// );
+    /// assert_eq!(
+    ///     line.to_line_protocol(Precision::Seconds, UnsignedPolicy::CheckedInteger),
+    ///     Err(ValidationError::UnsignedOutOfRange("field".into())),
+    /// );
+    /// # Ok::<(), ValidationError>(())
+    /// ```
+    pub fn to_line_protocol(
+        &self,
+        precision: Precision,
+        unsigned: UnsignedPolicy,
+    ) -> Result<String, ValidationError> {
+        let has_unsigned = self
+            .fields
+            .values()
+            .any(|value| matches!(value, FieldValue::UnsignedInteger(_)));
+        if unsigned == UnsignedPolicy::Unchanged || !has_unsigned {
+            return Ok(self.with_precision(precision).to_string());
+        }
+
+        let mut line = self.clone();
+        for (name, value) in line.fields.iter_mut() {
+            if let FieldValue::UnsignedInteger(unsigned_value) = *value {
+                *value = match unsigned {
+                    UnsignedPolicy::Unchanged => FieldValue::UnsignedInteger(unsigned_value),
+                    UnsignedPolicy::CheckedInteger => i64::try_from(unsigned_value)
+                        .map(FieldValue::Integer)
+                        .map_err(|_| ValidationError::UnsignedOutOfRange(name.as_str().to_string()))?,
+                    UnsignedPolicy::Float => FieldValue::Float(unsigned_value as f64),
+                    UnsignedPolicy::Error => {
+                        return Err(ValidationError::UnsignedNotAllowed(name.as_str().to_string()));
+                    }
+                };
+            }
+        }
+        Ok(line.with_precision(precision).to_string())
+    }
+
+    /// Parse a line in the Influx Line Protocol
+    ///
+    /// Escaped characters in measurement, tags and field names are unescaped,
+    /// and field values are parsed according to their type.
+    /// Timestamps, if present, are interpreted as nanoseconds from epoch.
+    ///
+    /// ```
+    /// # use rinfluxdb_lineprotocol::{FieldValue, Line};
+    /// # use chrono::{TimeZone, Utc};
+    /// let line = Line::parse("weather,location=us\\ midwest temperature=82i,raining=f 1465839830100400200")?;
+    ///
+    /// assert_eq!(line.measurement(), &"weather".into());
+    /// assert_eq!(line.tag("location"), Some(&"us midwest".into()));
+    /// assert_eq!(line.field("temperature"), Some(&FieldValue::Integer(82)));
+    /// assert_eq!(line.field("raining"), Some(&FieldValue::Boolean(false)));
+    /// assert_eq!(line.timestamp(), Some(&Utc.timestamp_nanos(1465839830100400200)));
+    /// # Ok::<(), rinfluxdb_lineprotocol::ParseError>(())
+    /// ```
+    pub fn parse(input: &str) -> Result<Self, ParseError> {
+        parse_line(input)
+    }
+
+    /// Check that the line would be accepted by InfluxDB
+    ///
+    /// The line must have at least one field, floating point fields must be
+    /// finite, tag values must not contain newlines, the measurement name
+    /// must not start with `_`, and the timestamp must be within the range
+    /// supported by InfluxDB, from 1677-09-21 to 2262-04-11.
+    ///
+    /// ```
+    /// # use rinfluxdb_lineprotocol::{LineBuilder, ValidationError};
+    /// let line = LineBuilder::new("indoor_environment")
+    ///     .insert_tag("room", "bedroom")
+    ///     .build();
+    ///
+    /// assert_eq!(line.validate(), Err(ValidationError::MissingFields));
+    /// ```
+    pub fn validate(&self) -> Result<(), ValidationError> {
+        if self.measurement.as_str().starts_with('_') {
+            return Err(ValidationError::ReservedMeasurement(
+                self.measurement.as_str().to_string(),
+            ));
+        }
+
+        if self.fields.is_empty() {
+            return Err(ValidationError::MissingFields);
+        }
+
+        if let Some((name, _)) = self.tags.iter().find(|(_, value)| value.as_str().contains('\n')) {
+            return Err(ValidationError::NewlineInTagValue(name.as_str().to_string()));
+        }
+
+        let non_finite = self.fields.iter().find(|(_, value)| match value {
+            FieldValue::Float(value) => !value.is_finite(),
+            _ => false,
+        });
+        if let Some((name, _)) = non_finite {
+            return Err(ValidationError::NonFiniteField(name.as_str().to_string()));
+        }
+
+        match self.timestamp {
+            Some(timestamp) if !is_timestamp_in_range(&timestamp) => {
+                Err(ValidationError::TimestampOutOfRange(timestamp))
+            }
+            _ => Ok(()),
+        }
+    }
 }
 
-impl fmt::Display for Line {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        let mut fields_vector: Vec<String> = self
+impl FromStr for Line {
+    type Err = ParseError;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        parse_line(input)
+    }
+}
+
+/// Truncate the timestamps of all lines to a precision
+///
+/// Lines without timestamp are left untouched.
+///
+/// ```
+/// # use rinfluxdb_lineprotocol::{truncate_timestamps, LineBuilder};
+/// # use rinfluxdb_types::Precision;
+/// # use chrono::{TimeZone, Utc};
+/// let mut lines = vec![
+///     LineBuilder::new("measurement")
+///         .insert_field("field", 42.0)
+///         .set_timestamp(Utc.with_ymd_and_hms(2014, 7, 8, 9, 10, 11).unwrap())
+///         .build(),
+///     LineBuilder::new("measurement")
+///         .insert_field("field", 43.0)
+///         .set_timestamp(Utc.with_ymd_and_hms(2014, 7, 8, 9, 10, 48).unwrap())
+///         .build(),
+/// ];
+///
+/// truncate_timestamps(&mut lines, Precision::Minutes);
+///
+/// assert_eq!(lines[0].timestamp(), Some(&Utc.with_ymd_and_hms(2014, 7, 8, 9, 10, 0).unwrap()));
+/// assert_eq!(lines[1].timestamp(), Some(&Utc.with_ymd_and_hms(2014, 7, 8, 9, 10, 0).unwrap()));
+/// ```
+pub fn truncate_timestamps(lines: &mut [Line], precision: Precision) {
+    for line in lines {
+        line.truncate_timestamp(precision);
+    }
+}
+
+/// Sort lines and merge lines for the same point
+///
+/// Lines are sorted by measurement, tag set and timestamp, the latter
+/// truncated to a precision.
+/// Lines with the same measurement, tag set and timestamp are merged into a
+/// single line, keeping the last value of each field, mirroring how InfluxDB
+/// upserts points.
+/// Lines without timestamp are considered to be at the same time, since the
+/// server assigns them the same timestamp when sent in the same request.
+///
+/// ```
+/// # use rinfluxdb_lineprotocol::{normalize_lines, FieldValue, LineBuilder};
+/// # use rinfluxdb_types::Precision;
+/// # use chrono::{TimeZone, Utc};
+/// let lines = vec![
+///     LineBuilder::new("measurement")
+///         .insert_tag("location", "Odense")
+///         .insert_field("field", 42.0)
+///         .insert_field("other", 1.0)
+///         .set_timestamp(Utc.with_ymd_and_hms(2014, 7, 8, 9, 10, 11).unwrap() + chrono::Duration::milliseconds(200))
+///         .build(),
+///     LineBuilder::new("measurement")
+///         .insert_tag("location", "Copenhagen")
+///         .insert_field("field", 41.0)
+///         .set_timestamp(Utc.with_ymd_and_hms(2014, 7, 8, 9, 10, 11).unwrap())
+///         .build(),
+///     LineBuilder::new("measurement")
+///         .insert_tag("location", "Odense")
+///         .insert_field("field", 43.0)
+///         .set_timestamp(Utc.with_ymd_and_hms(2014, 7, 8, 9, 10, 11).unwrap() + chrono::Duration::milliseconds(700))
+///         .build(),
+/// ];
+///
+/// let lines = normalize_lines(&lines, Precision::Seconds);
+///
+/// assert_eq!(lines.len(), 2);
+/// assert_eq!(lines[0].tag("location"), Some(&"Copenhagen".into()));
+/// assert_eq!(lines[1].tag("location"), Some(&"Odense".into()));
+/// assert_eq!(lines[1].field("field"), Some(&FieldValue::Float(43.0)));
+/// assert_eq!(lines[1].field("other"), Some(&FieldValue::Float(1.0)));
+/// ```
+pub fn normalize_lines(lines: &[Line], precision: Precision) -> Vec<Line> {
+    let mut keyed_lines: Vec<(PointKey, Line)> = lines
+        .iter()
+        .map(|line| {
+            let mut line = line.clone();
+            line.truncate_timestamp(precision);
+            (line.point_key(), line)
+        })
+        .collect();
+
+    // Sorting is stable, so lines for the same point keep their order
+    keyed_lines.sort_by(|(a, _), (b, _)| a.cmp(b));
+
+    let mut normalized: Vec<(PointKey, Line)> = Vec::with_capacity(keyed_lines.len());
+    for (key, line) in keyed_lines {
+        match normalized.last_mut() {
+            Some((last_key, last_line)) if *last_key == key => {
+                last_line.fields.extend(line.fields);
+            }
+            _ => normalized.push((key, line)),
+        }
+    }
+
+    normalized.into_iter().map(|(_, line)| line).collect()
+}
+
+/// Measurement, sorted tag set and timestamp of a line
+type PointKey = (String, Vec<(String, String)>, Option<DateTime<Utc>>);
+
+impl Line {
+    /// Return the key identifying the point of a line
+    fn point_key(&self) -> PointKey {
+        let tags: Vec<(String, String)> = self
+            .tags
+            .iter()
+            .map(|(name, value)| (name.as_str().to_string(), value.as_str().to_string()))
+            .collect();
+
+        (self.measurement.as_str().to_string(), tags, self.timestamp)
+    }
+}
+
+impl Line {
+    fn format(&self, f: &mut fmt::Formatter, precision: Precision) -> fmt::Result {
+        let fields_vector: Vec<String> = self
             .fields
             .iter()
             .map(|(key, value)| {
@@ -146,7 +528,6 @@ impl fmt::Display for Line {
                 )
             })
             .collect();
-        fields_vector.sort();
         let fields_chunk = fields_vector.join(",");
 
         write!(f, "{}", self.measurement.escape_to_line_protocol())?;
@@ -162,14 +543,43 @@ impl fmt::Display for Line {
 
         write!(f, " {}", fields_chunk)?;
 
-        if self.timestamp.is_some() {
-            write!(f, " {}", self.timestamp.unwrap().timestamp_nanos())?;
+        if let Some(timestamp) = self.timestamp {
+            // Compute in 128 bits, since timestamps far from epoch overflow
+            // 64 bits nanoseconds but may still fit in coarser precisions
+            let nanoseconds = timestamp.timestamp() as i128 * 1_000_000_000
+                + timestamp.timestamp_subsec_nanos() as i128;
+            write!(
+                f,
+                " {}",
+                nanoseconds.div_euclid(precision.nanoseconds() as i128),
+            )?;
         }
 
         Ok(())
     }
 }
 
+impl fmt::Display for Line {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.format(f, Precision::Nanoseconds)
+    }
+}
+
+/// A line formatted with timestamps in a precision
+///
+/// This type is returned by [`Line::with_precision()`](Line::with_precision).
+#[derive(Clone, Copy, Debug)]
+pub struct LineWithPrecision<'a> {
+    line: &'a Line,
+    precision: Precision,
+}
+
+impl<'a> fmt::Display for LineWithPrecision<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.line.format(f, self.precision)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -202,13 +612,102 @@ mod tests {
         line.insert_tag("city", "Odense");
         line.insert_field("latitude", FieldValue::Float(55.383333));
         line.insert_field("longitude", FieldValue::Float(10.383333));
-        line.set_timestamp(Utc.ymd(2014, 7, 8).and_hms(9, 10, 11));
+        line.set_timestamp(Utc.with_ymd_and_hms(2014, 7, 8, 9, 10, 11).unwrap());
 
         let expected = "location,city=Odense latitude=55.383333,longitude=10.383333 1404810611000000000";
 
         assert_eq!(line.to_string(), expected);
     }
 
+    #[test]
+    fn display_line_sorts_tags_and_fields() {
+        let mut line = Line::new("location");
+
+        line.insert_tag("region", "Syddanmark");
+        line.insert_tag("country", "Denmark");
+        line.insert_tag("city", "Odense");
+        line.insert_field("longitude", FieldValue::Float(10.383333));
+        line.insert_field("latitude", FieldValue::Float(55.383333));
+        line.insert_field("altitude", FieldValue::Float(13.5));
+
+        let expected = "location,city=Odense,country=Denmark,region=Syddanmark \
+            altitude=13.5,latitude=55.383333,longitude=10.383333";
+
+        assert_eq!(line.to_string(), expected);
+    }
+
+    #[test]
+    fn display_line_beyond_nanoseconds_range() {
+        let mut line = Line::new("location");
+
+        line.insert_field("latitude", FieldValue::Float(55.383333));
+        line.set_timestamp(Utc.with_ymd_and_hms(2300, 1, 1, 0, 0, 0).unwrap());
+
+        assert_eq!(
+            line.with_precision(Precision::Seconds).to_string(),
+            "location latitude=55.383333 10413792000",
+        );
+    }
+
+    #[test]
+    fn normalize_lines_merges_points() {
+        let mut first = Line::new("location");
+        first.insert_tag("city", "Odense");
+        first.insert_tag("country", "Denmark");
+        first.insert_field("latitude", FieldValue::Float(55.0));
+        first.insert_field("longitude", FieldValue::Float(10.0));
+
+        let mut second = Line::new("location");
+        second.insert_tag("country", "Denmark");
+        second.insert_tag("city", "Odense");
+        second.insert_field("latitude", FieldValue::Float(55.383333));
+
+        let mut third = Line::new("location");
+        third.insert_tag("city", "Odense");
+        third.insert_field("latitude", FieldValue::Float(55.383333));
+        third.set_timestamp(Utc.with_ymd_and_hms(2014, 7, 8, 9, 10, 11).unwrap());
+
+        let lines = normalize_lines(&[third.clone(), first, second], Precision::Nanoseconds);
+
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0], third);
+        assert_eq!(
+            lines[1],
+            Line::parse("location,city=Odense,country=Denmark latitude=55.383333,longitude=10").unwrap(),
+        );
+    }
+
+    #[test]
+    fn to_line_protocol_unsigned_policies() {
+        let mut line = Line::new("measurement");
+        line.insert_field("field", FieldValue::UnsignedInteger(42));
+
+        assert_eq!(
+            line.to_line_protocol(Precision::Nanoseconds, UnsignedPolicy::Unchanged),
+            Ok("measurement field=42u".to_string()),
+        );
+        assert_eq!(
+            line.to_line_protocol(Precision::Nanoseconds, UnsignedPolicy::CheckedInteger),
+            Ok("measurement field=42i".to_string()),
+        );
+        assert_eq!(
+            line.to_line_protocol(Precision::Nanoseconds, UnsignedPolicy::Float),
+            Ok("measurement field=42".to_string()),
+        );
+        assert_eq!(
+            line.to_line_protocol(Precision::Nanoseconds, UnsignedPolicy::Error),
+            Err(ValidationError::UnsignedNotAllowed("field".into())),
+        );
+    }
+
+    #[test]
+    fn parse_and_display_preserve_field_types() {
+        let input = "measurement boolean=true,float=1.5,integer=-82i,string=\"text\",unsigned=82u 1465839830100400200";
+        let line = Line::parse(input).unwrap();
+
+        assert_eq!(line.to_string(), input);
+    }
+
     #[quickcheck]
     #[ignore]
     fn display_line_quickcheck(line: Line) {
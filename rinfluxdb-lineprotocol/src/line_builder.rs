@@ -72,9 +72,9 @@ impl LineBuilder {
     /// # use rinfluxdb_lineprotocol::LineBuilder;
     /// # use chrono::{TimeZone, Utc};
     /// let line = LineBuilder::new("measurement")
-    ///     .set_timestamp(Utc.ymd(2014, 7, 8).and_hms(9, 10, 11))
+    ///     .set_timestamp(Utc.with_ymd_and_hms(2014, 7, 8, 9, 10, 11).unwrap())
     ///     .build();
-    /// assert_eq!(line.timestamp(), Some(&Utc.ymd(2014, 7, 8).and_hms(9, 10, 11)));
+    /// assert_eq!(line.timestamp(), Some(&Utc.with_ymd_and_hms(2014, 7, 8, 9, 10, 11).unwrap()));
     /// ```
     pub fn set_timestamp(self, timestamp: DateTime<Utc>) -> Self {
         let mut line = self.line;
@@ -91,13 +91,13 @@ impl LineBuilder {
     ///     .insert_field("latitude", 55.383333)
     ///     .insert_field("longitude", 10.383333)
     ///     .insert_tag("city", "Odense")
-    ///     .set_timestamp(Utc.ymd(2014, 7, 8).and_hms(9, 10, 11))
+    ///     .set_timestamp(Utc.with_ymd_and_hms(2014, 7, 8, 9, 10, 11).unwrap())
     ///     .build();
     /// assert_eq!(line.measurement(), &"measurement".into());
     /// assert_eq!(line.field("latitude"), Some(&55.383333.into()));
     /// assert_eq!(line.field("longitude"), Some(&10.383333.into()));
     /// assert_eq!(line.tag("city"), Some(&"Odense".into()));
-    /// assert_eq!(line.timestamp(), Some(&Utc.ymd(2014, 7, 8).and_hms(9, 10, 11)));
+    /// assert_eq!(line.timestamp(), Some(&Utc.with_ymd_and_hms(2014, 7, 8, 9, 10, 11).unwrap()));
     /// ```
     pub fn build(self) -> Line {
         self.line
@@ -116,14 +116,14 @@ mod tests {
             .insert_tag("city", "Odense")
             .insert_field("latitude", FieldValue::Float(55.383333))
             .insert_field("longitude", FieldValue::Float(10.383333))
-            .set_timestamp(Utc.ymd(2014, 7, 8).and_hms(9, 10, 11))
+            .set_timestamp(Utc.with_ymd_and_hms(2014, 7, 8, 9, 10, 11).unwrap())
             .build();
 
         let mut expected = Line::new("location");
         expected.insert_tag("city", "Odense");
         expected.insert_field("latitude", FieldValue::Float(55.383333));
         expected.insert_field("longitude", FieldValue::Float(10.383333));
-        expected.set_timestamp(Utc.ymd(2014, 7, 8).and_hms(9, 10, 11));
+        expected.set_timestamp(Utc.with_ymd_and_hms(2014, 7, 8, 9, 10, 11).unwrap());
 
         assert_eq!(actual, expected);
     }
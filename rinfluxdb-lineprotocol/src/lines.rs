@@ -0,0 +1,204 @@
+// Copyright Claudio Mattera 2021.
+// Distributed under the MIT License or Apache 2.0 License at your option.
+// See accompanying files License-MIT.txt and License-Apache-2.0, or online at
+// https://opensource.org/licenses/MIT
+// https://opensource.org/licenses/Apache-2.0
+
+use ::std::collections::HashMap;
+use ::std::convert::TryFrom;
+use ::std::ops::{Deref, DerefMut};
+
+use ::chrono::{DateTime, Utc};
+
+use ::rinfluxdb_types::{Columns, DataFrameError, FromQueryResult};
+
+use super::FieldValue;
+use super::Line;
+use super::TagName;
+use super::TagValue;
+
+/// A list of lines in the Influx Line Protocol
+///
+/// This type can be used as a target when decoding query results, so that
+/// they can be sent back to a server without an intermediate dataframe.
+/// Each row in the results is converted to a line, using the same
+/// measurement name and timestamp, and with one field for each column.
+///
+/// The tags of series returned by queries grouped by tags are inserted in
+/// all lines, and other tags can be added afterwards with
+/// [`insert_tag`](Lines::insert_tag).
+///
+/// ```
+/// # use std::convert::TryFrom;
+/// # use chrono::{TimeZone, Utc};
+/// # use rinfluxdb_types::{Columns, Value};
+/// # use rinfluxdb_lineprotocol::Lines;
+/// let index = vec![
+///     Utc.with_ymd_and_hms(2021, 3, 4, 17, 0, 0).unwrap(),
+///     Utc.with_ymd_and_hms(2021, 3, 4, 18, 0, 0).unwrap(),
+/// ];
+/// let mut columns = Columns::new();
+/// columns.insert("temperature".to_string(), vec![Value::Float(28.4), Value::Float(29.2)]);
+///
+/// let mut lines = Lines::try_from(("environment".to_string(), index, columns))?;
+/// lines.insert_tag("room", "bedroom");
+///
+/// assert_eq!(lines.len(), 2);
+/// assert_eq!(
+///     lines[0].to_string(),
+///     "environment,room=bedroom temperature=28.4 1614877200000000000",
+/// );
+/// # Ok::<(), rinfluxdb_types::DataFrameError>(())
+/// ```
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Lines(pub Vec<Line>);
+
+impl Lines {
+    /// Insert a tag in all lines
+    pub fn insert_tag(&mut self, name: impl Into<TagName>, value: impl Into<TagValue>) {
+        let name = name.into();
+        let value = value.into();
+        for line in &mut self.0 {
+            line.insert_tag(name.clone(), value.clone());
+        }
+    }
+}
+
+impl TryFrom<(String, Vec<DateTime<Utc>>, Columns)> for Lines {
+    type Error = DataFrameError;
+
+    fn try_from(
+        (name, index, columns): (String, Vec<DateTime<Utc>>, Columns),
+    ) -> Result<Self, Self::Error> {
+        if columns.values().any(|column| column.len() != index.len()) {
+            return Err(DataFrameError::Creation);
+        }
+
+        let mut lines: Vec<Line> = index
+            .into_iter()
+            .map(|timestamp| {
+                let mut line = Line::new(name.as_str());
+                line.set_timestamp(timestamp);
+                line
+            })
+            .collect();
+
+        for (field, column) in columns {
+            for (line, value) in lines.iter_mut().zip(column) {
+                if !value.is_null() {
+                    line.insert_field(field.as_str(), FieldValue::try_from(value)?);
+                }
+            }
+        }
+
+        Ok(Lines(lines))
+    }
+}
+
+impl FromQueryResult for Lines {
+    type Error = DataFrameError;
+
+    fn from_query_result(
+        name: String,
+        index: Vec<DateTime<Utc>>,
+        columns: Columns,
+        tags: Option<&HashMap<String, String>>,
+    ) -> Result<Self, Self::Error> {
+        let mut lines = Self::try_from((name, index, columns))?;
+        for (name, value) in tags.into_iter().flatten() {
+            lines.insert_tag(name.as_str(), value.as_str());
+        }
+        Ok(lines)
+    }
+}
+
+impl From<Lines> for Vec<Line> {
+    fn from(lines: Lines) -> Self {
+        lines.0
+    }
+}
+
+impl From<Vec<Line>> for Lines {
+    fn from(lines: Vec<Line>) -> Self {
+        Lines(lines)
+    }
+}
+
+impl Deref for Lines {
+    type Target = Vec<Line>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl DerefMut for Lines {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use chrono::TimeZone;
+
+    use rinfluxdb_types::Value;
+
+    #[test]
+    fn mismatched_lengths() {
+        let index = vec![Utc.with_ymd_and_hms(2021, 3, 4, 17, 0, 0).unwrap()];
+        let mut columns = Columns::new();
+        columns.insert(
+            "temperature".to_string(),
+            vec![Value::Float(28.4), Value::Float(29.2)],
+        );
+
+        let result = Lines::try_from(("environment".to_string(), index, columns));
+
+        assert!(matches!(result, Err(DataFrameError::Creation)));
+    }
+
+    #[test]
+    fn one_line_per_row() {
+        let index = vec![
+            Utc.with_ymd_and_hms(2021, 3, 4, 17, 0, 0).unwrap(),
+            Utc.with_ymd_and_hms(2021, 3, 4, 18, 0, 0).unwrap(),
+        ];
+        let mut columns = Columns::new();
+        columns.insert(
+            "temperature".to_string(),
+            vec![Value::Float(28.4), Value::Float(29.2)],
+        );
+        columns.insert(
+            "window".to_string(),
+            vec![Value::Boolean(true), Value::Boolean(false)],
+        );
+
+        let lines = Lines::try_from(("environment".to_string(), index, columns)).unwrap();
+
+        let actual: Vec<String> = lines.iter().map(|line| line.to_string()).collect();
+        let expected = vec![
+            "environment temperature=28.4,window=true 1614877200000000000".to_string(),
+            "environment temperature=29.2,window=false 1614880800000000000".to_string(),
+        ];
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn tags_from_query_result() {
+        let index = vec![Utc.with_ymd_and_hms(2021, 3, 4, 17, 0, 0).unwrap()];
+        let mut columns = Columns::new();
+        columns.insert("temperature".to_string(), vec![Value::Float(28.4)]);
+        let mut tags = HashMap::new();
+        tags.insert("room".to_string(), "bedroom".to_string());
+
+        let lines = Lines::from_query_result("environment".to_string(), index, columns, Some(&tags)).unwrap();
+
+        assert_eq!(
+            lines[0].to_string(),
+            "environment,room=bedroom temperature=28.4 1614877200000000000",
+        );
+    }
+}
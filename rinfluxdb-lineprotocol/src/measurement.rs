@@ -4,19 +4,25 @@
 // https://opensource.org/licenses/MIT
 // https://opensource.org/licenses/Apache-2.0
 
+use super::escape;
+
 /// Represent a measurement
-#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+#[derive(Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(transparent))]
 pub struct Measurement(String);
 
 impl Measurement {
+    /// Return the unescaped measurement name
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
     /// Escape a measurement to [InfluxDB line protocol](https://docs.influxdata.com/influxdb/v1.8/write_protocols/line_protocol_reference/)
     ///
-    /// The name is enclosed in double quotes, and characters ` `, `,` and `=` are escaped.
+    /// Characters ` ` and `,` are escaped, see [`escape::measurement()`].
     pub fn escape_to_line_protocol(&self) -> String {
-        self.0
-            .replace(" ", "\\ ")
-            .replace(",", "\\,")
-            .replace("=", "\\=")
+        escape::measurement(&self.0)
     }
 }
 
@@ -0,0 +1,353 @@
+// Copyright Claudio Mattera 2021.
+// Distributed under the MIT License or Apache 2.0 License at your option.
+// See accompanying files License-MIT.txt and License-Apache-2.0, or online at
+// https://opensource.org/licenses/MIT
+// https://opensource.org/licenses/Apache-2.0
+
+use chrono::{TimeZone, Utc};
+
+use thiserror::Error;
+
+use super::escape::{KEY_SPECIAL_CHARACTERS, MEASUREMENT_SPECIAL_CHARACTERS};
+use super::FieldValue;
+use super::Line;
+
+/// An error occurred while parsing the Influx Line Protocol
+#[derive(Error, Clone, Debug, PartialEq, Eq)]
+pub enum ParseError {
+    /// Line has no measurement
+    #[error("Missing measurement")]
+    MissingMeasurement,
+
+    /// Line has no fields
+    #[error("Missing fields")]
+    MissingFields,
+
+    /// Tag is not in the form `name=value`
+    #[error("Invalid tag: {0}")]
+    InvalidTag(String),
+
+    /// Field is not in the form `name=value`
+    #[error("Invalid field: {0}")]
+    InvalidField(String),
+
+    /// Field value is not a valid float, integer, string or boolean
+    #[error("Invalid field value: {0}")]
+    InvalidFieldValue(String),
+
+    /// Timestamp is not a valid integer
+    #[error("Invalid timestamp: {0}")]
+    InvalidTimestamp(String),
+
+    /// Error occurred on a specific line of a multi-line input
+    #[error("Error at line {line}: {error}")]
+    AtLine {
+        /// The line number, starting from 1
+        line: usize,
+
+        /// The error
+        error: Box<ParseError>,
+    },
+}
+
+/// Parse multiple lines in the Influx Line Protocol
+///
+/// Empty lines and comments starting with `#` are skipped.
+/// Errors are reported together with the number of the offending line.
+///
+/// ```
+/// # use rinfluxdb_lineprotocol::{parse_lines, FieldValue, ParseError};
+/// let input = "# Telegraf output\n\
+///     cpu,host=server01 usage_idle=97.5,processes=312i 1614877200000000000\n\
+///     \n\
+///     cpu,host=server02 usage_idle=99.1,processes=287i 1614877200000000000\n";
+///
+/// let lines = parse_lines(input)?;
+///
+/// assert_eq!(lines.len(), 2);
+/// assert_eq!(lines[1].tag("host"), Some(&"server02".into()));
+/// assert_eq!(lines[1].field("processes"), Some(&FieldValue::Integer(287)));
+///
+/// match parse_lines("cpu usage_idle=97.5\ncpu usage_idle=high") {
+///     Err(ParseError::AtLine { line, .. }) => assert_eq!(line, 2),
+///     _ => panic!("Expected an error"),
+/// }
+/// # Ok::<(), ParseError>(())
+/// ```
+pub fn parse_lines(input: &str) -> Result<Vec<Line>, ParseError> {
+    input
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| {
+            let line = line.trim();
+            !line.is_empty() && !line.starts_with('#')
+        })
+        .map(|(index, line)| {
+            parse_line(line).map_err(|error| ParseError::AtLine {
+                line: index + 1,
+                error: Box::new(error),
+            })
+        })
+        .collect()
+}
+
+/// Parse a single line in the Influx Line Protocol
+pub(crate) fn parse_line(input: &str) -> Result<Line, ParseError> {
+    let mut cursor = Cursor::new(input.trim_end_matches(&['\r', '\n'][..]));
+
+    let measurement = cursor.read_escaped(&[',', ' '], &MEASUREMENT_SPECIAL_CHARACTERS);
+    if measurement.is_empty() {
+        return Err(ParseError::MissingMeasurement);
+    }
+    let mut line = Line::new(measurement);
+
+    while cursor.next_if(',') {
+        let name = cursor.read_escaped(&['=', ',', ' '], &KEY_SPECIAL_CHARACTERS);
+        if name.is_empty() || !cursor.next_if('=') {
+            return Err(ParseError::InvalidTag(name));
+        }
+        let value = cursor.read_escaped(&[',', ' '], &KEY_SPECIAL_CHARACTERS);
+        if value.is_empty() {
+            return Err(ParseError::InvalidTag(name));
+        }
+        line.insert_tag(name, value);
+    }
+
+    if !cursor.next_if(' ') || cursor.is_at_end() {
+        return Err(ParseError::MissingFields);
+    }
+
+    loop {
+        let name = cursor.read_escaped(&['=', ',', ' '], &KEY_SPECIAL_CHARACTERS);
+        if name.is_empty() || !cursor.next_if('=') {
+            return Err(ParseError::InvalidField(name));
+        }
+        let value = if cursor.peek() == Some('"') {
+            cursor.read_string()?
+        } else {
+            parse_field_value(&cursor.read_raw(&[',', ' ']))?
+        };
+        line.insert_field(name, value);
+
+        if !cursor.next_if(',') {
+            break;
+        }
+    }
+
+    if cursor.next_if(' ') {
+        let text = cursor.read_raw(&[]);
+        let nanoseconds: i64 = text
+            .parse()
+            .map_err(|_| ParseError::InvalidTimestamp(text.clone()))?;
+        line.set_timestamp(Utc.timestamp_nanos(nanoseconds));
+    } else if !cursor.is_at_end() {
+        return Err(ParseError::InvalidTimestamp(cursor.read_raw(&[])));
+    }
+
+    Ok(line)
+}
+
+fn parse_field_value(text: &str) -> Result<FieldValue, ParseError> {
+    let invalid = || ParseError::InvalidFieldValue(text.to_string());
+
+    let value = match text {
+        "t" | "T" | "true" | "True" | "TRUE" => FieldValue::Boolean(true),
+        "f" | "F" | "false" | "False" | "FALSE" => FieldValue::Boolean(false),
+        _ => {
+            if let Some(integer) = text.strip_suffix('i') {
+                FieldValue::Integer(integer.parse().map_err(|_| invalid())?)
+            } else if let Some(integer) = text.strip_suffix('u') {
+                FieldValue::UnsignedInteger(integer.parse().map_err(|_| invalid())?)
+            } else {
+                let float: f64 = text.parse().map_err(|_| invalid())?;
+                if !float.is_finite() {
+                    return Err(invalid());
+                }
+                FieldValue::Float(float)
+            }
+        }
+    };
+
+    Ok(value)
+}
+
+struct Cursor {
+    characters: Vec<char>,
+    position: usize,
+}
+
+impl Cursor {
+    fn new(input: &str) -> Self {
+        Self {
+            characters: input.chars().collect(),
+            position: 0,
+        }
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.characters.get(self.position).copied()
+    }
+
+    fn is_at_end(&self) -> bool {
+        self.position >= self.characters.len()
+    }
+
+    fn next_if(&mut self, expected: char) -> bool {
+        if self.peek() == Some(expected) {
+            self.position += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Read until an unescaped terminator, removing escape backslashes
+    ///
+    /// A backslash only escapes one of the special characters, otherwise it
+    /// is kept literally.
+    fn read_escaped(&mut self, terminators: &[char], special_characters: &[char]) -> String {
+        let mut text = String::new();
+        while let Some(character) = self.peek() {
+            if terminators.contains(&character) {
+                break;
+            }
+            self.position += 1;
+            if character == '\\' {
+                if let Some(next) = self.peek() {
+                    if special_characters.contains(&next) {
+                        self.position += 1;
+                        text.push(next);
+                        continue;
+                    }
+                }
+            }
+            text.push(character);
+        }
+        text
+    }
+
+    /// Read until a terminator
+    fn read_raw(&mut self, terminators: &[char]) -> String {
+        let mut text = String::new();
+        while let Some(character) = self.peek() {
+            if terminators.contains(&character) {
+                break;
+            }
+            self.position += 1;
+            text.push(character);
+        }
+        text
+    }
+
+    /// Read a double-quoted string field value
+    fn read_string(&mut self) -> Result<FieldValue, ParseError> {
+        let start = self.position;
+        self.position += 1;
+
+        let mut text = String::new();
+        while let Some(character) = self.peek() {
+            self.position += 1;
+            match character {
+                '"' => return Ok(FieldValue::String(text)),
+                '\\' => match self.peek() {
+                    Some(next @ '"') | Some(next @ '\\') => {
+                        self.position += 1;
+                        text.push(next);
+                    }
+                    _ => text.push(character),
+                },
+                _ => text.push(character),
+            }
+        }
+
+        let unterminated: String = self.characters[start..].iter().collect();
+        Err(ParseError::InvalidFieldValue(unterminated))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use chrono::TimeZone;
+
+    #[test]
+    fn minimal_line() {
+        let line = parse_line("measurement value=1").unwrap();
+
+        let mut expected = Line::new("measurement");
+        expected.insert_field("value", FieldValue::Float(1.0));
+
+        assert_eq!(line, expected);
+    }
+
+    #[test]
+    fn full_line() {
+        let input = "location,city=Odense latitude=55.383333,longitude=10.383333 1404810611000000000";
+        let line = parse_line(input).unwrap();
+
+        let mut expected = Line::new("location");
+        expected.insert_tag("city", "Odense");
+        expected.insert_field("latitude", FieldValue::Float(55.383333));
+        expected.insert_field("longitude", FieldValue::Float(10.383333));
+        expected.set_timestamp(Utc.with_ymd_and_hms(2014, 7, 8, 9, 10, 11).unwrap());
+
+        assert_eq!(line, expected);
+        assert_eq!(line.to_string(), input);
+    }
+
+    #[test]
+    fn field_value_types() {
+        let input = r#"m f=-1.5e3,i=-42i,u=42u,s="a \"quoted\" \\ string, with spaces",b=T,c=false"#;
+        let line = parse_line(input).unwrap();
+
+        assert_eq!(line.field("f"), Some(&FieldValue::Float(-1500.0)));
+        assert_eq!(line.field("i"), Some(&FieldValue::Integer(-42)));
+        assert_eq!(line.field("u"), Some(&FieldValue::UnsignedInteger(42)));
+        assert_eq!(
+            line.field("s"),
+            Some(&FieldValue::String(
+                r#"a "quoted" \ string, with spaces"#.into()
+            )),
+        );
+        assert_eq!(line.field("b"), Some(&FieldValue::Boolean(true)));
+        assert_eq!(line.field("c"), Some(&FieldValue::Boolean(false)));
+    }
+
+    #[test]
+    fn escaped_identifiers() {
+        let input = r"my\ measurement,my\,tag=a\=b my\ field=1 -1";
+        let line = parse_line(input).unwrap();
+
+        assert_eq!(line.measurement(), &"my measurement".into());
+        assert_eq!(line.tag("my,tag"), Some(&"a=b".into()));
+        assert_eq!(line.field("my field"), Some(&FieldValue::Float(1.0)));
+        assert_eq!(line.timestamp(), Some(&Utc.timestamp_nanos(-1)));
+        assert_eq!(line.to_string(), input);
+    }
+
+    #[test]
+    fn invalid_lines() {
+        assert_eq!(parse_line(""), Err(ParseError::MissingMeasurement));
+        assert_eq!(parse_line("m"), Err(ParseError::MissingFields));
+        assert_eq!(parse_line("m "), Err(ParseError::MissingFields));
+        assert_eq!(parse_line("m,t value=1"), Err(ParseError::InvalidTag("t".into())));
+        assert_eq!(parse_line("m value"), Err(ParseError::InvalidField("value".into())));
+        assert_eq!(
+            parse_line("m value=1x"),
+            Err(ParseError::InvalidFieldValue("1x".into())),
+        );
+        assert_eq!(
+            parse_line("m value=inf"),
+            Err(ParseError::InvalidFieldValue("inf".into())),
+        );
+        assert_eq!(
+            parse_line(r#"m value="open"#),
+            Err(ParseError::InvalidFieldValue(r#""open"#.into())),
+        );
+        assert_eq!(
+            parse_line("m value=1 yesterday"),
+            Err(ParseError::InvalidTimestamp("yesterday".into())),
+        );
+    }
+}
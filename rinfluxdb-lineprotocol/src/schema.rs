@@ -0,0 +1,482 @@
+// Copyright Claudio Mattera 2021.
+// Distributed under the MIT License or Apache 2.0 License at your option.
+// See accompanying files License-MIT.txt and License-Apache-2.0, or online at
+// https://opensource.org/licenses/MIT
+// https://opensource.org/licenses/Apache-2.0
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::marker::PhantomData;
+
+use chrono::{DateTime, Utc};
+
+use thiserror::Error;
+
+use rinfluxdb_types::{Columns, Value};
+
+use super::FieldValue;
+use super::Line;
+use super::LineBuilder;
+use super::TagValue;
+
+/// The type of a field in a measurement schema
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum FieldType {
+    /// Floating point number
+    Float,
+
+    /// Signed integer number
+    Integer,
+
+    /// Unsigned integer number
+    UnsignedInteger,
+
+    /// String
+    String,
+
+    /// Boolean
+    Boolean,
+
+    /// Instant, represented as a nanosecond timestamp
+    Timestamp,
+}
+
+impl FieldType {
+    fn of(value: &Value) -> Option<Self> {
+        match value {
+            Value::Float(_) => Some(FieldType::Float),
+            Value::Integer(_) => Some(FieldType::Integer),
+            Value::UnsignedInteger(_) => Some(FieldType::UnsignedInteger),
+            Value::String(_) => Some(FieldType::String),
+            Value::Boolean(_) => Some(FieldType::Boolean),
+            Value::Timestamp(_) => Some(FieldType::Timestamp),
+            Value::Null => None,
+        }
+    }
+
+    /// Check whether a value decoded from a query result has this type
+    ///
+    /// Query results do not always distinguish integral floats from
+    /// integers, so integer values are accepted for float fields.
+    fn accepts(self, found: FieldType) -> bool {
+        match (self, found) {
+            (FieldType::Float, FieldType::Integer) => true,
+            (FieldType::Float, FieldType::UnsignedInteger) => true,
+            (expected, found) => expected == found,
+        }
+    }
+}
+
+/// A Rust type that can be used for fields in a measurement schema
+pub trait SchemaType: Into<FieldValue> {
+    /// The corresponding field type
+    const FIELD_TYPE: FieldType;
+}
+
+impl SchemaType for f64 {
+    const FIELD_TYPE: FieldType = FieldType::Float;
+}
+
+impl SchemaType for i64 {
+    const FIELD_TYPE: FieldType = FieldType::Integer;
+}
+
+impl SchemaType for u64 {
+    const FIELD_TYPE: FieldType = FieldType::UnsignedInteger;
+}
+
+impl SchemaType for String {
+    const FIELD_TYPE: FieldType = FieldType::String;
+}
+
+impl SchemaType for bool {
+    const FIELD_TYPE: FieldType = FieldType::Boolean;
+}
+
+impl SchemaType for DateTime<Utc> {
+    const FIELD_TYPE: FieldType = FieldType::Timestamp;
+}
+
+/// An error occurred while checking data against a measurement schema
+#[derive(Error, Clone, Debug, PartialEq, Eq)]
+pub enum SchemaError {
+    /// Data belongs to a different measurement
+    #[error("Expected measurement {expected}, found {found}")]
+    MeasurementMismatch {
+        /// The measurement in the schema
+        expected: String,
+
+        /// The measurement in the data
+        found: String,
+    },
+
+    /// Tag is not in the schema
+    #[error("Unknown tag {0}")]
+    UnknownTag(String),
+
+    /// Field is not in the schema
+    #[error("Unknown field {0}")]
+    UnknownField(String),
+
+    /// Tag has a non-string value
+    #[error("Tag {0} has a non-string value")]
+    InvalidTagValue(String),
+
+    /// Field has a different type than in the schema
+    #[error("Field {name} has type {found:?} instead of {expected:?}")]
+    FieldTypeConflict {
+        /// The field name
+        name: String,
+
+        /// The type in the schema
+        expected: FieldType,
+
+        /// The type in the data
+        found: FieldType,
+    },
+
+    /// Line has no fields
+    #[error("Missing fields")]
+    MissingFields,
+}
+
+/// A handle to a tag in a measurement schema
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TagHandle {
+    name: String,
+}
+
+impl TagHandle {
+    /// Return the tag name
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+/// A handle to a field of type `T` in a measurement schema
+#[derive(Debug, PartialEq, Eq)]
+pub struct FieldHandle<T> {
+    name: String,
+    field_type: PhantomData<T>,
+}
+
+impl<T> FieldHandle<T> {
+    /// Return the field name
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+impl<T> Clone for FieldHandle<T> {
+    fn clone(&self) -> Self {
+        Self {
+            name: self.name.clone(),
+            field_type: PhantomData,
+        }
+    }
+}
+
+/// The tags and fields of a measurement, described once and reused
+///
+/// Defining a tag or field returns a typed handle.
+/// Lines built through handles cannot contain fields of the wrong type,
+/// since values are checked at compile time.
+///
+/// ```
+/// # use rinfluxdb_types::{Columns, Value};
+/// # use rinfluxdb_lineprotocol::{FieldValue, Schema, SchemaError};
+/// let mut schema = Schema::new("indoor_environment");
+/// let room = schema.tag("room");
+/// let temperature = schema.field::<f64>("temperature");
+/// let occupied = schema.field::<bool>("occupied");
+///
+/// let line = schema.line()
+///     .tag(&room, "bedroom")
+///     .field(&temperature, 21.5)
+///     .field(&occupied, true)
+///     .build()?;
+///
+/// assert_eq!(line.field("temperature"), Some(&FieldValue::Float(21.5)));
+///
+/// // This would not compile, since temperature is a float field
+/// // schema.line().field(&temperature, "hot");
+///
+/// let mut columns = Columns::new();
+/// columns.insert("temperature".to_string(), vec![Value::String("hot".into())]);
+///
+/// assert!(matches!(
+///     schema.validate("indoor_environment", &columns),
+///     Err(SchemaError::FieldTypeConflict { .. }),
+/// ));
+/// # Ok::<(), SchemaError>(())
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Schema {
+    measurement: String,
+    tags: BTreeSet<String>,
+    fields: BTreeMap<String, FieldType>,
+}
+
+impl Schema {
+    /// Create a new schema for a measurement without tags and fields
+    pub fn new<T>(measurement: T) -> Self
+    where
+        T: Into<String>,
+    {
+        Self {
+            measurement: measurement.into(),
+            tags: BTreeSet::new(),
+            fields: BTreeMap::new(),
+        }
+    }
+
+    /// Return the measurement name
+    pub fn measurement(&self) -> &str {
+        &self.measurement
+    }
+
+    /// Define a tag and return a handle to it
+    pub fn tag<T>(&mut self, name: T) -> TagHandle
+    where
+        T: Into<String>,
+    {
+        let name = name.into();
+        self.tags.insert(name.clone());
+        TagHandle { name }
+    }
+
+    /// Define a field of type `T` and return a handle to it
+    ///
+    /// Defining a field again replaces its type.
+    pub fn field<T>(&mut self, name: impl Into<String>) -> FieldHandle<T>
+    where
+        T: SchemaType,
+    {
+        let name = name.into();
+        self.fields.insert(name.clone(), T::FIELD_TYPE);
+        FieldHandle {
+            name,
+            field_type: PhantomData,
+        }
+    }
+
+    /// Return the tag names
+    pub fn tags(&self) -> impl Iterator<Item = &str> {
+        self.tags.iter().map(String::as_str)
+    }
+
+    /// Return the field names and types
+    pub fn fields(&self) -> impl Iterator<Item = (&str, FieldType)> {
+        self.fields.iter().map(|(name, field_type)| (name.as_str(), *field_type))
+    }
+
+    /// Return the type of a field, if it is defined
+    pub fn field_type(&self, name: &str) -> Option<FieldType> {
+        self.fields.get(name).copied()
+    }
+
+    /// Create a builder for a line of this measurement
+    pub fn line(&self) -> SchemaLineBuilder<'_> {
+        SchemaLineBuilder {
+            schema: self,
+            builder: LineBuilder::new(self.measurement.as_str()),
+            has_fields: false,
+            error: None,
+        }
+    }
+
+    /// Validate columns decoded from a query result
+    ///
+    /// Every column must be a tag with string values or a field with values
+    /// of the right type.
+    /// Fields missing from the columns are not an error, since queries can
+    /// select a subset of them, and neither are missing values.
+    pub fn validate(
+        &self,
+        measurement: &str,
+        columns: &Columns,
+    ) -> Result<(), SchemaError> {
+        if measurement != self.measurement {
+            return Err(SchemaError::MeasurementMismatch {
+                expected: self.measurement.clone(),
+                found: measurement.to_string(),
+            });
+        }
+
+        for (name, values) in columns {
+            if let Some(&expected) = self.fields.get(name) {
+                let conflict = values
+                    .iter()
+                    .filter_map(FieldType::of)
+                    .find(|found| !expected.accepts(*found));
+                if let Some(found) = conflict {
+                    return Err(SchemaError::FieldTypeConflict {
+                        name: name.clone(),
+                        expected,
+                        found,
+                    });
+                }
+            } else if self.tags.contains(name) {
+                if values.iter().any(|value| !matches!(value, Value::String(_) | Value::Null)) {
+                    return Err(SchemaError::InvalidTagValue(name.clone()));
+                }
+            } else {
+                return Err(SchemaError::UnknownField(name.clone()));
+            }
+        }
+
+        Ok(())
+    }
+
+    fn check_tag(&self, tag: &TagHandle) -> Result<(), SchemaError> {
+        if self.tags.contains(&tag.name) {
+            Ok(())
+        } else {
+            Err(SchemaError::UnknownTag(tag.name.clone()))
+        }
+    }
+
+    fn check_field<T: SchemaType>(&self, field: &FieldHandle<T>) -> Result<(), SchemaError> {
+        match self.fields.get(&field.name) {
+            Some(&expected) if expected == T::FIELD_TYPE => Ok(()),
+            Some(&expected) => Err(SchemaError::FieldTypeConflict {
+                name: field.name.clone(),
+                expected,
+                found: T::FIELD_TYPE,
+            }),
+            None => Err(SchemaError::UnknownField(field.name.clone())),
+        }
+    }
+}
+
+/// Build a line of a measurement through typed handles
+///
+/// Handles from a different schema are rejected when building the line.
+#[derive(Clone, Debug)]
+pub struct SchemaLineBuilder<'a> {
+    schema: &'a Schema,
+    builder: LineBuilder,
+    has_fields: bool,
+    error: Option<SchemaError>,
+}
+
+impl<'a> SchemaLineBuilder<'a> {
+    /// Insert a tag in the line
+    pub fn tag(mut self, tag: &TagHandle, value: impl Into<TagValue>) -> Self {
+        if let Err(error) = self.schema.check_tag(tag) {
+            self.error.get_or_insert(error);
+        }
+        self.builder = self.builder.insert_tag(tag.name.as_str(), value);
+        self
+    }
+
+    /// Insert a field in the line
+    ///
+    /// Values of the wrong type are rejected at compile time.
+    ///
+    /// ```compile_fail
+    /// # use rinfluxdb_lineprotocol::Schema;
+    /// let mut schema = Schema::new("weather");
+    /// let temperature = schema.field::<f64>("temperature");
+    /// schema.line().field(&temperature, "hot");
+    /// ```
+    pub fn field<T>(mut self, field: &FieldHandle<T>, value: impl Into<T>) -> Self
+    where
+        T: SchemaType,
+    {
+        if let Err(error) = self.schema.check_field(field) {
+            self.error.get_or_insert(error);
+        }
+        self.builder = self.builder.insert_field(field.name.as_str(), value.into());
+        self.has_fields = true;
+        self
+    }
+
+    /// Set the line timestamp
+    pub fn set_timestamp(mut self, timestamp: DateTime<Utc>) -> Self {
+        self.builder = self.builder.set_timestamp(timestamp);
+        self
+    }
+
+    /// Return the underlying line builder
+    pub fn into_line_builder(self) -> Result<LineBuilder, SchemaError> {
+        if let Some(error) = self.error {
+            return Err(error);
+        }
+        if !self.has_fields {
+            return Err(SchemaError::MissingFields);
+        }
+        Ok(self.builder)
+    }
+
+    /// Build the line
+    pub fn build(self) -> Result<Line, SchemaError> {
+        self.into_line_builder().map(LineBuilder::build)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn foreign_handles() {
+        let mut schema = Schema::new("weather");
+        let temperature = schema.field::<f64>("temperature");
+
+        let mut other = Schema::new("weather");
+        let location = other.tag("location");
+        let other_temperature = other.field::<i64>("temperature");
+
+        assert_eq!(
+            schema.line().field(&temperature, 21.0).tag(&location, "Odense").build(),
+            Err(SchemaError::UnknownTag("location".into())),
+        );
+        assert_eq!(
+            schema.line().field(&other_temperature, 21).build(),
+            Err(SchemaError::FieldTypeConflict {
+                name: "temperature".into(),
+                expected: FieldType::Float,
+                found: FieldType::Integer,
+            }),
+        );
+        assert_eq!(other.line().tag(&location, "Odense").build(), Err(SchemaError::MissingFields));
+    }
+
+    #[test]
+    fn validate_columns() {
+        let mut schema = Schema::new("weather");
+        schema.tag("location");
+        schema.field::<f64>("temperature");
+        schema.field::<bool>("raining");
+
+        let mut columns = Columns::new();
+        columns.insert("location".to_string(), vec![Value::String("Odense".into())]);
+        columns.insert("temperature".to_string(), vec![Value::Float(21.5), Value::Integer(22)]);
+        assert_eq!(schema.validate("weather", &columns), Ok(()));
+
+        assert_eq!(
+            schema.validate("forecast", &columns),
+            Err(SchemaError::MeasurementMismatch {
+                expected: "weather".into(),
+                found: "forecast".into(),
+            }),
+        );
+
+        columns.insert("raining".to_string(), vec![Value::Integer(0)]);
+        assert_eq!(
+            schema.validate("weather", &columns),
+            Err(SchemaError::FieldTypeConflict {
+                name: "raining".into(),
+                expected: FieldType::Boolean,
+                found: FieldType::Integer,
+            }),
+        );
+
+        columns.remove("raining");
+        columns.insert("humidity".to_string(), vec![Value::Float(0.4)]);
+        assert_eq!(
+            schema.validate("weather", &columns),
+            Err(SchemaError::UnknownField("humidity".into())),
+        );
+    }
+}
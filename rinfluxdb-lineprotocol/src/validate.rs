@@ -0,0 +1,158 @@
+// Copyright Claudio Mattera 2021.
+// Distributed under the MIT License or Apache 2.0 License at your option.
+// See accompanying files License-MIT.txt and License-Apache-2.0, or online at
+// https://opensource.org/licenses/MIT
+// https://opensource.org/licenses/Apache-2.0
+
+use chrono::{DateTime, TimeZone, Utc};
+
+use thiserror::Error;
+
+use super::Line;
+
+/// The earliest timestamp accepted by InfluxDB, in nanoseconds from epoch
+const MIN_TIMESTAMP_NANOS: i64 = i64::MIN + 2;
+
+/// The latest timestamp accepted by InfluxDB, in nanoseconds from epoch
+const MAX_TIMESTAMP_NANOS: i64 = i64::MAX - 1;
+
+/// An error found while checking a line before sending it
+#[derive(Error, Clone, Debug, PartialEq)]
+pub enum ValidationError {
+    /// Measurement name starts with `_`, which is reserved by InfluxDB
+    #[error("Measurement {0} starts with reserved character _")]
+    ReservedMeasurement(String),
+
+    /// Line has no fields
+    #[error("Missing fields")]
+    MissingFields,
+
+    /// Tag value contains a newline, which cannot be escaped
+    #[error("Tag {0} contains a newline")]
+    NewlineInTagValue(String),
+
+    /// Floating point field is NaN or infinite
+    #[error("Field {0} is not a finite number")]
+    NonFiniteField(String),
+
+    /// Timestamp is outside the range accepted by InfluxDB
+    #[error("Timestamp {0} is out of range")]
+    TimestampOutOfRange(DateTime<Utc>),
+
+    /// Unsigned integer field does not fit a signed integer
+    #[error("Field {0} does not fit a signed integer")]
+    UnsignedOutOfRange(String),
+
+    /// Unsigned integer field is not allowed
+    #[error("Field {0} is an unsigned integer")]
+    UnsignedNotAllowed(String),
+}
+
+/// Check whether a timestamp is within the range accepted by InfluxDB
+pub(crate) fn is_timestamp_in_range(timestamp: &DateTime<Utc>) -> bool {
+    let min = Utc.timestamp_nanos(MIN_TIMESTAMP_NANOS);
+    let max = Utc.timestamp_nanos(MAX_TIMESTAMP_NANOS);
+    min <= *timestamp && *timestamp <= max
+}
+
+/// Check a sequence of lines before sending them
+///
+/// All lines are checked, and the position of every invalid line is
+/// returned together with the first problem found in it.
+///
+/// ```
+/// # use rinfluxdb_lineprotocol::{validate_lines, LineBuilder, ValidationError};
+/// let lines = vec![
+///     LineBuilder::new("indoor_environment")
+///         .insert_field("temperature", 21.5)
+///         .build(),
+///     LineBuilder::new("indoor_environment")
+///         .insert_field("temperature", f64::NAN)
+///         .build(),
+///     LineBuilder::new("_internal")
+///         .insert_field("temperature", 21.5)
+///         .build(),
+/// ];
+///
+/// assert_eq!(
+///     validate_lines(&lines),
+///     Err(vec![
+///         (1, ValidationError::NonFiniteField("temperature".into())),
+///         (2, ValidationError::ReservedMeasurement("_internal".into())),
+///     ]),
+/// );
+/// ```
+pub fn validate_lines(lines: &[Line]) -> Result<(), Vec<(usize, ValidationError)>> {
+    let errors: Vec<(usize, ValidationError)> = lines
+        .iter()
+        .enumerate()
+        .filter_map(|(index, line)| line.validate().err().map(|error| (index, error)))
+        .collect();
+
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(errors)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::LineBuilder;
+
+    #[test]
+    fn valid_line() {
+        let line = LineBuilder::new("indoor_environment")
+            .insert_tag("room", "bedroom")
+            .insert_field("temperature", 21.5)
+            .set_timestamp(Utc.with_ymd_and_hms(2021, 3, 4, 17, 0, 0).unwrap())
+            .build();
+        assert_eq!(line.validate(), Ok(()));
+    }
+
+    #[test]
+    fn missing_fields() {
+        let line = LineBuilder::new("indoor_environment")
+            .insert_tag("room", "bedroom")
+            .build();
+        assert_eq!(line.validate(), Err(ValidationError::MissingFields));
+    }
+
+    #[test]
+    fn non_finite_field() {
+        let line = LineBuilder::new("indoor_environment")
+            .insert_field("temperature", f64::INFINITY)
+            .build();
+        assert_eq!(
+            line.validate(),
+            Err(ValidationError::NonFiniteField("temperature".into())),
+        );
+    }
+
+    #[test]
+    fn newline_in_tag_value() {
+        let line = LineBuilder::new("indoor_environment")
+            .insert_tag("room", "bed\nroom")
+            .insert_field("temperature", 21.5)
+            .build();
+        assert_eq!(
+            line.validate(),
+            Err(ValidationError::NewlineInTagValue("room".into())),
+        );
+    }
+
+    #[test]
+    fn timestamp_out_of_range() {
+        let timestamp = Utc.with_ymd_and_hms(2300, 1, 1, 0, 0, 0).unwrap();
+        let line = LineBuilder::new("indoor_environment")
+            .insert_field("temperature", 21.5)
+            .set_timestamp(timestamp)
+            .build();
+        assert_eq!(
+            line.validate(),
+            Err(ValidationError::TimestampOutOfRange(timestamp)),
+        );
+    }
+}
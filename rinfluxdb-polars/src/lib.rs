@@ -9,127 +9,383 @@
 use std::collections::HashMap;
 use std::convert::TryFrom;
 
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, TimeZone, Utc};
 
-use rinfluxdb_types::Value;
+use rinfluxdb_types::{Columns, FromQueryResult, IndexMap, Value, ValueError};
+
+use rinfluxdb_lineprotocol::{FieldValue, Line, Measurement};
 
-use polars::chunked_array::ChunkedArray;
 use polars::frame::DataFrame;
-use polars::series::Series;
-use polars::datatypes::Date64Type;
-use polars::chunked_array::temporal::FromNaiveDateTime;
+use polars::series::{IntoSeries, Series};
+use polars::datatypes::{DataType, Int64Chunked, TimeUnit, Utf8Chunked};
 use polars::error::PolarsError;
 
+/// Columns of a query result: dataframe name, index and named columns
+pub type QueryResult = (String, Vec<DateTime<Utc>>, Columns);
+
 /// Wrapper around [Polars](https://lib.rs/crates/polars) dataframe
 ///
 /// It is not possible to implement
-/// `TryFrom<(String, Vec<DateTime<Utc>>, HashMap<String, Vec<Value>>), Error = E>`
-/// directly for Polars dataframes, so the newtype pattern is used with a unit
-/// struct.
+/// [`FromQueryResult`](rinfluxdb_types::FromQueryResult) directly for Polars
+/// dataframes, so the newtype pattern is used with a unit struct.
+/// Tags are ignored.
 ///
 /// Note that Polars dataframe cannot be indexed by datetimes, so the index is
-/// stored in a regular column named `index`.
+/// stored in a regular column named `index`, followed by the other columns in
+/// their original order.
+/// Instants are stored as `Datetime` series with nanosecond precision in
+/// UTC, see [`datetime_dtype()`].
 pub struct DataFrameWrapper(pub DataFrame);
 
-impl TryFrom<(String, Vec<DateTime<Utc>>, HashMap<String, Vec<Value>>)> for DataFrameWrapper {
+/// Return the type of datetime series, nanoseconds in UTC
+///
+/// ```
+/// # use polars::datatypes::{DataType, TimeUnit};
+/// # use rinfluxdb_polars::datetime_dtype;
+/// assert_eq!(
+///     datetime_dtype(),
+///     DataType::Datetime(TimeUnit::Nanoseconds, Some("UTC".to_string())),
+/// );
+/// ```
+pub fn datetime_dtype() -> DataType {
+    DataType::Datetime(TimeUnit::Nanoseconds, Some("UTC".to_string()))
+}
+
+impl TryFrom<(String, Vec<DateTime<Utc>>, Columns)> for DataFrameWrapper {
+    type Error = PolarsError;
+
+    fn try_from(query_result: QueryResult) -> Result<Self, Self::Error> {
+        Self::try_from_with_schema(query_result, &HashMap::new())
+    }
+}
+
+impl FromQueryResult for DataFrameWrapper {
     type Error = PolarsError;
 
-    fn try_from(
-        (_name, index, columns): (String, Vec<DateTime<Utc>>, HashMap<String, Vec<Value>>),
+    fn from_query_result(
+        name: String,
+        index: Vec<DateTime<Utc>>,
+        columns: Columns,
+        _tags: Option<&HashMap<String, String>>,
     ) -> Result<Self, Self::Error> {
-        let columns: HashMap<String, Result<Series, Self::Error>> = columns
+        Self::try_from((name, index, columns))
+    }
+}
+
+impl DataFrameWrapper {
+    /// Create a dataframe, using a schema for columns without values
+    ///
+    /// The type of a column is inferred from its values.
+    /// Columns without any value, either because they are empty or because
+    /// all values are missing, have the type specified in the schema, or
+    /// floating point type if they contain missing values and are not in the
+    /// schema.
+    /// Missing values are stored as nulls.
+    ///
+    /// An error is returned if a column is empty and it is not in the schema,
+    /// if the schema specifies a type other than `Float64`, `Int64`,
+    /// `UInt64`, `Utf8`, `Boolean` or `Datetime`, or if an instant cannot be
+    /// represented as nanoseconds from epoch.
+    pub fn try_from_with_schema(
+        (_name, index, columns): QueryResult,
+        schema: &HashMap<String, DataType>,
+    ) -> Result<Self, PolarsError> {
+        let columns: IndexMap<String, Result<Series, PolarsError>> = columns
             .into_iter()
             .map(|(name, column)| {
-                let column = match column.first() {
-                    Some(Value::Float(_)) => Ok(
-                        column
-                            .into_iter()
-                            .map(|element| element.into_f64())
-                            .collect(),
-                    ),
-                    Some(Value::Integer(_)) => Ok(
-                        column
-                            .into_iter()
-                            .map(|element| element.into_i64())
-                            .collect(),
-                    ),
-                    Some(Value::UnsignedInteger(_)) => Ok(
-                        column
-                            .into_iter()
-                            .map(|element| element.into_u64())
-                            .collect(),
-                    ),
-                    Some(Value::String(_)) => Ok(
-                        column
-                            .into_iter()
-                            .map(|element| element.into_string())
-                            .collect(),
-                    ),
-                    Some(Value::Boolean(_)) => Ok(
-                        column
-                            .into_iter()
-                            .map(|element| element.into_boolean())
-                            .collect(),
-                    ),
-                    Some(Value::Timestamp(_)) => Ok(
-                        datetime_value_column_to_series(&name, column),
-                    ),
-                    None => Err(PolarsError::ValueError("Empty column".into())),
+                let column = match column.iter().find(|value| !value.is_null()) {
+                    Some(Value::Float(_)) => optional_values(column, Value::try_into_f64)
+                        .map(|values| values.into_iter().collect()),
+                    Some(Value::Integer(_)) => optional_values(column, Value::try_into_i64)
+                        .map(|values| values.into_iter().collect()),
+                    Some(Value::UnsignedInteger(_)) => optional_values(column, Value::try_into_u64)
+                        .map(|values| values.into_iter().collect()),
+                    Some(Value::String(_)) => optional_values(column, Value::try_into_string)
+                        .map(|values| values.into_iter().collect::<Utf8Chunked>().into()),
+                    Some(Value::Boolean(_)) => optional_values(column, Value::try_into_boolean)
+                        .map(|values| values.into_iter().collect()),
+                    Some(Value::Timestamp(_)) => optional_values(column, Value::try_into_timestamp)
+                        .and_then(|values| datetimes_to_series(&name, values.into_iter())),
+                    Some(Value::Null) => unreachable!(),
+                    None => match schema.get(&name) {
+                        Some(dtype) => null_series(&name, dtype, column.len()),
+                        None if column.is_empty() => Err(PolarsError::ComputeError(
+                            format!("Empty column {} is not in schema", name).into(),
+                        )),
+                        None => Ok(column.iter().map(|_| None::<f64>).collect()),
+                    },
                 };
                 (name, column)
             })
             .collect();
 
-        let mut series_map: HashMap<String, Series> = flatten_map(columns)?;
-        series_map.insert("index".to_string(), datetimes_to_series("index", index.into_iter()));
+        let series_map: IndexMap<String, Series> = flatten_map(columns)?;
+
+        let mut series: Vec<Series> = vec![datetimes_to_series("index", index.into_iter().map(Some))?];
+        series.extend(
+            series_map
+                .into_iter()
+                .map(|(name, mut series)| {
+                    series.rename(&name);
+                    series
+                }),
+        );
+        let dataframe = DataFrame::new(series)?;
+        Ok(DataFrameWrapper(dataframe))
+    }
+}
+
+impl DataFrameWrapper {
+    /// Convert the dataframe to lines, one for each row
+    ///
+    /// The `index` column is used as timestamp, the tag columns as tags, and
+    /// all other columns as fields.
+    /// See [`to_lines_with_time_column`](DataFrameWrapper::to_lines_with_time_column).
+    pub fn to_lines<M>(&self, measurement: M, tag_columns: &[&str]) -> Result<Vec<Line>, PolarsError>
+    where
+        M: Into<Measurement>,
+    {
+        self.to_lines_with_time_column(measurement, "index", tag_columns)
+    }
+
+    /// Convert the dataframe to lines, using a column as timestamp
+    ///
+    /// The time column is used as timestamp, the tag columns as tags, and all
+    /// other columns as fields.
+    /// Tag values are formatted as strings.
+    /// Missing values and empty tag values are skipped, as well as rows that
+    /// have no timestamp or no field values, since they cannot be written.
+    ///
+    /// An error is returned if there is no time column, if it does not
+    /// contain datetimes, or if another column has a type that cannot be
+    /// written.
+    pub fn to_lines_with_time_column<M>(
+        &self,
+        measurement: M,
+        time_column: &str,
+        tag_columns: &[&str],
+    ) -> Result<Vec<Line>, PolarsError>
+    where
+        M: Into<Measurement>,
+    {
+        let measurement = measurement.into();
 
-        let series: Vec<Series> = series_map
+        let index = self.time_values(time_column)?;
+        let columns = self.value_columns(time_column)?;
+
+        let lines = index
             .into_iter()
-            .map(|(name, mut series)| {
-                series.rename(&name);
-                series
+            .enumerate()
+            .filter_map(|(i, time)| {
+                let time = time?;
+                let mut line = Line::new(measurement.clone());
+                let mut has_fields = false;
+                for (name, values) in columns.iter() {
+                    let value = values[i].clone();
+                    if value.is_null() {
+                        continue;
+                    }
+                    if tag_columns.contains(name) {
+                        let value = value.to_string();
+                        if !value.is_empty() {
+                            line.insert_tag(*name, value);
+                        }
+                    } else if let Ok(value) = FieldValue::try_from(value) {
+                        line.insert_field(*name, value);
+                        has_fields = true;
+                    }
+                }
+                line.set_timestamp(time);
+                if has_fields {
+                    Some(line)
+                } else {
+                    None
+                }
             })
             .collect();
-        let dataframe = DataFrame::new(series)?;
-        Ok(DataFrameWrapper(dataframe))
+        Ok(lines)
+    }
+
+    /// Convert the dataframe back to the columns of a query result
+    ///
+    /// This is the opposite of the conversion from a query result, and it
+    /// returns the name, the index and the columns.
+    /// Polars dataframes have no name, so the name is empty.
+    ///
+    /// An error is returned if there is no time column, if it does not
+    /// contain datetimes or it contains missing values, or if another column
+    /// has an unsupported type.
+    pub fn to_query_result(
+        &self,
+        time_column: &str,
+    ) -> Result<QueryResult, PolarsError> {
+        let index = self.time_values(time_column)?
+            .into_iter()
+            .collect::<Option<Vec<_>>>()
+            .ok_or_else(|| PolarsError::ComputeError(
+                format!("Time column {} has missing values", time_column).into(),
+            ))?;
+
+        let columns = self.value_columns(time_column)?
+            .into_iter()
+            .map(|(name, values)| (name.to_string(), values))
+            .collect();
+
+        Ok((String::new(), index, columns))
+    }
+
+    fn time_values(&self, time_column: &str) -> Result<Vec<Option<DateTime<Utc>>>, PolarsError> {
+        let series = self.0.column(time_column)?;
+        match series.dtype() {
+            DataType::Datetime(unit, _) => series.datetime()?
+                .into_iter()
+                .map(|value| value.map(|value| instant(value, *unit)).transpose())
+                .collect(),
+            dtype => Err(PolarsError::SchemaMismatch(
+                format!("Time column {} has type {:?}", time_column, dtype).into(),
+            )),
+        }
+    }
+
+    /// Return the values of all columns but the time column, in their
+    /// original order
+    fn value_columns(&self, time_column: &str) -> Result<Vec<(&str, Vec<Value>)>, PolarsError> {
+        self.0
+            .get_columns()
+            .iter()
+            .filter(|series| series.name() != time_column)
+            .map(|series| series_to_values(series).map(|values| (series.name(), values)))
+            .collect()
     }
 }
 
-fn datetimes_to_series<A>(name: &str, column: A) -> Series
+impl TryFrom<DataFrameWrapper> for QueryResult {
+    type Error = PolarsError;
+
+    /// Convert the dataframe back to the columns of a query result, using the
+    /// `index` column as index
+    fn try_from(wrapper: DataFrameWrapper) -> Result<Self, Self::Error> {
+        wrapper.to_query_result("index")
+    }
+}
+
+fn series_to_values(series: &Series) -> Result<Vec<Value>, PolarsError> {
+    fn collect<T, A, F>(values: A, convert: F) -> Vec<Value>
+    where
+        A: Iterator<Item=Option<T>>,
+        F: Fn(T) -> Value,
+    {
+        values
+            .map(|value| value.map(&convert).unwrap_or(Value::Null))
+            .collect()
+    }
+
+    let values = match series.dtype() {
+        DataType::Float64 => collect(series.f64()?.into_iter(), Value::Float),
+        DataType::Int64 => collect(series.i64()?.into_iter(), Value::Integer),
+        DataType::UInt64 => collect(series.u64()?.into_iter(), Value::UnsignedInteger),
+        DataType::Utf8 => collect(series.utf8()?.into_iter(), |value| Value::String(value.to_string())),
+        DataType::Boolean => collect(series.bool()?.into_iter(), Value::Boolean),
+        DataType::Datetime(unit, _) => series.datetime()?
+            .into_iter()
+            .map(|value| match value {
+                Some(value) => instant(value, *unit).map(Value::Timestamp),
+                None => Ok(Value::Null),
+            })
+            .collect::<Result<_, _>>()?,
+        dtype => {
+            return Err(PolarsError::SchemaMismatch(
+                format!("Cannot write column {} of type {:?}", series.name(), dtype).into(),
+            ))
+        }
+    };
+    Ok(values)
+}
+
+/// Create a series of missing values
+fn null_series(name: &str, dtype: &DataType, length: usize) -> Result<Series, PolarsError> {
+    match dtype {
+        DataType::Float64
+        | DataType::Int64
+        | DataType::UInt64
+        | DataType::Utf8
+        | DataType::Boolean
+        | DataType::Datetime(_, _) => Ok(Series::full_null(name, length, dtype)),
+        dtype => Err(PolarsError::SchemaMismatch(
+            format!("Unsupported type {:?} for column {}", dtype, name).into(),
+        )),
+    }
+}
+
+/// Create a series of instants, with nanosecond precision in UTC
+fn datetimes_to_series<A>(name: &str, column: A) -> Result<Series, PolarsError>
 where
-    A: Iterator<Item=DateTime<Utc>>
+    A: Iterator<Item=Option<DateTime<Utc>>>
 {
-    let values: Vec<_> = column
-        .map(|element| element.naive_utc())
-        .collect();
-    let array: ChunkedArray<Date64Type> = FromNaiveDateTime::new_from_naive_datetime(
-            name,
-            &values,
-        );
-    array.into()
+    let nanoseconds: Int64Chunked = column
+        .map(|value| value.map(nanoseconds).transpose())
+        .collect::<Result<_, _>>()?;
+    let mut series = nanoseconds
+        .into_datetime(TimeUnit::Nanoseconds, Some("UTC".to_string()))
+        .into_series();
+    series.rename(name);
+    Ok(series)
+}
+
+/// Convert an instant to nanoseconds from epoch
+///
+/// An error is returned if the instant is too far from epoch.
+fn nanoseconds(instant: DateTime<Utc>) -> Result<i64, PolarsError> {
+    let nanoseconds = instant.timestamp() as i128 * 1_000_000_000
+        + instant.timestamp_subsec_nanos() as i128;
+    i64::try_from(nanoseconds).map_err(|_| PolarsError::ComputeError(
+        format!("Instant {} cannot be represented as nanoseconds", instant).into(),
+    ))
 }
 
-fn values_to_datetimes<A>(values: A) -> impl Iterator<Item=DateTime<Utc>>
+/// Convert a datetime value from epoch to an instant
+///
+/// An error is returned if the value is too far from epoch.
+fn instant(value: i64, unit: TimeUnit) -> Result<DateTime<Utc>, PolarsError> {
+    let instant = match unit {
+        TimeUnit::Nanoseconds => Some(Utc.timestamp_nanos(value)),
+        TimeUnit::Microseconds => Utc
+            .timestamp_opt(
+                value.div_euclid(1_000_000),
+                (value.rem_euclid(1_000_000) * 1_000) as u32,
+            )
+            .single(),
+        TimeUnit::Milliseconds => Utc.timestamp_millis_opt(value).single(),
+    };
+    instant.ok_or_else(|| PolarsError::ComputeError(
+        format!("Datetime {} {} is out of range", value, unit).into(),
+    ))
+}
+
+fn optional_values<T, F>(column: Vec<Value>, convert: F) -> Result<Vec<Option<T>>, PolarsError>
 where
-    A: Iterator<Item=Value>,
+    F: Fn(Value) -> Result<T, ValueError>,
 {
-    values.map(|element| element.into_timestamp())
+    column
+        .into_iter()
+        .map(|value| match value {
+            Value::Null => Ok(None),
+            value => convert(value).map(Some).map_err(value_error),
+        })
+        .collect()
 }
 
-fn datetime_value_column_to_series(name: &str, column: Vec<Value>) -> Series {
-    datetimes_to_series(
-        name,
-        values_to_datetimes(column.into_iter()),
-    )
+fn value_error(error: ValueError) -> PolarsError {
+    PolarsError::ComputeError(error.to_string().into())
 }
 
-fn flatten_map<K, V, E>(map: HashMap<K, Result<V, E>>) -> Result<HashMap<K, V>, E>
+fn flatten_map<K, V, E>(map: IndexMap<K, Result<V, E>>) -> Result<IndexMap<K, V>, E>
 where
     K: Eq + std::hash::Hash,
     E: std::error::Error,
 {
     map.into_iter()
-        .try_fold(HashMap::new(), |mut accumulator, (name, column)| {
+        .try_fold(IndexMap::new(), |mut accumulator, (name, column)| {
             let column = column?;
             accumulator.insert(name, column);
             Ok(accumulator)
@@ -158,12 +414,12 @@ mod tests {
     fn polars_dataframe_creation() -> Result<(), Box<dyn std::error::Error>> {
         let name: String = "environment".into();
         let index: Vec<DateTime<Utc>> = vec![
-            Utc.ymd(2021, 10, 20).and_hms(5, 20, 21),
-            Utc.ymd(2021, 10, 20).and_hms(5, 20, 22),
-            Utc.ymd(2021, 10, 20).and_hms(5, 20, 23),
-            Utc.ymd(2021, 10, 20).and_hms(5, 20, 24),
+            Utc.with_ymd_and_hms(2021, 10, 20, 5, 20, 21).unwrap(),
+            Utc.with_ymd_and_hms(2021, 10, 20, 5, 20, 22).unwrap(),
+            Utc.with_ymd_and_hms(2021, 10, 20, 5, 20, 23).unwrap(),
+            Utc.with_ymd_and_hms(2021, 10, 20, 5, 20, 24).unwrap(),
         ];
-        let mut columns: HashMap<String, Vec<Value>> = HashMap::new();
+        let mut columns: Columns = Columns::new();
         columns.insert(
             "temperature".into(),
             vec![
@@ -193,34 +449,35 @@ mod tests {
         );
 
         let expected_dataframe = DataFrame::new(vec![
+                datetimes_to_series(
+                    "index",
+                    vec![
+                        Utc.with_ymd_and_hms(2021, 10, 20, 5, 20, 21).unwrap(),
+                        Utc.with_ymd_and_hms(2021, 10, 20, 5, 20, 22).unwrap(),
+                        Utc.with_ymd_and_hms(2021, 10, 20, 5, 20, 23).unwrap(),
+                        Utc.with_ymd_and_hms(2021, 10, 20, 5, 20, 24).unwrap(),
+                    ]
+                        .into_iter()
+                        .map(Some)
+                )?,
                 named_series!(
                     "temperature",
-                    vec![23.2, 23.5, 23.7, 23.4]
+                    [23.2, 23.5, 23.7, 23.4]
                         .iter()
                         .collect()
                 ),
                 named_series!(
-                    "rain",
-                    vec![false, true, true, false]
+                    "humidity",
+                    [40_u64, 38_u64, 34_u64, 39_u64]
                         .iter()
                         .collect()
                 ),
                 named_series!(
-                    "humidity",
-                    vec![40_u64, 38_u64, 34_u64, 39_u64]
+                    "rain",
+                    [false, true, true, false]
                         .iter()
                         .collect()
                 ),
-                datetimes_to_series(
-                    "index",
-                    vec![
-                        Utc.ymd(2021, 10, 20).and_hms(5, 20, 21),
-                        Utc.ymd(2021, 10, 20).and_hms(5, 20, 22),
-                        Utc.ymd(2021, 10, 20).and_hms(5, 20, 23),
-                        Utc.ymd(2021, 10, 20).and_hms(5, 20, 24),
-                    ]
-                        .into_iter()
-                ),
             ])?;
 
         let wrapper: Result<DataFrameWrapper, _> = (name, index, columns).try_into();
@@ -232,20 +489,179 @@ mod tests {
         println!("Dataframe: {:?}", dataframe);
         println!("Expected: {:?}", expected_dataframe);
 
-        // Columns order is non-deterministic but dataframes with different
-        // columns orders are not compared as equal, so the following assert
-        // fails non-deterministically
-        //assert!(dataframe.frame_equal(&expected_dataframe));
-
-        // Manually sort the columns and compare them one by one
-        let mut columns: Vec<_> = dataframe.get_columns().iter().collect();
-        let mut expected_columns: Vec<_> = expected_dataframe.get_columns().iter().collect();
-        columns.sort_by_key(|column| column.name());
-        expected_columns.sort_by_key(|column| column.name());
-        for (column, expected_column) in columns.into_iter().zip(expected_columns.into_iter()) {
-            assert!(column.series_equal(expected_column));
+        assert!(dataframe.frame_equal(&expected_dataframe));
+
+        Ok(())
+    }
+
+    #[test]
+    fn polars_dataframe_to_lines() -> Result<(), Box<dyn std::error::Error>> {
+        let index: Vec<DateTime<Utc>> = vec![
+            Utc.with_ymd_and_hms(2021, 10, 20, 5, 20, 21).unwrap(),
+            Utc.with_ymd_and_hms(2021, 10, 20, 5, 20, 22).unwrap(),
+        ];
+        let mut columns: Columns = Columns::new();
+        columns.insert(
+            "temperature".into(),
+            vec![Value::Float(23.2), Value::Null],
+        );
+        columns.insert(
+            "room".into(),
+            vec![Value::String("kitchen".into()), Value::String("bedroom".into())],
+        );
+
+        let wrapper: DataFrameWrapper = ("environment".to_string(), index, columns).try_into()?;
+        let lines = wrapper.to_lines("environment_1h", &["room"])?;
+
+        let mut expected = Line::new("environment_1h");
+        expected.insert_tag("room", "kitchen");
+        expected.insert_field("temperature", 23.2);
+        expected.set_timestamp(Utc.with_ymd_and_hms(2021, 10, 20, 5, 20, 21).unwrap());
+
+        assert_eq!(lines, vec![expected]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn polars_dataframe_to_query_result() -> Result<(), Box<dyn std::error::Error>> {
+        let index: Vec<DateTime<Utc>> = vec![
+            Utc.with_ymd_and_hms(2021, 10, 20, 5, 20, 21).unwrap(),
+            Utc.with_ymd_and_hms(2021, 10, 20, 5, 20, 22).unwrap(),
+        ];
+        let mut columns: Columns = Columns::new();
+        columns.insert(
+            "temperature".into(),
+            vec![Value::Float(23.2), Value::Null],
+        );
+
+        let wrapper: DataFrameWrapper = ("environment".to_string(), index.clone(), columns.clone()).try_into()?;
+        let (name, actual_index, actual_columns) = wrapper.try_into()?;
+
+        assert_eq!(name, "");
+        assert_eq!(actual_index, index);
+        assert_eq!(actual_columns, columns);
+
+        Ok(())
+    }
+
+    #[test]
+    fn polars_dataframe_to_lines_with_time_column() -> Result<(), Box<dyn std::error::Error>> {
+        let dataframe = DataFrame::new(vec![
+            datetimes_to_series("time", vec![Some(Utc.with_ymd_and_hms(2021, 10, 20, 5, 20, 21).unwrap())].into_iter())?,
+            named_series!("temperature", [23.2].iter().collect()),
+        ])?;
+
+        let lines = DataFrameWrapper(dataframe).to_lines_with_time_column("environment", "time", &[])?;
+
+        let mut expected = Line::new("environment");
+        expected.insert_field("temperature", 23.2);
+        expected.set_timestamp(Utc.with_ymd_and_hms(2021, 10, 20, 5, 20, 21).unwrap());
+
+        assert_eq!(lines, vec![expected]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn polars_dataframe_creation_with_schema() -> Result<(), Box<dyn std::error::Error>> {
+        let mut columns: Columns = Columns::new();
+        columns.insert("temperature".into(), vec![]);
+        columns.insert("room".into(), vec![]);
+
+        columns.insert("opened".into(), vec![]);
+
+        let mut schema = HashMap::new();
+        schema.insert("temperature".to_string(), DataType::Float64);
+        schema.insert("room".to_string(), DataType::Utf8);
+        schema.insert("opened".to_string(), datetime_dtype());
+
+        let query_result = ("environment".to_string(), vec![], columns);
+        let DataFrameWrapper(dataframe) = DataFrameWrapper::try_from_with_schema(query_result, &schema)?;
+
+        assert_eq!(dataframe.height(), 0);
+        assert_eq!(dataframe.column("index")?.dtype(), &datetime_dtype());
+        assert_eq!(dataframe.column("temperature")?.dtype(), &DataType::Float64);
+        assert_eq!(dataframe.column("room")?.dtype(), &DataType::Utf8);
+        assert_eq!(dataframe.column("opened")?.dtype(), &datetime_dtype());
+
+        Ok(())
+    }
+
+    #[test]
+    fn polars_dataframe_creation_empty_column_without_schema() {
+        let mut columns: Columns = Columns::new();
+        columns.insert("temperature".into(), vec![]);
+
+        let wrapper: Result<DataFrameWrapper, _> = ("environment".to_string(), vec![], columns).try_into();
+
+        assert!(wrapper.is_err());
+    }
+
+    #[test]
+    fn polars_dataframe_creation_missing_values() -> Result<(), Box<dyn std::error::Error>> {
+        let index = vec![Utc.with_ymd_and_hms(2021, 10, 20, 5, 20, 21).unwrap()];
+        let mut columns: Columns = Columns::new();
+        columns.insert("rain".into(), vec![Value::Null]);
+
+        let mut schema = HashMap::new();
+        schema.insert("rain".to_string(), DataType::Boolean);
+
+        let query_result = ("environment".to_string(), index, columns);
+        let DataFrameWrapper(dataframe) = DataFrameWrapper::try_from_with_schema(query_result, &schema)?;
+
+        let rain = dataframe.column("rain")?;
+        assert_eq!(rain.dtype(), &DataType::Boolean);
+        assert_eq!(rain.null_count(), 1);
+
+        Ok(())
+    }
+
+    #[test]
+    fn polars_dataframe_creation_preserves_missing_values() -> Result<(), Box<dyn std::error::Error>> {
+        let index = vec![
+            Utc.with_ymd_and_hms(2021, 10, 20, 5, 20, 21).unwrap(),
+            Utc.with_ymd_and_hms(2021, 10, 20, 5, 20, 22).unwrap(),
+        ];
+        let mut columns: Columns = Columns::new();
+        columns.insert("temperature".into(), vec![Value::Null, Value::Float(23.5)]);
+        columns.insert("humidity".into(), vec![Value::Integer(40), Value::Null]);
+        columns.insert("room".into(), vec![Value::Null, Value::String("kitchen".into())]);
+        columns.insert(
+            "opened".into(),
+            vec![Value::Timestamp(Utc.with_ymd_and_hms(2021, 10, 20, 5, 0, 0).unwrap()), Value::Null],
+        );
+
+        let wrapper: DataFrameWrapper = ("environment".to_string(), index.clone(), columns.clone()).try_into()?;
+
+        let dataframe = &wrapper.0;
+        assert_eq!(dataframe.column("temperature")?.dtype(), &DataType::Float64);
+        assert_eq!(dataframe.column("humidity")?.dtype(), &DataType::Int64);
+        assert_eq!(dataframe.column("room")?.dtype(), &DataType::Utf8);
+        assert_eq!(dataframe.column("opened")?.dtype(), &datetime_dtype());
+        for name in ["temperature", "humidity", "room", "opened"] {
+            assert_eq!(dataframe.column(name)?.null_count(), 1);
         }
 
+        let (_, actual_index, actual_columns) = wrapper.try_into()?;
+        assert_eq!(actual_index, index);
+        assert_eq!(actual_columns, columns);
+
+        Ok(())
+    }
+
+    #[test]
+    fn polars_dataframe_creation_keeps_nanoseconds() -> Result<(), Box<dyn std::error::Error>> {
+        let instant = Utc.with_ymd_and_hms(2021, 10, 20, 5, 20, 21).unwrap() + chrono::Duration::nanoseconds(123_456_789);
+        let mut columns: Columns = Columns::new();
+        columns.insert("temperature".into(), vec![Value::Float(23.2)]);
+
+        let wrapper: DataFrameWrapper = ("environment".to_string(), vec![instant], columns).try_into()?;
+        assert_eq!(wrapper.0.column("index")?.dtype(), &datetime_dtype());
+
+        let (_, index, _) = wrapper.try_into()?;
+        assert_eq!(index, vec![instant]);
+
         Ok(())
     }
 }
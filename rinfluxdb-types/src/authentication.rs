@@ -0,0 +1,218 @@
+// Copyright Claudio Mattera 2021.
+// Distributed under the MIT License or Apache 2.0 License at your option.
+// See accompanying files License-MIT.txt and License-Apache-2.0, or online at
+// https://opensource.org/licenses/MIT
+// https://opensource.org/licenses/Apache-2.0
+
+/// Authentication method used by clients
+///
+/// InfluxDB 1.x uses username and password, either with basic
+/// authentication or as query parameters, or JSON Web Tokens, while
+/// InfluxDB 2.x uses API tokens, sent as header `Authorization: Token <token>`,
+/// or session cookies.
+///
+/// The same authentication methods are supported by all clients, and they
+/// are set with their builders' `authentication()` method.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub enum Authentication {
+    /// No authentication
+    #[default]
+    None,
+
+    /// Basic authentication with username and password
+    Basic {
+        /// The username
+        username: String,
+
+        /// The password
+        password: String,
+    },
+
+    /// Token authentication
+    Token(String),
+
+    /// Username and password sent as query parameters `u` and `p`
+    ///
+    /// Credentials in URLs can end up in logs of servers and proxies, so
+    /// basic authentication should be preferred when available.
+    QueryParameters {
+        /// The username
+        username: String,
+
+        /// The password
+        password: String,
+    },
+
+    /// JSON Web Token, sent as header `Authorization: Bearer <token>`
+    ///
+    /// InfluxDB 1.x accepts tokens signed with the shared secret set in its
+    /// configuration, containing claims `username` and `exp`.
+    Bearer(String),
+
+    /// Session cookie, obtained by signing in with username and password
+    ///
+    /// InfluxDB 2.x clients sign in on endpoint `/api/v2/signin` before the
+    /// first request, and send the session cookie with all requests.
+    /// When a session expires, clients sign in again and repeat the request
+    /// once.
+    /// This is useful in deployments where API tokens are not allowed.
+    Session {
+        /// The username
+        username: String,
+
+        /// The password
+        password: String,
+    },
+}
+
+impl<T, S> From<Option<(T, S)>> for Authentication
+where
+    T: Into<String>,
+    S: Into<String>,
+{
+    fn from(credentials: Option<(T, S)>) -> Self {
+        match credentials {
+            Some((username, password)) => Authentication::Basic {
+                username: username.into(),
+                password: password.into(),
+            },
+            None => Authentication::None,
+        }
+    }
+}
+
+/// A request builder to which credentials can be added
+///
+/// This is implemented for Reqwest's blocking and asynchronous request
+/// builders, with features `reqwest` and `reqwest-async`.
+pub trait AuthenticatedRequest: Sized {
+    /// Add basic authentication with username and password
+    fn with_basic_auth(self, username: &str, password: &str) -> Self;
+
+    /// Add a header
+    fn with_header(self, name: &str, value: &str) -> Self;
+
+    /// Add query parameters
+    fn with_query(self, parameters: &[(&str, &str)]) -> Self;
+}
+
+/// Add credentials to a request
+///
+/// Parameter `cookie` is the session cookie, for clients using session
+/// authentication.
+/// Requests using session authentication are left untouched until the
+/// client has signed in.
+pub fn apply_authentication<R>(request: R, authentication: &Authentication, cookie: Option<&str>) -> R
+where
+    R: AuthenticatedRequest,
+{
+    match authentication {
+        Authentication::None => request,
+        Authentication::Basic { username, password } => {
+            request.with_basic_auth(username, password)
+        }
+        Authentication::Token(token) => {
+            request.with_header("Authorization", &format!("Token {}", token))
+        }
+        Authentication::QueryParameters { username, password } => {
+            request.with_query(&[("u", username), ("p", password)])
+        }
+        Authentication::Bearer(token) => {
+            request.with_header("Authorization", &format!("Bearer {}", token))
+        }
+        Authentication::Session { .. } => match cookie {
+            Some(cookie) => request.with_header("Cookie", cookie),
+            None => request,
+        },
+    }
+}
+
+#[cfg(feature = "reqwest")]
+impl AuthenticatedRequest for reqwest::blocking::RequestBuilder {
+    fn with_basic_auth(self, username: &str, password: &str) -> Self {
+        self.basic_auth(username, Some(password))
+    }
+
+    fn with_header(self, name: &str, value: &str) -> Self {
+        self.header(name, value)
+    }
+
+    fn with_query(self, parameters: &[(&str, &str)]) -> Self {
+        self.query(parameters)
+    }
+}
+
+#[cfg(feature = "reqwest-async")]
+impl AuthenticatedRequest for reqwest::RequestBuilder {
+    fn with_basic_auth(self, username: &str, password: &str) -> Self {
+        self.basic_auth(username, Some(password))
+    }
+
+    fn with_header(self, name: &str, value: &str) -> Self {
+        self.header(name, value)
+    }
+
+    fn with_query(self, parameters: &[(&str, &str)]) -> Self {
+        self.query(parameters)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A request recording the credentials added to it
+    #[derive(Debug, Default, PartialEq)]
+    struct RecordingRequest(Vec<String>);
+
+    impl AuthenticatedRequest for RecordingRequest {
+        fn with_basic_auth(mut self, username: &str, password: &str) -> Self {
+            self.0.push(format!("basic {}:{}", username, password));
+            self
+        }
+
+        fn with_header(mut self, name: &str, value: &str) -> Self {
+            self.0.push(format!("{}: {}", name, value));
+            self
+        }
+
+        fn with_query(mut self, parameters: &[(&str, &str)]) -> Self {
+            for (name, value) in parameters {
+                self.0.push(format!("{}={}", name, value));
+            }
+            self
+        }
+    }
+
+    fn apply(authentication: Authentication, cookie: Option<&str>) -> Vec<String> {
+        apply_authentication(RecordingRequest::default(), &authentication, cookie).0
+    }
+
+    #[test]
+    fn apply_credentials() {
+        assert!(apply(Authentication::None, None).is_empty());
+        assert_eq!(
+            apply(Authentication::Basic { username: "user".into(), password: "secret".into() }, None),
+            vec!["basic user:secret"],
+        );
+        assert_eq!(
+            apply(Authentication::Token("secret".into()), None),
+            vec!["Authorization: Token secret"],
+        );
+        assert_eq!(
+            apply(Authentication::QueryParameters { username: "user".into(), password: "secret".into() }, None),
+            vec!["u=user", "p=secret"],
+        );
+        assert_eq!(
+            apply(Authentication::Bearer("secret".into()), None),
+            vec!["Authorization: Bearer secret"],
+        );
+    }
+
+    #[test]
+    fn apply_session_cookie() {
+        let authentication = Authentication::Session { username: "user".into(), password: "secret".into() };
+        assert!(apply(authentication.clone(), None).is_empty());
+        assert_eq!(apply(authentication, Some("session=abc")), vec!["Cookie: session=abc"]);
+    }
+}
@@ -0,0 +1,499 @@
+// Copyright Claudio Mattera 2021.
+// Distributed under the MIT License or Apache 2.0 License at your option.
+// See accompanying files License-MIT.txt and License-Apache-2.0, or online at
+// https://opensource.org/licenses/MIT
+// https://opensource.org/licenses/Apache-2.0
+
+use std::cmp::Ordering;
+use std::convert::TryFrom;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::ops::{Add, Neg, Sub};
+use std::str::FromStr;
+
+use thiserror::Error;
+
+const NANOSECOND: i128 = 1;
+const MICROSECOND: i128 = 1_000 * NANOSECOND;
+const MILLISECOND: i128 = 1_000 * MICROSECOND;
+const SECOND: i128 = 1_000 * MILLISECOND;
+const MINUTE: i128 = 60 * SECOND;
+const HOUR: i128 = 60 * MINUTE;
+const DAY: i128 = 24 * HOUR;
+const WEEK: i128 = 7 * DAY;
+
+/// The units of durations, from the coarsest, with their suffixes
+type Unit = (i128, fn(i64) -> Duration, &'static str);
+const UNITS: [Unit; 7] = [
+    (DAY, Duration::Days, "d"),
+    (HOUR, Duration::Hours, "h"),
+    (MINUTE, Duration::Minutes, "m"),
+    (SECOND, Duration::Seconds, "s"),
+    (MILLISECOND, Duration::Milliseconds, "ms"),
+    (MICROSECOND, Duration::Microseconds, "us"),
+    (NANOSECOND, Duration::Nanoseconds, "ns"),
+];
+
+/// A duration
+///
+/// Note: this type is almost entirely equivalent to `chrono::Duration`, but
+/// it also supports infinite duration in addition.
+///
+/// Since `chrono::Duration` implements `Into<Duration>`, the former can be
+/// used everywhere the latter is expected.
+///
+/// Durations are compared and hashed by their length, regardless of their
+/// unit, and infinite durations are longer than any other.
+/// They are formatted with compound units, as in InfluxDB duration literals,
+/// and with feature `serde` they are serialized as such literals.
+///
+/// ```
+/// # use rinfluxdb_types::Duration;
+/// let duration: Duration = "1h30m".parse()?;
+/// assert_eq!(duration, Duration::Minutes(90));
+/// assert_eq!(Duration::Seconds(-5400).to_string(), "-1h30m");
+/// assert_eq!(Duration::Hours(1) + Duration::Milliseconds(250), Duration::Milliseconds(3_600_250));
+/// # Ok::<(), rinfluxdb_types::DurationError>(())
+/// ```
+#[derive(Clone, Copy, Debug)]
+pub enum Duration {
+    /// A duration expressed in nanoseconds
+    Nanoseconds(i64),
+
+    /// A duration expressed in microseconds
+    Microseconds(i64),
+
+    /// A duration expressed in milliseconds
+    Milliseconds(i64),
+
+    /// A duration expressed in seconds
+    Seconds(i64),
+
+    /// A duration expressed in minutes
+    Minutes(i64),
+
+    /// A duration expressed in hours
+    Hours(i64),
+
+    /// A duration expressed in days
+    Days(i64),
+
+    /// An infinite duration
+    Infinity,
+}
+
+/// An error occurred while parsing or converting a duration
+#[derive(Error, Clone, Debug, PartialEq, Eq)]
+pub enum DurationError {
+    /// Text is not a valid duration literal
+    #[error("Invalid duration {0}")]
+    Invalid(String),
+
+    /// Duration cannot be represented
+    #[error("Duration out of range")]
+    OutOfRange,
+
+    /// Duration is infinite
+    #[error("Infinite duration")]
+    Infinite,
+}
+
+impl Duration {
+    /// Return the length of the duration in nanoseconds, or `None` if it is
+    /// infinite
+    fn nanoseconds(&self) -> Option<i128> {
+        match *self {
+            Duration::Nanoseconds(value) => Some(value as i128 * NANOSECOND),
+            Duration::Microseconds(value) => Some(value as i128 * MICROSECOND),
+            Duration::Milliseconds(value) => Some(value as i128 * MILLISECOND),
+            Duration::Seconds(value) => Some(value as i128 * SECOND),
+            Duration::Minutes(value) => Some(value as i128 * MINUTE),
+            Duration::Hours(value) => Some(value as i128 * HOUR),
+            Duration::Days(value) => Some(value as i128 * DAY),
+            Duration::Infinity => None,
+        }
+    }
+
+    /// Create a duration from nanoseconds, in the coarsest unit up to hours
+    /// that represents it exactly
+    ///
+    /// Zero is represented as `Duration::Seconds(0)`.
+    fn from_nanoseconds(nanoseconds: i128) -> Option<Self> {
+        if nanoseconds == 0 {
+            return Some(Duration::Seconds(0));
+        }
+
+        let (unit, constructor) = UNITS
+            .iter()
+            .skip(1)
+            .find(|(unit, _, _)| nanoseconds % unit == 0)
+            .map(|(unit, constructor, _)| (unit, constructor))
+            .expect("Every duration is a multiple of a nanosecond");
+        i64::try_from(nanoseconds / unit).ok().map(constructor)
+    }
+
+    /// Check whether two durations are expressed in the same unit
+    fn same_unit(&self, other: &Duration) -> bool {
+        std::mem::discriminant(self) == std::mem::discriminant(other)
+    }
+
+    /// Check whether the duration is infinite
+    pub fn is_infinite(&self) -> bool {
+        matches!(self, Duration::Infinity)
+    }
+
+    /// Check whether the duration is finite and longer than zero
+    pub fn is_positive(&self) -> bool {
+        !self.is_infinite() && *self > Duration::Seconds(0)
+    }
+
+    /// Add two durations, returning `None` if the result cannot be
+    /// represented
+    ///
+    /// Adding anything to an infinite duration results in an infinite
+    /// duration.
+    pub fn checked_add(&self, other: &Duration) -> Option<Duration> {
+        match (self.nanoseconds(), other.nanoseconds()) {
+            (Some(left), Some(right)) => Duration::from_nanoseconds(left + right),
+            _ => Some(Duration::Infinity),
+        }
+    }
+
+    /// Negate a duration, returning `None` if it is infinite
+    pub fn checked_neg(&self) -> Option<Duration> {
+        self.nanoseconds()
+            .and_then(|nanoseconds| Duration::from_nanoseconds(-nanoseconds))
+    }
+}
+
+impl PartialEq for Duration {
+    fn eq(&self, other: &Self) -> bool {
+        self.nanoseconds() == other.nanoseconds()
+    }
+}
+
+impl Eq for Duration {}
+
+impl Hash for Duration {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.nanoseconds().hash(state)
+    }
+}
+
+impl PartialOrd for Duration {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Duration {
+    fn cmp(&self, other: &Self) -> Ordering {
+        match (self.nanoseconds(), other.nanoseconds()) {
+            (Some(left), Some(right)) => left.cmp(&right),
+            (Some(_), None) => Ordering::Less,
+            (None, Some(_)) => Ordering::Greater,
+            (None, None) => Ordering::Equal,
+        }
+    }
+}
+
+impl Add for Duration {
+    type Output = Duration;
+
+    /// # Panics
+    ///
+    /// Panics if the result cannot be represented, see
+    /// [`checked_add()`](Duration::checked_add) for a fallible alternative.
+    fn add(self, other: Duration) -> Duration {
+        self.checked_add(&other).expect("Duration out of range")
+    }
+}
+
+impl Sub for Duration {
+    type Output = Duration;
+
+    /// # Panics
+    ///
+    /// Panics if `other` is infinite or if the result cannot be represented.
+    fn sub(self, other: Duration) -> Duration {
+        self + -other
+    }
+}
+
+impl Neg for Duration {
+    type Output = Duration;
+
+    /// # Panics
+    ///
+    /// Panics if the duration is infinite, since negative infinite durations
+    /// cannot be represented, see [`checked_neg()`](Duration::checked_neg)
+    /// for a fallible alternative.
+    fn neg(self) -> Duration {
+        self.checked_neg().expect("Cannot negate an infinite duration")
+    }
+}
+
+impl fmt::Display for Duration {
+    /// Format the duration with compound units, such as `1h30m`
+    ///
+    /// The largest unit is hours, or days for durations expressed in days.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let nanoseconds = match self.nanoseconds() {
+            Some(nanoseconds) => nanoseconds,
+            None => return write!(f, "inf"),
+        };
+
+        let largest = match self {
+            Duration::Days(_) => 0,
+            _ => 1,
+        };
+        let units = &UNITS[largest..];
+
+        if nanoseconds == 0 {
+            let (_, _, suffix) = units
+                .iter()
+                .find(|(_, constructor, _)| constructor(0).same_unit(self))
+                .expect("Every finite duration has a unit");
+            return write!(f, "0{}", suffix);
+        }
+
+        if nanoseconds < 0 {
+            write!(f, "-")?;
+        }
+
+        let mut remainder = nanoseconds.abs();
+        for (unit, _, suffix) in units.iter() {
+            if remainder >= *unit {
+                write!(f, "{}{}", remainder / unit, suffix)?;
+                remainder %= unit;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for Duration {
+    type Err = DurationError;
+
+    /// Parse an InfluxDB duration literal, such as `1h30m`, `-15m` or `inf`
+    ///
+    /// Supported units are `ns`, `us` (or `µs`), `ms`, `s`, `m`, `h`, `d` and
+    /// `w`.
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let invalid = || DurationError::Invalid(text.to_string());
+
+        if text == "inf" {
+            return Ok(Duration::Infinity);
+        }
+
+        let (negative, mut rest) = match text.strip_prefix('-') {
+            Some(rest) => (true, rest),
+            None => (false, text),
+        };
+        if rest.is_empty() {
+            return Err(invalid());
+        }
+
+        let mut nanoseconds: i128 = 0;
+        while !rest.is_empty() {
+            let digits = rest
+                .find(|character: char| !character.is_ascii_digit())
+                .ok_or_else(invalid)?;
+            let suffix = rest[digits..]
+                .find(|character: char| character.is_ascii_digit())
+                .map(|end| digits + end)
+                .unwrap_or_else(|| rest.len());
+            let amount: i128 = rest[..digits].parse().map_err(|_| invalid())?;
+            let unit = match &rest[digits..suffix] {
+                "ns" => NANOSECOND,
+                "us" | "µs" => MICROSECOND,
+                "ms" => MILLISECOND,
+                "s" => SECOND,
+                "m" => MINUTE,
+                "h" => HOUR,
+                "d" => DAY,
+                "w" => WEEK,
+                _ => return Err(invalid()),
+            };
+            nanoseconds = amount
+                .checked_mul(unit)
+                .and_then(|amount| nanoseconds.checked_add(amount))
+                .ok_or(DurationError::OutOfRange)?;
+            rest = &rest[suffix..];
+        }
+
+        if negative {
+            nanoseconds = -nanoseconds;
+        }
+        Duration::from_nanoseconds(nanoseconds).ok_or(DurationError::OutOfRange)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for Duration {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serializer.collect_str(self)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for Duration {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let text = String::deserialize(deserializer)?;
+        text.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+impl From<chrono::Duration> for Duration {
+    fn from(duration: chrono::Duration) -> Self {
+        let nanoseconds = match duration.num_nanoseconds() {
+            Some(nanoseconds) => nanoseconds as i128,
+            None => duration.num_milliseconds() as i128 * MILLISECOND,
+        };
+        Duration::from_nanoseconds(nanoseconds)
+            .expect("Every chrono duration fits in a duration")
+    }
+}
+
+impl TryFrom<Duration> for chrono::Duration {
+    type Error = DurationError;
+
+    fn try_from(duration: Duration) -> Result<Self, Self::Error> {
+        let nanoseconds = duration.nanoseconds().ok_or(DurationError::Infinite)?;
+        match i64::try_from(nanoseconds) {
+            Ok(nanoseconds) => Ok(chrono::Duration::nanoseconds(nanoseconds)),
+            Err(_) => {
+                let milliseconds = i64::try_from(nanoseconds / MILLISECOND)
+                    .map_err(|_| DurationError::OutOfRange)?;
+                if nanoseconds % MILLISECOND != 0 {
+                    return Err(DurationError::OutOfRange);
+                }
+                Ok(chrono::Duration::milliseconds(milliseconds))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_literals() {
+        assert_eq!("1h30m".parse(), Ok(Duration::Minutes(90)));
+        assert_eq!("-15m".parse(), Ok(Duration::Minutes(-15)));
+        assert_eq!("168h0m0s".parse(), Ok(Duration::Days(7)));
+        assert!(matches!("168h0m0s".parse(), Ok(Duration::Hours(168))));
+        assert_eq!("2w".parse(), Ok(Duration::Days(14)));
+        assert_eq!("1s500ms".parse(), Ok(Duration::Milliseconds(1500)));
+        assert_eq!("10µs".parse(), Ok(Duration::Microseconds(10)));
+        assert_eq!("0s".parse(), Ok(Duration::Seconds(0)));
+        assert_eq!("inf".parse(), Ok(Duration::Infinity));
+    }
+
+    #[test]
+    fn positive_durations() {
+        assert!(Duration::Nanoseconds(1).is_positive());
+        assert!(Duration::Days(7).is_positive());
+        assert!(!Duration::Minutes(0).is_positive());
+        assert!(!Duration::Hours(-1).is_positive());
+        assert!(!Duration::Infinity.is_positive());
+    }
+
+    #[test]
+    fn parse_invalid_literals() {
+        for text in &["", "-", "15", "m", "1x", "1h 30m", "--1h", "1.5h"] {
+            assert_eq!(
+                text.parse::<Duration>(),
+                Err(DurationError::Invalid(text.to_string())),
+            );
+        }
+        assert_eq!(
+            "9223372036854775807d".parse::<Duration>(),
+            Err(DurationError::OutOfRange),
+        );
+    }
+
+    #[test]
+    fn compare_lengths() {
+        assert!(Duration::Minutes(90) < Duration::Hours(2));
+        assert!(Duration::Days(1) > Duration::Hours(23));
+        assert!(Duration::Days(10_000) < Duration::Infinity);
+        assert_eq!(Duration::Minutes(60).cmp(&Duration::Hours(1)), Ordering::Equal);
+
+        let mut durations = std::collections::HashSet::new();
+        durations.insert(Duration::Minutes(60));
+        assert!(durations.contains(&Duration::Hours(1)));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serialize_literals() {
+        let durations = vec![Duration::Minutes(90), Duration::Infinity];
+        let json = serde_json::to_string(&durations).unwrap();
+        assert_eq!(json, r#"["1h30m","inf"]"#);
+        assert_eq!(serde_json::from_str::<Vec<Duration>>(&json).unwrap(), durations);
+        assert!(serde_json::from_str::<Duration>(r#""1x""#).is_err());
+    }
+
+    #[test]
+    fn format_compound_units() {
+        assert_eq!(Duration::Minutes(90).to_string(), "1h30m");
+        assert_eq!(Duration::Minutes(-15).to_string(), "-15m");
+        assert_eq!(Duration::Milliseconds(90_061_001).to_string(), "25h1m1s1ms");
+        assert_eq!(Duration::Days(7).to_string(), "7d");
+        assert_eq!(Duration::Hours(24).to_string(), "24h");
+        assert_eq!(Duration::Nanoseconds(1_500).to_string(), "1us500ns");
+        assert_eq!(Duration::Hours(0).to_string(), "0h");
+        assert_eq!(Duration::Infinity.to_string(), "inf");
+    }
+
+    #[test]
+    fn format_and_parse_round_trip() {
+        let durations = [
+            Duration::Nanoseconds(-123_456_789_012),
+            Duration::Seconds(86_399),
+            Duration::Days(-3),
+            Duration::Infinity,
+        ];
+        for duration in durations.iter() {
+            assert_eq!(duration.to_string().parse(), Ok(*duration));
+        }
+    }
+
+    #[test]
+    fn arithmetic() {
+        assert_eq!(Duration::Hours(1) + Duration::Minutes(30), Duration::Minutes(90));
+        assert_eq!(Duration::Hours(1) - Duration::Minutes(90), Duration::Minutes(-30));
+        assert_eq!(-Duration::Seconds(5), Duration::Seconds(-5));
+        assert_eq!(Duration::Infinity + Duration::Days(-1), Duration::Infinity);
+        assert_eq!(Duration::Infinity.checked_neg(), None);
+        assert_eq!(Duration::Days(i64::MAX).checked_add(&Duration::Days(1)), None);
+    }
+
+    #[test]
+    fn chrono_conversions() {
+        assert_eq!(
+            Duration::from(chrono::Duration::milliseconds(1500)),
+            Duration::Milliseconds(1500),
+        );
+        assert_eq!(
+            Duration::from(chrono::Duration::minutes(-4)),
+            Duration::Minutes(-4),
+        );
+        assert_eq!(
+            chrono::Duration::try_from(Duration::Minutes(90)),
+            Ok(chrono::Duration::minutes(90)),
+        );
+        assert_eq!(
+            chrono::Duration::try_from(Duration::Infinity),
+            Err(DurationError::Infinite),
+        );
+    }
+}
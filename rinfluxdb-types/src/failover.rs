@@ -0,0 +1,76 @@
+// Copyright Claudio Mattera 2021.
+// Distributed under the MIT License or Apache 2.0 License at your option.
+// See accompanying files License-MIT.txt and License-Apache-2.0, or online at
+// https://opensource.org/licenses/MIT
+// https://opensource.org/licenses/Apache-2.0
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// A strategy for choosing among multiple server URLs
+///
+/// Requests fail over to the next URL when the connection fails or when the
+/// server responds with a 5xx status code.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum FailoverStrategy {
+    /// Send requests to the first URL, and to the following ones only when
+    /// it fails
+    #[default]
+    PrimaryWithFallback,
+
+    /// Send each request to the next URL in turn, and to the following ones
+    /// when it fails
+    RoundRobin,
+}
+
+/// The order in which server URLs are tried
+///
+/// ```
+/// # use rinfluxdb_types::{Failover, FailoverStrategy};
+/// let failover = Failover::new(FailoverStrategy::PrimaryWithFallback);
+/// assert_eq!(failover.order(3), vec![0, 1, 2]);
+/// assert_eq!(failover.order(3), vec![0, 1, 2]);
+///
+/// let failover = Failover::new(FailoverStrategy::RoundRobin);
+/// assert_eq!(failover.order(3), vec![0, 1, 2]);
+/// assert_eq!(failover.order(3), vec![1, 2, 0]);
+/// assert_eq!(failover.order(3), vec![2, 0, 1]);
+/// ```
+#[derive(Debug, Default)]
+pub struct Failover {
+    strategy: FailoverStrategy,
+    next: AtomicUsize,
+}
+
+impl Failover {
+    /// Create a failover according to a strategy
+    pub fn new(strategy: FailoverStrategy) -> Self {
+        Self {
+            strategy,
+            next: AtomicUsize::new(0),
+        }
+    }
+
+    /// Return the strategy
+    pub fn strategy(&self) -> FailoverStrategy {
+        self.strategy
+    }
+
+    /// Return the indices of `count` URLs in the order they are tried for a
+    /// request
+    pub fn order(&self, count: usize) -> Vec<usize> {
+        if count == 0 {
+            return Vec::new();
+        }
+        let first = match self.strategy {
+            FailoverStrategy::PrimaryWithFallback => 0,
+            FailoverStrategy::RoundRobin => self.next.fetch_add(1, Ordering::Relaxed) % count,
+        };
+        (first..count).chain(0..first).collect()
+    }
+
+    /// Check whether a response status code causes a request to fail over to
+    /// the next URL
+    pub fn is_failover_status(status_code: u16) -> bool {
+        (500..600).contains(&status_code)
+    }
+}
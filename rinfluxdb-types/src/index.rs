@@ -0,0 +1,134 @@
+// Copyright Claudio Mattera 2021.
+// Distributed under the MIT License or Apache 2.0 License at your option.
+// See accompanying files License-MIT.txt and License-Apache-2.0, or online at
+// https://opensource.org/licenses/MIT
+// https://opensource.org/licenses/Apache-2.0
+
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::marker::PhantomData;
+
+use chrono::{DateTime, Utc};
+
+use super::{Columns, FromQueryResult};
+
+/// A type that can be used as index of a dataframe
+///
+/// This is implemented for `DateTime<Utc>` and for `i64`, which represents
+/// nanoseconds since the Unix epoch.
+pub trait Timestamp: Sized {
+    /// Convert an instant to the index type
+    fn from_datetime(instant: DateTime<Utc>) -> Self;
+}
+
+impl Timestamp for DateTime<Utc> {
+    fn from_datetime(instant: DateTime<Utc>) -> Self {
+        instant
+    }
+}
+
+impl Timestamp for i64 {
+    /// Convert an instant to nanoseconds since the Unix epoch
+    ///
+    /// # Panics
+    ///
+    /// Panics if the instant is out of range, i.e. before year 1677 or after
+    /// year 2262.
+    fn from_datetime(instant: DateTime<Utc>) -> Self {
+        instant.timestamp_nanos()
+    }
+}
+
+/// A decode target for dataframes with a custom index type
+///
+/// Parsers and clients build dataframes from a tuple containing an index
+/// of `DateTime<Utc>`.
+/// This wrapper converts the index to another type `I`, and builds a
+/// dataframe of type `DF` from a tuple containing an index of `I`.
+/// The index type is therefore chosen at compile time, and the dataframe
+/// type never has to deal with `chrono` types.
+///
+/// ```
+/// # use std::convert::TryFrom;
+/// # use chrono::{TimeZone, Utc};
+/// # use rinfluxdb_types::{Columns, DataFrameError, Indexed, Value};
+/// struct EpochDataFrame {
+///     index: Vec<i64>,
+///     columns: Columns,
+/// }
+///
+/// impl TryFrom<(String, Vec<i64>, Columns)> for EpochDataFrame {
+///     type Error = DataFrameError;
+///
+///     fn try_from(
+///         (_name, index, columns): (String, Vec<i64>, Columns),
+///     ) -> Result<Self, Self::Error> {
+///         Ok(Self { index, columns })
+///     }
+/// }
+///
+/// let index = vec![Utc.with_ymd_and_hms(2021, 3, 4, 17, 0, 0).unwrap()];
+/// let mut columns = Columns::new();
+/// columns.insert("temperature".to_string(), vec![Value::Float(28.4)]);
+///
+/// let dataframe: Indexed<EpochDataFrame, i64> =
+///     Indexed::try_from(("environment".to_string(), index, columns))?;
+/// let dataframe = dataframe.into_inner();
+///
+/// assert_eq!(dataframe.index, vec![1614877200000000000]);
+/// # Ok::<(), DataFrameError>(())
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct Indexed<DF, I> {
+    dataframe: DF,
+    index: PhantomData<I>,
+}
+
+impl<DF, I> Indexed<DF, I> {
+    /// Return the wrapped dataframe
+    pub fn into_inner(self) -> DF {
+        self.dataframe
+    }
+}
+
+impl<DF, I> AsRef<DF> for Indexed<DF, I> {
+    fn as_ref(&self) -> &DF {
+        &self.dataframe
+    }
+}
+
+impl<DF, I, E> TryFrom<(String, Vec<DateTime<Utc>>, Columns)> for Indexed<DF, I>
+where
+    I: Timestamp,
+    DF: TryFrom<(String, Vec<I>, Columns), Error = E>,
+{
+    type Error = E;
+
+    fn try_from(
+        (name, index, columns): (String, Vec<DateTime<Utc>>, Columns),
+    ) -> Result<Self, Self::Error> {
+        let index = index.into_iter().map(I::from_datetime).collect();
+        let dataframe = DF::try_from((name, index, columns))?;
+        Ok(Self {
+            dataframe,
+            index: PhantomData,
+        })
+    }
+}
+
+impl<DF, I, E> FromQueryResult for Indexed<DF, I>
+where
+    I: Timestamp,
+    DF: TryFrom<(String, Vec<I>, Columns), Error = E>,
+{
+    type Error = E;
+
+    fn from_query_result(
+        name: String,
+        index: Vec<DateTime<Utc>>,
+        columns: Columns,
+        _tags: Option<&HashMap<String, String>>,
+    ) -> Result<Self, Self::Error> {
+        Self::try_from((name, index, columns))
+    }
+}
@@ -6,6 +6,7 @@
 
 //! Types used by other modules
 
+use std::convert::TryFrom;
 use std::fmt;
 
 use tracing::*;
@@ -14,8 +15,58 @@ use thiserror::Error;
 
 use chrono::{DateTime, SecondsFormat, Utc};
 
+mod authentication;
+mod duration;
+mod failover;
+mod index;
+mod metrics;
+mod ping;
+mod precision;
+mod query_client;
+mod query_result;
+mod retry;
+#[cfg(feature = "serde")]
+mod rows;
+mod session;
+mod stopwatch;
+mod tag;
+mod timeseries;
+mod transport;
+mod window;
+
+pub use self::authentication::*;
+pub use self::duration::*;
+pub use self::failover::*;
+pub use self::index::*;
+pub use self::metrics::*;
+pub use self::ping::*;
+pub use self::precision::*;
+pub use self::query_client::*;
+pub use self::query_result::*;
+pub use self::retry::*;
+#[cfg(feature = "serde")]
+pub use self::rows::*;
+pub use self::session::*;
+pub use self::stopwatch::*;
+pub use self::tag::*;
+pub use self::timeseries::*;
+pub use self::transport::*;
+pub use self::window::*;
+
+pub use indexmap::IndexMap;
+
+/// The columns of a query result, in the order returned by the server
+///
+/// Columns are stored in an [`IndexMap`], which can be accessed by name
+/// like a `HashMap`, but iterates over columns in insertion order.
+pub type Columns = IndexMap<String, Vec<Value>>;
+
 /// Value types supported by InfluxDB
+///
+/// With feature `serde`, values are serialized as externally tagged enums,
+/// such as `{"Float": 2.5}`, so that their type is preserved.
 #[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Value {
     /// A floating point value
     Float(f64),
@@ -34,67 +85,235 @@ pub enum Value {
 
     /// A datetime value (as nanosecond epoch)
     Timestamp(DateTime<Utc>),
+
+    /// A missing value
+    ///
+    /// InfluxDB returns missing values for fields that were not written at
+    /// some instants, and for aggregation windows without data.
+    Null,
 }
 
 impl Value {
+    /// Return the value as a float, panicking on mismatching types
+    ///
+    /// Integers and unsigned integers are converted to floats.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the value is not numeric, see
+    /// [`try_into_f64()`](Value::try_into_f64) for a fallible alternative.
     pub fn into_f64(self) -> f64 {
-        if let Value::Float(value) = self {
-            value
-        } else if let Value::Integer(value) = self {
-            value as f64
-        } else if let Value::UnsignedInteger(value) = self {
-            value as f64
-        } else {
-            panic!("Not a f64: {:?}", self);
-        }
+        self.try_into_f64().unwrap_or_else(|error| panic!("{}", error))
     }
 
+    /// Return the value as an integer, panicking on mismatching types
+    ///
+    /// Unsigned integers are converted to integers, and floats are
+    /// truncated.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the value is not numeric or does not fit an integer, see
+    /// [`try_into_i64()`](Value::try_into_i64) for a fallible alternative.
     pub fn into_i64(self) -> i64 {
-        if let Value::Integer(value) = self {
-            value
-        } else if let Value::UnsignedInteger(value) = self {
-            value as i64
-        } else if let Value::Float(value) = self {
-            warn!("Casting float to integer");
-            value as i64
-        } else {
-            panic!("Not a i64: {:?}", self);
-        }
+        self.try_into_i64().unwrap_or_else(|error| panic!("{}", error))
     }
 
+    /// Return the value as an unsigned integer, panicking on mismatching types
+    ///
+    /// # Panics
+    ///
+    /// Panics if the value is not an unsigned integer, see
+    /// [`try_into_u64()`](Value::try_into_u64) for a fallible alternative.
     pub fn into_u64(self) -> u64 {
-        if let Value::UnsignedInteger(value) = self {
-            value
-        } else {
-            panic!("Not a u64: {:?}", self);
-        }
+        self.try_into_u64().unwrap_or_else(|error| panic!("{}", error))
     }
 
+    /// Return the value as a boolean, panicking on mismatching types
+    ///
+    /// # Panics
+    ///
+    /// Panics if the value is not a boolean, see
+    /// [`try_into_boolean()`](Value::try_into_boolean) for a fallible
+    /// alternative.
     pub fn into_boolean(self) -> bool {
-        if let Value::Boolean(value) = self {
-            value
-        } else {
-            panic!("Not a boolean: {:?}", self);
-        }
+        self.try_into_boolean().unwrap_or_else(|error| panic!("{}", error))
     }
 
+    /// Return the value as a string, panicking on mismatching types
+    ///
+    /// # Panics
+    ///
+    /// Panics if the value is not a string, see
+    /// [`try_into_string()`](Value::try_into_string) for a fallible
+    /// alternative.
     pub fn into_string(self) -> String {
-        if let Value::String(value) = self {
-            value
-        } else {
-            panic!("Not a string: {:?}", self);
-        }
+        self.try_into_string().unwrap_or_else(|error| panic!("{}", error))
     }
 
+    /// Return the value as a timestamp, panicking on mismatching types
+    ///
+    /// # Panics
+    ///
+    /// Panics if the value is not a timestamp, see
+    /// [`try_into_timestamp()`](Value::try_into_timestamp) for a fallible
+    /// alternative.
     pub fn into_timestamp(self) -> DateTime<Utc> {
-        if let Value::Timestamp(value) = self {
-            value
-        } else {
-            panic!("Not a timestamp: {:?}", self);
+        self.try_into_timestamp().unwrap_or_else(|error| panic!("{}", error))
+    }
+
+    /// Return the value as a float
+    ///
+    /// Integers and unsigned integers are converted to floats.
+    ///
+    /// ```
+    /// # use rinfluxdb_types::{Value, ValueError};
+    /// assert_eq!(Value::Integer(3).try_into_f64(), Ok(3.0));
+    /// assert_eq!(
+    ///     Value::String("3".into()).try_into_f64(),
+    ///     Err(ValueError::TypeMismatch { expected: "float", found: Value::String("3".into()) }),
+    /// );
+    /// ```
+    pub fn try_into_f64(self) -> Result<f64, ValueError> {
+        match self {
+            Value::Float(value) => Ok(value),
+            Value::Integer(value) => Ok(value as f64),
+            Value::UnsignedInteger(value) => Ok(value as f64),
+            found => Err(ValueError::TypeMismatch { expected: "float", found }),
+        }
+    }
+
+    /// Return the value as an integer
+    ///
+    /// Unsigned integers are converted to integers if they fit, and floats
+    /// are truncated.
+    pub fn try_into_i64(self) -> Result<i64, ValueError> {
+        match self {
+            Value::Integer(value) => Ok(value),
+            Value::UnsignedInteger(value) => i64::try_from(value)
+                .map_err(|_| ValueError::OutOfRange(Value::UnsignedInteger(value))),
+            Value::Float(value) => {
+                warn!("Casting float to integer");
+                Ok(value as i64)
+            }
+            found => Err(ValueError::TypeMismatch { expected: "integer", found }),
+        }
+    }
+
+    /// Return the value as an unsigned integer
+    pub fn try_into_u64(self) -> Result<u64, ValueError> {
+        match self {
+            Value::UnsignedInteger(value) => Ok(value),
+            found => Err(ValueError::TypeMismatch { expected: "unsigned integer", found }),
+        }
+    }
+
+    /// Return the value as a boolean
+    pub fn try_into_boolean(self) -> Result<bool, ValueError> {
+        match self {
+            Value::Boolean(value) => Ok(value),
+            found => Err(ValueError::TypeMismatch { expected: "boolean", found }),
+        }
+    }
+
+    /// Return the value as a string
+    pub fn try_into_string(self) -> Result<String, ValueError> {
+        match self {
+            Value::String(value) => Ok(value),
+            found => Err(ValueError::TypeMismatch { expected: "string", found }),
+        }
+    }
+
+    /// Return the value as a timestamp
+    pub fn try_into_timestamp(self) -> Result<DateTime<Utc>, ValueError> {
+        match self {
+            Value::Timestamp(value) => Ok(value),
+            found => Err(ValueError::TypeMismatch { expected: "timestamp", found }),
+        }
+    }
+
+    /// Check whether the value is missing
+    pub fn is_null(&self) -> bool {
+        matches!(self, Value::Null)
+    }
+
+    /// Return a reference to the float, if the value is a float
+    ///
+    /// Unlike [`try_into_f64()`](Value::try_into_f64), no conversion is
+    /// performed.
+    ///
+    /// ```
+    /// # use rinfluxdb_types::Value;
+    /// assert_eq!(Value::Float(2.5).as_f64(), Some(&2.5));
+    /// assert_eq!(Value::Integer(2).as_f64(), None);
+    /// ```
+    pub fn as_f64(&self) -> Option<&f64> {
+        match self {
+            Value::Float(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    /// Return a reference to the integer, if the value is an integer
+    pub fn as_i64(&self) -> Option<&i64> {
+        match self {
+            Value::Integer(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    /// Return a reference to the unsigned integer, if the value is an
+    /// unsigned integer
+    pub fn as_u64(&self) -> Option<&u64> {
+        match self {
+            Value::UnsignedInteger(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    /// Return a reference to the boolean, if the value is a boolean
+    pub fn as_boolean(&self) -> Option<&bool> {
+        match self {
+            Value::Boolean(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    /// Return a reference to the string, if the value is a string
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Value::String(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    /// Return a reference to the timestamp, if the value is a timestamp
+    pub fn as_timestamp(&self) -> Option<&DateTime<Utc>> {
+        match self {
+            Value::Timestamp(value) => Some(value),
+            _ => None,
         }
     }
 }
 
+/// An error occurred while converting a value to a specific type
+#[derive(Error, Clone, Debug, PartialEq)]
+pub enum ValueError {
+    /// The value has a different type
+    #[error("Expected {expected}, found {found:?}")]
+    TypeMismatch {
+        /// The expected type
+        expected: &'static str,
+
+        /// The actual value
+        found: Value,
+    },
+
+    /// The value does not fit in the expected type
+    #[error("Value {0:?} is out of range")]
+    OutOfRange(Value),
+}
+
 impl fmt::Display for Value {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match &self {
@@ -116,64 +335,54 @@ impl fmt::Display for Value {
             Value::Timestamp(value) => {
                 write!(f, "{}", value)?;
             }
+            Value::Null => {
+                write!(f, "null")?;
+            }
         }
 
         Ok(())
     }
 }
 
-/// A duration
-///
-/// Note: this type is almost entirely equivalent to `chrono::Duration`, but
-/// it also supports infinite duration in addition.
-///
-/// Since `chrono::Duration` implements `Into<Duration>`, the former can be
-/// used everywhere the latter is expected.
-#[derive(Debug)]
-pub enum Duration {
-    /// A duration expressed in nanoseconds
-    Nanoseconds(i64),
-
-    /// A duration expressed in microseconds
-    Microseconds(i64),
-
-    /// A duration expressed in milliseconds
-    Milliseconds(i64),
-
-    /// A duration expressed in seconds
-    Seconds(i64),
+impl From<&str> for Value {
+    fn from(value: &str) -> Self {
+        Self::String(value.to_string())
+    }
+}
 
-    /// A duration expressed in minutes
-    Minutes(i64),
+impl From<String> for Value {
+    fn from(value: String) -> Self {
+        Self::String(value)
+    }
+}
 
-    /// A duration expressed in hours
-    Hours(i64),
+impl From<f64> for Value {
+    fn from(value: f64) -> Self {
+        Self::Float(value)
+    }
+}
 
-    /// A duration expressed in days
-    Days(i64),
+impl From<i64> for Value {
+    fn from(value: i64) -> Self {
+        Self::Integer(value)
+    }
+}
 
-    /// An infinite duration
-    Infinity,
+impl From<u64> for Value {
+    fn from(value: u64) -> Self {
+        Self::UnsignedInteger(value)
+    }
 }
 
-impl ToString for Duration {
-    fn to_string(&self) -> String {
-        match self {
-            Duration::Nanoseconds(seconds) => format!("{}ns", seconds),
-            Duration::Microseconds(seconds) => format!("{}us", seconds),
-            Duration::Milliseconds(seconds) => format!("{}ms", seconds),
-            Duration::Seconds(seconds) => format!("{}s", seconds),
-            Duration::Minutes(minutes) => format!("{}m", minutes),
-            Duration::Hours(hours) => format!("{}h", hours),
-            Duration::Days(days) => format!("{}d", days),
-            Duration::Infinity => "inf".to_string(),
-        }
+impl From<bool> for Value {
+    fn from(value: bool) -> Self {
+        Self::Boolean(value)
     }
 }
 
-impl From<chrono::Duration> for Duration {
-    fn from(duration: chrono::Duration) -> Self {
-        Duration::Seconds(duration.num_seconds())
+impl From<DateTime<Utc>> for Value {
+    fn from(value: DateTime<Utc>) -> Self {
+        Self::Timestamp(value)
     }
 }
 
@@ -183,7 +392,7 @@ impl From<chrono::Duration> for Duration {
 /// interprets them as the point in time relative to the current instant.
 /// E.g. if now is `2021-03-10T22:43:32Z`, the duration `Duration::Minutes(-4)`
 /// is interpreted as the instant `2021-03-10T22:39:32Z`.
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub enum InstantOrDuration {
     /// An instant in time
     Instant(DateTime<Utc>),
@@ -192,14 +401,15 @@ pub enum InstantOrDuration {
     Duration(Duration),
 }
 
-impl ToString for InstantOrDuration {
-    fn to_string(&self) -> String {
+impl fmt::Display for InstantOrDuration {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
-            InstantOrDuration::Instant(instant) => format!(
+            InstantOrDuration::Instant(instant) => write!(
+                f,
                 "'{}'",
                 instant.to_rfc3339_opts(SecondsFormat::AutoSi, true),
             ),
-            InstantOrDuration::Duration(duration) => duration.to_string(),
+            InstantOrDuration::Duration(duration) => write!(f, "{}", duration),
         }
     }
 }
@@ -228,4 +438,64 @@ pub enum DataFrameError {
     /// Error while creating the dataframe
     #[error("Error while creating the dataframe")]
     Creation,
+
+    /// A value in a column does not match the column type
+    #[error("Invalid value in column")]
+    ValueError(#[from] ValueError),
+
+    /// Two dataframes being joined have a column with the same name
+    #[error("Duplicate column {0}")]
+    DuplicateColumn(String),
+
+    /// Two dataframes being concatenated have different columns
+    #[error("Mismatching column {0}")]
+    SchemaMismatch(String),
+}
+
+/// An error occurred while building a query
+#[derive(Error, Clone, Debug, PartialEq, Eq)]
+pub enum BuildError {
+    /// The query does not select anything
+    #[error("Empty selection")]
+    EmptySelection,
+
+    /// Two clauses of the query cannot be used together
+    #[error("Conflicting clauses: {0}")]
+    ConflictingClauses(String),
+
+    /// The query has no time range, or sets it after steps that need it
+    #[error("Missing range")]
+    MissingRange,
+
+    /// An identifier is not valid
+    #[error("Invalid identifier \"{0}\"")]
+    InvalidIdentifier(String),
+
+    /// A duration is not valid in its context
+    #[error("Invalid duration {0}")]
+    InvalidDuration(String),
+
+    /// A value cannot be written in a query
+    #[error("Invalid value {0}")]
+    InvalidValue(String),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fallible_conversions() {
+        assert_eq!(Value::UnsignedInteger(7).try_into_i64(), Ok(7));
+        assert_eq!(
+            Value::UnsignedInteger(u64::MAX).try_into_i64(),
+            Err(ValueError::OutOfRange(Value::UnsignedInteger(u64::MAX))),
+        );
+        assert!(matches!(
+            Value::Boolean(true).try_into_string(),
+            Err(ValueError::TypeMismatch { expected: "string", .. }),
+        ));
+        assert_eq!(Value::String("a".into()).as_str(), Some("a"));
+        assert_eq!(Value::String("a".into()).as_boolean(), None);
+    }
 }
@@ -0,0 +1,105 @@
+// Copyright Claudio Mattera 2021.
+// Distributed under the MIT License or Apache 2.0 License at your option.
+// See accompanying files License-MIT.txt and License-Apache-2.0, or online at
+// https://opensource.org/licenses/MIT
+// https://opensource.org/licenses/Apache-2.0
+
+use std::error::Error;
+use std::fmt;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// An observer notified of writes and queries sent by clients
+///
+/// Clients call the observer set on their builders before and after each
+/// write or query, including all its retries and failovers, so applications
+/// can export metrics about their traffic to InfluxDB.
+/// All methods do nothing by default.
+///
+/// ```
+/// # use std::sync::atomic::{AtomicUsize, Ordering};
+/// # use std::time::Duration;
+/// # use rinfluxdb_types::MetricsObserver;
+/// #[derive(Debug, Default)]
+/// struct Counters {
+///     written_bytes: AtomicUsize,
+///     failed_writes: AtomicUsize,
+/// }
+///
+/// impl MetricsObserver for Counters {
+///     fn on_write_success(&self, _lines: usize, bytes: usize, _elapsed: Duration) {
+///         self.written_bytes.fetch_add(bytes, Ordering::Relaxed);
+///     }
+///
+///     fn on_write_failure(
+///         &self,
+///         _lines: usize,
+///         _bytes: usize,
+///         _elapsed: Duration,
+///         _error: &dyn std::error::Error,
+///     ) {
+///         self.failed_writes.fetch_add(1, Ordering::Relaxed);
+///     }
+/// }
+///
+/// let counters = Counters::default();
+/// counters.on_write_start(2, 58);
+/// counters.on_write_success(2, 58, Duration::from_millis(12));
+/// assert_eq!(counters.written_bytes.load(Ordering::Relaxed), 58);
+/// ```
+pub trait MetricsObserver: fmt::Debug + Send + Sync {
+    /// Called before sending lines
+    ///
+    /// Parameter `bytes` is the size of the request body.
+    fn on_write_start(&self, _lines: usize, _bytes: usize) {}
+
+    /// Called after lines were written
+    fn on_write_success(&self, _lines: usize, _bytes: usize, _elapsed: Duration) {}
+
+    /// Called after writing lines failed
+    fn on_write_failure(&self, _lines: usize, _bytes: usize, _elapsed: Duration, _error: &dyn Error) {}
+
+    /// Called before sending a query
+    ///
+    /// Parameter `query_bytes` is the size of the query text.
+    fn on_query_start(&self, _query_bytes: usize) {}
+
+    /// Called after a query returned a response
+    ///
+    /// Parameter `response_bytes` is the size of the response body, or zero
+    /// for responses that are streamed in chunks, in which case the query is
+    /// considered successful as soon as the response starts.
+    fn on_query_success(&self, _query_bytes: usize, _response_bytes: usize, _elapsed: Duration) {}
+
+    /// Called after a query failed
+    fn on_query_failure(&self, _query_bytes: usize, _elapsed: Duration, _error: &dyn Error) {}
+}
+
+impl<T> MetricsObserver for Arc<T>
+where
+    T: MetricsObserver + ?Sized,
+{
+    fn on_write_start(&self, lines: usize, bytes: usize) {
+        (**self).on_write_start(lines, bytes)
+    }
+
+    fn on_write_success(&self, lines: usize, bytes: usize, elapsed: Duration) {
+        (**self).on_write_success(lines, bytes, elapsed)
+    }
+
+    fn on_write_failure(&self, lines: usize, bytes: usize, elapsed: Duration, error: &dyn Error) {
+        (**self).on_write_failure(lines, bytes, elapsed, error)
+    }
+
+    fn on_query_start(&self, query_bytes: usize) {
+        (**self).on_query_start(query_bytes)
+    }
+
+    fn on_query_success(&self, query_bytes: usize, response_bytes: usize, elapsed: Duration) {
+        (**self).on_query_success(query_bytes, response_bytes, elapsed)
+    }
+
+    fn on_query_failure(&self, query_bytes: usize, elapsed: Duration, error: &dyn Error) {
+        (**self).on_query_failure(query_bytes, elapsed, error)
+    }
+}
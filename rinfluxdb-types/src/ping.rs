@@ -0,0 +1,18 @@
+// Copyright Claudio Mattera 2021.
+// Distributed under the MIT License or Apache 2.0 License at your option.
+// See accompanying files License-MIT.txt and License-Apache-2.0, or online at
+// https://opensource.org/licenses/MIT
+// https://opensource.org/licenses/Apache-2.0
+
+/// Information reported by an InfluxDB server when pinged
+///
+/// Fields are `None` if the server, or a proxy in front of it, did not
+/// report them.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Ping {
+    /// The server version, such as `1.8.10` or `2.0.4`
+    pub version: Option<String>,
+
+    /// The server build type, such as `OSS` or `ENT`
+    pub build: Option<String>,
+}
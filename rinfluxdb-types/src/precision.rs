@@ -0,0 +1,127 @@
+// Copyright Claudio Mattera 2021.
+// Distributed under the MIT License or Apache 2.0 License at your option.
+// See accompanying files License-MIT.txt and License-Apache-2.0, or online at
+// https://opensource.org/licenses/MIT
+// https://opensource.org/licenses/Apache-2.0
+
+use chrono::{DateTime, Duration, Utc};
+
+/// Precision of timestamps
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Precision {
+    /// Nanosecond precision
+    Nanoseconds,
+
+    /// Microsecond precision
+    Microseconds,
+
+    /// Millisecond precision
+    Milliseconds,
+
+    /// Second precision
+    Seconds,
+
+    /// Minute precision
+    Minutes,
+
+    /// Hour precision
+    Hours,
+}
+
+impl Precision {
+    /// Return the number of nanoseconds in a unit of this precision
+    pub fn nanoseconds(&self) -> i64 {
+        match self {
+            Precision::Nanoseconds => 1,
+            Precision::Microseconds => 1_000,
+            Precision::Milliseconds => 1_000_000,
+            Precision::Seconds => 1_000_000_000,
+            Precision::Minutes => 60 * 1_000_000_000,
+            Precision::Hours => 60 * 60 * 1_000_000_000,
+        }
+    }
+
+    /// Return the abbreviation used by InfluxDB for this precision
+    ///
+    /// ```
+    /// # use rinfluxdb_types::Precision;
+    /// assert_eq!(Precision::Nanoseconds.as_str(), "ns");
+    /// assert_eq!(Precision::Seconds.as_str(), "s");
+    /// ```
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Precision::Nanoseconds => "ns",
+            Precision::Microseconds => "u",
+            Precision::Milliseconds => "ms",
+            Precision::Seconds => "s",
+            Precision::Minutes => "m",
+            Precision::Hours => "h",
+        }
+    }
+
+    /// Truncate an instant to this precision
+    ///
+    /// ```
+    /// # use chrono::{TimeZone, Utc};
+    /// # use rinfluxdb_types::Precision;
+    /// let instant = Utc.with_ymd_and_hms(2021, 3, 7, 21, 13, 42).unwrap() + chrono::Duration::milliseconds(250);
+    ///
+    /// assert_eq!(
+    ///     Precision::Seconds.truncate(instant),
+    ///     Utc.with_ymd_and_hms(2021, 3, 7, 21, 13, 42).unwrap(),
+    /// );
+    /// assert_eq!(
+    ///     Precision::Minutes.truncate(instant),
+    ///     Utc.with_ymd_and_hms(2021, 3, 7, 21, 13, 0).unwrap(),
+    /// );
+    /// ```
+    pub fn truncate(&self, instant: DateTime<Utc>) -> DateTime<Utc> {
+        // Compute in 128 bits, since instants far from epoch overflow 64 bits
+        // nanoseconds
+        let nanoseconds = instant.timestamp() as i128 * 1_000_000_000
+            + instant.timestamp_subsec_nanos() as i128;
+        let remainder = nanoseconds.rem_euclid(self.nanoseconds() as i128) as i64;
+        instant - Duration::nanoseconds(remainder)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use chrono::TimeZone;
+
+    #[test]
+    fn truncate_before_epoch() {
+        let instant = Utc.with_ymd_and_hms(1969, 12, 31, 23, 59, 59).unwrap() + chrono::Duration::milliseconds(500);
+
+        assert_eq!(
+            Precision::Seconds.truncate(instant),
+            Utc.with_ymd_and_hms(1969, 12, 31, 23, 59, 59).unwrap(),
+        );
+    }
+
+    #[test]
+    fn truncate_nanoseconds_is_identity() {
+        let instant = Utc.with_ymd_and_hms(2021, 3, 7, 21, 13, 42).unwrap() + chrono::Duration::nanoseconds(123_456_789);
+
+        assert_eq!(Precision::Nanoseconds.truncate(instant), instant);
+    }
+
+    #[test]
+    fn truncate_outside_nanoseconds_range() {
+        let instant = Utc.with_ymd_and_hms(2500, 1, 1, 12, 30, 15).unwrap() + chrono::Duration::milliseconds(750);
+
+        assert_eq!(
+            Precision::Hours.truncate(instant),
+            Utc.with_ymd_and_hms(2500, 1, 1, 12, 0, 0).unwrap(),
+        );
+
+        let instant = Utc.with_ymd_and_hms(1500, 1, 1, 12, 30, 15).unwrap() + chrono::Duration::milliseconds(750);
+
+        assert_eq!(
+            Precision::Seconds.truncate(instant),
+            Utc.with_ymd_and_hms(1500, 1, 1, 12, 30, 15).unwrap(),
+        );
+    }
+}
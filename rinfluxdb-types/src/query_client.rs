@@ -0,0 +1,69 @@
+// Copyright Claudio Mattera 2021.
+// Distributed under the MIT License or Apache 2.0 License at your option.
+// See accompanying files License-MIT.txt and License-Apache-2.0, or online at
+// https://opensource.org/licenses/MIT
+// https://opensource.org/licenses/Apache-2.0
+
+use std::collections::HashMap;
+
+#[cfg(feature = "async")]
+use async_trait::async_trait;
+
+/// An error returned by a query client
+///
+/// Errors are boxed, so that clients for different query languages can be
+/// used through the same trait object.
+/// They can be downcast to the error type of each client.
+pub type QueryClientError = Box<dyn std::error::Error + Send + Sync>;
+
+/// Dataframes together with the values of their tags
+pub type TaggedDataframes<DF> = Vec<(DF, HashMap<String, String>)>;
+
+/// A client that queries an InfluxDB server for dataframes
+///
+/// The trait is implemented by both InfluxQL and Flux blocking clients, so
+/// applications can switch between InfluxDB 1.x and 2.x through generics or
+/// trait objects.
+/// Queries are passed as text, and must be written in the query language
+/// of the client.
+///
+/// ```
+/// # use std::collections::HashMap;
+/// # use rinfluxdb_types::{QueryClient, QueryClientError};
+/// fn print_rooms<C>(client: &C, query: &str) -> Result<(), QueryClientError>
+/// where
+///     C: QueryClient<String> + ?Sized,
+/// {
+///     for (room, dataframe) in client.fetch_dataframes_by_tag(query, "room")? {
+///         println!("{}: {}", room, dataframe);
+///     }
+///     Ok(())
+/// }
+/// ```
+pub trait QueryClient<DF> {
+    /// Query the server for a single dataframe
+    fn fetch_dataframe(&self, query: &str) -> Result<DF, QueryClientError>;
+
+    /// Query the server for dataframes grouped by a single tag
+    fn fetch_dataframes_by_tag(&self, query: &str, tag: &str) -> Result<HashMap<String, DF>, QueryClientError>;
+
+    /// Query the server for all dataframes, together with their tags
+    fn fetch_tagged_dataframes(&self, query: &str) -> Result<TaggedDataframes<DF>, QueryClientError>;
+}
+
+/// An asynchronous client that queries an InfluxDB server for dataframes
+///
+/// This is the same as [`QueryClient`], but for asynchronous clients.
+#[cfg(feature = "async")]
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+pub trait AsyncQueryClient<DF> {
+    /// Query the server for a single dataframe
+    async fn fetch_dataframe(&self, query: &str) -> Result<DF, QueryClientError>;
+
+    /// Query the server for dataframes grouped by a single tag
+    async fn fetch_dataframes_by_tag(&self, query: &str, tag: &str) -> Result<HashMap<String, DF>, QueryClientError>;
+
+    /// Query the server for all dataframes, together with their tags
+    async fn fetch_tagged_dataframes(&self, query: &str) -> Result<TaggedDataframes<DF>, QueryClientError>;
+}
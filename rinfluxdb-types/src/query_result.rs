@@ -0,0 +1,187 @@
+// Copyright Claudio Mattera 2021.
+// Distributed under the MIT License or Apache 2.0 License at your option.
+// See accompanying files License-MIT.txt and License-Apache-2.0, or online at
+// https://opensource.org/licenses/MIT
+// https://opensource.org/licenses/Apache-2.0
+
+use std::collections::HashMap;
+
+use chrono::{DateTime, Utc};
+
+use indexmap::IndexMap;
+
+use super::{Columns, DataFrameError, Value};
+
+/// A type that can be constructed from the result of a query
+///
+/// Parsers and clients construct dataframes through this trait, passing
+/// the name of the measurement, the index, the columns and, for series
+/// returned by queries grouped by tags, the tag values.
+/// Types that do not need tags can simply ignore them.
+///
+/// ```
+/// # use std::collections::HashMap;
+/// # use chrono::{DateTime, TimeZone, Utc};
+/// # use rinfluxdb_types::{Columns, DataFrameError, FromQueryResult, Value};
+/// struct Temperatures {
+///     room: Option<String>,
+///     values: Vec<Value>,
+/// }
+///
+/// impl FromQueryResult for Temperatures {
+///     type Error = DataFrameError;
+///
+///     fn from_query_result(
+///         _name: String,
+///         _index: Vec<DateTime<Utc>>,
+///         mut columns: Columns,
+///         tags: Option<&HashMap<String, String>>,
+///     ) -> Result<Self, Self::Error> {
+///         let values = columns.remove("temperature").ok_or(DataFrameError::Creation)?;
+///         let room = tags.and_then(|tags| tags.get("room")).cloned();
+///         Ok(Self { room, values })
+///     }
+/// }
+///
+/// let index = vec![Utc.with_ymd_and_hms(2021, 3, 4, 17, 0, 0).unwrap()];
+/// let mut columns = Columns::new();
+/// columns.insert("temperature".to_string(), vec![Value::Float(28.4)]);
+/// let mut tags = HashMap::new();
+/// tags.insert("room".to_string(), "kitchen".to_string());
+///
+/// let temperatures = Temperatures::from_query_result(
+///     "environment".to_string(),
+///     index,
+///     columns,
+///     Some(&tags),
+/// )?;
+///
+/// assert_eq!(temperatures.room, Some("kitchen".to_string()));
+/// assert_eq!(temperatures.values, vec![Value::Float(28.4)]);
+/// # Ok::<(), DataFrameError>(())
+/// ```
+pub trait FromQueryResult: Sized {
+    /// The error returned when the type cannot be constructed
+    type Error;
+
+    /// Construct from the name, index, columns and tags of a query result
+    fn from_query_result(
+        name: String,
+        index: Vec<DateTime<Utc>>,
+        columns: Columns,
+        tags: Option<&HashMap<String, String>>,
+    ) -> Result<Self, Self::Error>;
+}
+
+/// A single row of a query result
+///
+/// A list of records is the simplest type that can be constructed from a
+/// query result, for users that do not need dataframes.
+///
+/// ```
+/// # use chrono::{TimeZone, Utc};
+/// # use rinfluxdb_types::{Columns, FromQueryResult, Record, Value};
+/// let index = vec![
+///     Utc.with_ymd_and_hms(2021, 3, 4, 17, 0, 0).unwrap(),
+///     Utc.with_ymd_and_hms(2021, 3, 4, 18, 0, 0).unwrap(),
+/// ];
+/// let mut columns = Columns::new();
+/// columns.insert("temperature".to_string(), vec![Value::Float(28.4), Value::Float(29.2)]);
+///
+/// let records = Vec::<Record>::from_query_result("environment".to_string(), index, columns, None)?;
+///
+/// assert_eq!(records.len(), 2);
+/// assert_eq!(records[1].measurement, "environment");
+/// assert_eq!(records[1].time, Utc.with_ymd_and_hms(2021, 3, 4, 18, 0, 0).unwrap());
+/// assert_eq!(records[1].values.get("temperature"), Some(&Value::Float(29.2)));
+/// # Ok::<(), rinfluxdb_types::DataFrameError>(())
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct Record {
+    /// The name of the measurement
+    pub measurement: String,
+
+    /// The instant of the row
+    pub time: DateTime<Utc>,
+
+    /// The values of all columns, in their original order
+    pub values: IndexMap<String, Value>,
+
+    /// The tags of the series the row belongs to
+    pub tags: HashMap<String, String>,
+}
+
+impl FromQueryResult for Vec<Record> {
+    type Error = DataFrameError;
+
+    fn from_query_result(
+        name: String,
+        index: Vec<DateTime<Utc>>,
+        columns: Columns,
+        tags: Option<&HashMap<String, String>>,
+    ) -> Result<Self, Self::Error> {
+        if columns.values().any(|column| column.len() != index.len()) {
+            return Err(DataFrameError::Creation);
+        }
+
+        let tags = tags.cloned().unwrap_or_default();
+        let mut records: Vec<Record> = index
+            .into_iter()
+            .map(|time| Record {
+                measurement: name.clone(),
+                time,
+                values: IndexMap::with_capacity(columns.len()),
+                tags: tags.clone(),
+            })
+            .collect();
+
+        for (column_name, column) in columns {
+            for (record, value) in records.iter_mut().zip(column) {
+                record.values.insert(column_name.clone(), value);
+            }
+        }
+
+        Ok(records)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use chrono::TimeZone;
+
+    #[test]
+    fn records_with_tags() {
+        let index = vec![Utc.with_ymd_and_hms(2021, 3, 4, 17, 0, 0).unwrap()];
+        let mut columns = Columns::new();
+        columns.insert("temperature".to_string(), vec![Value::Float(28.4)]);
+        columns.insert("humidity".to_string(), vec![Value::Null]);
+        let mut tags = HashMap::new();
+        tags.insert("room".to_string(), "kitchen".to_string());
+
+        let records = Vec::<Record>::from_query_result(
+            "environment".to_string(),
+            index,
+            columns,
+            Some(&tags),
+        )
+        .unwrap();
+
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].tags, tags);
+        let names: Vec<&str> = records[0].values.keys().map(String::as_str).collect();
+        assert_eq!(names, vec!["temperature", "humidity"]);
+    }
+
+    #[test]
+    fn records_mismatched_lengths() {
+        let index = vec![Utc.with_ymd_and_hms(2021, 3, 4, 17, 0, 0).unwrap()];
+        let mut columns = Columns::new();
+        columns.insert("temperature".to_string(), vec![]);
+
+        let records = Vec::<Record>::from_query_result("environment".to_string(), index, columns, None);
+
+        assert!(matches!(records, Err(DataFrameError::Creation)));
+    }
+}
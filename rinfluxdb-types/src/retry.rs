@@ -0,0 +1,188 @@
+// Copyright Claudio Mattera 2021.
+// Distributed under the MIT License or Apache 2.0 License at your option.
+// See accompanying files License-MIT.txt and License-Apache-2.0, or online at
+// https://opensource.org/licenses/MIT
+// https://opensource.org/licenses/Apache-2.0
+
+use std::time::Duration as StdDuration;
+
+use chrono::{DateTime, Utc};
+
+/// A policy for retrying failed requests with exponential backoff
+///
+/// Requests are retried when the server responds with one of the retryable
+/// status codes, or when the connection fails.
+/// The delay before each retry is multiplied at every attempt, up to a
+/// maximal delay.
+///
+/// ```
+/// # use std::time::Duration;
+/// # use rinfluxdb_types::RetryPolicy;
+/// let policy = RetryPolicy::new(4)
+///     .backoff(Duration::from_millis(100), Duration::from_millis(300))
+///     .multiplier(2);
+///
+/// assert_eq!(policy.delay(1), Duration::from_millis(100));
+/// assert_eq!(policy.delay(2), Duration::from_millis(200));
+/// assert_eq!(policy.delay(3), Duration::from_millis(300));
+///
+/// assert!(policy.is_retryable_status(503));
+/// assert!(!policy.is_retryable_status(404));
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RetryPolicy {
+    max_attempts: u32,
+    initial_backoff: StdDuration,
+    max_backoff: StdDuration,
+    multiplier: u32,
+    retryable_status_codes: Vec<u16>,
+}
+
+impl RetryPolicy {
+    /// Create a policy sending requests at most `max_attempts` times
+    ///
+    /// The first retry happens after 100 ms, and the delay doubles up to
+    /// 10 s.
+    /// Status codes 429, 500, 502, 503 and 504 are retried.
+    pub fn new(max_attempts: u32) -> Self {
+        Self {
+            max_attempts: max_attempts.max(1),
+            initial_backoff: StdDuration::from_millis(100),
+            max_backoff: StdDuration::from_secs(10),
+            multiplier: 2,
+            retryable_status_codes: vec![429, 500, 502, 503, 504],
+        }
+    }
+
+    /// Create a policy never retrying requests
+    pub fn never() -> Self {
+        Self::new(1)
+    }
+
+    /// Set the delay before the first retry and the maximal delay
+    pub fn backoff(mut self, initial: StdDuration, max: StdDuration) -> Self {
+        self.initial_backoff = initial;
+        self.max_backoff = max;
+        self
+    }
+
+    /// Set the factor applied to the delay after each retry
+    pub fn multiplier(mut self, multiplier: u32) -> Self {
+        self.multiplier = multiplier.max(1);
+        self
+    }
+
+    /// Set the status codes that cause a request to be retried
+    pub fn retryable_status_codes(mut self, status_codes: impl IntoIterator<Item = u16>) -> Self {
+        self.retryable_status_codes = status_codes.into_iter().collect();
+        self
+    }
+
+    /// Return the maximal number of times a request is sent
+    pub fn max_attempts(&self) -> u32 {
+        self.max_attempts
+    }
+
+    /// Return the delay after a failed attempt, starting from 1
+    pub fn delay(&self, attempt: u32) -> StdDuration {
+        let mut delay = self.initial_backoff;
+        for _ in 1..attempt {
+            delay = delay
+                .checked_mul(self.multiplier)
+                .unwrap_or(self.max_backoff);
+            if delay >= self.max_backoff {
+                break;
+            }
+        }
+        delay.min(self.max_backoff)
+    }
+
+    /// Return the delay after a failed attempt, starting from 1, honouring
+    /// the delay requested by the server
+    ///
+    /// When the server sent header `Retry-After`, the delay it requests is
+    /// used instead of the backoff.
+    /// Return `None` if that delay is longer than the maximal delay, in which
+    /// case the request should not be retried, and the server response
+    /// should be reported as rate limited instead.
+    ///
+    /// ```
+    /// # use std::time::Duration;
+    /// # use rinfluxdb_types::RetryPolicy;
+    /// let policy = RetryPolicy::new(4);
+    ///
+    /// assert_eq!(policy.retry_delay(1, None), Some(Duration::from_millis(100)));
+    /// assert_eq!(
+    ///     policy.retry_delay(1, Some(Duration::from_secs(5))),
+    ///     Some(Duration::from_secs(5)),
+    /// );
+    /// assert_eq!(policy.retry_delay(1, Some(Duration::from_secs(999999999))), None);
+    /// ```
+    pub fn retry_delay(&self, attempt: u32, retry_after: Option<StdDuration>) -> Option<StdDuration> {
+        match retry_after {
+            Some(retry_after) if retry_after > self.max_backoff => None,
+            Some(retry_after) => Some(retry_after),
+            None => Some(self.delay(attempt)),
+        }
+    }
+
+    /// Check whether a response status code causes a request to be retried
+    pub fn is_retryable_status(&self, status_code: u16) -> bool {
+        self.retryable_status_codes.contains(&status_code)
+    }
+}
+
+/// Parse the value of header `Retry-After`, either a number of seconds or a
+/// date
+///
+/// Dates in the past are parsed as a zero delay.
+///
+/// ```
+/// # use std::time::Duration;
+/// # use rinfluxdb_types::parse_retry_after;
+/// assert_eq!(parse_retry_after("30"), Some(Duration::from_secs(30)));
+/// assert_eq!(parse_retry_after("Wed, 21 Oct 2015 07:28:00 GMT"), Some(Duration::from_secs(0)));
+/// assert_eq!(parse_retry_after("soon"), None);
+/// ```
+pub fn parse_retry_after(value: &str) -> Option<StdDuration> {
+    let value = value.trim();
+    match value.parse::<u64>() {
+        Ok(seconds) => Some(StdDuration::from_secs(seconds)),
+        Err(_) => {
+            let instant = DateTime::parse_from_rfc2822(value).ok()?;
+            let delay = instant.with_timezone(&Utc) - Utc::now();
+            Some(delay.to_std().unwrap_or_default())
+        }
+    }
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self::never()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn delay_is_capped() {
+        let policy = RetryPolicy::new(100)
+            .backoff(StdDuration::from_secs(1), StdDuration::from_secs(60))
+            .multiplier(10);
+
+        assert_eq!(policy.delay(1), StdDuration::from_secs(1));
+        assert_eq!(policy.delay(2), StdDuration::from_secs(10));
+        assert_eq!(policy.delay(3), StdDuration::from_secs(60));
+        assert_eq!(policy.delay(99), StdDuration::from_secs(60));
+    }
+
+    #[test]
+    fn never_retries() {
+        let policy = RetryPolicy::default();
+
+        assert_eq!(policy.max_attempts(), 1);
+        assert_eq!(RetryPolicy::new(0).max_attempts(), 1);
+    }
+}
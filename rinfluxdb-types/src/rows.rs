@@ -0,0 +1,184 @@
+// Copyright Claudio Mattera 2021.
+// Distributed under the MIT License or Apache 2.0 License at your option.
+// See accompanying files License-MIT.txt and License-Apache-2.0, or online at
+// https://opensource.org/licenses/MIT
+// https://opensource.org/licenses/Apache-2.0
+
+use std::collections::HashMap;
+use std::convert::TryFrom;
+
+use chrono::{DateTime, SecondsFormat, Utc};
+
+use serde::de::DeserializeOwned;
+
+use serde_json::{Map as JsonMap, Value as JsonValue};
+
+use super::{Columns, DataFrameError, FromQueryResult, Value};
+
+/// A list of rows, each mapping column names to JSON values
+///
+/// This is an alternative to dataframes for deserializing query results
+/// into user structs with Serde.
+/// Each row contains the instant in column `time`, encoded as a RFC3339
+/// string, and a value for each other column.
+/// Missing values are encoded as `null`, so they can be deserialized to
+/// `Option` struct fields.
+///
+/// ```
+/// # use std::convert::TryFrom;
+/// # use chrono::{TimeZone, Utc};
+/// # use serde::Deserialize;
+/// # use rinfluxdb_types::{Columns, Rows, Value};
+/// #[derive(Deserialize)]
+/// struct Reading {
+///     time: String,
+///     temperature: f64,
+///     humidity: Option<f64>,
+/// }
+///
+/// let index = vec![
+///     Utc.with_ymd_and_hms(2021, 3, 4, 17, 0, 0).unwrap(),
+///     Utc.with_ymd_and_hms(2021, 3, 4, 18, 0, 0).unwrap(),
+/// ];
+/// let mut columns = Columns::new();
+/// columns.insert("temperature".to_string(), vec![Value::Float(28.4), Value::Float(29.2)]);
+/// columns.insert("humidity".to_string(), vec![Value::Float(41.0), Value::Null]);
+/// let rows = Rows::try_from(("environment".to_string(), index, columns))?;
+///
+/// let readings: Vec<Reading> = rows.deserialize()?;
+///
+/// assert_eq!(readings.len(), 2);
+/// assert_eq!(readings[0].time, "2021-03-04T17:00:00Z");
+/// assert_eq!(readings[1].temperature, 29.2);
+/// assert_eq!(readings[1].humidity, None);
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Rows(pub Vec<JsonMap<String, JsonValue>>);
+
+impl Rows {
+    /// Insert tags as columns in all rows
+    ///
+    /// Existing columns with the same names are not replaced.
+    pub fn insert_tags(&mut self, tags: &HashMap<String, String>) {
+        for row in self.0.iter_mut() {
+            for (name, value) in tags {
+                row.entry(name.clone())
+                    .or_insert_with(|| JsonValue::String(value.clone()));
+            }
+        }
+    }
+
+    /// Append all rows of another list
+    pub fn extend(&mut self, other: Rows) {
+        self.0.extend(other.0);
+    }
+
+    /// Deserialize each row to a struct
+    pub fn deserialize<T>(self) -> Result<Vec<T>, serde_json::Error>
+    where
+        T: DeserializeOwned,
+    {
+        self.0
+            .into_iter()
+            .map(|row| serde_json::from_value(JsonValue::Object(row)))
+            .collect()
+    }
+}
+
+impl TryFrom<(String, Vec<DateTime<Utc>>, Columns)> for Rows {
+    type Error = DataFrameError;
+
+    fn try_from(
+        (_name, index, columns): (String, Vec<DateTime<Utc>>, Columns),
+    ) -> Result<Self, Self::Error> {
+        let mut rows: Vec<JsonMap<String, JsonValue>> = index
+            .iter()
+            .map(|instant| {
+                let mut row = JsonMap::new();
+                row.insert("time".into(), JsonValue::String(format_instant(instant)));
+                row
+            })
+            .collect();
+
+        for (name, column) in columns {
+            if column.len() != rows.len() {
+                return Err(DataFrameError::Creation);
+            }
+            for (row, value) in rows.iter_mut().zip(column) {
+                row.insert(name.clone(), to_json(value));
+            }
+        }
+
+        Ok(Rows(rows))
+    }
+}
+
+impl FromQueryResult for Rows {
+    type Error = DataFrameError;
+
+    /// Construct rows from a query result, inserting tags as columns
+    fn from_query_result(
+        name: String,
+        index: Vec<DateTime<Utc>>,
+        columns: Columns,
+        tags: Option<&HashMap<String, String>>,
+    ) -> Result<Self, Self::Error> {
+        let mut rows = Self::try_from((name, index, columns))?;
+        if let Some(tags) = tags {
+            rows.insert_tags(tags);
+        }
+        Ok(rows)
+    }
+}
+
+fn format_instant(instant: &DateTime<Utc>) -> String {
+    instant.to_rfc3339_opts(SecondsFormat::AutoSi, true)
+}
+
+fn to_json(value: Value) -> JsonValue {
+    match value {
+        Value::Float(value) => JsonValue::from(value),
+        Value::Integer(value) => JsonValue::from(value),
+        Value::UnsignedInteger(value) => JsonValue::from(value),
+        Value::String(value) => JsonValue::String(value),
+        Value::Boolean(value) => JsonValue::Bool(value),
+        Value::Timestamp(value) => JsonValue::String(format_instant(&value)),
+        Value::Null => JsonValue::Null,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use chrono::TimeZone;
+
+    #[test]
+    fn tags_do_not_replace_columns() {
+        let index = vec![Utc.with_ymd_and_hms(2021, 3, 4, 17, 0, 0).unwrap() + chrono::Duration::milliseconds(250)];
+        let mut columns = Columns::new();
+        columns.insert("room".to_string(), vec![Value::String("kitchen".into())]);
+        let mut rows = Rows::try_from(("environment".to_string(), index, columns)).unwrap();
+
+        let mut tags = HashMap::new();
+        tags.insert("room".to_string(), "bedroom".to_string());
+        tags.insert("building".to_string(), "b1".to_string());
+        rows.insert_tags(&tags);
+
+        let row = &rows.0[0];
+        assert_eq!(row["time"], JsonValue::from("2021-03-04T17:00:00.250Z"));
+        assert_eq!(row["room"], JsonValue::from("kitchen"));
+        assert_eq!(row["building"], JsonValue::from("b1"));
+    }
+
+    #[test]
+    fn mismatched_column_length() {
+        let index = vec![Utc.with_ymd_and_hms(2021, 3, 4, 17, 0, 0).unwrap()];
+        let mut columns = Columns::new();
+        columns.insert("value".to_string(), vec![]);
+
+        let rows = Rows::try_from(("environment".to_string(), index, columns));
+        assert!(matches!(rows, Err(DataFrameError::Creation)));
+    }
+}
@@ -0,0 +1,94 @@
+// Copyright Claudio Mattera 2021.
+// Distributed under the MIT License or Apache 2.0 License at your option.
+// See accompanying files License-MIT.txt and License-Apache-2.0, or online at
+// https://opensource.org/licenses/MIT
+// https://opensource.org/licenses/Apache-2.0
+
+use std::sync::Mutex;
+
+/// Path of the InfluxDB 2.x endpoint for signing in
+pub const SIGNIN_PATH: &str = "/api/v2/signin";
+
+/// A session cookie shared by all requests of a client
+///
+/// Clients using [`Authentication::Session`](crate::Authentication::Session)
+/// store the cookie obtained when signing in, and clear it when the session
+/// expires.
+#[derive(Debug, Default)]
+pub struct Session {
+    cookie: Mutex<Option<String>>,
+}
+
+impl Session {
+    /// Return the session cookie, if signed in
+    pub fn cookie(&self) -> Option<String> {
+        self.cookie.lock().expect("Session lock is not poisoned").clone()
+    }
+
+    /// Store the session cookie
+    pub fn set_cookie(&self, cookie: String) {
+        *self.cookie.lock().expect("Session lock is not poisoned") = Some(cookie);
+    }
+
+    /// Forget the session cookie
+    pub fn clear(&self) {
+        *self.cookie.lock().expect("Session lock is not poisoned") = None;
+    }
+}
+
+/// Return the cookies to send back to a server, from its `Set-Cookie` headers
+///
+/// Attributes such as `Path` or `Expires` are dropped, and cookies are
+/// joined as in a `Cookie` header.
+/// `None` is returned if there are no cookies.
+///
+/// ```
+/// # use rinfluxdb_types::cookie_from_set_cookie_headers;
+/// let headers = ["influxdb-oss-session=abc123; Path=/api/; HttpOnly"];
+/// assert_eq!(
+///     cookie_from_set_cookie_headers(headers.iter().copied()),
+///     Some("influxdb-oss-session=abc123".to_string()),
+/// );
+/// ```
+pub fn cookie_from_set_cookie_headers<'a, I>(headers: I) -> Option<String>
+where
+    I: IntoIterator<Item = &'a str>,
+{
+    let cookies: Vec<&str> = headers
+        .into_iter()
+        .filter_map(|header| header.split(';').next())
+        .map(str::trim)
+        .filter(|cookie| cookie.contains('='))
+        .collect();
+
+    if cookies.is_empty() {
+        None
+    } else {
+        Some(cookies.join("; "))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn join_cookies() {
+        let headers = ["a=1; Path=/", "b=2", "invalid"];
+        assert_eq!(
+            cookie_from_set_cookie_headers(headers.iter().copied()),
+            Some("a=1; b=2".to_string()),
+        );
+        assert_eq!(cookie_from_set_cookie_headers(Vec::new()), None);
+    }
+
+    #[test]
+    fn store_cookie() {
+        let session = Session::default();
+        assert_eq!(session.cookie(), None);
+        session.set_cookie("a=1".into());
+        assert_eq!(session.cookie(), Some("a=1".to_string()));
+        session.clear();
+        assert_eq!(session.cookie(), None);
+    }
+}
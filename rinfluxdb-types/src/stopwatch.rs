@@ -0,0 +1,93 @@
+// Copyright Claudio Mattera 2021.
+// Distributed under the MIT License or Apache 2.0 License at your option.
+// See accompanying files License-MIT.txt and License-Apache-2.0, or online at
+// https://opensource.org/licenses/MIT
+// https://opensource.org/licenses/Apache-2.0
+
+use std::time::Duration;
+
+/// A timer measuring the duration of requests
+///
+/// The standard library clock is not available on WebAssembly, where the
+/// browser clock is used instead.
+///
+/// ```
+/// # use std::time::Duration;
+/// # use rinfluxdb_types::Stopwatch;
+/// let stopwatch = Stopwatch::start();
+/// std::thread::sleep(Duration::from_millis(10));
+/// assert!(stopwatch.elapsed() >= Duration::from_millis(10));
+/// ```
+#[derive(Clone, Copy, Debug)]
+pub struct Stopwatch {
+    #[cfg(not(target_arch = "wasm32"))]
+    start: std::time::Instant,
+
+    #[cfg(target_arch = "wasm32")]
+    start: f64,
+}
+
+impl Stopwatch {
+    /// Start a new stopwatch
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn start() -> Self {
+        Self {
+            start: std::time::Instant::now(),
+        }
+    }
+
+    /// Start a new stopwatch
+    #[cfg(target_arch = "wasm32")]
+    pub fn start() -> Self {
+        Self {
+            start: js_sys::Date::now(),
+        }
+    }
+
+    /// Return the time elapsed since the stopwatch was started
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn elapsed(&self) -> Duration {
+        self.start.elapsed()
+    }
+
+    /// Return the time elapsed since the stopwatch was started
+    #[cfg(target_arch = "wasm32")]
+    pub fn elapsed(&self) -> Duration {
+        let milliseconds = (js_sys::Date::now() - self.start).max(0.0);
+        Duration::from_secs_f64(milliseconds / 1000.0)
+    }
+
+    /// Return the time elapsed since the stopwatch was started in
+    /// milliseconds
+    pub fn elapsed_ms(&self) -> u64 {
+        self.elapsed().as_millis() as u64
+    }
+}
+
+/// Record the outcome of a request on the current span
+///
+/// Client spans declare fields `status` and `elapsed_ms`, which are set to
+/// the status code and the duration of the last request sent within them.
+/// An event is emitted if the request failed or the server responded with
+/// an error status.
+pub fn record_response<E>(response: Result<u16, &E>, elapsed: Duration)
+where
+    E: std::fmt::Display,
+{
+    let span = tracing::Span::current();
+    let elapsed_ms = elapsed.as_millis() as u64;
+    span.record("elapsed_ms", elapsed_ms);
+    match response {
+        Ok(status) => {
+            span.record("status", status);
+            if (400..600).contains(&status) {
+                tracing::warn!(status, elapsed_ms, "Server responded with status {}", status);
+            } else {
+                tracing::debug!(status, elapsed_ms, "Server responded with status {}", status);
+            }
+        }
+        Err(error) => {
+            tracing::warn!(error = %error, elapsed_ms, "Request failed: {}", error);
+        }
+    }
+}
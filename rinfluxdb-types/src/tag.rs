@@ -0,0 +1,128 @@
+// Copyright Claudio Mattera 2021.
+// Distributed under the MIT License or Apache 2.0 License at your option.
+// See accompanying files License-MIT.txt and License-Apache-2.0, or online at
+// https://opensource.org/licenses/MIT
+// https://opensource.org/licenses/Apache-2.0
+
+use std::collections::HashMap;
+
+use thiserror::Error;
+
+/// An error occurred while decoding a tag value
+#[derive(Error, Clone, Debug, PartialEq, Eq)]
+pub enum TagValueError {
+    /// The tag is missing
+    #[error("Missing tag \"{0}\"")]
+    Missing(String),
+
+    /// The tag value cannot be decoded to the requested type
+    #[error("Invalid value \"{value}\" for tag \"{name}\"")]
+    Invalid {
+        /// The tag name
+        name: String,
+
+        /// The tag value
+        value: String,
+    },
+}
+
+/// A type that can be decoded from a tag value
+///
+/// Tags and group keys are always strings in InfluxDB.
+/// This trait allows decoding them to user-defined types, such as enums,
+/// instead of passing strings around.
+///
+/// ```
+/// # use std::collections::HashMap;
+/// # use rinfluxdb_types::{tag_value, FromTagValue, TagValueError};
+/// #[derive(Debug, PartialEq)]
+/// enum Severity {
+///     Warning,
+///     Critical,
+/// }
+///
+/// impl FromTagValue for Severity {
+///     fn from_tag_value(value: &str) -> Option<Self> {
+///         match value {
+///             "warning" => Some(Severity::Warning),
+///             "critical" => Some(Severity::Critical),
+///             _ => None,
+///         }
+///     }
+/// }
+///
+/// let mut tags = HashMap::new();
+/// tags.insert("severity".to_string(), "critical".to_string());
+/// tags.insert("room".to_string(), "bedroom".to_string());
+///
+/// assert_eq!(tag_value::<Severity>(&tags, "severity"), Ok(Severity::Critical));
+/// assert!(matches!(
+///     tag_value::<Severity>(&tags, "room"),
+///     Err(TagValueError::Invalid { .. }),
+/// ));
+/// ```
+pub trait FromTagValue: Sized {
+    /// Decode a tag value, returning `None` if it is not valid
+    fn from_tag_value(value: &str) -> Option<Self>;
+}
+
+impl FromTagValue for String {
+    fn from_tag_value(value: &str) -> Option<Self> {
+        Some(value.to_owned())
+    }
+}
+
+impl FromTagValue for i64 {
+    fn from_tag_value(value: &str) -> Option<Self> {
+        value.parse().ok()
+    }
+}
+
+impl FromTagValue for u64 {
+    fn from_tag_value(value: &str) -> Option<Self> {
+        value.parse().ok()
+    }
+}
+
+impl FromTagValue for bool {
+    fn from_tag_value(value: &str) -> Option<Self> {
+        value.parse().ok()
+    }
+}
+
+/// Decode the value of a tag from a map of tags
+pub fn tag_value<T>(tags: &HashMap<String, String>, name: &str) -> Result<T, TagValueError>
+where
+    T: FromTagValue,
+{
+    let value = tags
+        .get(name)
+        .ok_or_else(|| TagValueError::Missing(name.to_owned()))?;
+    T::from_tag_value(value).ok_or_else(|| TagValueError::Invalid {
+        name: name.to_owned(),
+        value: value.to_owned(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn missing_tag() {
+        let tags = HashMap::new();
+
+        let actual = tag_value::<String>(&tags, "room");
+
+        assert_eq!(actual, Err(TagValueError::Missing("room".into())));
+    }
+
+    #[test]
+    fn integer_tag() {
+        let mut tags = HashMap::new();
+        tags.insert("floor".to_string(), "2".to_string());
+
+        assert_eq!(tag_value::<i64>(&tags, "floor"), Ok(2));
+        assert!(tag_value::<bool>(&tags, "floor").is_err());
+    }
+}
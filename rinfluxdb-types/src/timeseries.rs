@@ -0,0 +1,136 @@
+// Copyright Claudio Mattera 2021.
+// Distributed under the MIT License or Apache 2.0 License at your option.
+// See accompanying files License-MIT.txt and License-Apache-2.0, or online at
+// https://opensource.org/licenses/MIT
+// https://opensource.org/licenses/Apache-2.0
+
+use std::collections::HashMap;
+use std::convert::TryFrom;
+
+use chrono::{DateTime, Utc};
+
+use super::{Columns, DataFrameError, FromQueryResult, Value};
+
+/// A set of time series, one for each field
+///
+/// This is a simpler alternative to dataframes, where each field is
+/// represented as a list of timestamped values.
+/// Missing values are skipped.
+///
+/// ```
+/// # use std::convert::TryFrom;
+/// # use chrono::{TimeZone, Utc};
+/// # use rinfluxdb_types::{Columns, TimeSeries, Value};
+/// let index = vec![
+///     Utc.with_ymd_and_hms(2021, 3, 4, 17, 0, 0).unwrap(),
+///     Utc.with_ymd_and_hms(2021, 3, 4, 18, 0, 0).unwrap(),
+/// ];
+/// let mut columns = Columns::new();
+/// columns.insert("temperature".to_string(), vec![Value::Float(28.4), Value::Float(29.2)]);
+///
+/// let series = TimeSeries::try_from(("environment".to_string(), index, columns))?;
+///
+/// assert_eq!(
+///     series.field("temperature"),
+///     Some(&[
+///         (Utc.with_ymd_and_hms(2021, 3, 4, 17, 0, 0).unwrap(), Value::Float(28.4)),
+///         (Utc.with_ymd_and_hms(2021, 3, 4, 18, 0, 0).unwrap(), Value::Float(29.2)),
+///     ][..]),
+/// );
+/// # Ok::<(), rinfluxdb_types::DataFrameError>(())
+/// ```
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct TimeSeries(pub HashMap<String, Vec<(DateTime<Utc>, Value)>>);
+
+impl TimeSeries {
+    /// Return the timestamped values of a field
+    pub fn field(&self, name: &str) -> Option<&[(DateTime<Utc>, Value)]> {
+        self.0.get(name).map(|values| values.as_slice())
+    }
+
+    /// Append all timestamped values of another set of time series
+    pub fn extend(&mut self, other: TimeSeries) {
+        for (name, values) in other.0 {
+            self.0.entry(name).or_default().extend(values);
+        }
+    }
+}
+
+impl TryFrom<(String, Vec<DateTime<Utc>>, Columns)> for TimeSeries {
+    type Error = DataFrameError;
+
+    fn try_from(
+        (_name, index, columns): (String, Vec<DateTime<Utc>>, Columns),
+    ) -> Result<Self, Self::Error> {
+        columns
+            .into_iter()
+            .map(|(name, column)| {
+                if column.len() == index.len() {
+                    let values = index
+                        .iter()
+                        .cloned()
+                        .zip(column)
+                        .filter(|(_, value)| !value.is_null())
+                        .collect();
+                    Ok((name, values))
+                } else {
+                    Err(DataFrameError::Creation)
+                }
+            })
+            .collect::<Result<_, _>>()
+            .map(TimeSeries)
+    }
+}
+
+impl FromQueryResult for TimeSeries {
+    type Error = DataFrameError;
+
+    fn from_query_result(
+        name: String,
+        index: Vec<DateTime<Utc>>,
+        columns: Columns,
+        _tags: Option<&HashMap<String, String>>,
+    ) -> Result<Self, Self::Error> {
+        Self::try_from((name, index, columns))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use chrono::TimeZone;
+
+    #[test]
+    fn mismatched_lengths() {
+        let index = vec![Utc.with_ymd_and_hms(2021, 3, 4, 17, 0, 0).unwrap()];
+        let mut columns = Columns::new();
+        columns.insert(
+            "temperature".to_string(),
+            vec![Value::Float(28.4), Value::Float(29.2)],
+        );
+
+        let result = TimeSeries::try_from(("environment".to_string(), index, columns));
+
+        assert!(matches!(result, Err(DataFrameError::Creation)));
+    }
+
+    #[test]
+    fn extend() {
+        let mut series = TimeSeries::default();
+        series.0.insert(
+            "temperature".into(),
+            vec![(Utc.with_ymd_and_hms(2021, 3, 4, 17, 0, 0).unwrap(), Value::Float(28.4))],
+        );
+
+        let mut other = TimeSeries::default();
+        other.0.insert(
+            "temperature".into(),
+            vec![(Utc.with_ymd_and_hms(2021, 3, 4, 18, 0, 0).unwrap(), Value::Float(29.2))],
+        );
+
+        series.extend(other);
+
+        assert_eq!(series.field("temperature").map(|values| values.len()), Some(2));
+    }
+}
@@ -0,0 +1,238 @@
+// Copyright Claudio Mattera 2021.
+// Distributed under the MIT License or Apache 2.0 License at your option.
+// See accompanying files License-MIT.txt and License-Apache-2.0, or online at
+// https://opensource.org/licenses/MIT
+// https://opensource.org/licenses/Apache-2.0
+
+use std::fmt;
+use std::sync::Arc;
+
+/// An error returned by a transport
+pub type TransportError = Box<dyn std::error::Error + Send + Sync>;
+
+/// An HTTP request sent through a transport
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct HttpRequest {
+    /// The method, such as `GET` or `POST`
+    pub method: String,
+
+    /// The full URL, including the query string
+    pub url: String,
+
+    /// The headers, in the order they were set
+    pub headers: Vec<(String, String)>,
+
+    /// The body, empty if the request has none
+    pub body: Vec<u8>,
+}
+
+impl HttpRequest {
+    /// Return the value of a header
+    ///
+    /// Header names are compared case-insensitively.
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(header, _)| header.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+/// An HTTP response returned by a transport
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct HttpResponse {
+    /// The status code
+    pub status: u16,
+
+    /// The headers
+    pub headers: Vec<(String, String)>,
+
+    /// The body
+    pub body: Vec<u8>,
+}
+
+impl HttpResponse {
+    /// Create a response with a status code and a body
+    pub fn new<B>(status: u16, body: B) -> Self
+    where
+        B: Into<Vec<u8>>,
+    {
+        Self {
+            status,
+            headers: Vec::new(),
+            body: body.into(),
+        }
+    }
+
+    /// Add a header to the response
+    pub fn with_header<N, V>(mut self, name: N, value: V) -> Self
+    where
+        N: Into<String>,
+        V: Into<String>,
+    {
+        self.headers.push((name.into(), value.into()));
+        self
+    }
+}
+
+/// A backend executing HTTP requests
+///
+/// Blocking clients send their requests through Reqwest by default, but a
+/// different transport can be set on their builders, for instance to use
+/// another HTTP library, or to return recorded responses in tests.
+///
+/// The whole response body is returned at once, so chunked responses are
+/// only parsed after they are received completely.
+///
+/// Transports are synchronous, so they are not supported by asynchronous
+/// clients, which always send their requests through Reqwest.
+///
+/// ```
+/// # use std::sync::Mutex;
+/// # use rinfluxdb_types::{HttpRequest, HttpResponse, HttpTransport, TransportError};
+/// #[derive(Debug, Default)]
+/// struct RecordingTransport {
+///     requests: Mutex<Vec<HttpRequest>>,
+/// }
+///
+/// impl HttpTransport for RecordingTransport {
+///     fn execute(&self, request: HttpRequest) -> Result<HttpResponse, TransportError> {
+///         self.requests.lock().unwrap().push(request);
+///         Ok(HttpResponse::new(204, ""))
+///     }
+/// }
+///
+/// let transport = RecordingTransport::default();
+/// let request = HttpRequest {
+///     method: "POST".into(),
+///     url: "https://example.com/write?db=house".into(),
+///     headers: vec![("Content-Type".into(), "text/plain".into())],
+///     body: b"indoor_environment temperature=21.3".to_vec(),
+/// };
+///
+/// let response = transport.execute(request)?;
+/// assert_eq!(response.status, 204);
+/// assert_eq!(transport.requests.lock().unwrap()[0].header("content-type"), Some("text/plain"));
+/// # Ok::<(), TransportError>(())
+/// ```
+pub trait HttpTransport: fmt::Debug + Send + Sync {
+    /// Execute a request and return its response
+    ///
+    /// Responses with error status codes must be returned as responses,
+    /// and errors are reserved to failures of the transport itself, which
+    /// are considered temporary when retrying requests.
+    fn execute(&self, request: HttpRequest) -> Result<HttpResponse, TransportError>;
+}
+
+impl<T> HttpTransport for Arc<T>
+where
+    T: HttpTransport + ?Sized,
+{
+    fn execute(&self, request: HttpRequest) -> Result<HttpResponse, TransportError> {
+        (**self).execute(request)
+    }
+}
+
+#[cfg(feature = "reqwest")]
+mod reqwest_transport {
+    use std::convert::TryFrom;
+
+    use super::{HttpRequest, HttpResponse, HttpTransport, TransportError};
+
+    impl HttpTransport for reqwest::blocking::Client {
+        fn execute(&self, request: HttpRequest) -> Result<HttpResponse, TransportError> {
+            let method = reqwest::Method::from_bytes(request.method.as_bytes())?;
+            let mut builder = self.request(method, request.url.as_str());
+            for (name, value) in request.headers {
+                builder = builder.header(name, value);
+            }
+            let response = builder.body(request.body).send()?;
+
+            let status = response.status().as_u16();
+            let headers = response
+                .headers()
+                .iter()
+                .filter_map(|(name, value)| {
+                    value.to_str().ok().map(|value| (name.to_string(), value.to_string()))
+                })
+                .collect();
+            let body = response.bytes()?.to_vec();
+
+            Ok(HttpResponse { status, headers, body })
+        }
+    }
+
+    impl TryFrom<reqwest::blocking::Request> for HttpRequest {
+        type Error = TransportError;
+
+        fn try_from(request: reqwest::blocking::Request) -> Result<Self, Self::Error> {
+            let headers = request
+                .headers()
+                .iter()
+                .map(|(name, value)| Ok((name.to_string(), value.to_str()?.to_string())))
+                .collect::<Result<Vec<_>, TransportError>>()?;
+            let body = match request.body() {
+                Some(body) => body
+                    .as_bytes()
+                    .ok_or("streaming request bodies are not supported")?
+                    .to_vec(),
+                None => Vec::new(),
+            };
+
+            Ok(Self {
+                method: request.method().to_string(),
+                url: request.url().to_string(),
+                headers,
+                body,
+            })
+        }
+    }
+
+    impl TryFrom<HttpResponse> for reqwest::blocking::Response {
+        type Error = TransportError;
+
+        fn try_from(response: HttpResponse) -> Result<Self, Self::Error> {
+            let mut builder = http::Response::builder().status(response.status);
+            for (name, value) in response.headers {
+                builder = builder.header(name, value);
+            }
+            Ok(builder.body(response.body)?.into())
+        }
+    }
+}
+
+#[cfg(all(test, feature = "reqwest"))]
+mod tests {
+    use std::convert::TryFrom;
+
+    use super::*;
+
+    #[test]
+    fn request_from_reqwest() {
+        let request = reqwest::blocking::Client::new()
+            .post("https://example.com/write?db=house")
+            .header("Content-Type", "text/plain")
+            .body("indoor_environment temperature=21.3")
+            .build()
+            .unwrap();
+
+        let request = HttpRequest::try_from(request).unwrap();
+
+        assert_eq!(request.method, "POST");
+        assert_eq!(request.url, "https://example.com/write?db=house");
+        assert_eq!(request.header("content-type"), Some("text/plain"));
+        assert_eq!(request.body, b"indoor_environment temperature=21.3");
+    }
+
+    #[test]
+    fn response_into_reqwest() {
+        let response = HttpResponse::new(400, r#"{"error":"database not found"}"#)
+            .with_header("X-Influxdb-Version", "1.8.10");
+
+        let response = reqwest::blocking::Response::try_from(response).unwrap();
+
+        assert_eq!(response.status().as_u16(), 400);
+        assert_eq!(response.headers()["x-influxdb-version"], "1.8.10");
+        assert_eq!(response.text().unwrap(), r#"{"error":"database not found"}"#);
+    }
+}
@@ -0,0 +1,173 @@
+// Copyright Claudio Mattera 2021.
+// Distributed under the MIT License or Apache 2.0 License at your option.
+// See accompanying files License-MIT.txt and License-Apache-2.0, or online at
+// https://opensource.org/licenses/MIT
+// https://opensource.org/licenses/Apache-2.0
+
+use chrono::{DateTime, Duration, Utc};
+
+/// A window of time between two instants
+///
+/// A window includes its start instant and excludes its stop instant, so
+/// consecutive windows never overlap.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TimeWindow {
+    /// The position of the window in the sequence it belongs to
+    pub index: usize,
+
+    /// The start of the window (included)
+    pub start: DateTime<Utc>,
+
+    /// The stop of the window (excluded)
+    pub stop: DateTime<Utc>,
+}
+
+/// An iterator splitting a range of time in consecutive windows
+///
+/// All windows have the same size, except the last one, which is truncated
+/// at the end of the range.
+///
+/// ```
+/// # use chrono::{Duration, TimeZone, Utc};
+/// # use rinfluxdb_types::TimeWindows;
+/// let windows: Vec<_> = TimeWindows::new(
+///     Utc.with_ymd_and_hms(2021, 3, 1, 0, 0, 0).unwrap(),
+///     Utc.with_ymd_and_hms(2021, 3, 1, 5, 0, 0).unwrap(),
+///     Duration::hours(2),
+/// ).collect();
+///
+/// assert_eq!(windows.len(), 3);
+/// assert_eq!(windows[0].start, Utc.with_ymd_and_hms(2021, 3, 1, 0, 0, 0).unwrap());
+/// assert_eq!(windows[0].stop, Utc.with_ymd_and_hms(2021, 3, 1, 2, 0, 0).unwrap());
+/// assert_eq!(windows[2].start, Utc.with_ymd_and_hms(2021, 3, 1, 4, 0, 0).unwrap());
+/// assert_eq!(windows[2].stop, Utc.with_ymd_and_hms(2021, 3, 1, 5, 0, 0).unwrap());
+/// ```
+#[derive(Clone, Debug)]
+pub struct TimeWindows {
+    start: DateTime<Utc>,
+    stop: DateTime<Utc>,
+    size: Duration,
+    index: usize,
+}
+
+impl TimeWindows {
+    /// Split the range between `start` and `stop` in windows of size `size`
+    ///
+    /// # Panics
+    ///
+    /// Panics if `size` is not positive.
+    pub fn new(start: DateTime<Utc>, stop: DateTime<Utc>, size: Duration) -> Self {
+        assert!(size > Duration::zero(), "window size must be positive");
+        Self {
+            start,
+            stop,
+            size,
+            index: 0,
+        }
+    }
+
+    /// Skip all windows before the one with the given index
+    ///
+    /// This can be used to resume an interrupted operation from the last
+    /// window that was not completely processed.
+    ///
+    /// ```
+    /// # use chrono::{Duration, TimeZone, Utc};
+    /// # use rinfluxdb_types::TimeWindows;
+    /// let mut windows = TimeWindows::new(
+    ///     Utc.with_ymd_and_hms(2021, 3, 1, 0, 0, 0).unwrap(),
+    ///     Utc.with_ymd_and_hms(2021, 3, 1, 5, 0, 0).unwrap(),
+    ///     Duration::hours(2),
+    /// ).resume_from(2);
+    ///
+    /// let window = windows.next().unwrap();
+    /// assert_eq!(window.index, 2);
+    /// assert_eq!(window.start, Utc.with_ymd_and_hms(2021, 3, 1, 4, 0, 0).unwrap());
+    /// assert!(windows.next().is_none());
+    /// ```
+    pub fn resume_from(mut self, index: usize) -> Self {
+        while self.index < index && self.next().is_some() {}
+        self
+    }
+}
+
+impl Iterator for TimeWindows {
+    type Item = TimeWindow;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.start >= self.stop {
+            return None;
+        }
+
+        let index = self.index;
+        let start = self.start;
+        let stop = start
+            .checked_add_signed(self.size)
+            .map_or(self.stop, |stop| stop.min(self.stop));
+
+        self.start = stop;
+        self.index += 1;
+
+        Some(TimeWindow { index, start, stop })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use chrono::TimeZone;
+
+    #[test]
+    fn exact_windows() {
+        let windows: Vec<_> = TimeWindows::new(
+            Utc.with_ymd_and_hms(2021, 3, 1, 0, 0, 0).unwrap(),
+            Utc.with_ymd_and_hms(2021, 3, 1, 3, 0, 0).unwrap(),
+            Duration::hours(1),
+        )
+        .collect();
+
+        let expected = vec![
+            TimeWindow {
+                index: 0,
+                start: Utc.with_ymd_and_hms(2021, 3, 1, 0, 0, 0).unwrap(),
+                stop: Utc.with_ymd_and_hms(2021, 3, 1, 1, 0, 0).unwrap(),
+            },
+            TimeWindow {
+                index: 1,
+                start: Utc.with_ymd_and_hms(2021, 3, 1, 1, 0, 0).unwrap(),
+                stop: Utc.with_ymd_and_hms(2021, 3, 1, 2, 0, 0).unwrap(),
+            },
+            TimeWindow {
+                index: 2,
+                start: Utc.with_ymd_and_hms(2021, 3, 1, 2, 0, 0).unwrap(),
+                stop: Utc.with_ymd_and_hms(2021, 3, 1, 3, 0, 0).unwrap(),
+            },
+        ];
+
+        assert_eq!(windows, expected);
+    }
+
+    #[test]
+    fn empty_range() {
+        let mut windows = TimeWindows::new(
+            Utc.with_ymd_and_hms(2021, 3, 1, 0, 0, 0).unwrap(),
+            Utc.with_ymd_and_hms(2021, 3, 1, 0, 0, 0).unwrap(),
+            Duration::hours(1),
+        );
+
+        assert!(windows.next().is_none());
+    }
+
+    #[test]
+    fn resume_past_end() {
+        let mut windows = TimeWindows::new(
+            Utc.with_ymd_and_hms(2021, 3, 1, 0, 0, 0).unwrap(),
+            Utc.with_ymd_and_hms(2021, 3, 1, 3, 0, 0).unwrap(),
+            Duration::hours(1),
+        )
+        .resume_from(10);
+
+        assert!(windows.next().is_none());
+    }
+}
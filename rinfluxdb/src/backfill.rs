@@ -0,0 +1,354 @@
+// Copyright Claudio Mattera 2021.
+// Distributed under the MIT License or Apache 2.0 License at your option.
+// See accompanying files License-MIT.txt and License-Apache-2.0, or online at
+// https://opensource.org/licenses/MIT
+// https://opensource.org/licenses/Apache-2.0
+
+use std::collections::HashMap;
+use std::fmt;
+
+use thiserror::Error;
+
+use crate::influxql::{ClientError as QueryError, Query, StatementResult};
+use crate::line_protocol::{Line, Lines, WriteTarget};
+use crate::types::{TimeWindow, TimeWindows};
+use crate::InfluxDbError;
+
+/// A callback notified after each window is copied
+type ProgressCallback<'a> = Box<dyn Fn(&BackfillProgress) + Send + Sync + 'a>;
+
+/// An error occurred while copying a window of data
+///
+/// Windows before the failed one were copied, so the backfill can be resumed
+/// with [`TimeWindows::resume_from`](crate::types::TimeWindows::resume_from).
+#[derive(Error, Debug)]
+#[error("Backfill failed at window {}", window.index)]
+pub struct BackfillError {
+    /// The window that could not be copied
+    pub window: TimeWindow,
+
+    /// The error that occurred
+    #[source]
+    pub error: InfluxDbError,
+}
+
+/// The progress of a backfill, reported after each window
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BackfillProgress {
+    /// The window that was copied
+    pub window: TimeWindow,
+
+    /// The number of lines copied in the window
+    pub lines: usize,
+
+    /// The number of lines copied so far
+    pub total_lines: usize,
+}
+
+/// A copy of data between databases or measurements, one window of time at
+/// a time
+///
+/// A query is created for each window, its results are converted to lines,
+/// optionally renamed, and written to the destination before moving to the
+/// next window.
+/// Tags are only preserved if the query groups by them, e.g. with
+/// `GROUP BY *`, otherwise they are returned as fields.
+///
+/// ```no_run
+/// # use chrono::{Duration, SecondsFormat, TimeZone, Utc};
+/// # use url::Url;
+/// # use rinfluxdb::{Backfill, InfluxLineClient, InfluxqlClient, InfluxqlQuery};
+/// # use rinfluxdb::types::TimeWindows;
+/// let source = InfluxqlClient::new(Url::parse("https://old.example.com/")?, Some(("username", "password")))?;
+/// let destination = InfluxLineClient::new(Url::parse("https://new.example.com/")?, Some(("username", "password")))?;
+///
+/// let backfill = Backfill::new("house")
+///     .measurement("indoor_environment")
+///     .rename_field("temp", "temperature")
+///     .rename_tag("location", "room")
+///     .on_progress(|progress| {
+///         println!("Copied {} lines up to {}", progress.total_lines, progress.window.stop);
+///     });
+///
+/// let windows = TimeWindows::new(
+///     Utc.with_ymd_and_hms(2021, 1, 1, 0, 0, 0).unwrap(),
+///     Utc.with_ymd_and_hms(2021, 3, 1, 0, 0, 0).unwrap(),
+///     Duration::days(1),
+/// );
+///
+/// let lines = backfill.run(&source, &destination, windows, |window| {
+///     InfluxqlQuery::new(format!(
+///         "SELECT * FROM environment WHERE time >= '{}' AND time < '{}' GROUP BY *",
+///         window.start.to_rfc3339_opts(SecondsFormat::AutoSi, true),
+///         window.stop.to_rfc3339_opts(SecondsFormat::AutoSi, true),
+///     ))
+/// })?;
+/// println!("Copied {} lines", lines);
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+pub struct Backfill<'a> {
+    target: WriteTarget,
+    measurement: Option<String>,
+    field_names: HashMap<String, String>,
+    tag_names: HashMap<String, String>,
+    on_progress: Option<ProgressCallback<'a>>,
+}
+
+impl<'a> Backfill<'a> {
+    /// Create a backfill writing to a target
+    ///
+    /// The target can be a database name, or a [`WriteTarget`](crate::line_protocol::WriteTarget)
+    /// for retention policies or InfluxDB 2.x buckets.
+    pub fn new<T>(target: T) -> Self
+    where
+        T: Into<WriteTarget>,
+    {
+        Self {
+            target: target.into(),
+            measurement: None,
+            field_names: HashMap::new(),
+            tag_names: HashMap::new(),
+            on_progress: None,
+        }
+    }
+
+    /// Write all lines to a measurement
+    ///
+    /// By default, lines are written to the measurement they were read from.
+    pub fn measurement<T>(mut self, measurement: T) -> Self
+    where
+        T: Into<String>,
+    {
+        self.measurement = Some(measurement.into());
+        self
+    }
+
+    /// Rename a field
+    pub fn rename_field<T, S>(mut self, name: T, new_name: S) -> Self
+    where
+        T: Into<String>,
+        S: Into<String>,
+    {
+        self.field_names.insert(name.into(), new_name.into());
+        self
+    }
+
+    /// Rename a tag
+    pub fn rename_tag<T, S>(mut self, name: T, new_name: S) -> Self
+    where
+        T: Into<String>,
+        S: Into<String>,
+    {
+        self.tag_names.insert(name.into(), new_name.into());
+        self
+    }
+
+    /// Set a callback notified after each window is copied
+    pub fn on_progress<F>(mut self, on_progress: F) -> Self
+    where
+        F: Fn(&BackfillProgress) + Send + Sync + 'a,
+    {
+        self.on_progress = Some(Box::new(on_progress));
+        self
+    }
+
+    /// Copy data window by window with blocking clients
+    ///
+    /// Windows are copied sequentially, and copying stops at the first
+    /// failed window.
+    /// Return the number of lines copied.
+    #[cfg(feature = "client-blocking")]
+    pub fn run<F>(
+        &self,
+        source: &crate::InfluxqlClient,
+        destination: &crate::InfluxLineClient,
+        windows: TimeWindows,
+        query: F,
+    ) -> Result<usize, BackfillError>
+    where
+        F: Fn(&TimeWindow) -> Query,
+    {
+        let mut total_lines = 0;
+        for window in windows {
+            let copy = || -> Result<usize, InfluxDbError> {
+                let lines = self.rewrite(source.fetch_readings(query(&window))?)?;
+                if !lines.is_empty() {
+                    destination.send(&self.target, &lines)?;
+                }
+                Ok(lines.len())
+            };
+            let lines = copy().map_err(|error| BackfillError { window, error })?;
+            total_lines += lines;
+            self.report(window, lines, total_lines);
+        }
+        Ok(total_lines)
+    }
+
+    /// Copy data window by window with asynchronous clients
+    ///
+    /// Windows are copied sequentially, and copying stops at the first
+    /// failed window.
+    /// Return the number of lines copied.
+    #[cfg(feature = "client-async")]
+    pub async fn run_async<F>(
+        &self,
+        source: &crate::InfluxqlAsyncClient,
+        destination: &crate::InfluxAsyncLineClient,
+        windows: TimeWindows,
+        query: F,
+    ) -> Result<usize, BackfillError>
+    where
+        F: Fn(&TimeWindow) -> Query,
+    {
+        let mut total_lines = 0;
+        for window in windows {
+            let copy = async {
+                let lines = self.rewrite(source.fetch_readings(query(&window)).await?)?;
+                if !lines.is_empty() {
+                    destination.send(&self.target, &lines).await?;
+                }
+                Ok::<_, InfluxDbError>(lines.len())
+            };
+            let lines = copy.await.map_err(|error| BackfillError { window, error })?;
+            total_lines += lines;
+            self.report(window, lines, total_lines);
+        }
+        Ok(total_lines)
+    }
+
+    /// Convert query results to lines, and rename them
+    fn rewrite(&self, statement_results: Vec<StatementResult<Lines>>) -> Result<Vec<Line>, QueryError> {
+        let mut lines = collect_lines(statement_results)?;
+
+        for line in &mut lines {
+            if let Some(measurement) = &self.measurement {
+                line.set_measurement(measurement.as_str());
+            }
+            for (name, new_name) in &self.field_names {
+                line.rename_field(name.as_str(), new_name.as_str());
+            }
+            for (name, new_name) in &self.tag_names {
+                line.rename_tag(name.as_str(), new_name.as_str());
+            }
+        }
+
+        Ok(lines)
+    }
+
+    /// Notify the progress callback
+    fn report(&self, window: TimeWindow, lines: usize, total_lines: usize) {
+        if let Some(on_progress) = &self.on_progress {
+            on_progress(&BackfillProgress {
+                window,
+                lines,
+                total_lines,
+            });
+        }
+    }
+}
+
+/// Collect the lines of all series of all statements of a query result
+pub(crate) fn collect_lines(statement_results: Vec<StatementResult<Lines>>) -> Result<Vec<Line>, QueryError> {
+    let mut lines = Vec::new();
+    for statement_result in statement_results {
+        for (series, _tags) in statement_result? {
+            lines.extend(Vec::from(series));
+        }
+    }
+    Ok(lines)
+}
+
+impl<'a> fmt::Debug for Backfill<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Backfill")
+            .field("target", &self.target)
+            .field("measurement", &self.measurement)
+            .field("field_names", &self.field_names)
+            .field("tag_names", &self.tag_names)
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(all(test, feature = "client-blocking"))]
+mod tests {
+    use super::*;
+
+    use std::sync::{Arc, Mutex};
+
+    use chrono::{Duration, TimeZone, Utc};
+
+    use url::Url;
+
+    use crate::types::{HttpRequest, HttpResponse, HttpTransport, TransportError};
+
+    #[derive(Debug, Default)]
+    struct RecordingTransport {
+        requests: Mutex<Vec<HttpRequest>>,
+    }
+
+    impl HttpTransport for RecordingTransport {
+        fn execute(&self, request: HttpRequest) -> Result<HttpResponse, TransportError> {
+            let response = if request.url.contains("/write") {
+                HttpResponse::new(204, "")
+            } else {
+                HttpResponse::new(200, r#"{
+                    "results": [{
+                        "statement_id": 0,
+                        "series": [{
+                            "name": "environment",
+                            "tags": {"location": "kitchen"},
+                            "columns": ["time", "temp"],
+                            "values": [["2021-03-04T17:00:00Z", 21.3]]
+                        }]
+                    }]
+                }"#)
+            };
+            self.requests.lock().unwrap().push(request);
+            Ok(response)
+        }
+    }
+
+    #[test]
+    fn copy_and_rename() -> Result<(), Box<dyn std::error::Error>> {
+        let transport = Arc::new(RecordingTransport::default());
+        let base_url = Url::parse("http://influxdb.test/")?;
+        let source = crate::influxql::blocking::ClientBuilder::new(base_url.clone())
+            .transport(transport.clone())
+            .build()?;
+        let destination = crate::line_protocol::blocking::ClientBuilder::new(base_url)
+            .transport(transport.clone())
+            .build()?;
+
+        let progress = Mutex::new(Vec::new());
+        let backfill = Backfill::new("house")
+            .measurement("indoor_environment")
+            .rename_field("temp", "temperature")
+            .rename_tag("location", "room")
+            .on_progress(|report| progress.lock().unwrap().push(*report));
+
+        let windows = TimeWindows::new(
+            Utc.with_ymd_and_hms(2021, 3, 4, 0, 0, 0).unwrap(),
+            Utc.with_ymd_and_hms(2021, 3, 6, 0, 0, 0).unwrap(),
+            Duration::days(1),
+        );
+        let lines = backfill.run(&source, &destination, windows, |window| {
+            Query::new(format!("SELECT * FROM environment WHERE time >= '{}' GROUP BY *", window.start))
+        })?;
+
+        assert_eq!(lines, 2);
+
+        drop(backfill);
+        let progress = progress.into_inner().unwrap();
+        assert_eq!(progress.len(), 2);
+        assert_eq!(progress[1].lines, 1);
+        assert_eq!(progress[1].total_lines, 2);
+
+        let requests = transport.requests.lock().unwrap();
+        assert_eq!(requests.len(), 4);
+        assert_eq!(
+            String::from_utf8_lossy(&requests[1].body),
+            "indoor_environment,room=kitchen temperature=21.3 1614877200000000000",
+        );
+        Ok(())
+    }
+}
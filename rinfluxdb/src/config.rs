@@ -0,0 +1,447 @@
+// Copyright Claudio Mattera 2021.
+// Distributed under the MIT License or Apache 2.0 License at your option.
+// See accompanying files License-MIT.txt and License-Apache-2.0, or online at
+// https://opensource.org/licenses/MIT
+// https://opensource.org/licenses/Apache-2.0
+
+use thiserror::Error;
+
+use url::Url;
+
+use crate::types::Authentication;
+
+/// Environment variable containing the server URL
+pub const URL_VARIABLE: &str = "INFLUXDB_URL";
+
+/// Environment variable containing the username
+pub const USERNAME_VARIABLE: &str = "INFLUXDB_USERNAME";
+
+/// Environment variable containing the password
+pub const PASSWORD_VARIABLE: &str = "INFLUXDB_PASSWORD";
+
+/// Environment variable containing the token
+pub const TOKEN_VARIABLE: &str = "INFLUXDB_TOKEN";
+
+/// Environment variable containing the organization
+pub const ORGANIZATION_VARIABLE: &str = "INFLUXDB_ORG";
+
+/// Environment variable containing the bucket
+pub const BUCKET_VARIABLE: &str = "INFLUXDB_BUCKET";
+
+/// Environment variable containing the database
+pub const DATABASE_VARIABLE: &str = "INFLUXDB_DATABASE";
+
+/// An error occurred while loading a client configuration
+#[derive(Error, Debug)]
+pub enum ConfigError {
+    /// A required setting is missing
+    #[error("Missing setting {0}")]
+    MissingSetting(String),
+
+    /// Error occurred while parsing a URL
+    #[error("URL parse error")]
+    UrlError(#[from] url::ParseError),
+
+    /// Error occurred while reading a configuration file
+    #[cfg(feature = "config-toml")]
+    #[error("IO error")]
+    IoError(#[from] std::io::Error),
+
+    /// Error occurred while parsing a configuration file
+    #[cfg(feature = "config-toml")]
+    #[error("TOML parse error")]
+    TomlError(#[from] toml::de::Error),
+}
+
+/// Settings shared by all clients, loaded from environment variables or from
+/// a configuration file
+///
+/// Clients are authenticated with the token if it is set, otherwise with
+/// username and password if both are set, and are not authenticated
+/// otherwise.
+/// Builders returned by the configuration can be further customized before
+/// building the clients.
+///
+/// ```no_run
+/// # use url::Url;
+/// # use rinfluxdb::ClientConfig;
+/// let config = ClientConfig::new(Url::parse("https://example.com/")?)
+///     .with_token("secret")
+///     .with_organization("home")
+///     .with_bucket("house");
+///
+/// let client = config.line_client_builder().build()?;
+/// client.send(config.write_target()?, &[])?;
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ClientConfig {
+    /// The server URL
+    pub url: Url,
+
+    /// The username
+    pub username: Option<String>,
+
+    /// The password
+    pub password: Option<String>,
+
+    /// The token
+    pub token: Option<String>,
+
+    /// The organization, for InfluxDB 2.x
+    pub organization: Option<String>,
+
+    /// The bucket, for InfluxDB 2.x
+    pub bucket: Option<String>,
+
+    /// The database, for InfluxDB 1.x
+    pub database: Option<String>,
+}
+
+impl ClientConfig {
+    /// Create a configuration for a server, without any other setting
+    pub fn new(url: Url) -> Self {
+        Self {
+            url,
+            username: None,
+            password: None,
+            token: None,
+            organization: None,
+            bucket: None,
+            database: None,
+        }
+    }
+
+    /// Load a configuration from environment variables
+    ///
+    /// Variable `INFLUXDB_URL` is required, while variables
+    /// `INFLUXDB_USERNAME`, `INFLUXDB_PASSWORD`, `INFLUXDB_TOKEN`,
+    /// `INFLUXDB_ORG`, `INFLUXDB_BUCKET` and `INFLUXDB_DATABASE` are optional.
+    /// Empty variables are considered unset.
+    pub fn from_env() -> Result<Self, ConfigError> {
+        Self::from_lookup(|name| std::env::var(name).ok())
+    }
+
+    /// Load a configuration from a TOML file
+    ///
+    /// The file contains key `url`, and optionally keys `username`,
+    /// `password`, `token`, `organization`, `bucket` and `database`.
+    ///
+    /// ```toml
+    /// url = "https://example.com/"
+    /// token = "secret"
+    /// organization = "home"
+    /// bucket = "house"
+    /// ```
+    #[cfg(feature = "config-toml")]
+    pub fn from_toml<P>(path: P) -> Result<Self, ConfigError>
+    where
+        P: AsRef<std::path::Path>,
+    {
+        let text = std::fs::read_to_string(path)?;
+        Self::from_toml_str(&text)
+    }
+
+    /// Load a configuration from the content of a TOML file
+    #[cfg(feature = "config-toml")]
+    pub fn from_toml_str(text: &str) -> Result<Self, ConfigError> {
+        let file: ConfigFile = toml::from_str(text)?;
+        Ok(Self {
+            url: Url::parse(&file.url)?,
+            username: file.username,
+            password: file.password,
+            token: file.token,
+            organization: file.organization,
+            bucket: file.bucket,
+            database: file.database,
+        })
+    }
+
+    /// Load a configuration from a function returning values of variables
+    fn from_lookup<F>(lookup: F) -> Result<Self, ConfigError>
+    where
+        F: Fn(&str) -> Option<String>,
+    {
+        let lookup = |name: &str| lookup(name).filter(|value| !value.is_empty());
+
+        let url = lookup(URL_VARIABLE).ok_or_else(|| ConfigError::MissingSetting(URL_VARIABLE.into()))?;
+        Ok(Self {
+            url: Url::parse(&url)?,
+            username: lookup(USERNAME_VARIABLE),
+            password: lookup(PASSWORD_VARIABLE),
+            token: lookup(TOKEN_VARIABLE),
+            organization: lookup(ORGANIZATION_VARIABLE),
+            bucket: lookup(BUCKET_VARIABLE),
+            database: lookup(DATABASE_VARIABLE),
+        })
+    }
+
+    /// Set the username and password
+    pub fn with_credentials<T, S>(mut self, username: T, password: S) -> Self
+    where
+        T: Into<String>,
+        S: Into<String>,
+    {
+        self.username = Some(username.into());
+        self.password = Some(password.into());
+        self
+    }
+
+    /// Set the token
+    pub fn with_token<T>(mut self, token: T) -> Self
+    where
+        T: Into<String>,
+    {
+        self.token = Some(token.into());
+        self
+    }
+
+    /// Set the organization
+    pub fn with_organization<T>(mut self, organization: T) -> Self
+    where
+        T: Into<String>,
+    {
+        self.organization = Some(organization.into());
+        self
+    }
+
+    /// Set the bucket
+    pub fn with_bucket<T>(mut self, bucket: T) -> Self
+    where
+        T: Into<String>,
+    {
+        self.bucket = Some(bucket.into());
+        self
+    }
+
+    /// Set the database
+    pub fn with_database<T>(mut self, database: T) -> Self
+    where
+        T: Into<String>,
+    {
+        self.database = Some(database.into());
+        self
+    }
+
+    /// Return the authentication used by clients
+    pub fn authentication(&self) -> Authentication {
+        match (&self.token, &self.username, &self.password) {
+            (Some(token), _, _) => Authentication::Token(token.clone()),
+            (None, Some(username), Some(password)) => Authentication::Basic {
+                username: username.clone(),
+                password: password.clone(),
+            },
+            _ => Authentication::None,
+        }
+    }
+
+    /// Return the target of writes
+    ///
+    /// The bucket in the organization is used if both are set, otherwise the
+    /// database is used.
+    #[cfg(feature = "lineprotocol")]
+    pub fn write_target(&self) -> Result<crate::line_protocol::WriteTarget, ConfigError> {
+        use crate::line_protocol::WriteTarget;
+
+        match (&self.organization, &self.bucket, &self.database) {
+            (Some(organization), Some(bucket), _) => Ok(WriteTarget::v2(organization, bucket)),
+            (_, _, Some(database)) => Ok(WriteTarget::v1(database)),
+            _ => Err(ConfigError::MissingSetting(DATABASE_VARIABLE.into())),
+        }
+    }
+
+    /// Return the database, or an error if it is not set
+    #[cfg(all(feature = "lineprotocol", feature = "influxql"))]
+    fn required_database(&self) -> Result<&str, ConfigError> {
+        self.database
+            .as_deref()
+            .ok_or_else(|| ConfigError::MissingSetting(DATABASE_VARIABLE.into()))
+    }
+
+    /// Return a builder for a blocking line protocol client
+    #[cfg(all(feature = "client-blocking", feature = "lineprotocol"))]
+    pub fn line_client_builder(&self) -> crate::line_protocol::blocking::ClientBuilder {
+        crate::line_protocol::blocking::ClientBuilder::new(self.url.clone())
+            .authentication(self.authentication())
+    }
+
+    /// Return a builder for an asynchronous line protocol client
+    #[cfg(all(feature = "client-async", feature = "lineprotocol"))]
+    pub fn async_line_client_builder(&self) -> crate::line_protocol::r#async::ClientBuilder {
+        crate::line_protocol::r#async::ClientBuilder::new(self.url.clone())
+            .authentication(self.authentication())
+    }
+
+    /// Return a builder for a blocking InfluxQL client
+    #[cfg(all(feature = "client-blocking", feature = "influxql"))]
+    pub fn influxql_client_builder(&self) -> crate::influxql::blocking::ClientBuilder {
+        crate::influxql::blocking::ClientBuilder::new(self.url.clone())
+            .authentication(self.authentication())
+    }
+
+    /// Return a builder for an asynchronous InfluxQL client
+    #[cfg(all(feature = "client-async", feature = "influxql"))]
+    pub fn async_influxql_client_builder(&self) -> crate::influxql::r#async::ClientBuilder {
+        crate::influxql::r#async::ClientBuilder::new(self.url.clone())
+            .authentication(self.authentication())
+    }
+
+    /// Return a builder for a blocking Flux client
+    ///
+    /// The organization is set on the builder if present.
+    #[cfg(all(feature = "client-blocking", feature = "flux"))]
+    pub fn flux_client_builder(&self) -> crate::flux::blocking::ClientBuilder {
+        let builder = crate::flux::blocking::ClientBuilder::new(self.url.clone())
+            .authentication(self.authentication());
+        match &self.organization {
+            Some(organization) => builder.organization(organization),
+            None => builder,
+        }
+    }
+
+    /// Return a builder for an asynchronous Flux client
+    ///
+    /// The organization is set on the builder if present.
+    #[cfg(all(feature = "client-async", feature = "flux"))]
+    pub fn async_flux_client_builder(&self) -> crate::flux::r#async::ClientBuilder {
+        let builder = crate::flux::r#async::ClientBuilder::new(self.url.clone())
+            .authentication(self.authentication());
+        match &self.organization {
+            Some(organization) => builder.organization(organization),
+            None => builder,
+        }
+    }
+
+    /// Create a blocking connection to the database
+    ///
+    /// [`ConfigError::MissingSetting`] is returned if the database is not set.
+    #[cfg(all(feature = "client-blocking", feature = "lineprotocol", feature = "influxql"))]
+    pub fn influxdb(&self) -> Result<crate::InfluxDb, crate::InfluxDbError> {
+        let database = self.required_database()?;
+        let line_client = self.line_client_builder().build()?;
+        let influxql_client = self.influxql_client_builder().build()?;
+        Ok(crate::InfluxDb::from_clients(line_client, influxql_client, database))
+    }
+
+    /// Create an asynchronous connection to the database
+    ///
+    /// [`ConfigError::MissingSetting`] is returned if the database is not set.
+    #[cfg(all(feature = "client-async", feature = "lineprotocol", feature = "influxql"))]
+    pub fn async_influxdb(&self) -> Result<crate::AsyncInfluxDb, crate::InfluxDbError> {
+        let database = self.required_database()?;
+        let line_client = self.async_line_client_builder().build()?;
+        let influxql_client = self.async_influxql_client_builder().build()?;
+        Ok(crate::AsyncInfluxDb::from_clients(line_client, influxql_client, database))
+    }
+}
+
+/// Content of a TOML configuration file
+#[cfg(feature = "config-toml")]
+#[derive(serde::Deserialize)]
+struct ConfigFile {
+    url: String,
+    username: Option<String>,
+    password: Option<String>,
+    token: Option<String>,
+    organization: Option<String>,
+    bucket: Option<String>,
+    database: Option<String>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::collections::HashMap;
+
+    fn from_variables(variables: &[(&str, &str)]) -> Result<ClientConfig, ConfigError> {
+        let variables: HashMap<String, String> = variables
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect();
+        ClientConfig::from_lookup(|name| variables.get(name).cloned())
+    }
+
+    #[test]
+    fn load_from_variables() -> Result<(), ConfigError> {
+        let config = from_variables(&[
+            ("INFLUXDB_URL", "http://influxdb.test/"),
+            ("INFLUXDB_TOKEN", "secret"),
+            ("INFLUXDB_ORG", "home"),
+            ("INFLUXDB_BUCKET", "house"),
+            ("INFLUXDB_DATABASE", ""),
+        ])?;
+
+        let expected = ClientConfig::new(Url::parse("http://influxdb.test/")?)
+            .with_token("secret")
+            .with_organization("home")
+            .with_bucket("house");
+        assert_eq!(config, expected);
+        assert_eq!(config.authentication(), Authentication::Token("secret".into()));
+        Ok(())
+    }
+
+    #[test]
+    fn missing_url() {
+        let result = from_variables(&[("INFLUXDB_TOKEN", "secret")]);
+        assert!(matches!(result, Err(ConfigError::MissingSetting(name)) if name == "INFLUXDB_URL"));
+    }
+
+    #[test]
+    fn basic_authentication() -> Result<(), ConfigError> {
+        let config = from_variables(&[
+            ("INFLUXDB_URL", "http://influxdb.test/"),
+            ("INFLUXDB_USERNAME", "username"),
+            ("INFLUXDB_PASSWORD", "password"),
+        ])?;
+        assert_eq!(
+            config.authentication(),
+            Authentication::Basic {
+                username: "username".into(),
+                password: "password".into(),
+            },
+        );
+
+        let config = from_variables(&[
+            ("INFLUXDB_URL", "http://influxdb.test/"),
+            ("INFLUXDB_USERNAME", "username"),
+        ])?;
+        assert_eq!(config.authentication(), Authentication::None);
+        Ok(())
+    }
+
+    #[cfg(feature = "lineprotocol")]
+    #[test]
+    fn write_target() -> Result<(), ConfigError> {
+        use crate::line_protocol::WriteTarget;
+
+        let config = ClientConfig::new(Url::parse("http://influxdb.test/")?).with_database("house");
+        assert_eq!(config.write_target()?, WriteTarget::v1("house"));
+
+        let config = config.with_organization("home").with_bucket("garden");
+        assert_eq!(config.write_target()?, WriteTarget::v2("home", "garden"));
+
+        let config = ClientConfig::new(Url::parse("http://influxdb.test/")?).with_bucket("garden");
+        assert!(config.write_target().is_err());
+        Ok(())
+    }
+
+    #[cfg(feature = "config-toml")]
+    #[test]
+    fn load_from_toml() -> Result<(), ConfigError> {
+        let config = ClientConfig::from_toml_str(
+            r#"
+            url = "http://influxdb.test/"
+            username = "username"
+            password = "password"
+            database = "house"
+            "#,
+        )?;
+
+        let expected = ClientConfig::new(Url::parse("http://influxdb.test/")?)
+            .with_credentials("username", "password")
+            .with_database("house");
+        assert_eq!(config, expected);
+        Ok(())
+    }
+}
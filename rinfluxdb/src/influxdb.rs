@@ -0,0 +1,330 @@
+// Copyright Claudio Mattera 2021.
+// Distributed under the MIT License or Apache 2.0 License at your option.
+// See accompanying files License-MIT.txt and License-Apache-2.0, or online at
+// https://opensource.org/licenses/MIT
+// https://opensource.org/licenses/Apache-2.0
+
+use thiserror::Error;
+
+use url::Url;
+
+use crate::influxql::{ClientError as QueryError, Query, ResponseError, StatementResult};
+use crate::line_protocol::{ClientError as WriteError, Line, WriteSummary};
+use crate::types::{Authentication, FromQueryResult};
+
+/// An error occurred while writing to or querying an InfluxDB server
+#[derive(Error, Debug)]
+pub enum InfluxDbError {
+    /// Error occurred while writing lines
+    #[error("Write error")]
+    WriteError(#[from] WriteError),
+
+    /// Error occurred while querying
+    #[error("Query error")]
+    QueryError(#[from] QueryError),
+
+    /// Error occurred while loading a client configuration
+    #[error("Configuration error")]
+    ConfigError(#[from] crate::ConfigError),
+}
+
+/// Return the first dataframe of the first statement of a query result
+fn first_dataframe<DF>(statement_results: Vec<StatementResult<DF>>) -> Result<DF, InfluxDbError> {
+    let statement_result = statement_results
+        .into_iter()
+        .next()
+        .ok_or(QueryError::EmptyError)?;
+    let (dataframe, _tags) = statement_result
+        .map_err(QueryError::from)?
+        .into_iter()
+        .next()
+        .ok_or(QueryError::EmptyError)?;
+    Ok(dataframe)
+}
+
+/// A blocking connection to a database on an InfluxDB server, for both
+/// writing and querying
+///
+/// Lines are written with the Influx Line Protocol, and queries are written
+/// in InfluxQL, and both target the same database.
+/// All clients share the same base URL and authentication, and clients
+/// configured with other options can be combined with
+/// [`from_clients`](InfluxDb::from_clients).
+///
+/// ```no_run
+/// # use url::Url;
+/// # use rinfluxdb::{InfluxDb, InfluxDbError, InfluxLineBuilder, InfluxqlQuery};
+/// # use rinfluxdb::dataframe::DataFrame;
+/// let influxdb = InfluxDb::new(
+///     Url::parse("https://example.com/")?,
+///     Some(("username", "password")),
+///     "house",
+/// )?;
+///
+/// let line = InfluxLineBuilder::new("indoor_environment")
+///     .insert_field("temperature", 21.3)
+///     .build();
+/// influxdb.write(&[line])?;
+///
+/// let query = InfluxqlQuery::new("SELECT temperature FROM indoor_environment");
+/// let dataframe: DataFrame = influxdb.query(query)?;
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+#[cfg(feature = "client-blocking")]
+#[derive(Debug)]
+pub struct InfluxDb {
+    line_client: crate::InfluxLineClient,
+    influxql_client: crate::InfluxqlClient,
+    database: String,
+}
+
+#[cfg(feature = "client-blocking")]
+impl InfluxDb {
+    /// Create a connection to a database on an InfluxDB server
+    ///
+    /// Parameter `authentication` can be an
+    /// [`Authentication`](crate::types::Authentication), or an optional pair
+    /// of username and password.
+    pub fn new<A, T>(base_url: Url, authentication: A, database: T) -> Result<Self, InfluxDbError>
+    where
+        A: Into<Authentication>,
+        T: Into<String>,
+    {
+        let authentication = authentication.into();
+        let line_client = crate::line_protocol::blocking::ClientBuilder::new(base_url.clone())
+            .authentication(authentication.clone())
+            .build()?;
+        let influxql_client = crate::influxql::blocking::ClientBuilder::new(base_url)
+            .authentication(authentication)
+            .build()?;
+        Ok(Self::from_clients(line_client, influxql_client, database))
+    }
+
+    /// Create a connection to a database from existing clients
+    pub fn from_clients<T>(
+        line_client: crate::InfluxLineClient,
+        influxql_client: crate::InfluxqlClient,
+        database: T,
+    ) -> Self
+    where
+        T: Into<String>,
+    {
+        Self {
+            line_client,
+            influxql_client,
+            database: database.into(),
+        }
+    }
+
+    /// Return the name of the database
+    pub fn database(&self) -> &str {
+        &self.database
+    }
+
+    /// Return the client used for writing lines
+    pub fn line_client(&self) -> &crate::InfluxLineClient {
+        &self.line_client
+    }
+
+    /// Return the client used for queries
+    pub fn influxql_client(&self) -> &crate::InfluxqlClient {
+        &self.influxql_client
+    }
+
+    /// Write lines to the database
+    pub fn write(&self, lines: &[Line]) -> Result<WriteSummary, InfluxDbError> {
+        Ok(self.line_client.send(self.database.as_str(), lines)?)
+    }
+
+    /// Query the database for a single dataframe
+    ///
+    /// Only the first dataframe of the first statement is returned, and
+    /// [`QueryError::EmptyError`](crate::influxql::ClientError::EmptyError) is
+    /// returned if the response does not contain dataframes.
+    pub fn query<DF, E>(&self, query: Query) -> Result<DF, InfluxDbError>
+    where
+        DF: FromQueryResult<Error = E>,
+        E: Into<ResponseError>,
+    {
+        let statement_results = self
+            .influxql_client
+            .fetch_readings_from_database(query, Some(self.database.as_str()))?;
+        first_dataframe(statement_results)
+    }
+}
+
+/// An asynchronous connection to a database on an InfluxDB server, for both
+/// writing and querying
+///
+/// This is the same as [`InfluxDb`](crate::InfluxDb), but with asynchronous
+/// clients.
+///
+/// ```no_run
+/// # use url::Url;
+/// # use rinfluxdb::{AsyncInfluxDb, InfluxDbError, InfluxLineBuilder, InfluxqlQuery};
+/// # use rinfluxdb::dataframe::DataFrame;
+/// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+/// let influxdb = AsyncInfluxDb::new(
+///     Url::parse("https://example.com/")?,
+///     Some(("username", "password")),
+///     "house",
+/// )?;
+///
+/// let line = InfluxLineBuilder::new("indoor_environment")
+///     .insert_field("temperature", 21.3)
+///     .build();
+/// influxdb.write(&[line]).await?;
+///
+/// let query = InfluxqlQuery::new("SELECT temperature FROM indoor_environment");
+/// let dataframe: DataFrame = influxdb.query(query).await?;
+/// # Ok(())
+/// # }
+/// ```
+#[cfg(feature = "client-async")]
+#[derive(Debug)]
+pub struct AsyncInfluxDb {
+    line_client: crate::InfluxAsyncLineClient,
+    influxql_client: crate::InfluxqlAsyncClient,
+    database: String,
+}
+
+#[cfg(feature = "client-async")]
+impl AsyncInfluxDb {
+    /// Create a connection to a database on an InfluxDB server
+    ///
+    /// Parameter `authentication` can be an
+    /// [`Authentication`](crate::types::Authentication), or an optional pair
+    /// of username and password.
+    pub fn new<A, T>(base_url: Url, authentication: A, database: T) -> Result<Self, InfluxDbError>
+    where
+        A: Into<Authentication>,
+        T: Into<String>,
+    {
+        let authentication = authentication.into();
+        let line_client = crate::line_protocol::r#async::ClientBuilder::new(base_url.clone())
+            .authentication(authentication.clone())
+            .build()?;
+        let influxql_client = crate::influxql::r#async::ClientBuilder::new(base_url)
+            .authentication(authentication)
+            .build()?;
+        Ok(Self::from_clients(line_client, influxql_client, database))
+    }
+
+    /// Create a connection to a database from existing clients
+    pub fn from_clients<T>(
+        line_client: crate::InfluxAsyncLineClient,
+        influxql_client: crate::InfluxqlAsyncClient,
+        database: T,
+    ) -> Self
+    where
+        T: Into<String>,
+    {
+        Self {
+            line_client,
+            influxql_client,
+            database: database.into(),
+        }
+    }
+
+    /// Return the name of the database
+    pub fn database(&self) -> &str {
+        &self.database
+    }
+
+    /// Return the client used for writing lines
+    pub fn line_client(&self) -> &crate::InfluxAsyncLineClient {
+        &self.line_client
+    }
+
+    /// Return the client used for queries
+    pub fn influxql_client(&self) -> &crate::InfluxqlAsyncClient {
+        &self.influxql_client
+    }
+
+    /// Write lines to the database
+    pub async fn write(&self, lines: &[Line]) -> Result<WriteSummary, InfluxDbError> {
+        Ok(self.line_client.send(self.database.as_str(), lines).await?)
+    }
+
+    /// Query the database for a single dataframe
+    ///
+    /// Only the first dataframe of the first statement is returned, and
+    /// [`QueryError::EmptyError`](crate::influxql::ClientError::EmptyError) is
+    /// returned if the response does not contain dataframes.
+    pub async fn query<DF, E>(&self, query: Query) -> Result<DF, InfluxDbError>
+    where
+        DF: FromQueryResult<Error = E>,
+        E: Into<ResponseError>,
+    {
+        let statement_results = self
+            .influxql_client
+            .fetch_readings_from_database(query, Some(self.database.as_str()))
+            .await?;
+        first_dataframe(statement_results)
+    }
+}
+
+#[cfg(all(test, feature = "client-blocking", feature = "dataframe"))]
+mod tests {
+    use super::*;
+
+    use std::sync::{Arc, Mutex};
+
+    use crate::dataframe::DataFrame;
+    use crate::types::{HttpRequest, HttpResponse, HttpTransport, TransportError};
+    use crate::InfluxLineBuilder;
+
+    #[derive(Debug, Default)]
+    struct RecordingTransport {
+        requests: Mutex<Vec<HttpRequest>>,
+    }
+
+    impl HttpTransport for RecordingTransport {
+        fn execute(&self, request: HttpRequest) -> Result<HttpResponse, TransportError> {
+            let response = if request.url.contains("/write") {
+                HttpResponse::new(204, "")
+            } else {
+                HttpResponse::new(200, r#"{
+                    "results": [{
+                        "statement_id": 0,
+                        "series": [{
+                            "name": "indoor_environment",
+                            "columns": ["time", "temperature"],
+                            "values": [["2021-03-04T17:00:00Z", 21.3]]
+                        }]
+                    }]
+                }"#)
+            };
+            self.requests.lock().unwrap().push(request);
+            Ok(response)
+        }
+    }
+
+    #[test]
+    fn write_and_query_database() -> Result<(), InfluxDbError> {
+        let transport = Arc::new(RecordingTransport::default());
+        let base_url = Url::parse("http://influxdb.test/").unwrap();
+        let line_client = crate::line_protocol::blocking::ClientBuilder::new(base_url.clone())
+            .transport(transport.clone())
+            .build()?;
+        let influxql_client = crate::influxql::blocking::ClientBuilder::new(base_url)
+            .transport(transport.clone())
+            .build()?;
+        let influxdb = InfluxDb::from_clients(line_client, influxql_client, "house");
+
+        let line = InfluxLineBuilder::new("indoor_environment")
+            .insert_field("temperature", 21.3)
+            .build();
+        influxdb.write(&[line])?;
+
+        let query = Query::new("SELECT temperature FROM indoor_environment");
+        let dataframe: DataFrame = influxdb.query(query)?;
+        assert_eq!(dataframe.index().len(), 1);
+
+        let requests = transport.requests.lock().unwrap();
+        assert_eq!(requests.len(), 2);
+        assert!(requests[0].url.contains("db=house"));
+        assert!(String::from_utf8_lossy(&requests[1].body).contains("db=house"));
+        Ok(())
+    }
+}
@@ -23,11 +23,57 @@ pub use rinfluxdb_dataframe as dataframe;
 #[cfg(feature = "polars")]
 pub use rinfluxdb_polars as polars;
 
-#[cfg(all(feature = "client", feature = "flux"))]
+#[cfg(feature = "arrow")]
+pub use rinfluxdb_arrow as arrow;
+
+#[cfg(feature = "testing")]
+pub mod testing;
+
+#[cfg(any(feature = "client-blocking", feature = "client-async"))]
+mod config;
+
+#[cfg(any(feature = "client-blocking", feature = "client-async"))]
+pub use self::config::*;
+
+#[cfg(all(
+    any(feature = "client-blocking", feature = "client-async"),
+    feature = "lineprotocol",
+    feature = "influxql",
+))]
+mod influxdb;
+
+#[cfg(all(
+    any(feature = "client-blocking", feature = "client-async"),
+    feature = "lineprotocol",
+    feature = "influxql",
+))]
+pub use self::influxdb::*;
+
+#[cfg(all(
+    any(feature = "client-blocking", feature = "client-async"),
+    feature = "lineprotocol",
+    feature = "influxql",
+))]
+mod backfill;
+
+#[cfg(all(
+    any(feature = "client-blocking", feature = "client-async"),
+    feature = "lineprotocol",
+    feature = "influxql",
+))]
+pub use self::backfill::*;
+
+#[cfg(all(feature = "client-blocking", feature = "lineprotocol", feature = "influxql"))]
+mod scheduler;
+
+#[cfg(all(feature = "client-blocking", feature = "lineprotocol", feature = "influxql"))]
+pub use self::scheduler::*;
+
+#[cfg(all(feature = "client-blocking", feature = "flux"))]
 /// A client for performing frequent Flux queries in a convenient way
 pub type FluxClient = flux::blocking::Client;
 
-#[cfg(all(feature = "client", feature = "flux"))]
+#[cfg(all(feature = "client-async", feature = "flux"))]
 /// A client for performing frequent asynchronous Flux queries in a convenient way
 pub type FluxAsyncClient = flux::r#async::Client;
 
@@ -39,11 +85,11 @@ pub type FluxQuery = flux::Query;
 #[cfg(feature = "flux")]
 pub type FluxQueryBuilder = flux::QueryBuilder;
 
-#[cfg(all(feature = "client", feature = "influxql"))]
+#[cfg(all(feature = "client-blocking", feature = "influxql"))]
 /// A client for performing frequent InfluxQL queries in a convenient way
 pub type InfluxqlClient = influxql::blocking::Client;
 
-#[cfg(all(feature = "client", feature = "influxql"))]
+#[cfg(all(feature = "client-async", feature = "influxql"))]
 /// A client for performing frequent asynchronous InfluxQL queries in a convenient way
 pub type InfluxqlAsyncClient = influxql::r#async::Client;
 
@@ -55,12 +101,12 @@ pub type InfluxqlQuery = influxql::Query;
 #[cfg(feature = "influxql")]
 pub type InfluxqlQueryBuilder = influxql::QueryBuilder;
 
-#[cfg(all(feature = "client", feature = "lineprotocol"))]
+#[cfg(all(feature = "client-blocking", feature = "lineprotocol"))]
 /// A client for sending data with Influx Line Protocol queries in a convenient
 /// way
 pub type InfluxLineClient = line_protocol::blocking::Client;
 
-#[cfg(all(feature = "client", feature = "lineprotocol"))]
+#[cfg(all(feature = "client-async", feature = "lineprotocol"))]
 /// A client for asynchronously sending data with Influx Line Protocol queries in a convenient
 /// way
 pub type InfluxAsyncLineClient = line_protocol::r#async::Client;
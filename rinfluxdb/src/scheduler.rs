@@ -0,0 +1,529 @@
+// Copyright Claudio Mattera 2021.
+// Distributed under the MIT License or Apache 2.0 License at your option.
+// See accompanying files License-MIT.txt and License-Apache-2.0, or online at
+// https://opensource.org/licenses/MIT
+// https://opensource.org/licenses/Apache-2.0
+
+use std::collections::hash_map::RandomState;
+use std::convert::TryFrom;
+use std::fmt;
+use std::hash::{BuildHasher, Hasher};
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use chrono::{DateTime, TimeZone, Utc};
+
+#[cfg(feature = "cron")]
+use thiserror::Error;
+
+use crate::backfill::collect_lines;
+use crate::influxql::{Query, ResponseError, StatementResult};
+use crate::line_protocol::{Lines, WriteTarget};
+use crate::types::FromQueryResult;
+use crate::{InfluxDb, InfluxDbError};
+
+/// A task run by a job at each scheduled time
+type Task = Box<dyn Fn(&InfluxDb, DateTime<Utc>) -> Result<(), InfluxDbError> + Send + Sync>;
+
+/// A callback notified when a job fails
+type ErrorCallback = Arc<dyn Fn(&str, &InfluxDbError) + Send + Sync>;
+
+/// An error occurred while parsing a cron expression
+#[cfg(feature = "cron")]
+#[derive(Error, Debug)]
+#[error("Invalid cron expression")]
+pub struct CronError(#[from] cron::error::Error);
+
+/// The times at which a job runs
+#[derive(Clone, Debug)]
+pub enum Schedule {
+    /// Run at every multiple of an interval since the Unix epoch
+    Every(Duration),
+
+    /// Run at the times matching a cron expression
+    #[cfg(feature = "cron")]
+    Cron(Box<cron::Schedule>),
+}
+
+impl Schedule {
+    /// Create a schedule running at every multiple of an interval
+    ///
+    /// Times are aligned to the interval, so a job running every 5 minutes
+    /// runs at 17:00, 17:05, 17:10, and so on, regardless of when it was
+    /// started.
+    ///
+    /// ```
+    /// # use std::time::Duration;
+    /// # use chrono::{TimeZone, Utc};
+    /// # use rinfluxdb::Schedule;
+    /// let schedule = Schedule::every(Duration::from_secs(300));
+    ///
+    /// assert_eq!(
+    ///     schedule.next_after(Utc.with_ymd_and_hms(2021, 3, 4, 17, 2, 30).unwrap()),
+    ///     Some(Utc.with_ymd_and_hms(2021, 3, 4, 17, 5, 0).unwrap()),
+    /// );
+    /// ```
+    pub fn every(interval: Duration) -> Self {
+        Schedule::Every(interval.max(Duration::from_millis(1)))
+    }
+
+    /// Create a schedule from a cron expression
+    ///
+    /// Expressions have fields for seconds, minutes, hours, days of month,
+    /// months, days of week and optionally years, and are evaluated in UTC.
+    ///
+    /// ```
+    /// # use chrono::{TimeZone, Utc};
+    /// # use rinfluxdb::Schedule;
+    /// let schedule = Schedule::cron("0 30 * * * *")?;
+    ///
+    /// assert_eq!(
+    ///     schedule.next_after(Utc.with_ymd_and_hms(2021, 3, 4, 17, 2, 30).unwrap()),
+    ///     Some(Utc.with_ymd_and_hms(2021, 3, 4, 17, 30, 0).unwrap()),
+    /// );
+    /// # Ok::<(), rinfluxdb::CronError>(())
+    /// ```
+    #[cfg(feature = "cron")]
+    pub fn cron(expression: &str) -> Result<Self, CronError> {
+        Ok(Schedule::Cron(Box::new(expression.parse()?)))
+    }
+
+    /// Return the first scheduled time strictly after an instant
+    ///
+    /// Return `None` if the schedule has no more times, or if the next time
+    /// cannot be represented.
+    /// Empty intervals have no times.
+    pub fn next_after(&self, instant: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        match self {
+            Schedule::Every(interval) => {
+                // Compute in 128 bits, since instants far from epoch overflow
+                // 64 bits nanoseconds
+                let interval = i128::try_from(interval.as_nanos()).ok()?;
+                let nanoseconds = instant.timestamp() as i128 * 1_000_000_000
+                    + instant.timestamp_subsec_nanos() as i128;
+                let next = nanoseconds
+                    .checked_div_euclid(interval)?
+                    .checked_add(1)?
+                    .checked_mul(interval)?;
+                let seconds = i64::try_from(next.div_euclid(1_000_000_000)).ok()?;
+                let subsecond_nanoseconds = next.rem_euclid(1_000_000_000) as u32;
+                Utc.timestamp_opt(seconds, subsecond_nanoseconds).single()
+            }
+            #[cfg(feature = "cron")]
+            Schedule::Cron(schedule) => schedule.after(&instant).next(),
+        }
+    }
+}
+
+/// A query run on a schedule
+struct Job {
+    name: String,
+    schedule: Schedule,
+    task: Task,
+}
+
+/// A client-side scheduler running queries periodically
+///
+/// This replaces continuous queries and tasks on servers where they are not
+/// available or not permitted.
+/// Each job runs a query at the times of its schedule, and either passes the
+/// results to a callback, or writes them back to the server as lines.
+/// Queries are created from the scheduled time, so they can select the data
+/// since the previous run.
+///
+/// Each job runs in its own thread, so a slow job does not delay other jobs.
+/// A job never overlaps with itself: if a run lasts past the next scheduled
+/// time, the runs missed in the meantime are skipped.
+/// Runs can be delayed by a random jitter, so that jobs scheduled at the same
+/// time do not hit the server all at once.
+///
+/// Errors are passed to the callback set with
+/// [`on_error()`](Scheduler::on_error), and do not stop the job.
+///
+/// ```no_run
+/// # use std::time::Duration;
+/// # use url::Url;
+/// # use rinfluxdb::{InfluxDb, InfluxqlQuery, Schedule, Scheduler};
+/// # use rinfluxdb::dataframe::DataFrame;
+/// # use rinfluxdb::influxql::StatementResult;
+/// # use rinfluxdb::line_protocol::WriteTarget;
+/// let influxdb = InfluxDb::new(
+///     Url::parse("https://example.com/")?,
+///     Some(("username", "password")),
+///     "house",
+/// )?;
+///
+/// let scheduler = Scheduler::new(influxdb)
+///     .jitter(Duration::from_secs(5))
+///     .on_error(|job, error| eprintln!("Job {} failed: {}", job, error))
+///     .write_back(
+///         "downsample",
+///         Schedule::every(Duration::from_secs(300)),
+///         |time| InfluxqlQuery::new(format!(
+///             "SELECT mean(temperature) AS temperature FROM indoor_environment \
+///              WHERE time >= {} AND time < {} GROUP BY time(5m), *",
+///             (time - chrono::Duration::minutes(5)).timestamp_nanos(),
+///             time.timestamp_nanos(),
+///         )),
+///         WriteTarget::V1 {
+///             database: "house".into(),
+///             retention_policy: Some("downsampled".into()),
+///         },
+///     )
+///     .query(
+///         "report",
+///         Schedule::every(Duration::from_secs(3600)),
+///         |_time| InfluxqlQuery::new("SELECT last(temperature) FROM indoor_environment"),
+///         |time, results: Vec<StatementResult<DataFrame>>| {
+///             println!("Results at {}: {} statements", time, results.len());
+///         },
+///     );
+///
+/// let handle = scheduler.start();
+/// // ...
+/// handle.stop();
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+pub struct Scheduler {
+    influxdb: Arc<InfluxDb>,
+    jitter: Duration,
+    on_error: Option<ErrorCallback>,
+    jobs: Vec<Job>,
+}
+
+impl Scheduler {
+    /// Create a scheduler running queries on a database
+    pub fn new(influxdb: InfluxDb) -> Self {
+        Self {
+            influxdb: Arc::new(influxdb),
+            jitter: Duration::from_secs(0),
+            on_error: None,
+            jobs: Vec::new(),
+        }
+    }
+
+    /// Delay each run by a random duration up to a maximum
+    pub fn jitter(mut self, jitter: Duration) -> Self {
+        self.jitter = jitter;
+        self
+    }
+
+    /// Set a callback notified with the name of a job and its error when a
+    /// run fails
+    pub fn on_error<F>(mut self, on_error: F) -> Self
+    where
+        F: Fn(&str, &InfluxDbError) + Send + Sync + 'static,
+    {
+        self.on_error = Some(Arc::new(on_error));
+        self
+    }
+
+    /// Register a job passing query results to a callback
+    ///
+    /// The callback receives the scheduled time and the results of all
+    /// statements.
+    pub fn query<T, Q, F, DF, E>(mut self, name: T, schedule: Schedule, query: Q, callback: F) -> Self
+    where
+        T: Into<String>,
+        Q: Fn(DateTime<Utc>) -> Query + Send + Sync + 'static,
+        F: Fn(DateTime<Utc>, Vec<StatementResult<DF>>) + Send + Sync + 'static,
+        DF: FromQueryResult<Error = E> + 'static,
+        E: Into<ResponseError> + 'static,
+    {
+        let task = move |influxdb: &InfluxDb, time: DateTime<Utc>| {
+            let statement_results = influxdb
+                .influxql_client()
+                .fetch_readings_from_database(query(time), Some(influxdb.database()))?;
+            callback(time, statement_results);
+            Ok(())
+        };
+        self.jobs.push(Job {
+            name: name.into(),
+            schedule,
+            task: Box::new(task),
+        });
+        self
+    }
+
+    /// Register a job writing query results back as lines
+    ///
+    /// Series are converted to lines with their measurement name, so queries
+    /// should alias their fields and group by tags, e.g. with `GROUP BY *`.
+    /// The target can be a database name, or a [`WriteTarget`](crate::line_protocol::WriteTarget)
+    /// for retention policies or InfluxDB 2.x buckets.
+    pub fn write_back<T, Q, S>(mut self, name: T, schedule: Schedule, query: Q, target: S) -> Self
+    where
+        T: Into<String>,
+        Q: Fn(DateTime<Utc>) -> Query + Send + Sync + 'static,
+        S: Into<WriteTarget>,
+    {
+        let target = target.into();
+        let task = move |influxdb: &InfluxDb, time: DateTime<Utc>| {
+            let statement_results = influxdb
+                .influxql_client()
+                .fetch_readings_from_database::<Lines, _, _>(query(time), Some(influxdb.database()))?;
+            let lines = collect_lines(statement_results)?;
+            if !lines.is_empty() {
+                influxdb.line_client().send(&target, &lines)?;
+            }
+            Ok(())
+        };
+        self.jobs.push(Job {
+            name: name.into(),
+            schedule,
+            task: Box::new(task),
+        });
+        self
+    }
+
+    /// Start running all jobs, each in its own thread
+    pub fn start(self) -> SchedulerHandle {
+        let Scheduler {
+            influxdb,
+            jitter,
+            on_error,
+            jobs,
+        } = self;
+        let stop = Arc::new(StopSignal::default());
+        let threads = jobs
+            .into_iter()
+            .map(|job| {
+                let influxdb = influxdb.clone();
+                let on_error = on_error.clone();
+                let stop = stop.clone();
+                thread::spawn(move || run(job, &influxdb, on_error, jitter, &stop))
+            })
+            .collect();
+        SchedulerHandle { stop, threads }
+    }
+}
+
+impl fmt::Debug for Scheduler {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let jobs: Vec<&str> = self.jobs.iter().map(|job| job.name.as_str()).collect();
+        f.debug_struct("Scheduler")
+            .field("influxdb", &self.influxdb)
+            .field("jitter", &self.jitter)
+            .field("jobs", &jobs)
+            .finish_non_exhaustive()
+    }
+}
+
+/// A handle to a running scheduler
+///
+/// Dropping the handle stops the scheduler, like [`stop()`](SchedulerHandle::stop).
+#[derive(Debug)]
+pub struct SchedulerHandle {
+    stop: Arc<StopSignal>,
+    threads: Vec<JoinHandle<()>>,
+}
+
+impl SchedulerHandle {
+    /// Stop all jobs
+    ///
+    /// Runs in progress are completed before returning, while runs not
+    /// started yet are cancelled.
+    pub fn stop(mut self) {
+        self.shutdown();
+    }
+
+    /// Signal all threads to stop, and wait for them
+    fn shutdown(&mut self) {
+        self.stop.stop();
+        for thread in self.threads.drain(..) {
+            // A panic in a callback only stops its own job
+            let _ = thread.join();
+        }
+    }
+}
+
+impl Drop for SchedulerHandle {
+    fn drop(&mut self) {
+        self.shutdown();
+    }
+}
+
+/// A flag waking up sleeping threads when set
+#[derive(Debug, Default)]
+struct StopSignal {
+    stopped: Mutex<bool>,
+    condvar: Condvar,
+}
+
+impl StopSignal {
+    /// Set the flag and wake up all threads
+    fn stop(&self) {
+        *self.stopped.lock().expect("Stop signal lock is not poisoned") = true;
+        self.condvar.notify_all();
+    }
+
+    /// Sleep for a duration, and return whether the flag was set meanwhile
+    fn wait(&self, timeout: Duration) -> bool {
+        let stopped = self.stopped.lock().expect("Stop signal lock is not poisoned");
+        let (stopped, _) = self
+            .condvar
+            .wait_timeout_while(stopped, timeout, |stopped| !*stopped)
+            .expect("Stop signal lock is not poisoned");
+        *stopped
+    }
+}
+
+/// Run a job at each scheduled time until the scheduler is stopped
+fn run(
+    job: Job,
+    influxdb: &InfluxDb,
+    on_error: Option<ErrorCallback>,
+    jitter: Duration,
+    stop: &StopSignal,
+) {
+    let mut now = Utc::now();
+    while let Some(time) = job.schedule.next_after(now) {
+        let delay = (time - Utc::now()).to_std().unwrap_or_default() + random_delay(jitter);
+        if stop.wait(delay) {
+            break;
+        }
+
+        if let Err(error) = (job.task)(influxdb, time) {
+            if let Some(on_error) = &on_error {
+                on_error(&job.name, &error);
+            }
+        }
+
+        // Times missed while running are skipped
+        now = Utc::now().max(time);
+    }
+}
+
+/// Return a random duration up to a maximum
+///
+/// Randomness comes from the random keys of the standard library hasher,
+/// which is plenty to spread runs over time.
+fn random_delay(max: Duration) -> Duration {
+    let max = max.as_nanos() as u64;
+    if max == 0 {
+        return Duration::from_secs(0);
+    }
+    let random = RandomState::new().build_hasher().finish();
+    Duration::from_nanos(random % max)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::time::Instant;
+
+    use url::Url;
+
+    use crate::types::{HttpRequest, HttpResponse, HttpTransport, TransportError};
+
+    #[derive(Debug, Default)]
+    struct RecordingTransport {
+        requests: Mutex<Vec<HttpRequest>>,
+    }
+
+    impl HttpTransport for RecordingTransport {
+        fn execute(&self, request: HttpRequest) -> Result<HttpResponse, TransportError> {
+            let response = if request.url.contains("/write") {
+                HttpResponse::new(204, "")
+            } else {
+                HttpResponse::new(200, r#"{
+                    "results": [{
+                        "statement_id": 0,
+                        "series": [{
+                            "name": "indoor_environment_5m",
+                            "columns": ["time", "temperature"],
+                            "values": [["2021-03-04T17:00:00Z", 21.3]]
+                        }]
+                    }]
+                }"#)
+            };
+            self.requests.lock().unwrap().push(request);
+            Ok(response)
+        }
+    }
+
+    #[test]
+    fn every_is_aligned() {
+        let schedule = Schedule::every(Duration::from_secs(300));
+
+        assert_eq!(
+            schedule.next_after(Utc.with_ymd_and_hms(2021, 3, 4, 17, 5, 0).unwrap()),
+            Some(Utc.with_ymd_and_hms(2021, 3, 4, 17, 10, 0).unwrap()),
+        );
+        assert_eq!(
+            schedule.next_after(Utc.with_ymd_and_hms(1969, 12, 31, 23, 58, 0).unwrap()),
+            Some(Utc.with_ymd_and_hms(1970, 1, 1, 0, 0, 0).unwrap()),
+        );
+    }
+
+    #[test]
+    fn every_does_not_overflow() {
+        let schedule = Schedule::every(Duration::from_secs(3600));
+        assert_eq!(
+            schedule.next_after(Utc.with_ymd_and_hms(2500, 1, 1, 12, 30, 0).unwrap()),
+            Some(Utc.with_ymd_and_hms(2500, 1, 1, 13, 0, 0).unwrap()),
+        );
+
+        let schedule = Schedule::Every(Duration::from_secs(0));
+        assert_eq!(schedule.next_after(Utc.with_ymd_and_hms(2021, 3, 4, 17, 5, 0).unwrap()), None);
+
+        let schedule = Schedule::every(Duration::from_secs(u64::MAX));
+        assert_eq!(schedule.next_after(Utc.with_ymd_and_hms(2021, 3, 4, 17, 5, 0).unwrap()), None);
+
+        let schedule = Schedule::every(Duration::from_secs(3600));
+        assert_eq!(schedule.next_after(DateTime::<Utc>::MAX_UTC), None);
+    }
+
+    #[test]
+    fn random_delay_is_bounded() {
+        assert_eq!(random_delay(Duration::from_secs(0)), Duration::from_secs(0));
+        for _ in 0..100 {
+            assert!(random_delay(Duration::from_millis(10)) < Duration::from_millis(10));
+        }
+    }
+
+    #[test]
+    fn write_back_until_stopped() -> Result<(), InfluxDbError> {
+        let transport = Arc::new(RecordingTransport::default());
+        let base_url = Url::parse("http://influxdb.test/").unwrap();
+        let line_client = crate::line_protocol::blocking::ClientBuilder::new(base_url.clone())
+            .transport(transport.clone())
+            .build()?;
+        let influxql_client = crate::influxql::blocking::ClientBuilder::new(base_url)
+            .transport(transport.clone())
+            .build()?;
+        let influxdb = InfluxDb::from_clients(line_client, influxql_client, "house");
+
+        let handle = Scheduler::new(influxdb)
+            .write_back(
+                "downsample",
+                Schedule::every(Duration::from_millis(10)),
+                |_time| Query::new("SELECT mean(temperature) AS temperature FROM indoor_environment"),
+                "house",
+            )
+            .start();
+
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while transport.requests.lock().unwrap().len() < 4 && Instant::now() < deadline {
+            thread::sleep(Duration::from_millis(10));
+        }
+        handle.stop();
+
+        let requests = transport.requests.lock().unwrap();
+        let count = requests.len();
+        assert!(count >= 4);
+        assert!(requests[1].url.contains("/write"));
+        assert_eq!(
+            String::from_utf8_lossy(&requests[1].body),
+            "indoor_environment_5m temperature=21.3 1614877200000000000",
+        );
+        drop(requests);
+
+        // No more runs after stopping
+        thread::sleep(Duration::from_millis(50));
+        assert_eq!(transport.requests.lock().unwrap().len(), count);
+        Ok(())
+    }
+}
@@ -0,0 +1,259 @@
+// Copyright Claudio Mattera 2021.
+// Distributed under the MIT License or Apache 2.0 License at your option.
+// See accompanying files License-MIT.txt and License-Apache-2.0, or online at
+// https://opensource.org/licenses/MIT
+// https://opensource.org/licenses/Apache-2.0
+
+//! Utilities for testing code that uses InfluxDB clients
+
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use url::Url;
+
+use crate::types::{HttpRequest, HttpResponse, HttpTransport, TransportError};
+
+/// The base URL of mock clients
+const MOCK_URL: &str = "http://influxdb.test/";
+
+/// A mock InfluxDB server for unit tests
+///
+/// Mock clients return canned responses and record all requests, so that
+/// code sending data or performing queries can be tested without running a
+/// server.
+/// The clients returned by [`line_client`](MockInfluxClient::line_client),
+/// [`influxql_client`](MockInfluxClient::influxql_client) and
+/// [`flux_client`](MockInfluxClient::flux_client) are ordinary blocking
+/// clients with the same fetch and send functions, and they share the
+/// canned responses and the recorded requests of the mock client.
+///
+/// Requests are answered with:
+///
+/// 1. the response registered for the query they contain, if any;
+/// 2. otherwise, the first response pushed with
+///    [`push_response`](MockInfluxClient::push_response);
+/// 3. otherwise, a successful response with no data.
+///
+/// ```
+/// # use rinfluxdb::dataframe::DataFrame;
+/// # use rinfluxdb::influxql::Query;
+/// # use rinfluxdb::line_protocol::LineBuilder;
+/// # use rinfluxdb::testing::MockInfluxClient;
+/// # use rinfluxdb::types::HttpResponse;
+/// let mock = MockInfluxClient::new();
+/// mock.respond_to_query(
+///     "SELECT temperature FROM indoor_environment",
+///     HttpResponse::new(200, r#"{
+///         "results": [{
+///             "statement_id": 0,
+///             "series": [{
+///                 "name": "indoor_environment",
+///                 "columns": ["time", "temperature"],
+///                 "values": [["2021-03-04T17:00:00Z", 21.3]]
+///             }]
+///         }]
+///     }"#),
+/// );
+///
+/// let query = Query::new("SELECT temperature FROM indoor_environment");
+/// let dataframe: DataFrame = mock.influxql_client()?.fetch_dataframe(query)?;
+/// assert_eq!(dataframe.index().len(), 1);
+///
+/// let line = LineBuilder::new("indoor_environment")
+///     .insert_field("temperature", 21.3)
+///     .build();
+/// mock.line_client()?.send("house", &[line.clone()])?;
+///
+/// assert_eq!(mock.queries(), vec!["SELECT temperature FROM indoor_environment"]);
+/// assert_eq!(mock.written_lines(), vec![line]);
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+///
+/// A mock client is also a [transport](HttpTransport), so it can be set on
+/// client builders configured by the code under test.
+#[derive(Clone, Debug, Default)]
+pub struct MockInfluxClient {
+    state: Arc<Mutex<State>>,
+}
+
+#[derive(Debug, Default)]
+struct State {
+    query_responses: Vec<(String, HttpResponse)>,
+    responses: VecDeque<HttpResponse>,
+    requests: Vec<HttpRequest>,
+}
+
+impl MockInfluxClient {
+    /// Create a mock client with no canned responses
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Return the base URL of the clients created by the mock client
+    pub fn base_url() -> Url {
+        Url::parse(MOCK_URL).expect("mock URL is valid")
+    }
+
+    /// Respond to all requests containing a query with a response
+    ///
+    /// Both InfluxQL and Flux queries are matched exactly against the query
+    /// text.
+    /// A later response for the same query replaces the earlier one.
+    pub fn respond_to_query<T>(&self, query: T, response: HttpResponse)
+    where
+        T: Into<String>,
+    {
+        let query = query.into();
+        let mut state = self.state();
+        state.query_responses.retain(|(existing, _)| *existing != query);
+        state.query_responses.push((query, response));
+    }
+
+    /// Respond to the next request with a response
+    ///
+    /// Responses are returned in the same order they are pushed, each one to
+    /// a single request.
+    pub fn push_response(&self, response: HttpResponse) {
+        self.state().responses.push_back(response);
+    }
+
+    /// Return all requests received so far
+    pub fn requests(&self) -> Vec<HttpRequest> {
+        self.state().requests.clone()
+    }
+
+    /// Return all InfluxQL and Flux queries received so far
+    pub fn queries(&self) -> Vec<String> {
+        self.state().requests.iter().filter_map(query).collect()
+    }
+
+    /// Forget all requests received so far
+    ///
+    /// Canned responses are kept.
+    pub fn clear_requests(&self) {
+        self.state().requests.clear();
+    }
+
+    fn state(&self) -> MutexGuard<'_, State> {
+        self.state.lock().unwrap_or_else(|error| error.into_inner())
+    }
+}
+
+#[cfg(feature = "lineprotocol")]
+impl MockInfluxClient {
+    /// Return all lines written so far, in the order they were sent
+    ///
+    /// # Panics
+    ///
+    /// Panics if a request to a write endpoint does not contain valid line
+    /// protocol.
+    pub fn written_lines(&self) -> Vec<crate::line_protocol::Line> {
+        self.state()
+            .requests
+            .iter()
+            .filter(|request| {
+                matches!(path(request).as_deref(), Some("/write") | Some("/api/v2/write"))
+            })
+            .flat_map(|request| {
+                let body = String::from_utf8_lossy(&request.body);
+                crate::line_protocol::parse_lines(&body).expect("written lines are not valid")
+            })
+            .collect()
+    }
+
+    /// Return a line protocol client sending requests to the mock client
+    pub fn line_client(
+        &self,
+    ) -> Result<crate::line_protocol::blocking::Client, crate::line_protocol::ClientError> {
+        crate::line_protocol::blocking::ClientBuilder::new(Self::base_url())
+            .transport(self.clone())
+            .build()
+    }
+}
+
+#[cfg(feature = "influxql")]
+impl MockInfluxClient {
+    /// Return an InfluxQL client sending requests to the mock client
+    pub fn influxql_client(
+        &self,
+    ) -> Result<crate::influxql::blocking::Client, crate::influxql::ClientError> {
+        crate::influxql::blocking::ClientBuilder::new(Self::base_url())
+            .transport(self.clone())
+            .build()
+    }
+}
+
+#[cfg(feature = "flux")]
+impl MockInfluxClient {
+    /// Return a Flux client sending requests to the mock client
+    pub fn flux_client(&self) -> Result<crate::flux::blocking::Client, crate::flux::ClientError> {
+        crate::flux::blocking::ClientBuilder::new(Self::base_url())
+            .transport(self.clone())
+            .build()
+    }
+}
+
+impl HttpTransport for MockInfluxClient {
+    fn execute(&self, request: HttpRequest) -> Result<HttpResponse, TransportError> {
+        let mut state = self.state();
+
+        let query = query(&request);
+        let canned = query
+            .and_then(|query| {
+                state
+                    .query_responses
+                    .iter()
+                    .find(|(existing, _)| *existing == query)
+                    .map(|(_, response)| response.clone())
+            })
+            .or_else(|| state.responses.pop_front());
+        let response = match canned {
+            Some(response) => response,
+            None => default_response(&request),
+        };
+
+        state.requests.push(request);
+
+        Ok(response)
+    }
+}
+
+/// Return the path of the URL of a request
+fn path(request: &HttpRequest) -> Option<String> {
+    Url::parse(&request.url).ok().map(|url| url.path().to_string())
+}
+
+/// Return the InfluxQL or Flux query contained in a request, if any
+///
+/// InfluxQL queries are sent in parameter `q`, either in the URL or in a
+/// form body, while Flux queries are sent in a JSON body.
+fn query(request: &HttpRequest) -> Option<String> {
+    let url = Url::parse(&request.url).ok()?;
+    match url.path() {
+        "/query" => url
+            .query_pairs()
+            .chain(url::form_urlencoded::parse(&request.body))
+            .find(|(name, _)| name == "q")
+            .map(|(_, value)| value.into_owned()),
+        "/api/v2/query" => {
+            let body: serde_json::Value = serde_json::from_slice(&request.body).ok()?;
+            body.get("query")?.as_str().map(String::from)
+        }
+        _ => None,
+    }
+}
+
+/// Return a successful response with no data for a request
+fn default_response(request: &HttpRequest) -> HttpResponse {
+    match path(request).as_deref() {
+        Some("/query") => HttpResponse::new(200, r#"{"results":[{"statement_id":0}]}"#)
+            .with_header("Content-Type", "application/json"),
+        Some("/api/v2/query") => HttpResponse::new(200, "")
+            .with_header("Content-Type", "text/csv"),
+        Some("/health") => HttpResponse::new(200, r#"{"name":"influxdb","status":"pass"}"#)
+            .with_header("Content-Type", "application/json"),
+        Some("/ping") => HttpResponse::new(204, "")
+            .with_header("X-Influxdb-Version", "mock"),
+        _ => HttpResponse::new(204, ""),
+    }
+}